@@ -0,0 +1,316 @@
+//! Derive macros for json-joy.
+//!
+//! `#[derive(CrdtSchema)]` maps a struct with named fields onto a JSON CRDT
+//! `obj` (or `vec`) node. It implements `json_joy::json_crdt::typed::CrdtValue`
+//! and `CrdtSchema` for the struct and generates a typed `<Name>Node` handle
+//! with read and write accessors. See the `json_crdt::typed` module docs for
+//! the supported attributes.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr, Type};
+
+#[proc_macro_derive(CrdtSchema, attributes(crdt))]
+pub fn derive_crdt_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// ── Attributes ─────────────────────────────────────────────────────────────
+
+/// Container node used for the struct itself.
+#[derive(Clone, Copy, PartialEq)]
+enum Container {
+    Obj,
+    Vec,
+}
+
+/// Node kind chosen for a field.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Default,
+    Con,
+    Val,
+    Str,
+    Bin,
+    Arr,
+    Obj,
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    key: String,
+    kind: Kind,
+}
+
+fn container_attr(input: &DeriveInput) -> syn::Result<Container> {
+    let mut container = Container::Obj;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("crdt")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("obj") {
+                container = Container::Obj;
+            } else if meta.path.is_ident("vec") {
+                container = Container::Vec;
+            } else {
+                return Err(meta.error("expected `obj` or `vec`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(container)
+}
+
+fn field_attrs(field: &syn::Field, container: Container) -> syn::Result<Field> {
+    let ident = field.ident.clone().expect("named field");
+    let mut key = ident.to_string();
+    let mut kind = Kind::Default;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("crdt")) {
+        attr.parse_nested_meta(|meta| {
+            let path = &meta.path;
+            if path.is_ident("rename") {
+                if container == Container::Vec {
+                    return Err(meta.error(
+                        "`rename` is not supported in a `vec` struct, whose fields are stored by position",
+                    ));
+                }
+                key = meta.value()?.parse::<LitStr>()?.value();
+                return Ok(());
+            }
+            kind = if path.is_ident("con") {
+                Kind::Con
+            } else if path.is_ident("val") {
+                Kind::Val
+            } else if path.is_ident("str") {
+                Kind::Str
+            } else if path.is_ident("bin") {
+                Kind::Bin
+            } else if path.is_ident("arr") {
+                Kind::Arr
+            } else if path.is_ident("obj") {
+                Kind::Obj
+            } else {
+                return Err(meta.error(
+                    "expected one of `con`, `val`, `str`, `bin`, `arr`, `obj` or `rename`",
+                ));
+            };
+            Ok(())
+        })?;
+    }
+    Ok(Field {
+        ident,
+        ty: field.ty.clone(),
+        key,
+        kind,
+    })
+}
+
+// ── Expansion ──────────────────────────────────────────────────────────────
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let container = container_attr(&input)?;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "CrdtSchema cannot be derived for generic structs",
+        ));
+    }
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "CrdtSchema requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "CrdtSchema can only be derived for structs",
+            ))
+        }
+    };
+    let fields = named
+        .iter()
+        .map(|field| field_attrs(field, container))
+        .collect::<syn::Result<Vec<_>>>()?;
+    if container == Container::Vec && fields.len() > 256 {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "a `vec` struct can have at most 256 fields",
+        ));
+    }
+
+    let rt = quote!(::json_joy::json_crdt::typed);
+    let ts = quote!(::json_joy::json_crdt_patch::clock::Ts);
+    let api_error = quote!(::json_joy::json_crdt::model::api::ApiError);
+    let model = quote!(::json_joy::json_crdt::Model);
+    let model_api = quote!(::json_joy::json_crdt::ModelApi<'_>);
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let node = format_ident!("{}Node", name);
+
+    let schemas: Vec<TokenStream2> = fields.iter().map(|f| field_schema(f, &rt)).collect();
+    let views = fields.iter().map(|f| {
+        let ident = &f.ident;
+        quote!(#rt::CrdtValue::to_json(&self.#ident))
+    });
+    let keys: Vec<TokenStream2> = fields
+        .iter()
+        .enumerate()
+        .map(|(index, f)| match container {
+            Container::Obj => {
+                let key = &f.key;
+                quote!(#key)
+            }
+            Container::Vec => {
+                let index = index as u8;
+                quote!(#index)
+            }
+        })
+        .collect();
+    let reads = fields.iter().zip(&keys).map(|(f, key)| {
+        let ident = &f.ident;
+        let ty = &f.ty;
+        let lookup = match container {
+            Container::Obj => quote!(#rt::obj_field(value, #key)?),
+            Container::Vec => quote!(#rt::vec_field(value, #key as usize)?),
+        };
+        quote!(#ident: <#ty as #rt::CrdtValue>::from_json(#lookup)?)
+    });
+    let (schema, view) = match container {
+        Container::Obj => (
+            quote!(#rt::obj_schema(vec![#((#keys, #schemas)),*])),
+            quote!(#rt::obj_json(vec![#((#keys, #views)),*])),
+        ),
+        Container::Vec => (
+            quote!(#rt::vec_schema(vec![#(#schemas),*])),
+            quote!(#rt::vec_json(vec![#(#views),*])),
+        ),
+    };
+    let (child, set_child, set_val) = match container {
+        Container::Obj => (
+            quote!(obj_child),
+            quote!(set_obj_child),
+            quote!(set_obj_val),
+        ),
+        Container::Vec => (
+            quote!(vec_child),
+            quote!(set_vec_child),
+            quote!(set_vec_val),
+        ),
+    };
+
+    let accessors = fields.iter().zip(&keys).map(|(f, key)| {
+        let ident = &f.ident;
+        let ty = &f.ty;
+        let id_fn = format_ident!("{}_id", ident);
+        let set_fn = format_ident!("set_{}", ident);
+        let set = match f.kind {
+            Kind::Val => quote!(#rt::#set_val(api, self.id, #key, value)),
+            _ => {
+                let schema = field_schema_of(f, &rt, quote!(value));
+                quote!(#rt::#set_child(api, self.id, #key, value, #schema))
+            }
+        };
+        let nested = (f.kind == Kind::Obj).then(|| {
+            let node_fn = format_ident!("{}_node", ident);
+            quote! {
+                /// Typed handle to the nested struct.
+                pub fn #node_fn(
+                    &self,
+                    model: &#model,
+                ) -> ::core::result::Result<<#ty as #rt::CrdtSchema>::Node, #api_error> {
+                    ::core::result::Result::Ok(<#ty as #rt::CrdtSchema>::node(self.#id_fn(model)?))
+                }
+            }
+        });
+        quote! {
+            /// Reads the field.
+            pub fn #ident(&self, model: &#model) -> ::core::result::Result<#ty, #api_error> {
+                #rt::read(model, self.#id_fn(model)?)
+            }
+
+            /// ID of the node holding the field.
+            pub fn #id_fn(&self, model: &#model) -> ::core::result::Result<#ts, #api_error> {
+                #rt::#child(model, self.id, #key)
+            }
+
+            /// Writes the field.
+            pub fn #set_fn(
+                &self,
+                api: &mut #model_api,
+                value: &#ty,
+            ) -> ::core::result::Result<(), #api_error> {
+                #set
+            }
+
+            #nested
+        }
+    });
+
+    let doc = format!("Typed handle to a JSON CRDT node holding a [`{name}`].");
+    Ok(quote! {
+        impl #rt::CrdtValue for #name {
+            fn schema(&self) -> ::std::boxed::Box<dyn ::json_joy::json_crdt_patch::schema::NodeBuilder> {
+                #schema
+            }
+
+            fn to_json(&self) -> #rt::Value {
+                #view
+            }
+
+            fn from_json(value: &#rt::Value) -> ::core::option::Option<Self> {
+                ::core::option::Option::Some(Self { #(#reads),* })
+            }
+        }
+
+        impl #rt::CrdtSchema for #name {
+            type Node = #node;
+
+            fn node(id: #ts) -> #node {
+                #node { id }
+            }
+        }
+
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #vis struct #node {
+            /// ID of the underlying node.
+            pub id: #ts,
+        }
+
+        impl #node {
+            /// Reads the whole struct.
+            pub fn read(&self, model: &#model) -> ::core::result::Result<#name, #api_error> {
+                #rt::read(model, self.id)
+            }
+
+            #(#accessors)*
+        }
+    })
+}
+
+/// Schema expression for a field of `self`.
+fn field_schema(field: &Field, rt: &TokenStream2) -> TokenStream2 {
+    let ident = &field.ident;
+    field_schema_of(field, rt, quote!(&self.#ident))
+}
+
+/// Schema expression for `value`, a reference to the field's type.
+fn field_schema_of(field: &Field, rt: &TokenStream2, value: TokenStream2) -> TokenStream2 {
+    match field.kind {
+        Kind::Default | Kind::Obj => quote!(#rt::CrdtValue::schema(#value)),
+        Kind::Con => quote!(#rt::con_schema(#value)),
+        Kind::Val => quote!(#rt::val_schema(#value)),
+        Kind::Str => quote!(#rt::str_schema(#value)),
+        Kind::Bin => quote!(#rt::bin_schema(#value)),
+        Kind::Arr => quote!(#rt::arr_schema(#value)),
+    }
+}
@@ -227,7 +227,9 @@ impl Model {
     /// The result is cached by `inner.tick` (which increments on every
     /// `apply_patch`).  Repeated calls on an unchanged document are O(1) —
     /// a single `JsValue` reference-count bump — mirroring the tick-based
-    /// `_view` cache in the upstream TypeScript nodes.
+    /// `_view` cache in the upstream TypeScript nodes. After a change, the
+    /// JSON view is taken from [`CrdtModel::cached_view`],
+    /// which re-renders only the nodes the change touched.
     ///
     /// Mirrors `model.view()`.
     pub fn view(&mut self) -> JsValue {
@@ -237,7 +239,7 @@ impl Model {
                 return v.clone();
             }
         }
-        let val = self.inner.view();
+        let val = self.inner.cached_view();
         let ser = serde_wasm_bindgen::Serializer::json_compatible();
         let js = val.serialize(&ser).unwrap_or(JsValue::NULL);
         self.view_cache = Some((tick, js.clone()));
@@ -0,0 +1,580 @@
+//! Causal delivery buffer for out-of-order patches.
+//!
+//! # Overview
+//!
+//! [`Model::apply_patch`] assumes every node and RGA element a patch refers
+//! to has already been applied; operations targeting unknown IDs are
+//! silently dropped. A [`CausalBuffer`] sits in front of the model and only
+//! applies a patch once all of its dependencies are present:
+//!
+//! 1. Each referenced ID (see [`Op::refs`](crate::json_crdt_patch::operations::Op::refs))
+//!    that is not created by the patch itself must be present in the model:
+//!    - RGA items (insertion anchors, deleted ranges, moved slots) must be
+//!      found in the target node's RGA, tombstones included. Items missing
+//!      from it are only accepted when the model's
+//!      [`gc_horizon`](Model::gc_horizon) covers them, i.e. they may have
+//!      been collected.
+//!    - Nodes must exist in the model's
+//!      [`NodeIndex`](crate::json_crdt::nodes::NodeIndex) or be listed in
+//!      [`removed_nodes`](Model::removed_nodes): overwritten subtrees are
+//!      removed from the index, and operations on them are no-ops. The
+//!      clock cannot tell whether an earlier patch of a session is still
+//!      missing, so it is only trusted for nodes the model had seen before
+//!      the buffer first delivered to it (say, nodes of a decoded snapshot
+//!      overwritten before it was taken).
+//! 2. IDs covered by a patch that is still waiting in the buffer are always
+//!    treated as missing, even if the clock has already moved past them.
+//! 3. Patches of the same session are delivered in ID order: a patch waits
+//!    for every buffered patch of its session with a lower time.
+//! 4. Patches whose dependencies are missing are held back and retried
+//!    every time another patch is delivered.
+
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::model::Model;
+use crate::json_crdt::nodes::rga::{ChunkData, Rga};
+use crate::json_crdt::nodes::{CrdtNode, IndexExt, TsKey};
+use crate::json_crdt_patch::clock::{compare, ClockVector, Ts, Tss};
+use crate::json_crdt_patch::enums::SESSION;
+use crate::json_crdt_patch::operations::Op;
+use crate::json_crdt_patch::patch::Patch;
+
+/// A buffered patch together with the dependencies it is waiting for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blocked {
+    /// ID of the buffered patch.
+    pub id: Ts,
+    /// Referenced timestamp spans that have not been delivered yet.
+    pub missing: Vec<Tss>,
+}
+
+/// Holds patches until their causal predecessors have been applied.
+#[derive(Debug, Clone, Default)]
+pub struct CausalBuffer {
+    /// Buffered patches, ordered by ID.
+    pending: Vec<Patch>,
+    /// Model clock when the buffer first delivered to it.
+    baseline: Option<ClockVector>,
+}
+
+impl CausalBuffer {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delivers `patch`: applies it to `model` if its dependencies are
+    /// satisfied, otherwise buffers it. Any buffered patches unblocked as a
+    /// result are applied too.
+    ///
+    /// Returns the IDs of all patches applied by this call, in application
+    /// order. Empty patches are ignored.
+    pub fn push(&mut self, model: &mut Model, patch: Patch) -> Vec<Ts> {
+        let Some(id) = patch.get_id() else {
+            return Vec::new();
+        };
+        let at = self
+            .pending
+            .partition_point(|p| p.get_id().is_some_and(|pid| compare(pid, id) < 0));
+        self.pending.insert(at, patch);
+        self.flush(model)
+    }
+
+    /// Applies every buffered patch whose dependencies are now satisfied,
+    /// repeating until no further progress can be made.
+    ///
+    /// Call this after applying patches to `model` without going through the
+    /// buffer. Returns the IDs of the applied patches in application order.
+    pub fn flush(&mut self, model: &mut Model) -> Vec<Ts> {
+        self.baseline.get_or_insert_with(|| model.clock.clone());
+        let mut applied = Vec::new();
+        while let Some(index) =
+            (0..self.pending.len()).find(|&i| self.missing_at(model, i).is_empty())
+        {
+            let patch = self.pending.remove(index);
+            model.apply_patch(&patch);
+            applied.extend(patch.get_id());
+        }
+        applied
+    }
+
+    /// Returns the dependencies of `patch` which are not yet available,
+    /// taking the patches still held by this buffer into account.
+    pub fn missing(&self, model: &Model, patch: &Patch) -> Vec<Tss> {
+        self.missing_for(model, patch, None)
+    }
+
+    /// Returns every buffered patch with the dependencies it is waiting for.
+    pub fn blocked(&self, model: &Model) -> Vec<Blocked> {
+        self.pending
+            .iter()
+            .enumerate()
+            .filter_map(|(i, patch)| {
+                Some(Blocked {
+                    id: patch.get_id()?,
+                    missing: self.missing_at(model, i),
+                })
+            })
+            .collect()
+    }
+
+    /// Buffered patches, ordered by ID.
+    pub fn pending(&self) -> &[Patch] {
+        &self.pending
+    }
+
+    /// Number of buffered patches.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if no patches are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Drops all buffered patches, returning them.
+    pub fn clear(&mut self) -> Vec<Patch> {
+        std::mem::take(&mut self.pending)
+    }
+
+    fn missing_at(&self, model: &Model, index: usize) -> Vec<Tss> {
+        self.missing_for(model, &self.pending[index], Some(index))
+    }
+
+    fn missing_for(&self, model: &Model, patch: &Patch, skip: Option<usize>) -> Vec<Tss> {
+        let Some(id) = patch.get_id() else {
+            return Vec::new();
+        };
+        let own = Tss::new(id.sid, id.time, patch.span());
+        // Earlier patches of the same session are causal predecessors.
+        let mut missing: Vec<Tss> = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != skip)
+            .filter_map(|(_, p)| Some((p.get_id()?, p.span())))
+            .filter(|(pid, _)| pid.sid == id.sid && pid.time < id.time)
+            .map(|(pid, span)| Tss::new(pid.sid, pid.time, span))
+            .collect();
+        for op in &patch.ops {
+            for dep in deps(op) {
+                let span = dep.span();
+                if within(own, span) {
+                    continue;
+                }
+                if self.is_available(model, dep, skip) || missing.contains(&span) {
+                    continue;
+                }
+                missing.push(span);
+            }
+        }
+        missing
+    }
+
+    /// A dependency is available once it is not covered by a buffered patch
+    /// and the model holds it (see the module docs).
+    fn is_available(&self, model: &Model, dep: Dep, skip: Option<usize>) -> bool {
+        let span = dep.span();
+        let held = self.pending.iter().enumerate().any(|(i, p)| {
+            Some(i) != skip
+                && p.get_id()
+                    .is_some_and(|pid| overlaps(Tss::new(pid.sid, pid.time, p.span()), span))
+        });
+        if held {
+            return false;
+        }
+        let last = Ts::new(span.sid, span.time + span.span.saturating_sub(1));
+        match dep {
+            Dep::Node(span) => {
+                span.sid == SESSION::SYSTEM
+                    || (span.time..=last.time).all(|time| {
+                        let id = Ts::new(span.sid, time);
+                        model.index.contains_ts(&id)
+                            || model.removed_nodes.contains(&TsKey::from(id))
+                    })
+                    || self
+                        .baseline
+                        .as_ref()
+                        .is_some_and(|baseline| baseline.has_seen(last))
+            }
+            Dep::Items { obj, span } => {
+                let found = match IndexExt::get(&model.index, &obj) {
+                    Some(CrdtNode::Str(node)) => contains(&node.rga, span),
+                    Some(CrdtNode::Bin(node)) => contains(&node.rga, span),
+                    Some(CrdtNode::Arr(node)) => contains(&node.rga, span),
+                    // The operation is a no-op; `obj` itself is a node
+                    // dependency.
+                    _ => true,
+                };
+                found
+                    || model
+                        .gc_horizon
+                        .as_ref()
+                        .is_some_and(|horizon| horizon.has_seen(last))
+            }
+        }
+    }
+}
+
+/// Something an operation refers to.
+#[derive(Debug, Clone, Copy)]
+enum Dep {
+    /// A node.
+    Node(Tss),
+    /// Items of the RGA node `obj`.
+    Items { obj: Ts, span: Tss },
+}
+
+impl Dep {
+    fn span(self) -> Tss {
+        match self {
+            Dep::Node(span) | Dep::Items { span, .. } => span,
+        }
+    }
+}
+
+/// Dependencies of `op`: the spans of [`Op::refs`], with those naming RGA
+/// items tied to their node. Anchors at the start of an RGA are omitted.
+fn deps(op: &Op) -> Vec<Dep> {
+    let items = |obj: Ts, ids: &[Ts]| -> Vec<Tss> {
+        ids.iter()
+            .filter(|id| **id != obj && **id != ORIGIN)
+            .map(|id| Tss::new(id.sid, id.time, 1))
+            .collect()
+    };
+    let (obj, items) = match op {
+        Op::InsStr { obj, after, .. }
+        | Op::InsBin { obj, after, .. }
+        | Op::InsArr { obj, after, .. }
+        | Op::UpdArr { obj, after, .. } => (*obj, items(*obj, &[*after])),
+        Op::MovArr {
+            obj, after, elem, ..
+        } => (*obj, items(*obj, &[*after, *elem])),
+        Op::Del { obj, what, .. } => (*obj, what.clone()),
+        _ => (ORIGIN, Vec::new()),
+    };
+    let mut deps: Vec<Dep> = op
+        .refs()
+        .into_iter()
+        .filter(|span| !items.contains(span))
+        .map(Dep::Node)
+        .collect();
+    deps.extend(items.into_iter().map(|span| Dep::Items { obj, span }));
+    deps
+}
+
+/// Returns `true` if every item of `span` is in `rga`, deleted or not.
+fn contains<T: Clone + ChunkData>(rga: &Rga<T>, span: Tss) -> bool {
+    let end = span.time + span.span;
+    let mut time = span.time;
+    while time < end {
+        let Some(idx) = rga.find_by_id(Ts::new(span.sid, time)) else {
+            return false;
+        };
+        let chunk = rga.slot(idx);
+        time = chunk.id.time + chunk.span;
+    }
+    true
+}
+
+/// Returns `true` if spans `a` and `b` share at least one timestamp.
+fn overlaps(a: Tss, b: Tss) -> bool {
+    a.sid == b.sid && a.time < b.time + b.span && b.time < a.time + a.span
+}
+
+/// Returns `true` if span `b` lies entirely within span `a`.
+fn within(a: Tss, b: Tss) -> bool {
+    a.sid == b.sid && a.time <= b.time && b.time + b.span <= a.time + a.span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::model::ModelApi;
+    use crate::json_crdt_patch::patch_builder::PatchBuilder;
+    use serde_json::json;
+
+    const A: u64 = 100_001;
+    const B: u64 = 100_002;
+    const C: u64 = 100_003;
+
+    /// A document `{"text": "ab"}` authored by session `A`, plus the ID of
+    /// the string node.
+    fn make_doc() -> (Model, Ts) {
+        let mut model = Model::new(A);
+        let mut api = ModelApi::new(&mut model);
+        api.set(&json!({"text": "ab"})).unwrap();
+        let root = api.model.root.val;
+        let text = api.find(root, &[json!("text")]).unwrap();
+        (model, text)
+    }
+
+    fn replica(model: &Model, sid: u64) -> Model {
+        let mut replica = Model::from_binary(&model.to_binary()).unwrap();
+        replica.clock = replica.clock.fork(sid);
+        replica
+    }
+
+    /// Builds a patch on `author`, applies it there and returns it.
+    fn edit(author: &mut Model, f: impl FnOnce(&mut PatchBuilder)) -> Patch {
+        let mut builder = PatchBuilder::new(author.clock.sid, author.clock.time);
+        f(&mut builder);
+        let patch = builder.flush();
+        author.apply_patch(&patch);
+        patch
+    }
+
+    #[test]
+    fn applies_ready_patches_immediately() {
+        let (mut doc, text) = make_doc();
+        let mut peer = replica(&doc, B);
+        let patch = edit(&mut peer, |b| {
+            b.ins_str(text, text, "x".to_string());
+        });
+        let mut buffer = CausalBuffer::new();
+        let applied = buffer.push(&mut doc, patch.clone());
+        assert_eq!(applied, vec![patch.get_id().unwrap()]);
+        assert!(buffer.is_empty());
+        assert_eq!(doc.view(), json!({"text": "xab"}));
+    }
+
+    #[test]
+    fn holds_patch_until_dependency_arrives() {
+        let (mut doc, text) = make_doc();
+        let mut peer = replica(&doc, B);
+        let p1 = edit(&mut peer, |b| {
+            b.ins_str(text, text, "x".to_string());
+        });
+        let x = p1.get_id().unwrap();
+        let p2 = edit(&mut peer, |b| {
+            b.ins_str(text, x, "y".to_string());
+        });
+        assert_eq!(peer.view(), json!({"text": "xyab"}));
+
+        let mut buffer = CausalBuffer::new();
+        assert!(buffer.push(&mut doc, p2.clone()).is_empty());
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(doc.view(), json!({"text": "ab"}));
+        let blocked = buffer.blocked(&doc);
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].id, p2.get_id().unwrap());
+        assert_eq!(blocked[0].missing, vec![Tss::new(B, x.time, 1)]);
+
+        let applied = buffer.push(&mut doc, p1.clone());
+        assert_eq!(applied, vec![x, p2.get_id().unwrap()]);
+        assert!(buffer.is_empty());
+        assert_eq!(doc.view(), json!({"text": "xyab"}));
+    }
+
+    #[test]
+    fn same_session_patches_are_delivered_in_order() {
+        let (mut doc, text) = make_doc();
+        let mut peer_b = replica(&doc, B);
+        let mut peer_c = replica(&doc, C);
+        let q = edit(&mut peer_c, |b| {
+            b.ins_str(text, text, "q".to_string());
+        });
+        peer_b.apply_patch(&q);
+        let p1 = edit(&mut peer_b, |b| {
+            b.ins_str(text, q.get_id().unwrap(), "x".to_string());
+        });
+        let p2 = edit(&mut peer_b, |b| {
+            b.ins_str(text, p1.get_id().unwrap(), "y".to_string());
+        });
+        let p3 = edit(&mut peer_b, |b| {
+            b.ins_str(text, text, "z".to_string());
+        });
+        assert_eq!(peer_b.view(), json!({"text": "zqxyab"}));
+
+        let mut buffer = CausalBuffer::new();
+        // p1 waits for C's patch; p3 waits behind p1; p2 waits for p1.
+        assert!(buffer.push(&mut doc, p1.clone()).is_empty());
+        assert!(buffer.push(&mut doc, p3.clone()).is_empty());
+        assert!(buffer.push(&mut doc, p2).is_empty());
+        let p1_span = Tss::new(B, p1.get_id().unwrap().time, 1);
+        let blocked = buffer.blocked(&doc);
+        assert_eq!(blocked.len(), 3);
+        assert_eq!(
+            blocked[0].missing,
+            vec![Tss::new(C, q.get_id().unwrap().time, 1)]
+        );
+        assert!(blocked[1].missing.contains(&p1_span));
+        assert!(blocked[2].missing.contains(&p1_span));
+
+        assert_eq!(buffer.push(&mut doc, q).len(), 4);
+        assert!(buffer.is_empty());
+        assert_eq!(doc.view(), peer_b.view());
+    }
+
+    #[test]
+    fn waits_for_missing_node() {
+        let (mut doc, _) = make_doc();
+        let mut peer = replica(&doc, B);
+        let p1 = edit(&mut peer, |b| {
+            let s = b.str_node();
+            b.ins_str(s, s, "new".to_string());
+            b.root(s);
+        });
+        let str_id = p1.get_id().unwrap();
+        let p2 = edit(&mut peer, |b| {
+            b.ins_str(str_id, str_id, ">".to_string());
+        });
+
+        let mut buffer = CausalBuffer::new();
+        assert!(buffer.push(&mut doc, p2.clone()).is_empty());
+        assert_eq!(buffer.missing(&doc, &p2), vec![Tss::new(B, str_id.time, 1)]);
+        assert_eq!(buffer.push(&mut doc, p1).len(), 2);
+        assert_eq!(doc.view(), json!(">new"));
+    }
+
+    #[test]
+    fn flush_picks_up_externally_applied_patches() {
+        let (mut doc, text) = make_doc();
+        let mut peer = replica(&doc, B);
+        let p1 = edit(&mut peer, |b| {
+            b.ins_str(text, text, "x".to_string());
+        });
+        let p2 = edit(&mut peer, |b| {
+            b.ins_str(text, p1.get_id().unwrap(), "y".to_string());
+        });
+        let mut buffer = CausalBuffer::new();
+        buffer.push(&mut doc, p2);
+        doc.apply_patch(&p1);
+        assert_eq!(buffer.flush(&mut doc).len(), 1);
+        assert_eq!(doc.view(), json!({"text": "xyab"}));
+    }
+
+    #[test]
+    fn clock_gaps_do_not_hide_missing_items() {
+        let (mut doc, text) = make_doc();
+        let mut peer = replica(&doc, B);
+        let p1 = edit(&mut peer, |b| {
+            b.ins_str(text, text, "x".to_string());
+        });
+        let p2 = edit(&mut peer, |b| {
+            b.ins_str(text, text, "z".to_string());
+        });
+        let x = p1.get_id().unwrap();
+        let p3 = edit(&mut peer, |b| {
+            b.ins_str(text, x, "y".to_string());
+        });
+
+        // p1 is lost in transit for now; p2 moves the clock past it.
+        let mut buffer = CausalBuffer::new();
+        assert_eq!(buffer.push(&mut doc, p2).len(), 1);
+        assert!(doc.clock.has_seen(x));
+        assert!(buffer.push(&mut doc, p3).is_empty());
+        assert_eq!(
+            buffer.blocked(&doc)[0].missing,
+            vec![Tss::new(B, x.time, 1)]
+        );
+
+        assert_eq!(buffer.push(&mut doc, p1).len(), 2);
+        assert_eq!(doc.view(), peer.view());
+    }
+
+    #[test]
+    fn clock_gaps_do_not_hide_missing_nodes() {
+        let (mut doc, _) = make_doc();
+        let mut peer = replica(&doc, B);
+        let p1 = edit(&mut peer, |b| {
+            let s = b.str_node();
+            b.ins_str(s, s, "new".to_string());
+            b.root(s);
+        });
+        let str_id = p1.get_id().unwrap();
+        let p2 = edit(&mut peer, |b| {
+            let c = b.con_val(json_joy_json_pack::PackValue::Integer(1));
+            b.root(c);
+        });
+        let p3 = edit(&mut peer, |b| {
+            b.ins_str(str_id, str_id, ">".to_string());
+        });
+
+        // p1 is lost in transit for now; p2 moves the clock past it.
+        let mut buffer = CausalBuffer::new();
+        assert_eq!(buffer.push(&mut doc, p2).len(), 1);
+        assert!(doc.clock.has_seen(str_id));
+        assert!(buffer.push(&mut doc, p3).is_empty());
+        assert_eq!(
+            buffer.blocked(&doc)[0].missing,
+            vec![Tss::new(B, str_id.time, 1)]
+        );
+
+        assert_eq!(buffer.push(&mut doc, p1).len(), 2);
+        assert_eq!(doc.view(), peer.view());
+    }
+
+    #[test]
+    fn overwritten_nodes_are_available() {
+        let (mut doc, text) = make_doc();
+        let mut peer = replica(&doc, B);
+        let late = edit(&mut peer, |b| {
+            b.ins_str(text, text, "x".to_string());
+        });
+        edit(&mut doc, |b| {
+            let obj = b.obj();
+            b.root(obj);
+        });
+        assert!(!doc.index.contains_ts(&text));
+        let mut buffer = CausalBuffer::new();
+        assert!(buffer.missing(&doc, &late).is_empty());
+        assert_eq!(buffer.push(&mut doc, late).len(), 1);
+    }
+
+    #[test]
+    fn nodes_overwritten_before_a_snapshot_are_available() {
+        let (mut doc, text) = make_doc();
+        let mut peer = replica(&doc, B);
+        let late = edit(&mut peer, |b| {
+            b.ins_str(text, text, "x".to_string());
+        });
+        edit(&mut doc, |b| {
+            let obj = b.obj();
+            b.root(obj);
+        });
+        let mut restored = Model::from_binary(&doc.to_binary()).unwrap();
+        assert!(restored.removed_nodes.is_empty());
+        let mut buffer = CausalBuffer::new();
+        assert_eq!(buffer.push(&mut restored, late).len(), 1);
+    }
+
+    #[test]
+    fn collected_items_are_available() {
+        let (mut doc, text) = make_doc();
+        let b_item = match IndexExt::get(&doc.index, &text) {
+            Some(CrdtNode::Str(node)) => {
+                let first = node.rga.iter().next().unwrap().id;
+                Ts::new(first.sid, first.time + 1)
+            }
+            _ => panic!("expected a str node"),
+        };
+        edit(&mut doc, |b| {
+            b.del(text, vec![Tss::new(b_item.sid, b_item.time, 1)]);
+        });
+        let horizon = doc.clock.clone();
+        assert_eq!(doc.gc(&horizon), 1);
+
+        // A concurrent delete of the collected item.
+        let mut builder = PatchBuilder::new(C, 100);
+        builder.del(text, vec![Tss::new(b_item.sid, b_item.time, 1)]);
+        let late = builder.flush();
+        let buffer = CausalBuffer::new();
+        assert!(buffer.missing(&doc, &late).is_empty());
+
+        // Without a horizon covering it, the item is waited for.
+        doc.gc_horizon = None;
+        assert_eq!(
+            buffer.missing(&doc, &late),
+            vec![Tss::new(b_item.sid, b_item.time, 1)]
+        );
+    }
+
+    #[test]
+    fn empty_patches_are_ignored() {
+        let (mut doc, _) = make_doc();
+        let mut buffer = CausalBuffer::new();
+        assert!(buffer.push(&mut doc, Patch::new()).is_empty());
+        assert!(buffer.is_empty());
+    }
+}
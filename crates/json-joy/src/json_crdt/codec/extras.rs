@@ -0,0 +1,335 @@
+//! Model state kept outside the upstream node formats.
+//!
+//! Not part of the upstream TypeScript. Two pieces of replica state have no
+//! place in the upstream snapshot formats:
+//!
+//! - the GC horizon ([`Model::gc_horizon`], see
+//!   [`gc`](crate::json_crdt::model::gc));
+//! - the move registers of `arr` nodes ([`ArrMoves`]), without which a
+//!   replica would resolve later moves and deletes of moved elements
+//!   differently from its peers.
+//!
+//! Every snapshot codec stores them in a slot that upstream decoders
+//! ignore, and omits them when both are empty:
+//!
+//! - structural binary and sidecar `meta`: binary sections after the clock
+//!   table (after the node tree in server-clock mode);
+//! - structural compact: a third array element, the JSON object below;
+//! - structural verbose: the `"gc"` and `"moves"` fields of the JSON object
+//!   below, at the top level;
+//! - indexed: an `"x"` field holding the binary sections.
+//!
+//! Binary sections, timestamps written as absolute `vu57(sid) vu57(time)`:
+//!
+//! ```text
+//! 0x01 vu57(n) n × (sid, time)          GC horizon, local entry first
+//! 0x02 vu57(n) n × arr_moves            move registers
+//!
+//! arr_moves = arr_id vu57(k) k × (slot, origin) vu57(m) m × (origin, slot)
+//! ```
+//!
+//! JSON object:
+//!
+//! ```json
+//! {
+//!   "gc": [sid, time, sid, time, ...],
+//!   "moves": [[arr_sid, arr_time, [slot_sid, slot_time, origin_sid, origin_time, ...],
+//!              [origin_sid, origin_time, slot_sid, slot_time, ...]], ...]
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::json_crdt::model::Model;
+use crate::json_crdt::nodes::{ArrMoves, CrdtNode, IndexExt, TsKey};
+use crate::json_crdt_patch::clock::{ClockVector, Ts};
+use crate::json_crdt_patch::util::binary::{CrdtReader, CrdtWriter};
+
+const SECTION_GC: u8 = 1;
+const SECTION_MOVES: u8 = 2;
+
+/// `arr` nodes of `model` that have move registers, in ID order.
+fn moved_arrs(model: &Model) -> impl Iterator<Item = (Ts, &ArrMoves)> {
+    model.index.values().filter_map(|node| match node {
+        CrdtNode::Arr(arr) if !arr.moves.origin.is_empty() || !arr.moves.position.is_empty() => {
+            Some((arr.id, &arr.moves))
+        }
+        _ => None,
+    })
+}
+
+/// Install decoded move registers on the `arr` node `id`.
+fn set_moves(model: &mut Model, id: Ts, moves: ArrMoves) -> Result<(), String> {
+    match model.index.get_mut_ts(&id) {
+        Some(CrdtNode::Arr(arr)) => {
+            arr.moves = moves;
+            Ok(())
+        }
+        _ => Err(format!("moves for unknown arr {}.{}", id.sid, id.time)),
+    }
+}
+
+fn clock_entries(clock: &ClockVector) -> impl Iterator<Item = Ts> + '_ {
+    std::iter::once(Ts::new(clock.sid, clock.time)).chain(clock.peers.values().copied())
+}
+
+fn clock_from_entries(mut entries: impl Iterator<Item = Ts>) -> Option<ClockVector> {
+    let local = entries.next()?;
+    let mut clock = ClockVector::new(local.sid, local.time);
+    for peer in entries {
+        clock.observe(peer, 1);
+    }
+    Some(clock)
+}
+
+// ── Binary ────────────────────────────────────────────────────────────────
+
+fn write_ts(w: &mut CrdtWriter, id: Ts) {
+    w.vu57(id.sid);
+    w.vu57(id.time);
+}
+
+fn read_ts(r: &mut CrdtReader) -> Ts {
+    let sid = r.vu57();
+    Ts::new(sid, r.vu57())
+}
+
+fn write_pairs(w: &mut CrdtWriter, map: &BTreeMap<TsKey, Ts>) {
+    w.vu57(map.len() as u64);
+    for (key, value) in map {
+        write_ts(w, Ts::new(key.sid, key.time));
+        write_ts(w, *value);
+    }
+}
+
+fn read_pairs(r: &mut CrdtReader) -> BTreeMap<TsKey, Ts> {
+    let n = r.vu57();
+    let mut map = BTreeMap::new();
+    for _ in 0..n {
+        if r.x >= r.data.len() {
+            break;
+        }
+        let key = read_ts(r);
+        map.insert(TsKey::from(key), read_ts(r));
+    }
+    map
+}
+
+/// Appends the extra sections of `model`, if any, to a binary snapshot.
+pub(crate) fn write_binary(model: &Model, w: &mut CrdtWriter) {
+    if let Some(horizon) = &model.gc_horizon {
+        w.u8(SECTION_GC);
+        w.vu57(horizon.peers.len() as u64 + 1);
+        for entry in clock_entries(horizon) {
+            write_ts(w, entry);
+        }
+    }
+    let arrs: Vec<_> = moved_arrs(model).collect();
+    if !arrs.is_empty() {
+        w.u8(SECTION_MOVES);
+        w.vu57(arrs.len() as u64);
+        for (id, moves) in arrs {
+            write_ts(w, id);
+            write_pairs(w, &moves.origin);
+            write_pairs(w, &moves.position);
+        }
+    }
+}
+
+/// Encodes the extra sections of `model` on their own, or `None` if it has
+/// none.
+pub(crate) fn to_binary(model: &Model) -> Option<Vec<u8>> {
+    let mut w = CrdtWriter::new();
+    write_binary(model, &mut w);
+    let bytes = w.flush();
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+/// Restores the sections written by [`write_binary`] from the bytes that
+/// follow a binary snapshot. Must run after the nodes are decoded.
+pub(crate) fn read_binary(model: &mut Model, data: &[u8]) -> Result<(), String> {
+    let mut r = CrdtReader::new(data);
+    while r.x < data.len() {
+        match r.u8() {
+            SECTION_GC => {
+                let n = r.vu57();
+                let entries: Vec<Ts> = (0..n).map(|_| read_ts(&mut r)).collect();
+                model.gc_horizon =
+                    Some(clock_from_entries(entries.into_iter()).ok_or("empty GC horizon")?);
+            }
+            SECTION_MOVES => {
+                let n = r.vu57();
+                for _ in 0..n {
+                    let id = read_ts(&mut r);
+                    let origin = read_pairs(&mut r);
+                    let position = read_pairs(&mut r);
+                    set_moves(model, id, ArrMoves { origin, position })?;
+                }
+            }
+            tag => return Err(format!("unknown snapshot section {tag}")),
+        }
+        if r.x > data.len() {
+            return Err("truncated snapshot section".into());
+        }
+    }
+    Ok(())
+}
+
+// ── JSON ──────────────────────────────────────────────────────────────────
+
+fn flat_json(ids: impl IntoIterator<Item = Ts>) -> Value {
+    Value::Array(
+        ids.into_iter()
+            .flat_map(|id| [json!(id.sid), json!(id.time)])
+            .collect(),
+    )
+}
+
+fn ids_from_json(value: &Value) -> Result<Vec<Ts>, String> {
+    let flat = value
+        .as_array()
+        .filter(|flat| flat.len() % 2 == 0)
+        .ok_or("expected [sid, time, ...]")?;
+    flat.chunks(2)
+        .map(|pair| match (pair[0].as_u64(), pair[1].as_u64()) {
+            (Some(sid), Some(time)) => Ok(Ts::new(sid, time)),
+            _ => Err("expected [sid, time, ...]".to_string()),
+        })
+        .collect()
+}
+
+fn pairs_json(map: &BTreeMap<TsKey, Ts>) -> Value {
+    flat_json(
+        map.iter()
+            .flat_map(|(key, value)| [Ts::new(key.sid, key.time), *value]),
+    )
+}
+
+fn pairs_from_json(value: &Value) -> Result<BTreeMap<TsKey, Ts>, String> {
+    let ids = ids_from_json(value)?;
+    if ids.len() % 2 != 0 {
+        return Err("expected key/value pairs".into());
+    }
+    Ok(ids
+        .chunks(2)
+        .map(|pair| (TsKey::from(pair[0]), pair[1]))
+        .collect())
+}
+
+/// The extra fields of `model`; empty if it has none.
+pub(crate) fn to_json(model: &Model) -> Map<String, Value> {
+    let mut fields = Map::new();
+    if let Some(horizon) = &model.gc_horizon {
+        fields.insert("gc".into(), flat_json(clock_entries(horizon)));
+    }
+    let moves: Vec<Value> = moved_arrs(model)
+        .map(|(id, moves)| {
+            json!([
+                id.sid,
+                id.time,
+                pairs_json(&moves.origin),
+                pairs_json(&moves.position)
+            ])
+        })
+        .collect();
+    if !moves.is_empty() {
+        fields.insert("moves".into(), Value::Array(moves));
+    }
+    fields
+}
+
+/// Restores the fields written by [`to_json`]; other fields are ignored.
+/// Must run after the nodes are decoded.
+pub(crate) fn read_json(model: &mut Model, fields: &Map<String, Value>) -> Result<(), String> {
+    if let Some(horizon) = fields.get("gc") {
+        let entries = ids_from_json(horizon).map_err(|e| format!("invalid GC horizon: {e}"))?;
+        model.gc_horizon = Some(clock_from_entries(entries.into_iter()).ok_or("empty GC horizon")?);
+    }
+    if let Some(moves) = fields.get("moves") {
+        for entry in moves.as_array().ok_or("invalid moves")? {
+            let (id, origin, position) = match entry.as_array().map(Vec::as_slice) {
+                Some([sid, time, origin, position]) => (
+                    Ts::new(
+                        sid.as_u64().ok_or("invalid moves")?,
+                        time.as_u64().ok_or("invalid moves")?,
+                    ),
+                    pairs_from_json(origin)?,
+                    pairs_from_json(position)?,
+                ),
+                _ => return Err("invalid moves".into()),
+            };
+            set_moves(model, id, ArrMoves { origin, position })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::codec::{indexed, sidecar, structural};
+    use crate::json_crdt::model::ModelApi;
+    use crate::json_crdt_patch::clock::Tss;
+    use crate::json_crdt_patch::patch_builder::PatchBuilder;
+    use serde_json::json;
+
+    fn round_trips(model: &Model) -> Vec<Model> {
+        let (view, meta) = sidecar::binary::encode(model);
+        vec![
+            Model::from_binary(&model.to_binary()).unwrap(),
+            structural::compact::decode(&structural::compact::encode(model)).unwrap(),
+            structural::verbose::decode(&structural::verbose::encode(model)).unwrap(),
+            indexed::binary::decode(&indexed::binary::encode(model)).unwrap(),
+            sidecar::binary::decode(&view, &meta).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn plain_models_have_no_extras() {
+        let mut model = Model::new(100_001);
+        ModelApi::new(&mut model).set_root(&json!([1, 2])).unwrap();
+        assert!(to_binary(&model).is_none());
+        assert!(to_json(&model).is_empty());
+        assert_eq!(
+            structural::compact::encode(&model)
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn restored_replicas_resolve_moves_like_the_original() {
+        let mut model = Model::new(100_001);
+        ModelApi::new(&mut model)
+            .set_root(&json!([1, 2, 3]))
+            .unwrap();
+        let arr_id = model.root.val;
+        let first_slot = match model.index.get(&TsKey::from(arr_id)) {
+            Some(CrdtNode::Arr(arr)) => arr.rga.iter().next().unwrap().id,
+            _ => panic!("expected an arr"),
+        };
+        let mut api = ModelApi::new(&mut model);
+        api.arr_move(arr_id, 0, 2).unwrap();
+        api.apply();
+        assert_eq!(model.view(), json!([2, 3, 1]));
+
+        // A peer that has not seen the move deletes the element by its
+        // original slot.
+        let mut builder = PatchBuilder::new(100_002, 50);
+        builder.del(arr_id, vec![Tss::new(first_slot.sid, first_slot.time, 1)]);
+        let delete = builder.flush();
+
+        let mut expected = model.clone();
+        expected.apply_patch(&delete);
+        assert_eq!(expected.view(), json!([2, 3]));
+        for mut decoded in round_trips(&model) {
+            assert_eq!(decoded.view(), json!([2, 3, 1]));
+            decoded.apply_patch(&delete);
+            assert_eq!(decoded.view(), expected.view());
+        }
+    }
+}
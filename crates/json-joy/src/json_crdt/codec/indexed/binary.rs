@@ -8,6 +8,8 @@
 //! Wire format: a `HashMap<String, Vec<u8>>` where:
 //! - `"c"` → clock table bytes (ClockTable written as [sid, time] pairs)
 //! - `"r"` → (optional) root value timestamp bytes
+//! - `"x"` → (optional) sections not part of the upstream format (see
+//!   [`extras`](crate::json_crdt::codec::extras))
 //! - `"<sidIdx>_<time>"` in base-36 → encoded node bytes
 //!
 //! Each node is encoded using the same CBOR-like binary encoding as the
@@ -23,10 +25,11 @@
 
 use std::collections::HashMap;
 
+use crate::json_crdt::codec::extras;
 use crate::json_crdt::constants::UNDEFINED_TS;
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{
-    ArrNode, BinNode, ConNode, CrdtNode, NodeIndex, ObjNode, StrNode, TsKey, ValNode, VecNode,
+    ArrNode, BinNode, ConNode, CrdtNode, ObjNode, StrNode, TsKey, ValNode, VecNode,
 };
 use crate::json_crdt_patch::clock::{ts as mk_ts, ClockVector, Ts};
 use crate::json_crdt_patch::codec::clock::ClockTable;
@@ -67,6 +70,11 @@ pub fn encode(model: &Model) -> IndexedFields {
         fields.insert("r".to_string(), w.flush());
     }
 
+    // Encode state outside the upstream format
+    if let Some(bytes) = extras::to_binary(model) {
+        fields.insert("x".to_string(), bytes);
+    }
+
     // Encode each node
     for (key, node) in &model.index {
         let id = mk_ts(key.sid, key.time);
@@ -308,12 +316,7 @@ pub fn decode(fields: &IndexedFields) -> Result<Model, DecodeError> {
     for entry in &table.by_idx[1..] {
         clock.observe(*entry, 1);
     }
-    let mut model = Model {
-        root: crate::json_crdt::nodes::RootNode::new(),
-        index: NodeIndex::default(),
-        clock,
-        tick: 0,
-    };
+    let mut model = Model::new_from_clock(clock);
 
     // Decode root reference
     if let Some(root_bytes) = fields.get("r") {
@@ -326,7 +329,7 @@ pub fn decode(fields: &IndexedFields) -> Result<Model, DecodeError> {
 
     // Decode all nodes
     for (field, bytes) in fields {
-        if field == "c" || field == "r" {
+        if field == "c" || field == "r" || field == "x" {
             continue;
         }
         // Parse field name: "<sidIdx>_<time>" in base-36
@@ -335,6 +338,9 @@ pub fn decode(fields: &IndexedFields) -> Result<Model, DecodeError> {
         let node = decode_node(&mut r, id, &table, &model.clock)?;
         model.index.insert(TsKey::from(id), node);
     }
+    if let Some(bytes) = fields.get("x") {
+        extras::read_binary(&mut model, bytes).map_err(DecodeError::Format)?;
+    }
 
     Ok(model)
 }
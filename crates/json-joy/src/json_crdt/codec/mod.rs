@@ -8,6 +8,7 @@
 //! - [`indexed`] — each node separately in a field map
 //! - [`sidecar`] — view bytes + metadata bytes split
 
+pub mod extras;
 pub mod indexed;
 pub mod sidecar;
 pub mod structural;
@@ -16,7 +16,11 @@
 //!
 //! The decoder reconstructs the document by replaying the meta stream and
 //! reading view values from the view stream at the appropriate positions.
+//!
+//! The clock table may be followed by sections that upstream decoders
+//! ignore (see [`extras`](crate::json_crdt::codec::extras)).
 
+use crate::json_crdt::codec::extras;
 use crate::json_crdt::constants::UNDEFINED_TS;
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{
@@ -71,6 +75,7 @@ pub fn encode(model: &Model) -> (Vec<u8>, Vec<u8>) {
         meta_w.vu57(flat[i + 1]);
         i += 2;
     }
+    extras::write_binary(model, &mut meta_w);
 
     (view_w.flush(), meta_w.flush())
 }
@@ -446,12 +451,15 @@ pub fn decode(view: &[u8], meta: &[u8]) -> Result<Model, DecodeError> {
     }
     let clock = cd.clock.clone();
     let mut model = Model::new_from_clock(clock);
+    let extras_start = meta_r.x;
 
     // Return to tree start
     meta_r.x = tree_start;
 
     let root = decode_root(&mut view_r, &mut meta_r, &mut model, &cd)?;
     model.root.val = root;
+    extras::read_binary(&mut model, meta.get(extras_start..).unwrap_or_default())
+        .map_err(DecodeError::Format)?;
     Ok(model)
 }
 
@@ -15,6 +15,9 @@
 //! [0x80] [vu57 server_time] [tree of nodes]
 //! ```
 //!
+//! Either format may be followed by sections that upstream decoders ignore
+//! (see [`extras`](crate::json_crdt::codec::extras)).
+//!
 //! Each node starts with an encoded timestamp, then a type-length byte:
 //! - Bits 7-5: CRDT major type (0=con, 1=val, 2=obj, 3=vec, 4=str, 5=bin, 6=arr)
 //! - Bits 4-0: inline length (0-30), or 31 = read extended vu57
@@ -22,6 +25,7 @@
 //! Timestamps in logical mode are encoded via `writer.id(session_index, time_diff)`.
 //! Timestamps in server mode are encoded as plain `vu57(time)`.
 
+use crate::json_crdt::codec::extras;
 use crate::json_crdt::constants::UNDEFINED_TS;
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{
@@ -64,6 +68,7 @@ fn encode_server(model: &Model, w: &mut CrdtWriter) {
     w.u8(0x80);
     w.vu57(server_time);
     encode_root_server(model, w, server_time);
+    extras::write_binary(model, w);
 }
 
 fn encode_logical(model: &Model, w: &mut CrdtWriter) {
@@ -96,6 +101,7 @@ fn encode_logical(model: &Model, w: &mut CrdtWriter) {
         w.vu57(flat[i + 1]); // time
         i += 2;
     }
+    extras::write_binary(model, w);
 }
 
 fn encode_root_server(model: &Model, w: &mut CrdtWriter, server_time: u64) {
@@ -475,6 +481,8 @@ fn decode_server(data: &[u8]) -> Result<Model, DecodeError> {
     let mut model = Model::new_server(server_time);
     let root = decode_root_server(&mut r, &mut model, server_time)?;
     model.root.val = root;
+    extras::read_binary(&mut model, data.get(r.x..).unwrap_or_default())
+        .map_err(DecodeError::Format)?;
     Ok(model)
 }
 
@@ -513,12 +521,15 @@ fn decode_logical(data: &[u8]) -> Result<Model, DecodeError> {
     }
     let clock = cd.clock.clone();
     let mut model = Model::new_from_clock(clock);
+    let extras_start = r.x;
 
     // Return to tree position
     r.x = tree_start;
 
     let root = decode_root_logical(&mut r, &mut model, &cd)?;
     model.root.val = root;
+    extras::read_binary(&mut model, data.get(extras_start..).unwrap_or_default())
+        .map_err(DecodeError::Format)?;
     Ok(model)
 }
 
@@ -10,6 +10,10 @@
 //! [clock_table_or_server_time, root_node_or_0]
 //! ```
 //!
+//! A third element, an object of state not part of the upstream format
+//! (see [`extras`](crate::json_crdt::codec::extras)), is appended when
+//! there is any; upstream decoders ignore it.
+//!
 //! `clock_table_or_server_time`:
 //! - A plain integer → server-clock mode (value is the server time).
 //! - An array of numbers `[sid, time, sid, time, ...]` → logical-clock mode.
@@ -21,6 +25,8 @@
 
 use serde_json::{json, Value};
 
+use super::con_bytes_from_json;
+use crate::json_crdt::codec::extras;
 use crate::json_crdt::constants::UNDEFINED_TS;
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{
@@ -46,8 +52,19 @@ const ARR: u8 = JsonCrdtDataType::Arr as u8;
 
 /// Encode a [`Model`] to the compact JSON format.
 ///
-/// Returns a 2-element JSON array `[clock, root]`.
+/// Returns a JSON array `[clock, root]`, or `[clock, root, extras]`.
 pub fn encode(model: &Model) -> Value {
+    let fields = extras::to_json(model);
+    match encode_doc(model) {
+        Value::Array(mut doc) if !fields.is_empty() => {
+            doc.push(Value::Object(fields));
+            Value::Array(doc)
+        }
+        doc => doc,
+    }
+}
+
+fn encode_doc(model: &Model) -> Value {
     let is_server = model.clock.sid == SESSION::SERVER;
     if is_server {
         let server_time = model.clock.time;
@@ -142,6 +159,10 @@ fn encode_con(node: &ConNode, state: &mut EncodeState) -> Value {
                     // undefined: [CON, id, 0, 0]
                     json!([CON, id, 0, 0])
                 }
+                PackValue::Bytes(_) => {
+                    // binary: [CON, id, 1, data_uri]
+                    json!([CON, id, 1, serde_json::Value::from(pv.clone())])
+                }
                 _ => {
                     let v = serde_json::Value::from(pv.clone());
                     json!([CON, id, v])
@@ -309,6 +330,12 @@ pub fn decode(data: &Value) -> Result<Model, DecodeError> {
         let node_id = decode_node_into(root_val, &mut model, &mut dec)?;
         model.root.val = node_id;
     }
+    if let Some(fields) = arr.get(2) {
+        let fields = fields
+            .as_object()
+            .ok_or_else(|| DecodeError::Format("expected extras object".into()))?;
+        extras::read_json(&mut model, fields).map_err(DecodeError::Format)?;
+    }
 
     Ok(model)
 }
@@ -410,9 +437,12 @@ fn decode_con(
     let id = state.decode_ts(&arr[1])?;
 
     let val = if arr.len() > 3 {
-        // Special: arr[2] == 0 and arr[3] is either 0 (undefined) or a ts
+        // Special: arr[2] == 1 and arr[3] is binary data, or arr[2] == 0 and
+        // arr[3] is either 0 (undefined) or a ts
         let special = &arr[3];
-        if special.as_u64() == Some(0) {
+        if arr[2].as_u64() == Some(1) {
+            ConValue::Val(con_bytes_from_json(special).map_err(DecodeError::Format)?)
+        } else if special.as_u64() == Some(0) {
             ConValue::Val(PackValue::Undefined)
         } else {
             let ref_ts = state.decode_ts(special)?;
@@ -680,6 +710,30 @@ mod tests {
         model
     }
 
+    #[test]
+    fn roundtrip_con_bytes_and_data_uri_strings() {
+        let s = sid();
+        let uri = "data:application/octet-stream;base64,AQI=".to_string();
+        let values = [PackValue::Bytes(vec![1, 2]), PackValue::Str(uri)];
+        for (i, pv) in values.into_iter().enumerate() {
+            let mut model = Model::new(s);
+            model.apply_operation(&Op::NewCon {
+                id: ts(s, 1),
+                val: ConValue::Val(pv.clone()),
+            });
+            model.apply_operation(&Op::InsVal {
+                id: ts(s, 2),
+                obj: crate::json_crdt::constants::ORIGIN,
+                val: ts(s, 1),
+            });
+            let decoded = decode(&encode(&model)).expect("decode should succeed");
+            match decoded.index.get(&TsKey::from(ts(s, 1))) {
+                Some(CrdtNode::Con(con)) => assert_eq!(con.val, ConValue::Val(pv), "value {i}"),
+                _ => panic!("expected a con node"),
+            }
+        }
+    }
+
     #[test]
     fn encode_empty_model() {
         let model = Model::new(sid());
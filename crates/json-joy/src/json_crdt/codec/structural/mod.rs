@@ -6,3 +6,22 @@ pub mod binary;
 pub mod compact;
 pub mod compact_binary;
 pub mod verbose;
+
+use json_joy_base64::from_base64;
+use json_joy_json_pack::PackValue;
+use serde_json::Value;
+
+/// Decodes a binary `con` value written by a JSON codec.
+///
+/// The `PackValue` → JSON conversion writes bytes as a data URI, which is
+/// indistinguishable from a string; each codec marks binary constants in its
+/// own way and only calls this for marked values.
+fn con_bytes_from_json(value: &Value) -> Result<PackValue, String> {
+    let b64 = value
+        .as_str()
+        .and_then(|s| s.strip_prefix("data:application/octet-stream;base64,"))
+        .ok_or("binary con value is not a data URI")?;
+    from_base64(b64)
+        .map(PackValue::Bytes)
+        .map_err(|e| format!("base64 decode error: {}", e))
+}
@@ -13,10 +13,16 @@
 //!   "root": { "type": "val", "id": ..., "value": <node> }
 //! }
 //! ```
+//!
+//! State not part of the upstream format is added as `"gc"` and `"moves"`
+//! fields (see [`extras`](crate::json_crdt::codec::extras)); upstream
+//! decoders ignore them.
 
 use json_joy_base64::{from_base64, to_base64};
 use serde_json::{json, Value};
 
+use super::con_bytes_from_json;
+use crate::json_crdt::codec::extras;
 use crate::json_crdt::constants::UNDEFINED_TS;
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{
@@ -39,7 +45,11 @@ pub fn encode(model: &Model) -> Value {
     };
 
     let root = encode_val_root(model);
-    json!({ "time": time, "root": root })
+    let mut doc = json!({ "time": time, "root": root });
+    if let Value::Object(doc) = &mut doc {
+        doc.extend(extras::to_json(model));
+    }
+    doc
 }
 
 fn encode_clock(clock: &ClockVector) -> Value {
@@ -117,6 +127,10 @@ fn encode_con(node: &ConNode) -> Value {
             PackValue::Undefined => {
                 json!({ "type": "con", "id": id })
             }
+            PackValue::Bytes(_) => {
+                let v = serde_json::Value::from(pv.clone());
+                json!({ "type": "con", "id": id, "binary": true, "value": v })
+            }
             _ => {
                 let v = serde_json::Value::from(pv.clone());
                 json!({ "type": "con", "id": id, "value": v })
@@ -267,6 +281,7 @@ pub fn decode(data: &Value) -> Result<Model, DecodeError> {
     };
 
     decode_root(root_val, &mut model)?;
+    extras::read_json(&mut model, obj).map_err(DecodeError::Format)?;
     Ok(model)
 }
 
@@ -365,6 +380,11 @@ fn decode_con(val: &Value, model: &mut Model) -> Result<Ts, DecodeError> {
             .ok_or_else(|| DecodeError::MissingField("con.value (timestamp)".into()))?;
         let ref_ts = decode_ts(ts_val)?;
         ConValue::Ref(ref_ts)
+    } else if obj.get("binary").and_then(|v| v.as_bool()).unwrap_or(false) {
+        let v = obj
+            .get("value")
+            .ok_or_else(|| DecodeError::MissingField("con.value (binary)".into()))?;
+        ConValue::Val(con_bytes_from_json(v).map_err(DecodeError::Format)?)
     } else {
         match obj.get("value") {
             None => ConValue::Val(PackValue::Undefined),
@@ -636,6 +656,30 @@ mod tests {
         assert_eq!(decoded.view(), view);
     }
 
+    #[test]
+    fn roundtrip_con_bytes_and_data_uri_strings() {
+        let s = sid();
+        let uri = "data:application/octet-stream;base64,AQI=".to_string();
+        let values = [PackValue::Bytes(vec![1, 2]), PackValue::Str(uri)];
+        for (i, pv) in values.into_iter().enumerate() {
+            let mut model = Model::new(s);
+            model.apply_operation(&Op::NewCon {
+                id: ts(s, 1),
+                val: ConValue::Val(pv.clone()),
+            });
+            model.apply_operation(&Op::InsVal {
+                id: ts(s, 2),
+                obj: crate::json_crdt::constants::ORIGIN,
+                val: ts(s, 1),
+            });
+            let decoded = decode(&encode(&model)).expect("decode should succeed");
+            match decoded.index.get(&TsKey::from(ts(s, 1))) {
+                Some(CrdtNode::Con(con)) => assert_eq!(con.val, ConValue::Val(pv), "value {i}"),
+                _ => panic!("expected a con node"),
+            }
+        }
+    }
+
     #[test]
     fn roundtrip_con_number() {
         let mut model = Model::new(sid());
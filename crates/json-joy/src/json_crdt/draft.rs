@@ -6,7 +6,7 @@
 //!
 //! - `base`  — committed state (what has been saved / accepted by remote).
 //! - `head`  — current editing state (`base` + applied head patches).
-//! - `tip`   — future patches queued ahead of `head` (for undo/redo).
+//! - `tip`   — redo buffer: undo patches which `redo` can revert.
 //!
 //! # Overview
 //!
@@ -14,15 +14,18 @@
 //! buffer of a JSON CRDT document:
 //!
 //! 1. `base` starts as a clone of the provided model.
-//! 2. Each patch in `head_patches` is applied to `head` so that the head
-//!    reflects the user's current edits.
-//! 3. `tip` holds future patches not yet applied to `head` (for redo).
+//! 2. Each local patch is applied to `head` and kept in `patches` until it is
+//!    committed to `base` with [`Draft::advance`].
+//! 3. [`Draft::undo`] reverts the latest undoable local patch by applying an
+//!    inverse patch (built the same way as [`Log::undo`](crate::json_crdt::log::Log::undo))
+//!    and pushes that inverse onto `tip`; [`Draft::redo`] reverts the inverse.
 //!
 //! The `rebase` method applies a batch of remote patches to both `base` and
-//! `head`, keeping the editing state up-to-date. The `advance`, `undo`, and
-//! `redo` methods are stubs reserved for future implementation.
+//! `head`, keeping the editing state up-to-date.
 
+use crate::json_crdt::log::undo_patch;
 use crate::json_crdt::model::Model;
+use crate::json_crdt_patch::clock::Ts;
 use crate::json_crdt_patch::patch::Patch;
 
 /// Draft state machine.
@@ -37,16 +40,26 @@ pub struct Draft {
     /// The current editing head (base + head patches applied).
     pub head: Model,
 
-    /// Future patches queued ahead of `head` (undo/redo buffer).
+    /// Local patches applied to `head` but not yet committed to `base`, in
+    /// application order. Includes patches generated by `undo` and `redo`.
+    pub patches: Vec<Patch>,
+
+    /// Redo buffer — undo patches (each also present in `patches`) which can
+    /// be reverted by `redo`, most recent last.
     pub tip: Vec<Patch>,
+
+    /// IDs of patches in `patches` which can be reverted by `undo`, most
+    /// recent last.
+    undo_stack: Vec<Ts>,
 }
 
 impl Draft {
     /// Creates a new `Draft`.
     ///
     /// - `base` is cloned to produce an independent `head`.
-    /// - Each patch in `head_patches` is applied to `head` in order.
-    /// - `tip` is stored as-is for future undo/redo use.
+    /// - Each patch in `head_patches` is applied to `head` in order and
+    ///   becomes undoable.
+    /// - `tip` is stored as-is as the redo buffer.
     ///
     /// Mirrors `new Draft({ base, head, tip })` in upstream TypeScript.
     pub fn new(base: Model, head_patches: Vec<Patch>, tip: Vec<Patch>) -> Self {
@@ -54,7 +67,28 @@ impl Draft {
         for patch in &head_patches {
             head.apply_patch(patch);
         }
-        Self { base, head, tip }
+        let undo_stack = head_patches.iter().filter_map(Patch::get_id).collect();
+        Self {
+            base,
+            head,
+            patches: head_patches,
+            tip,
+            undo_stack,
+        }
+    }
+
+    /// Applies a local patch to `head` and records it as undoable.
+    ///
+    /// Any pending redo history in `tip` is discarded. Empty patches are
+    /// ignored.
+    pub fn apply(&mut self, patch: Patch) {
+        let Some(id) = patch.get_id() else {
+            return;
+        };
+        self.head.apply_patch(&patch);
+        self.patches.push(patch);
+        self.undo_stack.push(id);
+        self.tip.clear();
     }
 
     /// Applies a batch of remote patches to both `base` and `head`.
@@ -70,20 +104,83 @@ impl Draft {
         }
     }
 
-    /// Stub — reserved for future implementation.
+    /// Commits the first `index` uncommitted local patches to `base`.
+    ///
+    /// `index` is clamped to the number of uncommitted patches. Committed
+    /// patches can no longer be undone or redone.
     ///
     /// Mirrors `Draft.advance(index)` in upstream TypeScript.
-    pub fn advance(&mut self, _index: usize) {}
+    pub fn advance(&mut self, index: usize) {
+        let index = index.min(self.patches.len());
+        if index == 0 {
+            return;
+        }
+        for patch in self.patches.drain(..index) {
+            self.base.apply_patch(&patch);
+        }
+        let patches = &self.patches;
+        let is_pending = |id: Ts| patches.iter().any(|p| p.get_id() == Some(id));
+        self.undo_stack.retain(|id| is_pending(*id));
+        self.tip.retain(|p| p.get_id().is_some_and(is_pending));
+    }
 
-    /// Stub — reserved for future implementation.
+    /// Reverts the most recent undoable local patch.
+    ///
+    /// The inverse patch is applied to `head`, recorded in `patches` and
+    /// pushed onto `tip` so it can be redone. Patches with nothing left to
+    /// revert are skipped. Returns the inverse patch, or `None` when there is
+    /// nothing left to undo.
     ///
     /// Mirrors `Draft.undo()` in upstream TypeScript.
-    pub fn undo(&mut self) {}
+    pub fn undo(&mut self) -> Option<Patch> {
+        while let Some(id) = self.undo_stack.pop() {
+            if let Some(inverse) = self.invert(id) {
+                self.tip.push(inverse.clone());
+                return Some(inverse);
+            }
+        }
+        None
+    }
 
-    /// Stub — reserved for future implementation.
+    /// Reverts the most recent undo patch in `tip`.
+    ///
+    /// The resulting patch is applied to `head`, recorded in `patches` and
+    /// becomes undoable again. Undo patches with nothing left to revert are
+    /// skipped. Returns the patch, or `None` when `tip` is empty.
     ///
     /// Mirrors `Draft.redo()` in upstream TypeScript.
-    pub fn redo(&mut self) {}
+    pub fn redo(&mut self) -> Option<Patch> {
+        while let Some(undo) = self.tip.pop() {
+            let Some(inverse) = undo.get_id().and_then(|id| self.invert(id)) else {
+                continue;
+            };
+            if let Some(id) = inverse.get_id() {
+                self.undo_stack.push(id);
+            }
+            return Some(inverse);
+        }
+        None
+    }
+
+    /// Builds the inverse of the uncommitted patch `id` against the state
+    /// that preceded it, then applies and records it.
+    fn invert(&mut self, id: Ts) -> Option<Patch> {
+        let index = self.patches.iter().position(|p| p.get_id() == Some(id))?;
+        let (base, patches) = (&self.base, &self.patches);
+        let inverse = undo_patch(&patches[index], &self.head.clock, || {
+            let mut model = base.clone();
+            for patch in &patches[..index] {
+                model.apply_patch(patch);
+            }
+            model
+        });
+        if inverse.ops.is_empty() {
+            return None;
+        }
+        self.head.apply_patch(&inverse);
+        self.patches.push(inverse.clone());
+        Some(inverse)
+    }
 }
 
 // ──────────────────────────────────────────────────────────────────────────────
@@ -94,8 +191,9 @@ impl Draft {
 mod tests {
     use super::*;
     use crate::json_crdt::constants::ORIGIN;
-    use crate::json_crdt_patch::clock::ts;
+    use crate::json_crdt_patch::clock::{ts, tss};
     use crate::json_crdt_patch::operations::{ConValue, Op};
+    use crate::json_crdt_patch::patch_builder::PatchBuilder;
     use json_joy_json_pack::PackValue;
     use serde_json::json;
 
@@ -257,16 +355,177 @@ mod tests {
         assert_eq!(draft.head.view(), json!({"foo": "bar", "x": 1, "y": 2}));
     }
 
-    // ── Stub methods ──────────────────────────────────────────────────────
+    // ── Draft::undo / Draft::redo / Draft::advance ────────────────────────
+
+    /// Builds a local patch against the current `head` clock.
+    fn local(draft: &Draft, f: impl FnOnce(&mut PatchBuilder)) -> Patch {
+        let mut builder = PatchBuilder::new(draft.head.clock.sid, draft.head.clock.time);
+        f(&mut builder);
+        builder.flush()
+    }
+
+    #[test]
+    fn undo_and_redo_string_insert() {
+        let s = sid();
+        let mut draft = Draft::new(make_base(), vec![], vec![]);
+        let patch = local(&draft, |b| {
+            b.ins_str(ts(s, 2), ts(s, 5), "!".to_string());
+        });
+        draft.apply(patch);
+        assert_eq!(draft.head.view(), json!({"foo": "bar!"}));
+
+        assert!(draft.undo().is_some());
+        assert_eq!(draft.head.view(), json!({"foo": "bar"}));
+        assert_eq!(draft.tip.len(), 1);
+
+        assert!(draft.redo().is_some());
+        assert_eq!(draft.head.view(), json!({"foo": "bar!"}));
+        assert!(draft.tip.is_empty());
+
+        // The redo is itself undoable.
+        draft.undo();
+        assert_eq!(draft.head.view(), json!({"foo": "bar"}));
+        // Base never sees uncommitted edits.
+        assert_eq!(draft.base.view(), json!({"foo": "bar"}));
+    }
+
+    #[test]
+    fn undo_and_redo_string_delete() {
+        let s = sid();
+        let mut draft = Draft::new(make_base(), vec![], vec![]);
+        let patch = local(&draft, |b| {
+            b.del(ts(s, 2), vec![tss(s, 4, 2)]);
+        });
+        draft.apply(patch);
+        assert_eq!(draft.head.view(), json!({"foo": "b"}));
+        draft.undo();
+        assert_eq!(draft.head.view(), json!({"foo": "bar"}));
+        draft.redo();
+        assert_eq!(draft.head.view(), json!({"foo": "b"}));
+    }
+
+    #[test]
+    fn undo_reverts_patches_in_reverse_order() {
+        let s = sid();
+        let mut draft = Draft::new(make_base(), vec![], vec![]);
+        let p1 = local(&draft, |b| {
+            let id = b.con_val(PackValue::Integer(1));
+            b.ins_obj(ts(s, 1), vec![("x".to_string(), id)]);
+        });
+        draft.apply(p1);
+        let p2 = local(&draft, |b| {
+            let id = b.con_val(PackValue::Integer(2));
+            b.ins_obj(ts(s, 1), vec![("x".to_string(), id)]);
+        });
+        draft.apply(p2);
+        assert_eq!(draft.head.view(), json!({"foo": "bar", "x": 2}));
+        draft.undo();
+        assert_eq!(draft.head.view(), json!({"foo": "bar", "x": 1}));
+        draft.undo();
+        assert_eq!(draft.head.view(), json!({"foo": "bar"}));
+        assert!(draft.undo().is_none());
+        draft.redo();
+        draft.redo();
+        assert_eq!(draft.head.view(), json!({"foo": "bar", "x": 2}));
+        assert!(draft.redo().is_none());
+    }
+
+    #[test]
+    fn apply_clears_redo_tip() {
+        let s = sid();
+        let mut draft = Draft::new(make_base(), vec![], vec![]);
+        let p1 = local(&draft, |b| {
+            b.ins_str(ts(s, 2), ts(s, 5), "!".to_string());
+        });
+        draft.apply(p1);
+        draft.undo();
+        assert_eq!(draft.tip.len(), 1);
+        let p2 = local(&draft, |b| {
+            b.ins_str(ts(s, 2), ts(s, 5), "?".to_string());
+        });
+        draft.apply(p2);
+        assert!(draft.tip.is_empty());
+        assert_eq!(draft.head.view(), json!({"foo": "bar?"}));
+    }
+
+    #[test]
+    fn undo_skips_patches_with_nothing_to_revert() {
+        let s = sid();
+        let mut draft = Draft::new(make_base(), vec![], vec![]);
+        let p1 = local(&draft, |b| {
+            b.ins_str(ts(s, 2), ts(s, 5), "!".to_string());
+        });
+        draft.apply(p1);
+        // Creates a node without attaching it: the inverse is empty.
+        let p2 = local(&draft, |b| {
+            b.con_val(PackValue::Integer(1));
+        });
+        draft.apply(p2);
+
+        assert!(draft.undo().is_some());
+        assert_eq!(draft.head.view(), json!({"foo": "bar"}));
+        assert!(draft.undo().is_none());
+        assert!(draft.redo().is_some());
+        assert_eq!(draft.head.view(), json!({"foo": "bar!"}));
+    }
+
+    #[test]
+    fn undo_survives_remote_rebase() {
+        let s = sid();
+        let remote = 333_333;
+        let mut draft = Draft::new(make_base(), vec![], vec![]);
+        let patch = local(&draft, |b| {
+            b.ins_str(ts(s, 2), ts(s, 5), "!".to_string());
+        });
+        draft.apply(patch);
+
+        let mut builder = PatchBuilder::new(remote, 50);
+        builder.ins_str(ts(s, 2), ts(s, 2), ">".to_string());
+        draft.rebase(&[builder.flush()]);
+        assert_eq!(draft.head.view(), json!({"foo": ">bar!"}));
+
+        draft.undo();
+        assert_eq!(draft.head.view(), json!({"foo": ">bar"}));
+        assert_eq!(draft.base.view(), json!({"foo": ">bar"}));
+    }
 
     #[test]
-    fn advance_undo_redo_do_not_panic() {
+    fn advance_commits_patches_to_base() {
+        let s = sid();
+        let mut draft = Draft::new(make_base(), vec![], vec![]);
+        let p1 = local(&draft, |b| {
+            b.ins_str(ts(s, 2), ts(s, 5), "!".to_string());
+        });
+        draft.apply(p1);
+        let p2 = local(&draft, |b| {
+            b.ins_str(ts(s, 2), ts(s, 2), "?".to_string());
+        });
+        draft.apply(p2);
+
+        draft.advance(1);
+        assert_eq!(draft.base.view(), json!({"foo": "bar!"}));
+        assert_eq!(draft.head.view(), json!({"foo": "?bar!"}));
+        assert_eq!(draft.patches.len(), 1);
+
+        // Only the uncommitted patch can still be undone.
+        draft.undo();
+        assert_eq!(draft.head.view(), json!({"foo": "bar!"}));
+        assert!(draft.undo().is_none());
+
+        // Advancing past the end commits everything, including the undo.
+        draft.advance(usize::MAX);
+        assert!(draft.patches.is_empty());
+        assert!(draft.tip.is_empty());
+        assert_eq!(draft.base.view(), json!({"foo": "bar!"}));
+    }
+
+    #[test]
+    fn advance_undo_redo_on_empty_draft_are_noops() {
         let base = make_base();
         let mut draft = Draft::new(base, vec![], vec![]);
         draft.advance(0);
-        draft.undo();
-        draft.redo();
-        // Still alive with unchanged views.
+        assert!(draft.undo().is_none());
+        assert!(draft.redo().is_none());
         assert_eq!(draft.base.view(), json!({"foo": "bar"}));
     }
 }
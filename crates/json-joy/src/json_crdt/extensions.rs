@@ -20,12 +20,38 @@
 //! └─ 1: any   (extension payload)
 //! ```
 //!
+//! The header bytes let a reader tell an extension node apart from a plain
+//! `vec` that happens to start with a binary constant: the last two bytes
+//! must match the `vec` node's own ID. Because extension nodes are ordinary
+//! `vec` nodes, every structural codec round-trips them unchanged.
+//!
+//! # Custom extensions
+//!
+//! User-defined CRDT types implement [`AnyExtension`] — an ID, a node-shape
+//! schema ([`AnyExtension::schema`]) and a view function
+//! ([`AnyExtension::view`]) — and are registered on a model's
+//! [`Model::extensions`](super::Model::extensions) registry. [`ModelApi::ext_node`] builds a new
+//! extension node and [`ExtHandle`] is the hook for a typed API object,
+//! obtained through [`NodeApi::as_ext`](crate::json_crdt::model::api::nodes::NodeApi::as_ext).
+//! Decoders return models with an empty registry; re-attach it after
+//! decoding.
+//!
 //! The concrete extension implementations (cnt, mval, peritext) live in
 //! `crate::json_crdt_extensions`; this module provides only the framework
 //! types that the model layer uses for lookup and dispatch.
 
+use std::fmt;
+
+use json_joy_json_pack::PackValue;
 use serde_json::Value;
 
+use super::model::ModelApi;
+use super::nodes::{CrdtNode, IndexExt, NodeIndex};
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::operations::ConValue;
+use crate::json_crdt_patch::patch_builder::PatchBuilder;
+use crate::json_crdt_patch::schema::{self, NodeBuilder};
+
 // ── ExtNode trait ─────────────────────────────────────────────────────────
 
 /// Trait implemented by every extension node type.
@@ -67,6 +93,119 @@ pub trait AnyExtension: Send + Sync {
 
     /// The human-readable name of the extension.
     fn name(&self) -> &str;
+
+    /// Schema of the payload node for a new extension node initialised from
+    /// `value`.  Defaults to a `con` holding the value.
+    ///
+    /// Mirrors `Extension.schema` in `Extension.ts`.
+    fn schema(&self, value: &Value) -> Box<dyn NodeBuilder> {
+        Box::new(schema::ConNode {
+            raw: PackValue::from(value.clone()),
+        })
+    }
+
+    /// View of an extension node whose payload is the node `data`.  Defaults
+    /// to the payload's own view.
+    ///
+    /// Mirrors `ExtNode.view()` in `ExtNode.ts`.
+    fn view(&self, extensions: &Extensions, index: &NodeIndex, data: Ts) -> Value {
+        extensions.view_node(index, data)
+    }
+}
+
+// ── ExtHandle trait ───────────────────────────────────────────────────────
+
+/// Typed API object for an extension node.
+///
+/// Mirrors `Extension.Api` in `Extension.ts`: implementors wrap a mutable
+/// [`ModelApi`] borrow together with the extension node they operate on.
+pub trait ExtHandle<'m, 'a>: Sized {
+    /// Extension ID the wrapped node must carry.
+    const EXT_ID: u8;
+
+    /// Wrap the extension node `node`.
+    fn wrap(api: &'m mut ModelApi<'a>, node: ExtRef) -> Self;
+}
+
+// ── Extension nodes ───────────────────────────────────────────────────────
+
+/// Location of an extension node in the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtRef {
+    /// ID of the `vec` node holding the extension.
+    pub id: Ts,
+    /// ID of the payload node (slot 1 of the `vec`).
+    pub data: Ts,
+}
+
+impl ExtRef {
+    /// If `id` is an extension node, returns its extension ID and location.
+    pub fn of(index: &NodeIndex, id: Ts) -> Option<(u8, ExtRef)> {
+        let Some(CrdtNode::Vec(vec)) = IndexExt::get(index, &id) else {
+            return None;
+        };
+        let header = (*vec.elements.first()?)?;
+        let data = (*vec.elements.get(1)?)?;
+        let Some(CrdtNode::Con(con)) = IndexExt::get(index, &header) else {
+            return None;
+        };
+        match &con.val {
+            ConValue::Val(PackValue::Bytes(bytes)) => match bytes.as_slice() {
+                [ext_id, ..] if *bytes == ext_header(*ext_id, id) => {
+                    Some((*ext_id, ExtRef { id, data }))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Header bytes of the extension node with `vec` ID `id`.
+fn ext_header(ext_id: u8, id: Ts) -> Vec<u8> {
+    vec![ext_id, (id.sid % 256) as u8, (id.time % 256) as u8]
+}
+
+/// Schema of an extension node: a `vec` holding the header and `data`.
+///
+/// Mirrors the `ext` schema node in `json-crdt-patch/schema.ts`.
+#[derive(Debug)]
+pub struct ExtNodeSchema {
+    pub ext_id: u8,
+    pub data: Box<dyn NodeBuilder>,
+}
+
+impl NodeBuilder for ExtNodeSchema {
+    fn build(&self, builder: &mut PatchBuilder) -> Ts {
+        let vec_id = builder.vec();
+        let header = builder.con_val(PackValue::Bytes(ext_header(self.ext_id, vec_id)));
+        let data = self.data.build(builder);
+        builder.ins_vec(vec_id, vec![(0, header), (1, data)]);
+        vec_id
+    }
+}
+
+impl<'a> ModelApi<'a> {
+    /// Queues the creation of a new `ext` extension node initialised from
+    /// `value` and returns its ID.  Insert the ID somewhere in the document
+    /// and call [`ModelApi::apply`].
+    pub fn ext_node(&mut self, ext: &dyn AnyExtension, value: &Value) -> Ts {
+        ExtNodeSchema {
+            ext_id: ext.id() as u8,
+            data: ext.schema(value),
+        }
+        .build(&mut self.builder)
+    }
+}
+
+// ── Error type ────────────────────────────────────────────────────────────
+
+/// Errors returned by [`Extensions::register`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExtensionError {
+    /// The extension ID does not fit in the 8-bit node header.
+    #[error("INVALID_EXTENSION_ID")]
+    InvalidId(u32),
 }
 
 // ── Extensions registry ───────────────────────────────────────────────────
@@ -92,8 +231,16 @@ impl Extensions {
 
     /// Register an extension.  If an extension with the same ID was already
     /// registered it is silently replaced.
-    pub fn register(&mut self, ext: Box<dyn AnyExtension>) {
-        self.ext.insert(ext.id(), ext);
+    ///
+    /// Fails with [`ExtensionError::InvalidId`] if the extension ID does not
+    /// fit in 8 bits.
+    pub fn register(&mut self, ext: Box<dyn AnyExtension>) -> Result<(), ExtensionError> {
+        let id = ext.id();
+        if id > 255 {
+            return Err(ExtensionError::InvalidId(id));
+        }
+        self.ext.insert(id, ext);
+        Ok(())
     }
 
     /// Look up an extension by its numeric ID.
@@ -118,6 +265,61 @@ impl Extensions {
     pub fn clone_empty(&self) -> Self {
         Self::new()
     }
+
+    /// JSON view of the node `id`, rendering registered extension nodes
+    /// through [`AnyExtension::view`] and everything else as
+    /// [`CrdtNode::view`] does.
+    pub fn view_node(&self, index: &NodeIndex, id: Ts) -> Value {
+        if let Some((ext_id, node)) = ExtRef::of(index, id) {
+            if let Some(ext) = self.get(ext_id as u32) {
+                return ext.view(self, index, node.data);
+            }
+        }
+        match IndexExt::get(index, &id) {
+            Some(CrdtNode::Val(val)) => self.view_node(index, val.val),
+            Some(CrdtNode::Obj(obj)) => {
+                let mut map = serde_json::Map::new();
+                for (key, &child) in &obj.keys {
+                    match IndexExt::get(index, &child) {
+                        Some(CrdtNode::Con(con))
+                            if matches!(con.val, ConValue::Val(PackValue::Undefined)) => {}
+                        Some(_) => {
+                            map.insert(key.clone(), self.view_node(index, child));
+                        }
+                        None => {}
+                    }
+                }
+                Value::Object(map)
+            }
+            Some(CrdtNode::Vec(vec)) => Value::Array(
+                vec.elements
+                    .iter()
+                    .map(|slot| match slot {
+                        Some(child) => self.view_node(index, *child),
+                        None => Value::Null,
+                    })
+                    .collect(),
+            ),
+            Some(CrdtNode::Arr(arr)) => Value::Array(
+                arr.rga
+                    .iter_live()
+                    .filter_map(|chunk| chunk.data.as_ref())
+                    .flatten()
+                    .map(|child| self.view_node(index, *child))
+                    .collect(),
+            ),
+            Some(node) => node.view(index),
+            None => Value::Null,
+        }
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids: Vec<_> = self.ext.iter().map(|(id, e)| (*id, e.name())).collect();
+        ids.sort_unstable();
+        f.debug_map().entries(ids).finish()
+    }
 }
 
 // ── Tests ─────────────────────────────────────────────────────────────────
@@ -162,7 +364,8 @@ mod tests {
         exts.register(Box::new(MockExt {
             id: 42,
             name: "mock",
-        }));
+        }))
+        .unwrap();
         assert!(exts.get(42).is_some());
         assert_eq!(exts.get(42).unwrap().name(), "mock");
         assert_eq!(exts.get(42).unwrap().id(), 42);
@@ -178,9 +381,11 @@ mod tests {
     fn extensions_size_tracks_registrations() {
         let mut exts = Extensions::new();
         assert_eq!(exts.size(), 0);
-        exts.register(Box::new(MockExt { id: 1, name: "a" }));
+        exts.register(Box::new(MockExt { id: 1, name: "a" }))
+            .unwrap();
         assert_eq!(exts.size(), 1);
-        exts.register(Box::new(MockExt { id: 2, name: "b" }));
+        exts.register(Box::new(MockExt { id: 2, name: "b" }))
+            .unwrap();
         assert_eq!(exts.size(), 2);
     }
 
@@ -190,11 +395,13 @@ mod tests {
         exts.register(Box::new(MockExt {
             id: 7,
             name: "first",
-        }));
+        }))
+        .unwrap();
         exts.register(Box::new(MockExt {
             id: 7,
             name: "second",
-        }));
+        }))
+        .unwrap();
         assert_eq!(exts.size(), 1);
         assert_eq!(exts.get(7).unwrap().name(), "second");
     }
@@ -203,7 +410,8 @@ mod tests {
     fn extensions_multiple_independent_ids() {
         let mut exts = Extensions::new();
         for i in 0u32..5 {
-            exts.register(Box::new(MockExt { id: i, name: "ext" }));
+            exts.register(Box::new(MockExt { id: i, name: "ext" }))
+                .unwrap();
         }
         assert_eq!(exts.size(), 5);
         for i in 0u32..5 {
@@ -230,6 +438,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn extensions_register_rejects_wide_id() {
+        let mut exts = Extensions::new();
+        let result = exts.register(Box::new(MockExt { id: 256, name: "x" }));
+        assert_eq!(result, Err(ExtensionError::InvalidId(256)));
+        assert_eq!(exts.size(), 0);
+    }
+
+    // -- ExtRef ----------------------------------------------------------------
+
+    #[test]
+    fn ext_ref_requires_matching_header() {
+        use crate::json_crdt::model::Model;
+
+        let mut model = Model::new(123_456);
+        let mut api = ModelApi::new(&mut model);
+        let ext = api.ext_node(&MockExt { id: 9, name: "m" }, &Value::from(1));
+        let tuple = api.json(&serde_json::json!([[9, 0, 0], 1])).unwrap();
+        api.apply();
+        let (ext_id, node) = ExtRef::of(&model.index, ext).unwrap();
+        assert_eq!((ext_id, node.id), (9, ext));
+        assert_eq!(model.extensions.view_node(&model.index, node.data), 1);
+        assert!(ExtRef::of(&model.index, tuple).is_none());
+    }
+
+    #[test]
+    fn ext_ref_rejects_empty_header() {
+        use crate::json_crdt::model::Model;
+
+        let mut model = Model::new(123_456);
+        let mut api = ModelApi::new(&mut model);
+        let vec = api.builder.vec();
+        let header = api.builder.con_val(PackValue::Bytes(vec![]));
+        let data = api.builder.con_val(PackValue::Integer(1));
+        api.builder.ins_vec(vec, vec![(0, header), (1, data)]);
+        api.apply();
+        assert!(ExtRef::of(&model.index, vec).is_none());
+        // Rendered as a plain tuple.
+        assert_eq!(model.extensions.view_node(&model.index, vec)[1], 1);
+    }
+
     #[test]
     fn ext_node_trait_methods() {
         let node = MockExtNode { ext_id: 3 };
@@ -310,7 +310,9 @@ impl<'a> JsonPatch<'a> {
 
     /// Perform the `move` operation.
     ///
-    /// Mirrors `JsonPatch.move()`.
+    /// Mirrors `JsonPatch.move()`. A move within one `arr` node is emitted as
+    /// a single `mov_arr` operation instead of delete + re-insert, so that
+    /// concurrent moves of the same element do not duplicate it.
     pub fn move_op(&mut self, path: &str, from: &str) -> Result<(), JsonPatchError> {
         let path_steps = self.to_path(path);
         let from_steps = self.to_path(from);
@@ -320,6 +322,13 @@ impl<'a> JsonPatch<'a> {
             return Err(JsonPatchError::InvalidChild);
         }
 
+        if let Some((arr_id, from_idx, to_idx)) = self.array_move(&from_steps, &path_steps) {
+            let mut api = ModelApi::new(self.model);
+            return api
+                .arr_move(arr_id, from_idx, to_idx)
+                .map_err(JsonPatchError::from);
+        }
+
         let json = self.get_json(from)?;
         self.remove(from)?;
         self.add(path, &json)
@@ -433,6 +442,33 @@ impl<'a> JsonPatch<'a> {
         result
     }
 
+    /// Resolves a `move` whose `from` and `path` are indices of the same
+    /// `arr` node to `(arr_id, from, to)`.
+    fn array_move(
+        &self,
+        from: &[String],
+        path: &[String],
+    ) -> Option<(crate::json_crdt_patch::clock::Ts, usize, usize)> {
+        let (from_key, parent) = from.split_last()?;
+        let (to_key, to_parent) = path.split_last()?;
+        if parent != to_parent {
+            return None;
+        }
+        let parent_steps: Vec<Value> = parent.iter().map(|s| Value::String(s.clone())).collect();
+        let parent_id = find_path(self.model, self.model.root.val, &parent_steps).ok()?;
+        let parent_id = unwrap_val(self.model, parent_id);
+        let Some(CrdtNode::Arr(node)) = IndexExt::get(&self.model.index, &parent_id) else {
+            return None;
+        };
+        let size = node.size();
+        let from_idx = from_key.parse::<usize>().ok().filter(|i| *i < size)?;
+        let to_idx = match to_key.as_str() {
+            "-" => size - 1,
+            key => key.parse::<usize>().ok().filter(|i| *i < size)?,
+        };
+        Some((parent_id, from_idx, to_idx))
+    }
+
     /// Read the JSON value at `path` (with prefix), returning an error if not found.
     fn get_json(&self, path: &str) -> Result<Value, JsonPatchError> {
         let steps = self.to_path(path);
@@ -714,6 +750,22 @@ mod tests {
         assert_eq!(model.view()["full_name"], json!("Alice"));
     }
 
+    #[test]
+    fn move_within_array_emits_single_op() {
+        let mut model = make_arr_model();
+        let before = model.clock.time;
+        let mut patcher = JsonPatch::new(&mut model);
+        patcher.move_op("/2", "/0").unwrap();
+        assert_eq!(model.view(), json!([2, 3, 1]));
+        assert_eq!(model.clock.time, before + 1);
+        let mut patcher = JsonPatch::new(&mut model);
+        patcher.move_op("/-", "/0").unwrap();
+        assert_eq!(model.view(), json!([3, 1, 2]));
+        let mut patcher = JsonPatch::new(&mut model);
+        patcher.move_op("/0", "/2").unwrap();
+        assert_eq!(model.view(), json!([2, 3, 1]));
+    }
+
     #[test]
     fn move_into_child_returns_invalid_child() {
         let mut model = Model::create();
@@ -15,15 +15,34 @@
 //! The log supports replaying to any point in history via [`Log::replay_to_end`]
 //! and [`Log::replay_to`], advancing the baseline via [`Log::advance_to`], and
 //! rebasing concurrent batches via [`Log::rebase_batch`].
+//!
+//! # Time travel
+//!
+//! [`Log::view_at`] and [`Log::model_at`] answer historical queries without
+//! replaying the whole history. The log lazily caches a [`Model`] checkpoint
+//! every [`Log::checkpoint_interval`] patches, so a query only replays the
+//! patches between the nearest checkpoint and its target. Checkpoints after
+//! a newly recorded patch are discarded, so out-of-order delivery keeps the
+//! cache consistent. Code that mutates [`Log::patches`] directly must call
+//! [`Log::clear_checkpoints`] itself.
+//!
+//! # Compaction
+//!
+//! [`Log::squash`] merges runs of consecutive patches from the same session,
+//! [`Log::truncate_before`] and [`Log::retain_last`] bake old history into
+//! the baseline while keeping the frontier. Each returns a
+//! [`CompactionReport`] with the encoded size before and after.
 
 use std::collections::BTreeMap;
+use std::ops::Bound;
 
 use serde_json::Value;
 
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{rga::ChunkData, CrdtNode, TsKey};
 use crate::json_crdt::schema::to_schema;
-use crate::json_crdt_patch::clock::{compare, Ts, Tss};
+use crate::json_crdt_patch::clock::{compare, ClockVector, Ts, Tss};
+use crate::json_crdt_patch::compaction::{combine, compact};
 use crate::json_crdt_patch::patch::Patch;
 use crate::json_crdt_patch::patch_builder::PatchBuilder;
 use json_joy_json_pack::PackValue;
@@ -45,6 +64,30 @@ impl PatchKey {
     }
 }
 
+/// Outcome of a compaction pass over a [`Log`].
+///
+/// Sizes are measured as the binary encoding of the baseline model plus the
+/// binary encoding of every patch in the history, which is what the log
+/// codec persists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionReport {
+    /// Number of patches in the history before compaction.
+    pub patches_before: usize,
+    /// Number of patches in the history after compaction.
+    pub patches_after: usize,
+    /// Encoded size in bytes before compaction.
+    pub bytes_before: usize,
+    /// Encoded size in bytes after compaction.
+    pub bytes_after: usize,
+}
+
+impl CompactionReport {
+    /// Returns the number of bytes saved (zero if the log grew).
+    pub fn bytes_reclaimed(&self) -> usize {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
 /// History log for a JSON CRDT model.
 ///
 /// Stores a start-model factory, an ordered set of patches, and the current
@@ -62,8 +105,17 @@ pub struct Log {
 
     /// Arbitrary key/value metadata stored alongside the log.
     pub metadata: serde_json::Map<String, Value>,
+
+    /// Cached model states keyed by the last patch each one includes.
+    checkpoints: BTreeMap<PatchKey, Model>,
+
+    /// Number of patches between two consecutive checkpoints.
+    checkpoint_interval: usize,
 }
 
+/// Default number of patches between two time-travel checkpoints.
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 256;
+
 impl Log {
     // ──────────────────────────────────────────────────────────────────────
     // Constructors
@@ -85,6 +137,8 @@ impl Log {
             patches: BTreeMap::new(),
             end: model,
             metadata: serde_json::Map::new(),
+            checkpoints: BTreeMap::new(),
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
         }
     }
 
@@ -105,6 +159,8 @@ impl Log {
             patches: BTreeMap::new(),
             end: model.clone(),
             metadata: serde_json::Map::new(),
+            checkpoints: BTreeMap::new(),
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
         }
     }
 
@@ -135,7 +191,10 @@ impl Log {
     /// Useful when the patch has already been applied to `end` externally.
     pub fn record(&mut self, patch: Patch) {
         if let Some(id) = patch.get_id() {
-            self.patches.insert(PatchKey::from_ts(id), patch);
+            let key = PatchKey::from_ts(id);
+            // Checkpoints at or after `key` no longer reflect the history.
+            self.checkpoints.split_off(&key);
+            self.patches.insert(key, patch);
         }
     }
 
@@ -180,6 +239,98 @@ impl Log {
         model
     }
 
+    // ──────────────────────────────────────────────────────────────────────
+    // Time travel
+    // ──────────────────────────────────────────────────────────────────────
+
+    /// Returns the number of patches between two consecutive checkpoints.
+    pub fn checkpoint_interval(&self) -> usize {
+        self.checkpoint_interval
+    }
+
+    /// Sets the number of patches between two consecutive checkpoints and
+    /// discards the existing ones. Smaller intervals trade memory for faster
+    /// queries. An interval of `0` is treated as `1`.
+    pub fn set_checkpoint_interval(&mut self, interval: usize) {
+        self.checkpoint_interval = interval.max(1);
+        self.checkpoints.clear();
+    }
+
+    /// Discards all cached checkpoints. They are rebuilt on the next query.
+    pub fn clear_checkpoints(&mut self) {
+        self.checkpoints.clear();
+    }
+
+    /// Returns the view of the document right after the patch at `ts`.
+    ///
+    /// Equivalent to `self.replay_to(ts, true).view()`, but replays at most
+    /// [`Log::checkpoint_interval`] patches once the checkpoints leading up
+    /// to `ts` have been built.
+    pub fn view_at(&mut self, ts: Ts) -> Value {
+        self.model_through(PatchKey::from_ts(ts)).view()
+    }
+
+    /// Returns the document as seen by a replica whose clock is `clock`:
+    /// the baseline plus every patch `clock` has observed.
+    ///
+    /// The longest fully-observed prefix of the history is served from the
+    /// checkpoints; the observed patches after it are applied on top.
+    pub fn model_at(&mut self, clock: &ClockVector) -> Model {
+        let first_unseen = self
+            .patches
+            .iter()
+            .find(|(_, patch)| !seen_by(patch, clock))
+            .map(|(key, _)| *key);
+        let Some(first_unseen) = first_unseen else {
+            return match self.patches.keys().next_back() {
+                Some(last) => self.model_through(*last),
+                None => self.start(),
+            };
+        };
+        let mut model = match self.patches.range(..first_unseen).next_back() {
+            Some((key, _)) => self.model_through(*key),
+            None => self.start(),
+        };
+        let max_time = clock
+            .peers
+            .values()
+            .map(|peer| peer.time)
+            .fold(clock.time, u64::max);
+        for (key, patch) in self.patches.range(first_unseen..) {
+            if key.time > max_time {
+                break;
+            }
+            if seen_by(patch, clock) {
+                model.apply_patch(patch);
+            }
+        }
+        model
+    }
+
+    /// Returns the model after every patch up to and including `target`,
+    /// starting from the nearest checkpoint and extending the checkpoint
+    /// chain when `target` lies past its end.
+    fn model_through(&mut self, target: PatchKey) -> Model {
+        let (mut model, from) = match self.checkpoints.range(..=target).next_back() {
+            Some((key, model)) => (model.clone(), Bound::Excluded(*key)),
+            None => (self.start(), Bound::Unbounded),
+        };
+        let extend = match (self.checkpoints.keys().next_back(), from) {
+            (None, _) => true,
+            (Some(last), Bound::Excluded(key)) => *last == key,
+            _ => false,
+        };
+        let mut count = 0;
+        for (key, patch) in self.patches.range((from, Bound::Included(target))) {
+            model.apply_patch(patch);
+            count += 1;
+            if extend && count % self.checkpoint_interval == 0 {
+                self.checkpoints.insert(*key, model.clone());
+            }
+        }
+        model
+    }
+
     // ──────────────────────────────────────────────────────────────────────
     // Advance baseline
     // ──────────────────────────────────────────────────────────────────────
@@ -219,6 +370,109 @@ impl Log {
             model
         });
         self.start_fn = new_start;
+        // Checkpoints inside the new baseline would be older than `start()`.
+        self.checkpoints
+            .retain(|key, _| compare(ts, Ts::new(key.sid, key.time)) < 0);
+    }
+
+    // ──────────────────────────────────────────────────────────────────────
+    // Compaction
+    // ──────────────────────────────────────────────────────────────────────
+
+    /// Merges every run of consecutive patches (in history order) that share
+    /// a session ID and metadata into a single patch, then merges adjacent
+    /// string inserts inside it.
+    ///
+    /// The document state at the end of each run is unchanged; intermediate
+    /// states inside a run are no longer addressable by [`Log::replay_to`].
+    pub fn squash(&mut self) -> CompactionReport {
+        let patches_before = self.patches.len();
+        let bytes_before = self.encoded_size();
+        let mut runs: Vec<Vec<Patch>> = Vec::new();
+        for patch in std::mem::take(&mut self.patches).into_values() {
+            let joins = match runs.last().and_then(|run| run.last()) {
+                Some(prev) => {
+                    prev.meta == patch.meta
+                        && prev.get_id().map(|id| id.sid) == patch.get_id().map(|id| id.sid)
+                }
+                None => false,
+            };
+            match runs.last_mut() {
+                Some(run) if joins => run.push(patch),
+                _ => runs.push(vec![patch]),
+            }
+        }
+        for mut run in runs {
+            combine(&mut run);
+            for mut patch in run {
+                compact(&mut patch);
+                if let Some(id) = patch.get_id() {
+                    self.patches.insert(PatchKey::from_ts(id), patch);
+                }
+            }
+        }
+        self.checkpoints.clear();
+        CompactionReport {
+            patches_before,
+            patches_after: self.patches.len(),
+            bytes_before,
+            bytes_after: self.encoded_size(),
+        }
+    }
+
+    /// Drops the history strictly before `ts`, baking it into a re-frozen
+    /// baseline. The patch at `ts` (if any) and everything after it stay in
+    /// the history, and `end` is untouched.
+    pub fn truncate_before(&mut self, ts: Ts) -> CompactionReport {
+        let retain_from = PatchKey::from_ts(ts);
+        self.truncate_history(|key| *key < retain_from)
+    }
+
+    /// Keeps only the `count` most recent patches in the history, baking
+    /// older ones into a re-frozen baseline.
+    pub fn retain_last(&mut self, count: usize) -> CompactionReport {
+        let drop = self.patches.len().saturating_sub(count);
+        match self.patches.keys().nth(drop) {
+            Some(first_kept) => {
+                let first_kept = *first_kept;
+                self.truncate_history(|key| *key < first_kept)
+            }
+            None => self.truncate_history(|_| true),
+        }
+    }
+
+    /// Moves the leading patches matching `bake` into the baseline and
+    /// replaces the start factory with a binary snapshot of the result.
+    fn truncate_history(&mut self, bake: impl Fn(&PatchKey) -> bool) -> CompactionReport {
+        let patches_before = self.patches.len();
+        let bytes_before = self.encoded_size();
+        let baked: Vec<PatchKey> = self.patches.keys().copied().take_while(&bake).collect();
+        if let Some(last) = baked.last() {
+            let frozen = self.model_through(*last).to_binary();
+            for key in &baked {
+                self.patches.remove(key);
+            }
+            self.start_fn = Box::new(move || {
+                Model::from_binary(&frozen).expect("Log::truncate_history: corrupt snapshot")
+            });
+            self.checkpoints.retain(|key, _| !bake(key));
+        }
+        CompactionReport {
+            patches_before,
+            patches_after: self.patches.len(),
+            bytes_before,
+            bytes_after: self.encoded_size(),
+        }
+    }
+
+    /// Returns the encoded size of the baseline plus the patch history.
+    fn encoded_size(&self) -> usize {
+        self.start().to_binary().len()
+            + self
+                .patches
+                .values()
+                .map(|patch| patch.to_binary().len())
+                .sum::<usize>()
     }
 
     // ──────────────────────────────────────────────────────────────────────
@@ -240,6 +494,19 @@ impl Log {
         None
     }
 
+    /// Returns every patch in the history that a replica with clock `remote`
+    /// has not seen yet, in history order.
+    ///
+    /// A patch counts as seen once `remote` has observed its last timestamp.
+    /// Patches already baked into the baseline by [`Log::advance_to`] are not
+    /// part of the history and are never returned.
+    pub fn patches_since(&self, remote: &ClockVector) -> Vec<&Patch> {
+        self.patches
+            .values()
+            .filter(|patch| patch.get_id().is_some() && !seen_by(patch, remote))
+            .collect()
+    }
+
     /// Rebases a batch of concurrent patches on top of the latest known
     /// time in this log (or on top of the latest patch for a specific SID).
     ///
@@ -295,6 +562,8 @@ impl Log {
             patches,
             end: self.end.clone(),
             metadata: self.metadata.clone(),
+            checkpoints: BTreeMap::new(),
+            checkpoint_interval: self.checkpoint_interval,
         }
     }
 
@@ -307,6 +576,8 @@ impl Log {
         self.start_fn = other.start_fn;
         self.metadata = other.metadata;
         self.patches = other.patches;
+        self.checkpoints = other.checkpoints;
+        self.checkpoint_interval = other.checkpoint_interval;
         // In-place replacement of `end`: copy clock and nodes from other.end.
         self.end = other.end;
     }
@@ -315,158 +586,198 @@ impl Log {
     ///
     /// Mirrors `Log.undo(patch)` in upstream TypeScript.
     pub fn undo(&self, patch: &Patch) -> Patch {
-        let ops = &patch.ops;
-        if ops.is_empty() {
-            panic!("EMPTY_PATCH");
-        }
         let id = patch.get_id().expect("EMPTY_PATCH");
-        let mut replay_model: Option<Model> = None;
-        let mut builder = PatchBuilder::new(self.end.clock.sid, self.end.clock.time);
-
-        for op in ops.iter().rev() {
-            let op_id = op.id();
-            match op {
-                crate::json_crdt_patch::operations::Op::InsStr { obj, .. }
-                | crate::json_crdt_patch::operations::Op::InsArr { obj, .. }
-                | crate::json_crdt_patch::operations::Op::InsBin { obj, .. } => {
-                    builder.del(*obj, vec![Tss::new(op_id.sid, op_id.time, op.span())]);
-                    continue;
-                }
-                _ => {}
+        undo_patch(patch, &self.end.clock, || self.replay_to(id, false))
+    }
+}
+
+/// Returns `true` if `clock` has observed the last timestamp of `patch`.
+/// Empty patches count as seen.
+fn seen_by(patch: &Patch, clock: &ClockVector) -> bool {
+    match patch.get_id() {
+        Some(id) => clock.has_seen(Ts::new(id.sid, id.time + patch.span() - 1)),
+        None => true,
+    }
+}
+
+/// Builds the inverse of `patch`.
+///
+/// `before` lazily produces the model state immediately preceding `patch`;
+/// it is only invoked when the inverse cannot be derived from the patch
+/// alone (i.e. for anything other than plain RGA inserts). New operations
+/// are allocated from `clock`.
+///
+/// Shared by [`Log::undo`] and [`Draft::undo`](crate::json_crdt::draft::Draft::undo).
+pub(crate) fn undo_patch(
+    patch: &Patch,
+    clock: &ClockVector,
+    before: impl FnOnce() -> Model,
+) -> Patch {
+    let ops = &patch.ops;
+    if ops.is_empty() {
+        panic!("EMPTY_PATCH");
+    }
+    let mut before = Some(before);
+    let mut replay_model: Option<Model> = None;
+    let mut builder = PatchBuilder::new(clock.sid, clock.time);
+
+    for op in ops.iter().rev() {
+        let op_id = op.id();
+        match op {
+            crate::json_crdt_patch::operations::Op::InsStr { obj, .. }
+            | crate::json_crdt_patch::operations::Op::InsArr { obj, .. }
+            | crate::json_crdt_patch::operations::Op::InsBin { obj, .. } => {
+                builder.del(*obj, vec![Tss::new(op_id.sid, op_id.time, op.span())]);
+                continue;
             }
+            _ => {}
+        }
 
-            let model = replay_model.get_or_insert_with(|| self.replay_to(id, false));
+        let model = replay_model.get_or_insert_with(|| (before.take().expect("replayed once"))());
 
-            match op {
-                crate::json_crdt_patch::operations::Op::InsVal { obj, .. } => {
-                    if let Some(CrdtNode::Val(val)) = model.index.get(&TsKey::from(*obj)) {
-                        let new_id = if let Some(node) = model.index.get(&TsKey::from(val.val)) {
-                            let schema = to_schema(node, &model.index);
-                            schema.build(&mut builder)
-                        } else {
-                            builder.con_val(PackValue::Undefined)
-                        };
-                        builder.set_val(*obj, new_id);
-                    }
+        match op {
+            crate::json_crdt_patch::operations::Op::InsVal { obj, .. } => {
+                if let Some(CrdtNode::Val(val)) = model.index.get(&TsKey::from(*obj)) {
+                    let new_id = if let Some(node) = model.index.get(&TsKey::from(val.val)) {
+                        let schema = to_schema(node, &model.index);
+                        schema.build(&mut builder)
+                    } else {
+                        builder.con_val(PackValue::Undefined)
+                    };
+                    builder.set_val(*obj, new_id);
                 }
-                crate::json_crdt_patch::operations::Op::InsObj { obj, data, .. } => {
-                    let container = model.index.get(&TsKey::from(*obj));
-                    let mut restore: Vec<(String, Ts)> = Vec::with_capacity(data.len());
-                    for (key, _) in data {
-                        let restored = match container {
-                            Some(CrdtNode::Obj(node)) => node
-                                .keys
-                                .get(key)
-                                .and_then(|id| model.index.get(&TsKey::from(*id)))
-                                .map(|node| {
-                                    let schema = to_schema(node, &model.index);
-                                    schema.build(&mut builder)
-                                }),
-                            _ => None,
-                        }
-                        .unwrap_or_else(|| builder.con_val(PackValue::Undefined));
-                        restore.push((key.clone(), restored));
-                    }
-                    if !restore.is_empty() {
-                        builder.ins_obj(*obj, restore);
+            }
+            crate::json_crdt_patch::operations::Op::InsObj { obj, data, .. } => {
+                let container = model.index.get(&TsKey::from(*obj));
+                let mut restore: Vec<(String, Ts)> = Vec::with_capacity(data.len());
+                for (key, _) in data {
+                    let restored = match container {
+                        Some(CrdtNode::Obj(node)) => node
+                            .keys
+                            .get(key)
+                            .and_then(|id| model.index.get(&TsKey::from(*id)))
+                            .map(|node| {
+                                let schema = to_schema(node, &model.index);
+                                schema.build(&mut builder)
+                            }),
+                        _ => None,
                     }
+                    .unwrap_or_else(|| builder.con_val(PackValue::Undefined));
+                    restore.push((key.clone(), restored));
                 }
-                crate::json_crdt_patch::operations::Op::InsVec { obj, data, .. } => {
-                    let container = model.index.get(&TsKey::from(*obj));
-                    let mut restore: Vec<(u8, Ts)> = Vec::with_capacity(data.len());
-                    for (key, _) in data {
-                        let restored = match container {
-                            Some(CrdtNode::Vec(node)) => node
-                                .elements
-                                .get(*key as usize)
-                                .and_then(|id| *id)
-                                .and_then(|id| model.index.get(&TsKey::from(id)))
-                                .map(|node| {
-                                    let schema = to_schema(node, &model.index);
-                                    schema.build(&mut builder)
-                                }),
-                            _ => None,
-                        }
-                        .unwrap_or_else(|| builder.con_val(PackValue::Undefined));
-                        restore.push((*key, restored));
+                if !restore.is_empty() {
+                    builder.ins_obj(*obj, restore);
+                }
+            }
+            crate::json_crdt_patch::operations::Op::InsVec { obj, data, .. } => {
+                let container = model.index.get(&TsKey::from(*obj));
+                let mut restore: Vec<(u8, Ts)> = Vec::with_capacity(data.len());
+                for (key, _) in data {
+                    let restored = match container {
+                        Some(CrdtNode::Vec(node)) => node
+                            .elements
+                            .get(*key as usize)
+                            .and_then(|id| *id)
+                            .and_then(|id| model.index.get(&TsKey::from(id)))
+                            .map(|node| {
+                                let schema = to_schema(node, &model.index);
+                                schema.build(&mut builder)
+                            }),
+                        _ => None,
                     }
-                    if !restore.is_empty() {
-                        builder.ins_vec(*obj, restore);
+                    .unwrap_or_else(|| builder.con_val(PackValue::Undefined));
+                    restore.push((*key, restored));
+                }
+                if !restore.is_empty() {
+                    builder.ins_vec(*obj, restore);
+                }
+            }
+            crate::json_crdt_patch::operations::Op::MovArr { obj, elem, .. } => {
+                // Move the element back after the slot that preceded it.
+                if let Some(CrdtNode::Arr(arr_node)) = model.index.get(&TsKey::from(*obj)) {
+                    let current = arr_node.moves.current(*elem);
+                    let live = arr_node
+                        .rga
+                        .find_by_id(current)
+                        .is_some_and(|idx| !arr_node.rga.slot(idx).deleted);
+                    if live {
+                        let after = prev_id(&arr_node.rga, current).unwrap_or(*obj);
+                        builder.mov_arr(*obj, after, *elem);
                     }
                 }
-                crate::json_crdt_patch::operations::Op::Del { obj, what, .. } => {
-                    if let Some(node) = model.index.get(&TsKey::from(*obj)) {
-                        match node {
-                            CrdtNode::Str(str_node) => {
-                                let mut restored = String::new();
-                                for span in what {
-                                    for part in span_view_str(&str_node.rga, *span) {
-                                        restored.push_str(&part);
-                                    }
-                                }
-                                let mut after = *obj;
-                                if let Some(first_span) = what.first() {
-                                    let first = Ts::new(first_span.sid, first_span.time);
-                                    if let Some(prev) = prev_id(&str_node.rga, first) {
-                                        after = prev;
-                                    }
-                                }
-                                if !restored.is_empty() {
-                                    builder.ins_str(*obj, after, restored);
+            }
+            crate::json_crdt_patch::operations::Op::Del { obj, what, .. } => {
+                if let Some(node) = model.index.get(&TsKey::from(*obj)) {
+                    match node {
+                        CrdtNode::Str(str_node) => {
+                            let mut restored = String::new();
+                            for span in what {
+                                for part in span_view_str(&str_node.rga, *span) {
+                                    restored.push_str(&part);
                                 }
                             }
-                            CrdtNode::Bin(bin_node) => {
-                                let mut restored: Vec<u8> = Vec::new();
-                                for span in what {
-                                    for part in span_view_bin(&bin_node.rga, *span) {
-                                        restored.extend(part);
-                                    }
+                            let mut after = *obj;
+                            if let Some(first_span) = what.first() {
+                                let first = Ts::new(first_span.sid, first_span.time);
+                                if let Some(prev) = prev_id(&str_node.rga, first) {
+                                    after = prev;
                                 }
-                                let mut after = *obj;
-                                if let Some(first_span) = what.first() {
-                                    let first = Ts::new(first_span.sid, first_span.time);
-                                    if let Some(prev) = prev_id(&bin_node.rga, first) {
-                                        after = prev;
-                                    }
+                            }
+                            if !restored.is_empty() {
+                                builder.ins_str(*obj, after, restored);
+                            }
+                        }
+                        CrdtNode::Bin(bin_node) => {
+                            let mut restored: Vec<u8> = Vec::new();
+                            for span in what {
+                                for part in span_view_bin(&bin_node.rga, *span) {
+                                    restored.extend(part);
                                 }
-                                if !restored.is_empty() {
-                                    builder.ins_bin(*obj, after, restored);
+                            }
+                            let mut after = *obj;
+                            if let Some(first_span) = what.first() {
+                                let first = Ts::new(first_span.sid, first_span.time);
+                                if let Some(prev) = prev_id(&bin_node.rga, first) {
+                                    after = prev;
                                 }
                             }
-                            CrdtNode::Arr(arr_node) => {
-                                let mut copies: Vec<Ts> = Vec::new();
-                                for span in what {
-                                    for ids in span_view_arr(&arr_node.rga, *span) {
-                                        for id in ids {
-                                            if let Some(src) = model.index.get(&TsKey::from(id)) {
-                                                let schema = to_schema(src, &model.index);
-                                                let new_id = schema.build(&mut builder);
-                                                copies.push(new_id);
-                                            }
+                            if !restored.is_empty() {
+                                builder.ins_bin(*obj, after, restored);
+                            }
+                        }
+                        CrdtNode::Arr(arr_node) => {
+                            let mut copies: Vec<Ts> = Vec::new();
+                            for span in what {
+                                for ids in span_view_arr(&arr_node.rga, *span) {
+                                    for id in ids {
+                                        if let Some(src) = model.index.get(&TsKey::from(id)) {
+                                            let schema = to_schema(src, &model.index);
+                                            let new_id = schema.build(&mut builder);
+                                            copies.push(new_id);
                                         }
                                     }
                                 }
-                                let mut after = *obj;
-                                if let Some(first_span) = what.first() {
-                                    let first = Ts::new(first_span.sid, first_span.time);
-                                    if let Some(prev) = prev_id(&arr_node.rga, first) {
-                                        after = prev;
-                                    }
-                                }
-                                if !copies.is_empty() {
-                                    builder.ins_arr(*obj, after, copies);
+                            }
+                            let mut after = *obj;
+                            if let Some(first_span) = what.first() {
+                                let first = Ts::new(first_span.sid, first_span.time);
+                                if let Some(prev) = prev_id(&arr_node.rga, first) {
+                                    after = prev;
                                 }
                             }
-                            _ => {}
+                            if !copies.is_empty() {
+                                builder.ins_arr(*obj, after, copies);
+                            }
                         }
+                        _ => {}
                     }
                 }
-                _ => {}
             }
+            _ => {}
         }
-
-        builder.flush()
     }
+
+    builder.flush()
 }
 
 fn prev_id<T: Clone + ChunkData>(rga: &crate::json_crdt::nodes::rga::Rga<T>, id: Ts) -> Option<Ts> {
@@ -1576,6 +1887,202 @@ mod tests {
         assert!(log.find_max(999_999).is_none());
     }
 
+    // ── Log::patches_since ────────────────────────────────────────────────
+
+    #[test]
+    fn patches_since_returns_patches_unseen_by_remote() {
+        let mut model = make_model();
+        let p1 = make_patch(&mut model, "a");
+        model.apply_patch(&p1);
+        let p2 = make_patch(&mut model, "b");
+        let mut log = Log::from_new_model(Model::new(sid()));
+        log.apply(p1.clone());
+        log.apply(p2.clone());
+
+        let fresh = ClockVector::new(999_999, 1);
+        assert_eq!(log.patches_since(&fresh).len(), 2);
+
+        let mut partial = ClockVector::new(999_999, 1);
+        let p1_id = p1.get_id().unwrap();
+        partial.observe(p1_id, p1.span());
+        let missing = log.patches_since(&partial);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].get_id(), p2.get_id());
+
+        assert!(log.patches_since(&log.end.clock).is_empty());
+    }
+
+    // ── Log::view_at / Log::model_at ──────────────────────────────────────
+
+    /// Appends a single-character insert at the start of the root string,
+    /// authored by `sid` at the log's current Lamport time.
+    fn push_char(log: &mut Log, str_id: Ts, sid: u64, ch: char) -> Ts {
+        let id = ts(sid, log.end.clock.time);
+        log.apply(Patch {
+            ops: vec![Op::InsStr {
+                id,
+                obj: str_id,
+                after: crate::json_crdt::constants::ORIGIN,
+                data: ch.to_string(),
+            }],
+            meta: None,
+        });
+        id
+    }
+
+    #[test]
+    fn view_at_matches_full_replay_and_builds_checkpoints() {
+        let (mut log, str_id) = make_root_str_log("");
+        log.set_checkpoint_interval(4);
+        let ids: Vec<Ts> = "abcdefghijklmnopqrst"
+            .chars()
+            .map(|ch| push_char(&mut log, str_id, sid(), ch))
+            .collect();
+        // Query out of order to exercise both extending and reusing the chain.
+        for &i in &[19, 3, 10, 0, 15, 7] {
+            assert_eq!(log.view_at(ids[i]), log.replay_to(ids[i], true).view());
+        }
+        assert_eq!(log.checkpoints.len(), 5);
+        assert_eq!(log.view_at(ids[19]), log.end.view());
+    }
+
+    #[test]
+    fn late_patch_invalidates_later_checkpoints() {
+        let (mut log, str_id) = make_root_str_log("");
+        log.set_checkpoint_interval(2);
+        let mut ids = Vec::new();
+        for ch in "abcdef".chars() {
+            ids.push(push_char(&mut log, str_id, sid(), ch));
+        }
+        log.view_at(ids[5]);
+        assert_eq!(log.checkpoints.len(), 3);
+
+        // A concurrent patch from another session sorts before `ids[3]`.
+        let late = Patch {
+            ops: vec![Op::InsStr {
+                id: ts(sid() + 1, ids[2].time),
+                obj: str_id,
+                after: crate::json_crdt::constants::ORIGIN,
+                data: "x".into(),
+            }],
+            meta: None,
+        };
+        log.apply(late);
+        assert_eq!(log.checkpoints.len(), 1);
+        for id in &ids {
+            assert_eq!(log.view_at(*id), log.replay_to(*id, true).view());
+        }
+        assert_eq!(log.view_at(ids[5]), log.end.view());
+    }
+
+    #[test]
+    fn model_at_skips_patches_unseen_by_clock() {
+        let (mut log, str_id) = make_root_str_log("");
+        log.set_checkpoint_interval(2);
+        let other = sid() + 1;
+        push_char(&mut log, str_id, sid(), 'a');
+        push_char(&mut log, str_id, sid(), 'b');
+        let remote = push_char(&mut log, str_id, other, 'x');
+        push_char(&mut log, str_id, sid(), 'c');
+
+        // A replica that has every local patch but not the remote one.
+        let mut clock = ClockVector::new(sid(), log.end.clock.time);
+        clock.observe(ts(other, remote.time - 1), 1);
+        assert_eq!(log.model_at(&clock).view(), json!("cba"));
+
+        clock.observe(remote, 1);
+        assert_eq!(log.model_at(&clock).view(), log.end.view());
+        assert_eq!(log.model_at(&ClockVector::new(sid(), 1)).view(), json!(""));
+    }
+
+    #[test]
+    fn advance_to_drops_checkpoints_inside_the_baseline() {
+        let (mut log, str_id) = make_root_str_log("");
+        log.set_checkpoint_interval(2);
+        let mut ids = Vec::new();
+        for ch in "abcdef".chars() {
+            ids.push(push_char(&mut log, str_id, sid(), ch));
+        }
+        log.view_at(ids[5]);
+        log.advance_to(ids[2]);
+        assert_eq!(log.checkpoints.len(), 2);
+        assert_eq!(log.view_at(ids[1]), json!("cba"));
+        assert_eq!(log.view_at(ids[4]), json!("edcba"));
+    }
+
+    // ── Log compaction ────────────────────────────────────────────────────
+
+    #[test]
+    fn squash_merges_same_session_runs() {
+        let (mut log, str_id) = make_root_str_log("");
+        let other = sid() + 1;
+        let mut ids = Vec::new();
+        for ch in "abc".chars() {
+            ids.push(push_char(&mut log, str_id, sid(), ch));
+        }
+        push_char(&mut log, str_id, other, 'x');
+        push_char(&mut log, str_id, other, 'y');
+        push_char(&mut log, str_id, sid(), 'd');
+        let view = log.end.view();
+
+        let report = log.squash();
+        assert_eq!(report.patches_before, 6);
+        assert_eq!(report.patches_after, 3);
+        assert!(report.bytes_reclaimed() > 0);
+        assert_eq!(
+            report.bytes_before - report.bytes_after,
+            report.bytes_reclaimed()
+        );
+        assert_eq!(log.replay_to_end().view(), view);
+        assert_eq!(log.view_at(ids[0]), json!("cba"));
+    }
+
+    #[test]
+    fn squash_keeps_patches_with_different_meta_apart() {
+        let (mut log, str_id) = make_root_str_log("");
+        push_char(&mut log, str_id, sid(), 'a');
+        let id = ts(sid(), log.end.clock.time);
+        log.apply(Patch {
+            ops: vec![Op::InsStr {
+                id,
+                obj: str_id,
+                after: crate::json_crdt::constants::ORIGIN,
+                data: "b".into(),
+            }],
+            meta: Some(PackValue::Str("tagged".into())),
+        });
+        assert_eq!(log.squash().patches_after, 2);
+    }
+
+    #[test]
+    fn truncate_before_keeps_frontier() {
+        let (mut log, str_id) = make_root_str_log("");
+        let mut ids = Vec::new();
+        for ch in "abcdef".chars() {
+            ids.push(push_char(&mut log, str_id, sid(), ch));
+        }
+        let report = log.truncate_before(ids[4]);
+        assert_eq!(report.patches_before, 6);
+        assert_eq!(report.patches_after, 2);
+        assert!(report.bytes_reclaimed() > 0);
+        assert_eq!(log.start().view(), json!("dcba"));
+        assert_eq!(log.replay_to_end().view(), log.end.view());
+        assert_eq!(log.view_at(ids[4]), json!("edcba"));
+    }
+
+    #[test]
+    fn retain_last_bounds_history_length() {
+        let (mut log, str_id) = make_root_str_log("");
+        for ch in "abcdef".chars() {
+            push_char(&mut log, str_id, sid(), ch);
+        }
+        assert_eq!(log.retain_last(10).patches_after, 6);
+        assert_eq!(log.retain_last(3).patches_after, 3);
+        assert_eq!(log.start().view(), json!("cba"));
+        assert_eq!(log.retain_last(0).patches_after, 0);
+        assert_eq!(log.start().view(), log.end.view());
+    }
+
     // ── Log::rebase_batch ─────────────────────────────────────────────────
 
     #[test]
@@ -1809,6 +2316,27 @@ mod tests {
         assert_eq!(log.end.view(), json!([1, 2, 3]));
     }
 
+    #[test]
+    fn undo_arr_move() {
+        let (mut log, arr_id, ins_arr_id) = make_root_arr_log(&[1, 2, 3]);
+        // Move the first element after the last one.
+        let patch = Patch {
+            ops: vec![Op::MovArr {
+                id: ts(sid(), log.end.clock.time),
+                obj: arr_id,
+                after: ts(sid(), ins_arr_id.time + 2),
+                elem: ts(sid(), ins_arr_id.time),
+            }],
+            meta: None,
+        };
+        log.apply(patch.clone());
+        assert_eq!(log.end.view(), json!([2, 3, 1]));
+        let undo = log.undo(&patch);
+        assert!(matches!(undo.ops[..], [Op::MovArr { .. }]));
+        log.apply(undo);
+        assert_eq!(log.end.view(), json!([1, 2, 3]));
+    }
+
     #[test]
     fn undo_lww_obj_vec_and_val_writes() {
         let (mut log, obj_id) = make_root_obj_with_foo_bar();
@@ -7,6 +7,7 @@
 //! - All JSON CRDT node types ([`nodes`])
 //! - The UNDEFINED_TS / ORIGIN sentinel constants ([`constants`])
 
+pub mod causal;
 pub mod codec;
 pub mod constants;
 pub mod draft;
@@ -18,9 +19,13 @@ pub mod model;
 pub mod nodes;
 pub mod partial_edit;
 pub mod schema;
+pub mod sync;
+pub mod typed;
 
 pub use constants::{ORIGIN, UNDEFINED_TS};
-pub use extensions::{AnyExtension, ExtApi, ExtNode, Extensions};
+pub use extensions::{
+    AnyExtension, ExtApi, ExtHandle, ExtNode, ExtNodeSchema, ExtRef, ExtensionError, Extensions,
+};
 pub use model::Model;
 pub use model::ModelApi;
 pub use nodes::{CrdtNode, NodeIndex};
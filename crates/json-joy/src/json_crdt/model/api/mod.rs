@@ -6,18 +6,28 @@
 //!
 //! [`ModelApi`] wraps a [`Model`] and a [`PatchBuilder`], buffering operations
 //! locally. Calling [`ModelApi::apply`] flushes all pending operations into the
-//! model. Each node-type accessor (e.g. [`StrNodeRef`], [`ObjNodeRef`]) holds a
-//! node ID and provides editing methods that borrow `&mut ModelApi`.
+//! model. Typed node handles (e.g. [`StrApi`], [`ObjApi`]) hold a node ID and
+//! provide editing methods that borrow `&mut ModelApi` (see [`nodes`]).
+//! [`ViewRef`] reads the document in place, without building a
+//! `serde_json::Value` (see [`view_ref`]).
 //!
 //! ## What is skipped vs. the upstream TypeScript
 //!
 //! - Event emitters (`FanOut`, `MicrotaskBufferFanOut`, `MergeFanOut`, `onReset`, …)
-//! - Change listeners (`onSelfChange`, `onChildChange`, `onSubtreeChange`,
-//!   `onBeforeChange`) — JS-specific event patterns without a direct Rust equivalent
+//!   — patch and node change listeners live on [`Model`] instead (see
+//!   [`events`](crate::json_crdt::model::events))
 //! - JS Proxy accessor (`.s` property)
 //! - `SyncStore<T>` interface
 //! - `.read()` observable method
-//! - Extension node API (`asExt`)
+//!
+//! Extension nodes are reached through [`NodeApi::as_ext`], which returns
+//! the extension's own API object (see [`ExtHandle`](crate::json_crdt::ExtHandle)).
+
+pub mod nodes;
+pub mod view_ref;
+
+pub use nodes::{ArrApi, BinApi, ConApi, NodeApi, ObjApi, StrApi, ValApi, VecApi};
+pub use view_ref::{ArrRef, ObjRef, StrRef, VecRef, ViewRef};
 
 use serde_json::Value;
 
@@ -438,6 +448,38 @@ impl<'a> ModelApi<'a> {
         Ok(())
     }
 
+    /// Move the element at position `from` of an `arr` node so that it ends
+    /// up at position `to`.
+    ///
+    /// Emits a single `mov_arr` operation, so concurrent moves of the same
+    /// element never duplicate it (the latest move wins).
+    pub fn arr_move(&mut self, arr_id: Ts, from: usize, to: usize) -> Result<(), ApiError> {
+        let (elem, after) = {
+            let node = match IndexExt::get(&self.model.index, &arr_id) {
+                Some(CrdtNode::Arr(n)) => n,
+                _ => return Err(ApiError::NotFound),
+            };
+            let size = node.size();
+            if from >= size || to >= size {
+                return Err(ApiError::OutOfBounds);
+            }
+            if from == to {
+                return Ok(());
+            }
+            let elem = node.find(from).ok_or(ApiError::OutOfBounds)?;
+            // Anchor on the element that precedes `to` once `from` is gone.
+            let after = match to {
+                0 => arr_id,
+                _ if to < from => node.find(to - 1).ok_or(ApiError::OutOfBounds)?,
+                _ => node.find(to).ok_or(ApiError::OutOfBounds)?,
+            };
+            (elem, after)
+        };
+        self.builder.mov_arr(arr_id, after, elem);
+        self.apply();
+        Ok(())
+    }
+
     /// Return the current number of live elements in an `arr` node.
     pub fn arr_len(&self, arr_id: Ts) -> Option<usize> {
         match IndexExt::get(&self.model.index, &arr_id) {
@@ -0,0 +1,536 @@
+//! Typed node handles for [`ModelApi`].
+//!
+//! Mirrors `packages/json-joy/src/json-crdt/model/api/nodes.ts`.
+//!
+//! # Overview
+//!
+//! [`NodeApi`] is an untyped handle to a node; [`NodeApi::as_str`] and its
+//! siblings check the node kind once and return a typed handle
+//! ([`StrApi`], [`BinApi`], [`ArrApi`], [`ObjApi`], [`VecApi`], [`ValApi`],
+//! [`ConApi`]) that only exposes the operations valid for that kind. Each
+//! handle mutably borrows the [`ModelApi`] it came from, and edits are
+//! applied immediately, like the `ModelApi` methods they delegate to.
+//!
+//! ```ignore
+//! api.str(&[json!("title")])?.ins(0, "Hello ")?;
+//! api.arr(&[json!("tags")])?.ins(0, &[json!("draft")])?;
+//! ```
+//!
+//! Typed lookups (everything except [`NodeApi::as_val`]) look through `val`
+//! registers, so `api.con(&[json!("list"), json!(0)])` reaches the constant
+//! stored inside an array element's register.
+
+use serde_json::Value;
+
+use super::{find_path, ApiError, ModelApi, NodeView};
+use crate::json_crdt::extensions::{ExtHandle, ExtRef};
+use crate::json_crdt::nodes::{CrdtNode, IndexExt};
+use crate::json_crdt_patch::clock::Ts;
+
+// ── NodeApi ─────────────────────────────────────────────────────────────────
+
+/// Untyped handle to a node in the document.
+///
+/// Mirrors `NodeApi` in the upstream TypeScript.
+pub struct NodeApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+impl<'m, 'a> NodeApi<'m, 'a> {
+    pub(super) fn new(api: &'m mut ModelApi<'a>, id: Ts) -> Self {
+        Self { api, id }
+    }
+
+    /// ID of the node this handle points to.
+    pub fn id(&self) -> Ts {
+        self.id
+    }
+
+    /// JSON view of the node.
+    pub fn view(&self) -> Value {
+        view_of(self.api, self.id)
+    }
+
+    /// Navigates to a descendant by `path`.
+    ///
+    /// Mirrors `NodeApi.in()` in the upstream TypeScript.
+    pub fn find(self, path: &[Value]) -> Result<NodeApi<'m, 'a>, ApiError> {
+        let id = find_path(self.api.model, self.id, path)?;
+        Ok(NodeApi { api: self.api, id })
+    }
+
+    /// Returns the node as a `val` register handle, without unwrapping it.
+    pub fn as_val(self) -> Result<ValApi<'m, 'a>, ApiError> {
+        match IndexExt::get(&self.api.model.index, &self.id) {
+            Some(CrdtNode::Val(_)) => Ok(ValApi {
+                api: self.api,
+                id: self.id,
+            }),
+            Some(_) => Err(ApiError::WrongType),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    /// Navigates by `path` and returns the target as a `val` register.
+    pub fn val(self, path: &[Value]) -> Result<ValApi<'m, 'a>, ApiError> {
+        self.find(path)?.as_val()
+    }
+}
+
+macro_rules! typed_lookup {
+    ($as_fn:ident, $path_fn:ident, $variant:ident, $handle:ident, $kind:literal) => {
+        impl<'m, 'a> NodeApi<'m, 'a> {
+            #[doc = concat!("Returns the node as a `", $kind, "` handle, looking through `val` registers.")]
+            pub fn $as_fn(self) -> Result<$handle<'m, 'a>, ApiError> {
+                let id = leaf(self.api, self.id);
+                match IndexExt::get(&self.api.model.index, &id) {
+                    Some(CrdtNode::$variant(_)) => Ok($handle { api: self.api, id }),
+                    Some(_) => Err(ApiError::WrongType),
+                    None => Err(ApiError::NotFound),
+                }
+            }
+
+            #[doc = concat!("Navigates by `path` and returns the target as a `", $kind, "` handle.")]
+            pub fn $path_fn(self, path: &[Value]) -> Result<$handle<'m, 'a>, ApiError> {
+                self.find(path)?.$as_fn()
+            }
+        }
+    };
+}
+
+impl<'m, 'a> NodeApi<'m, 'a> {
+    /// Returns the node as the extension API object `E`, looking through
+    /// `val` registers.
+    ///
+    /// Mirrors `NodeApi.asExt()` in the upstream TypeScript.
+    pub fn as_ext<E: ExtHandle<'m, 'a>>(self) -> Result<E, ApiError> {
+        let id = leaf(self.api, self.id);
+        if !self.api.model.index.contains_ts(&id) {
+            return Err(ApiError::NotFound);
+        }
+        match ExtRef::of(&self.api.model.index, id) {
+            Some((ext_id, node)) if ext_id == E::EXT_ID => Ok(E::wrap(self.api, node)),
+            _ => Err(ApiError::WrongType),
+        }
+    }
+
+    /// Navigates by `path` and returns the target as the extension API
+    /// object `E`.
+    pub fn ext<E: ExtHandle<'m, 'a>>(self, path: &[Value]) -> Result<E, ApiError> {
+        self.find(path)?.as_ext()
+    }
+}
+
+typed_lookup!(as_con, con, Con, ConApi, "con");
+typed_lookup!(as_obj, obj, Obj, ObjApi, "obj");
+typed_lookup!(as_vec, vec, Vec, VecApi, "vec");
+typed_lookup!(as_str, str, Str, StrApi, "str");
+typed_lookup!(as_bin, bin, Bin, BinApi, "bin");
+typed_lookup!(as_arr, arr, Arr, ArrApi, "arr");
+
+/// Follows `val` registers starting at `id`.
+fn leaf(api: &ModelApi<'_>, mut id: Ts) -> Ts {
+    while let Some(CrdtNode::Val(v)) = IndexExt::get(&api.model.index, &id) {
+        id = v.val;
+    }
+    id
+}
+
+fn view_of(api: &ModelApi<'_>, id: Ts) -> Value {
+    NodeView {
+        id,
+        model: api.model,
+    }
+    .view()
+}
+
+macro_rules! common_methods {
+    ($handle:ident) => {
+        impl<'m, 'a> $handle<'m, 'a> {
+            /// ID of the node this handle points to.
+            pub fn id(&self) -> Ts {
+                self.id
+            }
+
+            /// JSON view of the node.
+            pub fn view(&self) -> Value {
+                view_of(self.api, self.id)
+            }
+
+            /// Converts back into an untyped handle.
+            pub fn into_node(self) -> NodeApi<'m, 'a> {
+                NodeApi::new(self.api, self.id)
+            }
+
+            /// Navigates to a descendant by `path`, reborrowing this handle.
+            pub fn find(&mut self, path: &[Value]) -> Result<NodeApi<'_, 'a>, ApiError> {
+                let id = find_path(self.api.model, self.id, path)?;
+                Ok(NodeApi::new(self.api, id))
+            }
+        }
+    };
+}
+
+// ── ConApi ──────────────────────────────────────────────────────────────────
+
+/// Handle to a `con` (constant) node.
+///
+/// Mirrors `ConApi` in the upstream TypeScript.
+pub struct ConApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+common_methods!(ConApi);
+
+// ── ValApi ──────────────────────────────────────────────────────────────────
+
+/// Handle to a `val` (LWW register) node.
+///
+/// Mirrors `ValApi` in the upstream TypeScript.
+pub struct ValApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+common_methods!(ValApi);
+
+impl<'m, 'a> ValApi<'m, 'a> {
+    /// ID of the node currently stored in the register.
+    pub fn get(&self) -> Ts {
+        match IndexExt::get(&self.api.model.index, &self.id) {
+            Some(CrdtNode::Val(v)) => v.val,
+            _ => unreachable!("ValApi always points to a val node"),
+        }
+    }
+
+    /// Replaces the register value.
+    pub fn set(&mut self, json: &Value) -> Result<(), ApiError> {
+        self.api.val_set(self.id, json)
+    }
+}
+
+// ── ObjApi ──────────────────────────────────────────────────────────────────
+
+/// Handle to an `obj` (LWW map) node.
+///
+/// Mirrors `ObjApi` in the upstream TypeScript.
+pub struct ObjApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+common_methods!(ObjApi);
+
+impl<'m, 'a> ObjApi<'m, 'a> {
+    /// Sets one or more keys.
+    pub fn set(&mut self, entries: &[(String, Value)]) -> Result<(), ApiError> {
+        self.api.obj_set(self.id, entries)
+    }
+
+    /// Deletes keys.
+    pub fn del(&mut self, keys: &[String]) -> Result<(), ApiError> {
+        self.api.obj_del(self.id, keys)
+    }
+
+    /// Returns `true` if `key` is set.
+    pub fn has(&self, key: &str) -> bool {
+        self.api.obj_has(self.id, key)
+    }
+
+    /// ID of the node stored at `key`.
+    pub fn get(&self, key: &str) -> Option<Ts> {
+        self.api.obj_get(self.id, key)
+    }
+}
+
+// ── VecApi ──────────────────────────────────────────────────────────────────
+
+/// Handle to a `vec` (LWW tuple) node.
+///
+/// Mirrors `VecApi` in the upstream TypeScript.
+pub struct VecApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+common_methods!(VecApi);
+
+impl<'m, 'a> VecApi<'m, 'a> {
+    /// Sets one or more elements.
+    pub fn set(&mut self, entries: &[(usize, Value)]) -> Result<(), ApiError> {
+        self.api.vec_set(self.id, entries)
+    }
+
+    /// ID of the node stored at `index`.
+    pub fn get(&self, index: usize) -> Option<Ts> {
+        match IndexExt::get(&self.api.model.index, &self.id) {
+            Some(CrdtNode::Vec(n)) => n.elements.get(index).copied().flatten(),
+            _ => None,
+        }
+    }
+
+    /// Number of slots, including unset ones.
+    pub fn len(&self) -> usize {
+        match IndexExt::get(&self.api.model.index, &self.id) {
+            Some(CrdtNode::Vec(n)) => n.elements.len(),
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` if the tuple has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ── StrApi ──────────────────────────────────────────────────────────────────
+
+/// Handle to a `str` (RGA string) node.
+///
+/// Mirrors `StrApi` in the upstream TypeScript.
+pub struct StrApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+common_methods!(StrApi);
+
+impl<'m, 'a> StrApi<'m, 'a> {
+    /// Inserts `text` at character position `index`.
+    pub fn ins(&mut self, index: usize, text: &str) -> Result<(), ApiError> {
+        self.api.str_ins(self.id, index, text)
+    }
+
+    /// Deletes `length` characters starting at `index`.
+    pub fn del(&mut self, index: usize, length: usize) -> Result<(), ApiError> {
+        self.api.str_del(self.id, index, length)
+    }
+
+    /// Number of live characters.
+    pub fn len(&self) -> usize {
+        self.api.str_len(self.id).unwrap_or(0)
+    }
+
+    /// Returns `true` if the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ── BinApi ──────────────────────────────────────────────────────────────────
+
+/// Handle to a `bin` (RGA binary) node.
+///
+/// Mirrors `BinApi` in the upstream TypeScript.
+pub struct BinApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+common_methods!(BinApi);
+
+impl<'m, 'a> BinApi<'m, 'a> {
+    /// Inserts `data` at byte position `index`.
+    pub fn ins(&mut self, index: usize, data: &[u8]) -> Result<(), ApiError> {
+        self.api.bin_ins(self.id, index, data)
+    }
+
+    /// Deletes `length` bytes starting at `index`.
+    pub fn del(&mut self, index: usize, length: usize) -> Result<(), ApiError> {
+        self.api.bin_del(self.id, index, length)
+    }
+
+    /// Number of live bytes.
+    pub fn len(&self) -> usize {
+        self.api.bin_len(self.id).unwrap_or(0)
+    }
+
+    /// Returns `true` if the blob is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ── ArrApi ──────────────────────────────────────────────────────────────────
+
+/// Handle to an `arr` (RGA array) node.
+///
+/// Mirrors `ArrApi` in the upstream TypeScript.
+pub struct ArrApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    id: Ts,
+}
+
+common_methods!(ArrApi);
+
+impl<'m, 'a> ArrApi<'m, 'a> {
+    /// Inserts `values` at position `index`.
+    pub fn ins(&mut self, index: usize, values: &[Value]) -> Result<(), ApiError> {
+        self.api.arr_ins(self.id, index, values)
+    }
+
+    /// Deletes `length` elements starting at `index`.
+    pub fn del(&mut self, index: usize, length: usize) -> Result<(), ApiError> {
+        self.api.arr_del(self.id, index, length)
+    }
+
+    /// Moves the element at `from` so that it ends up at index `to`.
+    pub fn mov(&mut self, from: usize, to: usize) -> Result<(), ApiError> {
+        self.api.arr_move(self.id, from, to)
+    }
+
+    /// ID of the element at `index`.
+    pub fn get(&self, index: usize) -> Option<Ts> {
+        self.api.arr_get(self.id, index)
+    }
+
+    /// Number of live elements.
+    pub fn len(&self) -> usize {
+        self.api.arr_len(self.id).unwrap_or(0)
+    }
+
+    /// Returns `true` if the array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ── ModelApi entry points ───────────────────────────────────────────────────
+
+macro_rules! root_lookup {
+    ($path_fn:ident, $handle:ident, $kind:literal) => {
+        #[doc = concat!("Navigates from the document root by `path` and returns a `", $kind, "` handle.")]
+        pub fn $path_fn(&mut self, path: &[Value]) -> Result<$handle<'_, 'a>, ApiError> {
+            self.root().$path_fn(path)
+        }
+    };
+}
+
+impl<'a> ModelApi<'a> {
+    /// Untyped handle to the node identified by `id`.
+    ///
+    /// Mirrors `ModelApi.wrap()` in the upstream TypeScript.
+    pub fn wrap(&mut self, id: Ts) -> NodeApi<'_, 'a> {
+        NodeApi::new(self, id)
+    }
+
+    /// Untyped handle to the document root's current value.
+    ///
+    /// Mirrors `ModelApi.r` in the upstream TypeScript.
+    pub fn root(&mut self) -> NodeApi<'_, 'a> {
+        let id = self.model.root.val;
+        NodeApi::new(self, id)
+    }
+
+    root_lookup!(con, ConApi, "con");
+    root_lookup!(val, ValApi, "val");
+    root_lookup!(obj, ObjApi, "obj");
+    root_lookup!(vec, VecApi, "vec");
+    root_lookup!(str, StrApi, "str");
+    root_lookup!(bin, BinApi, "bin");
+    root_lookup!(arr, ArrApi, "arr");
+
+    /// Navigates from the document root by `path` and returns the extension
+    /// API object `E`.
+    pub fn ext<'m, E: ExtHandle<'m, 'a>>(&'m mut self, path: &[Value]) -> Result<E, ApiError> {
+        self.root().ext(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::model::Model;
+    use serde_json::json;
+
+    fn doc(json: Value) -> Model {
+        let mut model = Model::create();
+        ModelApi::new(&mut model).set(&json).unwrap();
+        model
+    }
+
+    #[test]
+    fn str_handle_edits_nested_string() {
+        let mut model = doc(json!({"title": "world"}));
+        let mut api = ModelApi::new(&mut model);
+        let mut title = api.str(&[json!("title")]).unwrap();
+        title.ins(0, "hello ").unwrap();
+        title.del(5, 1).unwrap();
+        assert_eq!(title.len(), 10);
+        assert_eq!(title.view(), json!("helloworld"));
+        assert_eq!(model.view(), json!({"title": "helloworld"}));
+    }
+
+    #[test]
+    fn typed_lookup_rejects_wrong_kind() {
+        let mut model = doc(json!({"title": "x", "tags": []}));
+        let mut api = ModelApi::new(&mut model);
+        assert_eq!(api.arr(&[json!("title")]).err(), Some(ApiError::WrongType));
+        assert_eq!(api.str(&[json!("missing")]).err(), Some(ApiError::NotFound));
+        assert!(api.arr(&[json!("tags")]).is_ok());
+    }
+
+    #[test]
+    fn arr_obj_and_vec_handles() {
+        let mut model = doc(json!({"tags": ["a"], "meta": {}}));
+        let mut api = ModelApi::new(&mut model);
+        let mut tags = api.arr(&[json!("tags")]).unwrap();
+        tags.ins(1, &[json!("b"), json!(3)]).unwrap();
+        tags.del(0, 1).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags.view(), json!(["b", 3]));
+
+        let meta_id = {
+            let mut meta = api.obj(&[json!("meta")]).unwrap();
+            meta.set(&[("k".into(), json!(1))]).unwrap();
+            assert!(meta.has("k"));
+            meta.del(&["k".into()]).unwrap();
+            assert_eq!(meta.view(), json!({}));
+            meta.id()
+        };
+
+        let vec_id = api.builder.vec();
+        api.builder.ins_obj(meta_id, vec![("pos".into(), vec_id)]);
+        api.apply();
+        let mut pos = api.vec(&[json!("meta"), json!("pos")]).unwrap();
+        pos.set(&[(1, json!(7))]).unwrap();
+        assert_eq!(pos.len(), 2);
+        assert!(pos.get(0).is_none());
+        assert_eq!(model.view()["meta"]["pos"], json!([null, 7]));
+    }
+
+    #[test]
+    fn val_and_con_handles_look_through_registers() {
+        let mut model = doc(json!({"list": [1, 2]}));
+        let mut api = ModelApi::new(&mut model);
+        let mut first = api.val(&[json!("list"), json!(0)]).unwrap();
+        first.set(&json!(10)).unwrap();
+        assert_eq!(first.view(), json!(10));
+        let con = api.con(&[json!("list"), json!(0)]).unwrap();
+        assert_eq!(con.view(), json!(10));
+        assert_eq!(api.val(&[json!("list")]).err(), Some(ApiError::WrongType));
+    }
+
+    #[test]
+    fn bin_handle_and_nested_navigation() {
+        let mut model = doc(json!({"doc": {}}));
+        let mut api = ModelApi::new(&mut model);
+        let bin_id = api.builder.bin();
+        let doc_id = api.obj(&[json!("doc")]).unwrap().id();
+        api.builder.ins_obj(doc_id, vec![("blob".into(), bin_id)]);
+        api.apply();
+
+        let mut root = api.obj(&[]).unwrap();
+        let mut blob = root
+            .find(&[json!("doc"), json!("blob")])
+            .unwrap()
+            .as_bin()
+            .unwrap();
+        blob.ins(0, &[1, 2, 3]).unwrap();
+        blob.del(1, 1).unwrap();
+        assert_eq!(blob.len(), 2);
+        assert_eq!(api.wrap(bin_id).as_bin().unwrap().len(), 2);
+    }
+}
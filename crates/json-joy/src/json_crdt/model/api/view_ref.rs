@@ -0,0 +1,429 @@
+//! Borrowing, allocation-free read access to a document.
+//!
+//! Not part of the upstream TypeScript, where `view()` results are shared
+//! JS objects and reading them is already cheap.
+//!
+//! [`ViewRef`] reads the same data as [`CrdtNode::view`] but borrows it from
+//! the node index instead of building a [`Value`]: objects and arrays are
+//! walked through their child IDs, strings are read chunk by chunk from the
+//! RGA, and `val` registers are followed transparently. Use
+//! [`ViewRef::to_value`] to materialize a subtree when an owned copy is
+//! needed.
+//!
+//! Extension nodes are exposed as their underlying CRDT structure.
+
+use std::fmt;
+
+use json_joy_json_pack::PackValue;
+use serde_json::Value;
+
+use super::NodeView;
+use crate::json_crdt::model::Model;
+use crate::json_crdt::nodes::{
+    ArrNode, BinNode, ConNode, CrdtNode, IndexExt, NodeIndex, ObjNode, StrNode, VecNode,
+};
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::operations::ConValue;
+
+// ── ViewRef ───────────────────────────────────────────────────────────────
+
+/// A borrowed view of a document value.
+#[derive(Clone, Copy)]
+pub enum ViewRef<'a> {
+    /// A missing node, which views as `null`.
+    Null,
+    Con(&'a ConNode),
+    Str(StrRef<'a>),
+    Bin(&'a BinNode),
+    Obj(ObjRef<'a>),
+    Vec(VecRef<'a>),
+    Arr(ArrRef<'a>),
+}
+
+impl<'a> ViewRef<'a> {
+    /// View of node `id` in `index`, following `val` registers.
+    pub fn new(index: &'a NodeIndex, id: Ts) -> Self {
+        let mut id = id;
+        loop {
+            return match IndexExt::get(index, &id) {
+                None => ViewRef::Null,
+                Some(CrdtNode::Val(node)) => {
+                    id = node.val;
+                    continue;
+                }
+                Some(CrdtNode::Con(node)) => ViewRef::Con(node),
+                Some(CrdtNode::Str(node)) => ViewRef::Str(StrRef { node }),
+                Some(CrdtNode::Bin(node)) => ViewRef::Bin(node),
+                Some(CrdtNode::Obj(node)) => ViewRef::Obj(ObjRef { node, index }),
+                Some(CrdtNode::Vec(node)) => ViewRef::Vec(VecRef { node, index }),
+                Some(CrdtNode::Arr(node)) => ViewRef::Arr(ArrRef { node, index }),
+            };
+        }
+    }
+
+    /// Whether this views as `null`.
+    pub fn is_null(&self) -> bool {
+        match self {
+            ViewRef::Null => true,
+            ViewRef::Con(node) => matches!(
+                node.val,
+                ConValue::Ref(_) | ConValue::Val(PackValue::Null | PackValue::Undefined)
+            ),
+            _ => false,
+        }
+    }
+
+    /// The constant value, if this is a `con` node holding one.
+    pub fn as_pack(&self) -> Option<&'a PackValue> {
+        match self {
+            ViewRef::Con(ConNode {
+                val: ConValue::Val(value),
+                ..
+            }) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.as_pack()? {
+            PackValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.as_pack()? {
+            PackValue::Integer(n) => Some(*n),
+            PackValue::UInteger(n) => i64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.as_pack()? {
+            PackValue::Float(n) => Some(*n),
+            PackValue::Integer(n) => Some(*n as f64),
+            PackValue::UInteger(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// The string, if this is a `str` node.
+    pub fn as_str(&self) -> Option<StrRef<'a>> {
+        match self {
+            ViewRef::Str(s) => Some(*s),
+            _ => None,
+        }
+    }
+
+    /// The string constant, if this is a `con` node holding one.
+    pub fn as_con_str(&self) -> Option<&'a str> {
+        match self.as_pack()? {
+            PackValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_obj(&self) -> Option<ObjRef<'a>> {
+        match self {
+            ViewRef::Obj(obj) => Some(*obj),
+            _ => None,
+        }
+    }
+
+    /// Member `key`, if this is an object holding it.
+    pub fn get(&self, key: &str) -> Option<ViewRef<'a>> {
+        self.as_obj()?.get(key)
+    }
+
+    /// Element `index`, if this is an array or vector that long.
+    pub fn at(&self, index: usize) -> Option<ViewRef<'a>> {
+        match self {
+            ViewRef::Arr(arr) => arr.iter().nth(index),
+            ViewRef::Vec(vec) => vec.get(index),
+            _ => None,
+        }
+    }
+
+    /// Materialize this value, as [`CrdtNode::view`] does.
+    pub fn to_value(&self) -> Value {
+        match self {
+            ViewRef::Null => Value::Null,
+            ViewRef::Con(node) => node.view(),
+            ViewRef::Str(s) => s.node.view(),
+            ViewRef::Bin(node) => node.view_json(),
+            ViewRef::Obj(obj) => obj.node.view(obj.index),
+            ViewRef::Vec(vec) => vec.node.view(vec.index),
+            ViewRef::Arr(arr) => arr.node.view(arr.index),
+        }
+    }
+}
+
+impl fmt::Debug for ViewRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ViewRef({})", self.to_value())
+    }
+}
+
+// ── StrRef ────────────────────────────────────────────────────────────────
+
+/// A borrowed `str` node, assembled from its chunks on demand.
+#[derive(Clone, Copy)]
+pub struct StrRef<'a> {
+    node: &'a StrNode,
+}
+
+impl<'a> StrRef<'a> {
+    /// The visible text, chunk by chunk.
+    pub fn chunks(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.node.rga.iter_live().filter_map(|c| c.data.as_deref())
+    }
+
+    /// Length in UTF-16 code units.
+    pub fn len(&self) -> usize {
+        self.node.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks().all(str::is_empty)
+    }
+
+    /// The visible characters.
+    pub fn chars(&self) -> impl Iterator<Item = char> + 'a {
+        self.chunks().flat_map(str::chars)
+    }
+
+    /// Whether the text equals `other`, compared without assembling it.
+    pub fn eq_str(&self, other: &str) -> bool {
+        let mut rest = other;
+        for chunk in self.chunks() {
+            match rest.strip_prefix(chunk) {
+                Some(tail) => rest = tail,
+                None => return false,
+            }
+        }
+        rest.is_empty()
+    }
+}
+
+impl fmt::Display for StrRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+impl fmt::Debug for StrRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl PartialEq<str> for StrRef<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<&str> for StrRef<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+// ── ObjRef ────────────────────────────────────────────────────────────────
+
+/// A borrowed `obj` node.
+#[derive(Clone, Copy)]
+pub struct ObjRef<'a> {
+    node: &'a ObjNode,
+    index: &'a NodeIndex,
+}
+
+impl<'a> ObjRef<'a> {
+    /// Member `key`; omitted members (see [`iter`](Self::iter)) are `None`.
+    pub fn get(&self, key: &str) -> Option<ViewRef<'a>> {
+        let id = *self.node.keys.get(key)?;
+        member(self.index, id)
+    }
+
+    /// Members in insertion order. Like [`ObjNode::view`], skips keys set
+    /// to `undefined` or to a missing node.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, ViewRef<'a>)> + 'a {
+        let index = self.index;
+        self.node
+            .keys
+            .iter()
+            .filter_map(move |(key, id)| Some((key.as_str(), member(index, *id)?)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Number of members.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// An object member, unless the object view omits it.
+fn member(index: &NodeIndex, id: Ts) -> Option<ViewRef<'_>> {
+    match IndexExt::get(index, &id)? {
+        CrdtNode::Con(ConNode {
+            val: ConValue::Val(PackValue::Undefined),
+            ..
+        }) => None,
+        _ => Some(ViewRef::new(index, id)),
+    }
+}
+
+// ── VecRef / ArrRef ───────────────────────────────────────────────────────
+
+/// A borrowed `vec` node.
+#[derive(Clone, Copy)]
+pub struct VecRef<'a> {
+    node: &'a VecNode,
+    index: &'a NodeIndex,
+}
+
+impl<'a> VecRef<'a> {
+    /// Element `index`; unset elements are [`ViewRef::Null`].
+    pub fn get(&self, index: usize) -> Option<ViewRef<'a>> {
+        let element = self.node.elements.get(index)?;
+        Some(element.map_or(ViewRef::Null, |id| ViewRef::new(self.index, id)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ViewRef<'a>> + 'a {
+        let index = self.index;
+        self.node
+            .elements
+            .iter()
+            .map(move |element| element.map_or(ViewRef::Null, |id| ViewRef::new(index, id)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.node.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node.elements.is_empty()
+    }
+}
+
+/// A borrowed `arr` node.
+#[derive(Clone, Copy)]
+pub struct ArrRef<'a> {
+    node: &'a ArrNode,
+    index: &'a NodeIndex,
+}
+
+impl<'a> ArrRef<'a> {
+    /// Visible elements in order.
+    pub fn iter(&self) -> impl Iterator<Item = ViewRef<'a>> + 'a {
+        let index = self.index;
+        self.node
+            .rga
+            .iter_live()
+            .filter_map(|chunk| chunk.data.as_ref())
+            .flatten()
+            .map(move |id| ViewRef::new(index, *id))
+    }
+
+    /// Number of visible elements.
+    pub fn len(&self) -> usize {
+        self.node.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ── Entry points ──────────────────────────────────────────────────────────
+
+impl Model {
+    /// Borrowed view of the document root.
+    pub fn view_ref(&self) -> ViewRef<'_> {
+        ViewRef::new(&self.index, self.root.val)
+    }
+}
+
+impl<'a> NodeView<'a> {
+    /// Borrowed view of this node, without building a [`Value`].
+    pub fn view_ref(&self) -> ViewRef<'a> {
+        ViewRef::new(&self.model.index, self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::model::api::ModelApi;
+    use serde_json::json;
+
+    fn model(value: Value) -> Model {
+        let mut model = Model::create();
+        ModelApi::new(&mut model).set_root(&value).unwrap();
+        model
+    }
+
+    #[test]
+    fn reads_nested_values() {
+        let model = model(json!({
+            "user": {"name": "Ada", "age": 36, "admin": true},
+            "tags": ["x", 2.5, null],
+        }));
+        let root = model.view_ref();
+        let user = root.get("user").unwrap();
+        assert!(user.get("name").unwrap().as_str().unwrap() == "Ada");
+        assert_eq!(user.get("age").unwrap().as_i64(), Some(36));
+        assert_eq!(user.get("admin").unwrap().as_bool(), Some(true));
+        assert!(user.get("email").is_none());
+        assert_eq!(
+            root.as_obj().unwrap().keys().collect::<Vec<_>>(),
+            ["user", "tags"]
+        );
+        let tags = root.get("tags").unwrap();
+        assert_eq!(tags.at(1).unwrap().as_f64(), Some(2.5));
+        assert!(tags.at(2).unwrap().is_null());
+        assert!(tags.at(3).is_none());
+        assert_eq!(root.to_value(), model.view());
+    }
+
+    #[test]
+    fn assembles_strings_from_chunks() {
+        let mut model = model(json!({"text": "world"}));
+        let text = model.view_ref().get("text").unwrap().as_str().unwrap();
+        let id = text.node.id;
+        let mut api = ModelApi::new(&mut model);
+        api.str_ins(id, 0, "hello ").unwrap();
+        api.str_del(id, 2, 2).unwrap();
+        api.str_ins(id, 9, "!").unwrap();
+        api.apply();
+
+        let text = model.view_ref().get("text").unwrap().as_str().unwrap();
+        assert!(text.chunks().count() > 1);
+        assert!(text == "heo world!");
+        assert!(!text.eq_str("heo world"));
+        assert_eq!(text.len(), 10);
+        assert_eq!(text.to_string(), "heo world!");
+        assert_eq!(text.chars().last(), Some('!'));
+    }
+
+    #[test]
+    fn node_view_borrows() {
+        let mut model = model(json!({"list": [1, {"a": "b"}]}));
+        let api = ModelApi::new(&mut model);
+        let list = api.root_view().find(&[json!("list")]).unwrap().view_ref();
+        let ViewRef::Arr(arr) = list else {
+            panic!("expected an arr");
+        };
+        assert_eq!(arr.len(), 2);
+        let values: Vec<Value> = arr.iter().map(|item| item.to_value()).collect();
+        assert_eq!(values, [json!(1), json!({"a": "b"})]);
+    }
+}
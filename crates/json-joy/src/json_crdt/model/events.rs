@@ -0,0 +1,374 @@
+//! Change subscription for a JSON CRDT [`Model`].
+//!
+//! Mirrors the `onBeforePatch` / `onPatch` fan-outs of the upstream
+//! `ModelApi` and the `onSelfChange` / `onSubtreeChange` listeners of
+//! `NodeApi`.
+//!
+//! # Overview
+//!
+//! Listeners are registered on the model itself and are invoked by
+//! [`Model::apply_patch`]:
+//!
+//! 1. Before-patch listeners receive the patch and its [`ChangeOrigin`]
+//!    before any operation is applied.
+//! 2. After the patch is applied, patch listeners receive a [`ChangeEvent`]
+//!    carrying the IDs of every node whose contents were mutated.
+//! 3. Node listeners receive the same event, but only when the watched node
+//!    (or, for subtree listeners, any of its descendants) is among the
+//!    changed IDs.
+//!
+//! Listeners are bound to a model instance: cloning a model does not copy
+//! them, and [`Model::apply_operation`] does not emit events.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use super::Model;
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::nodes::IndexExt;
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::patch::Patch;
+
+/// Where a patch came from, relative to the model it is applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeOrigin {
+    /// The patch was authored by the model's own session.
+    Local,
+    /// The patch was authored by another session.
+    Remote,
+}
+
+impl ChangeOrigin {
+    /// Classifies `patch` against the session ID `sid`.
+    pub fn of(patch: &Patch, sid: u64) -> Self {
+        match patch.get_id() {
+            Some(id) if id.sid == sid => Self::Local,
+            _ => Self::Remote,
+        }
+    }
+}
+
+/// A change notification emitted after a patch has been applied.
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeEvent<'a> {
+    /// The patch that was applied.
+    pub patch: &'a Patch,
+    /// Whether the patch was authored locally or by a remote peer.
+    pub origin: ChangeOrigin,
+    /// IDs of the nodes whose contents were mutated, in first-touched order.
+    ///
+    /// The document root register is reported as [`ORIGIN`].
+    pub changed: &'a [Ts],
+}
+
+impl ChangeEvent<'_> {
+    /// Returns `true` if the node `id` itself was mutated.
+    pub fn touches(&self, id: Ts) -> bool {
+        self.changed.contains(&id)
+    }
+}
+
+/// Handle returned by the subscription methods; pass it to
+/// [`Model::off`] to unsubscribe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+type BeforePatchListener = Box<dyn FnMut(&Model, &Patch, ChangeOrigin) + Send + Sync>;
+type ChangeListener = Box<dyn FnMut(&Model, &ChangeEvent<'_>) + Send + Sync>;
+
+/// Which changes a [`ChangeListener`] is interested in.
+enum Scope {
+    /// Every applied patch.
+    Patch,
+    /// Changes to the node itself.
+    Node(Ts),
+    /// Changes to the node or any of its descendants.
+    Subtree(Ts),
+}
+
+/// Registry of listeners attached to a [`Model`].
+#[derive(Default)]
+pub(crate) struct Listeners {
+    next_id: u64,
+    before: Vec<(ListenerId, BeforePatchListener)>,
+    after: Vec<(ListenerId, Scope, ChangeListener)>,
+}
+
+impl Listeners {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.after.is_empty()
+    }
+
+    fn next_id(&mut self) -> ListenerId {
+        self.next_id += 1;
+        ListenerId(self.next_id)
+    }
+
+    pub(crate) fn emit_before(&mut self, model: &Model, patch: &Patch, origin: ChangeOrigin) {
+        for (_, listener) in &mut self.before {
+            listener(model, patch, origin);
+        }
+    }
+
+    pub(crate) fn emit(&mut self, model: &Model, event: &ChangeEvent<'_>) {
+        for (_, scope, listener) in &mut self.after {
+            let matches = match scope {
+                Scope::Patch => true,
+                Scope::Node(id) => event.touches(*id),
+                Scope::Subtree(id) => subtree_touched(model, *id, event.changed),
+            };
+            if matches {
+                listener(model, event);
+            }
+        }
+    }
+}
+
+/// Listeners are bound to a model instance and are not copied by clones.
+impl Clone for Listeners {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Listeners")
+            .field("before", &self.before.len())
+            .field("after", &self.after.len())
+            .finish()
+    }
+}
+
+/// Returns `true` if `root` or any node reachable from it is in `changed`.
+fn subtree_touched(model: &Model, root: Ts, changed: &[Ts]) -> bool {
+    if changed.is_empty() {
+        return false;
+    }
+    if root == ORIGIN {
+        return true;
+    }
+    let mut seen: HashSet<Ts> = HashSet::new();
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        if changed.contains(&id) {
+            return true;
+        }
+        if let Some(node) = IndexExt::get(&model.index, &id) {
+            stack.extend(node.child_ids());
+        }
+    }
+    false
+}
+
+impl Model {
+    /// Registers a listener invoked before each patch is applied.
+    ///
+    /// Mirrors `ModelApi.onBeforePatch` in the upstream TypeScript.
+    pub fn on_before_patch<F>(&mut self, listener: F) -> ListenerId
+    where
+        F: FnMut(&Model, &Patch, ChangeOrigin) + Send + Sync + 'static,
+    {
+        let id = self.listeners.next_id();
+        self.listeners.before.push((id, Box::new(listener)));
+        id
+    }
+
+    /// Registers a listener invoked after each patch is applied.
+    ///
+    /// Mirrors `ModelApi.onPatch` in the upstream TypeScript.
+    pub fn on_patch<F>(&mut self, listener: F) -> ListenerId
+    where
+        F: FnMut(&Model, &ChangeEvent<'_>) + Send + Sync + 'static,
+    {
+        self.subscribe(Scope::Patch, listener)
+    }
+
+    /// Registers a listener invoked when the node `id` itself is mutated.
+    ///
+    /// Use [`ORIGIN`] to watch the document root register.
+    ///
+    /// Mirrors `NodeApi.onSelfChange` in the upstream TypeScript.
+    pub fn on_self_change<F>(&mut self, id: Ts, listener: F) -> ListenerId
+    where
+        F: FnMut(&Model, &ChangeEvent<'_>) + Send + Sync + 'static,
+    {
+        self.subscribe(Scope::Node(id), listener)
+    }
+
+    /// Registers a listener invoked when the node `id` or any node in its
+    /// subtree is mutated.
+    ///
+    /// Use [`ORIGIN`] to watch the whole document.
+    ///
+    /// Mirrors `NodeApi.onSubtreeChange` in the upstream TypeScript.
+    pub fn on_subtree_change<F>(&mut self, id: Ts, listener: F) -> ListenerId
+    where
+        F: FnMut(&Model, &ChangeEvent<'_>) + Send + Sync + 'static,
+    {
+        self.subscribe(Scope::Subtree(id), listener)
+    }
+
+    /// Removes a previously registered listener.
+    ///
+    /// Returns `false` if no listener with that ID is registered.
+    pub fn off(&mut self, id: ListenerId) -> bool {
+        let listeners = &mut self.listeners;
+        let before = listeners.before.len() + listeners.after.len();
+        listeners.before.retain(|(lid, _)| *lid != id);
+        listeners.after.retain(|(lid, _, _)| *lid != id);
+        before != listeners.before.len() + listeners.after.len()
+    }
+
+    fn subscribe<F>(&mut self, scope: Scope, listener: F) -> ListenerId
+    where
+        F: FnMut(&Model, &ChangeEvent<'_>) + Send + Sync + 'static,
+    {
+        let id = self.listeners.next_id();
+        self.listeners.after.push((id, scope, Box::new(listener)));
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::model::ModelApi;
+    use crate::json_crdt_patch::patch_builder::PatchBuilder;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    fn sid() -> u64 {
+        123456
+    }
+
+    /// Builds `{"a": {"b": "x"}, "c": 1}` and returns the model with the
+    /// IDs of the root object, the nested object and the string.
+    fn make_model() -> (Model, Ts, Ts, Ts) {
+        let mut model = Model::new(sid());
+        {
+            let mut api = ModelApi::new(&mut model);
+            api.set(&json!({"a": {"b": "x"}, "c": 1})).unwrap();
+        }
+        let root = model.root.val;
+        let api = ModelApi::new(&mut model);
+        let a = api.find(root, &[json!("a")]).unwrap();
+        let b = api.find(root, &[json!("a"), json!("b")]).unwrap();
+        (model, root, a, b)
+    }
+
+    fn counter() -> (Arc<Mutex<usize>>, Arc<Mutex<usize>>) {
+        let count = Arc::new(Mutex::new(0));
+        (count.clone(), count)
+    }
+
+    #[test]
+    fn before_and_after_patch_listeners_fire_in_order() {
+        let (mut model, _, _, b) = make_model();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l1 = log.clone();
+        model.on_before_patch(move |model, _, origin| {
+            l1.lock().unwrap().push(("before", model.view(), origin));
+        });
+        let l2 = log.clone();
+        model.on_patch(move |model, event| {
+            l2.lock()
+                .unwrap()
+                .push(("after", model.view(), event.origin));
+        });
+        let mut api = ModelApi::new(&mut model);
+        api.str_ins(b, 1, "y").unwrap();
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].0, "before");
+        assert_eq!(log[0].1, json!({"a": {"b": "x"}, "c": 1}));
+        assert_eq!(log[1].0, "after");
+        assert_eq!(log[1].1, json!({"a": {"b": "xy"}, "c": 1}));
+        assert_eq!(log[1].2, ChangeOrigin::Local);
+    }
+
+    #[test]
+    fn change_event_reports_mutated_nodes() {
+        let (mut model, root, _, b) = make_model();
+        let changed = Arc::new(Mutex::new(Vec::new()));
+        let c = changed.clone();
+        model.on_patch(move |_, event| {
+            c.lock().unwrap().extend_from_slice(event.changed);
+        });
+        let mut builder = PatchBuilder::new(sid(), model.clock.time);
+        builder.ins_str(b, b, "!".to_string());
+        let con = builder.con_val(json_joy_json_pack::PackValue::Integer(2));
+        builder.ins_obj(root, vec![("c".to_string(), con)]);
+        model.apply_patch(&builder.flush());
+        assert_eq!(*changed.lock().unwrap(), vec![b, root]);
+    }
+
+    #[test]
+    fn remote_patches_are_reported_as_remote() {
+        let (mut model, _, _, b) = make_model();
+        let origin = Arc::new(Mutex::new(None));
+        let o = origin.clone();
+        model.on_patch(move |_, event| {
+            *o.lock().unwrap() = Some(event.origin);
+        });
+        let mut builder = PatchBuilder::new(999_999, model.clock.time);
+        builder.ins_str(b, b, "!".to_string());
+        model.apply_patch(&builder.flush());
+        assert_eq!(*origin.lock().unwrap(), Some(ChangeOrigin::Remote));
+    }
+
+    #[test]
+    fn self_change_listener_only_fires_for_its_node() {
+        let (mut model, root, a, b) = make_model();
+        let (on_a, count_a) = counter();
+        let (on_b, count_b) = counter();
+        model.on_self_change(a, move |_, _| *on_a.lock().unwrap() += 1);
+        model.on_self_change(b, move |_, _| *on_b.lock().unwrap() += 1);
+        let mut api = ModelApi::new(&mut model);
+        api.str_ins(b, 0, "y").unwrap();
+        api.obj_set(root, &[("c".to_string(), json!(5))]).unwrap();
+        assert_eq!(*count_a.lock().unwrap(), 0);
+        assert_eq!(*count_b.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn subtree_listener_fires_for_descendant_changes() {
+        let (mut model, root, a, b) = make_model();
+        let (on_a, count_a) = counter();
+        let (on_root, count_root) = counter();
+        model.on_subtree_change(a, move |_, _| *on_a.lock().unwrap() += 1);
+        model.on_subtree_change(ORIGIN, move |_, _| *on_root.lock().unwrap() += 1);
+        let mut api = ModelApi::new(&mut model);
+        api.str_ins(b, 0, "y").unwrap();
+        api.obj_set(root, &[("c".to_string(), json!(5))]).unwrap();
+        assert_eq!(*count_a.lock().unwrap(), 1);
+        assert_eq!(*count_root.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn off_removes_listener() {
+        let (mut model, _, _, b) = make_model();
+        let (on_patch, count) = counter();
+        let id = model.on_patch(move |_, _| *on_patch.lock().unwrap() += 1);
+        assert!(model.off(id));
+        assert!(!model.off(id));
+        let mut api = ModelApi::new(&mut model);
+        api.str_ins(b, 0, "y").unwrap();
+        assert_eq!(*count.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn clones_do_not_inherit_listeners() {
+        let (mut model, _, _, b) = make_model();
+        let (on_patch, count) = counter();
+        model.on_patch(move |_, _| *on_patch.lock().unwrap() += 1);
+        let mut copy = model.clone();
+        let mut api = ModelApi::new(&mut copy);
+        api.str_ins(b, 0, "y").unwrap();
+        assert_eq!(*count.lock().unwrap(), 0);
+    }
+}
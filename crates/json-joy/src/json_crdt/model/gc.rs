@@ -0,0 +1,345 @@
+//! Tombstone garbage collection for a JSON CRDT [`Model`].
+//!
+//! # Overview
+//!
+//! Deleted RGA content (in `str`, `bin` and `arr` nodes) is kept as
+//! tombstone chunks so that concurrent operations can still reference it.
+//! Once every replica is known to have reached some clock — the *GC
+//! horizon* — tombstones whose delete it has seen can be dropped with
+//! [`Model::gc`] (see [`Rga::gc`](crate::json_crdt::nodes::rga::Rga::gc)
+//! for the exact stability requirement).
+//!
+//! The model remembers its horizon in [`Model::gc_horizon`]. Peers that are
+//! still behind it can no longer be served safely, so:
+//!
+//! - [`check_peer`] rejects a peer clock that has not reached the horizon
+//!   (the sync protocol does this when answering a state vector);
+//! - [`Model::check_patch`] rejects a patch that inserts or moves after a
+//!   collected item, or moves one, instead of silently misplacing it.
+//!
+//! The horizon is persisted by every snapshot codec (see
+//! [`codec::extras`](crate::json_crdt::codec::extras)).
+
+use super::Model;
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::nodes::{CrdtNode, TsKey};
+use crate::json_crdt_patch::clock::{ClockVector, Ts};
+use crate::json_crdt_patch::operations::Op;
+use crate::json_crdt_patch::patch::Patch;
+
+/// Errors reported when interacting with a garbage-collected model.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum GcError {
+    /// A peer has not reached the GC horizon; it must re-sync from a
+    /// snapshot.
+    #[error("peer is behind the GC horizon (missing {sid}.{time})")]
+    BehindHorizon { sid: u64, time: u64 },
+    /// A patch references an item that has already been collected.
+    #[error("patch references collected item {}.{}", .0.sid, .0.time)]
+    Collected(Ts),
+}
+
+impl Model {
+    /// Collects RGA tombstones deleted below `horizon` in every node and records
+    /// `horizon` as [`Model::gc_horizon`]. Returns the number of chunks
+    /// removed.
+    ///
+    /// The view is unchanged. Horizons are expected to only move forward.
+    pub fn gc(&mut self, horizon: &ClockVector) -> usize {
+        let mut removed = 0;
+        let clock = &self.clock;
+        let journaled = self.journal.is_active();
+        for node in self.index.values_mut() {
+            // Keep a copy for the running transaction to roll back to.
+            let rga = matches!(node, CrdtNode::Str(_) | CrdtNode::Bin(_) | CrdtNode::Arr(_));
+            let before = (journaled && rga).then(|| node.clone());
+            let collected = match node {
+                CrdtNode::Str(node) => node.rga.gc(horizon, clock),
+                CrdtNode::Bin(node) => node.rga.gc(horizon, clock),
+                CrdtNode::Arr(node) => node.rga.gc(horizon, clock),
+                _ => 0,
+            };
+            if let Some(before) = before.filter(|_| collected > 0) {
+                self.journal.record_copy(before);
+            }
+            removed += collected;
+        }
+        self.gc_horizon = Some(horizon.clone());
+        removed
+    }
+
+    /// Checks that `patch` does not insert after, or move, an item that was
+    /// collected by [`Model::gc`].
+    pub fn check_patch(&self, patch: &Patch) -> Result<(), GcError> {
+        let Some(horizon) = &self.gc_horizon else {
+            return Ok(());
+        };
+        for op in &patch.ops {
+            let (obj, items) = match op {
+                Op::InsStr { obj, after, .. }
+                | Op::InsBin { obj, after, .. }
+                | Op::InsArr { obj, after, .. } => (*obj, vec![*after]),
+                Op::MovArr {
+                    obj, after, elem, ..
+                } => (*obj, vec![*after, *elem]),
+                _ => continue,
+            };
+            for item in items {
+                if item == obj || item == ORIGIN || !horizon.has_seen(item) {
+                    continue;
+                }
+                let found = match self.index.get(&TsKey::from(obj)) {
+                    Some(CrdtNode::Str(node)) => node.rga.find_by_id(item).is_some(),
+                    Some(CrdtNode::Bin(node)) => node.rga.find_by_id(item).is_some(),
+                    Some(CrdtNode::Arr(node)) => node.rga.find_by_id(item).is_some(),
+                    _ => true,
+                };
+                if !found {
+                    return Err(GcError::Collected(item));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies `patch` after validating it with [`Model::check_patch`].
+    pub fn apply_patch_checked(&mut self, patch: &Patch) -> Result<(), GcError> {
+        self.check_patch(patch)?;
+        self.apply_patch(patch);
+        Ok(())
+    }
+}
+
+/// Checks that a peer with clock `peer` has reached `horizon`.
+pub fn check_peer(horizon: &ClockVector, peer: &ClockVector) -> Result<(), GcError> {
+    if peer.has_reached(horizon) {
+        return Ok(());
+    }
+    let own = (horizon.time > 1).then(|| Ts::new(horizon.sid, horizon.time - 1));
+    let missing = own
+        .into_iter()
+        .chain(horizon.peers.values().copied())
+        .find(|id| !peer.has_seen(*id))
+        .unwrap_or(Ts::new(horizon.sid, horizon.time));
+    Err(GcError::BehindHorizon {
+        sid: missing.sid,
+        time: missing.time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::model::ModelApi;
+    use crate::json_crdt_patch::clock::Tss;
+    use serde_json::json;
+
+    const A: u64 = 300_001;
+    const B: u64 = 300_002;
+
+    fn text_model() -> (Model, Ts) {
+        let mut model = Model::new(A);
+        let mut api = ModelApi::new(&mut model);
+        let id = api.builder.str_node();
+        api.builder.ins_str(id, id, "hello world".into());
+        api.builder.root(id);
+        api.apply();
+        (model, id)
+    }
+
+    fn del(model: &mut Model, str_id: Ts, time: u64, len: u64) {
+        let mut api = ModelApi::new(model);
+        api.builder.del(str_id, vec![Tss::new(A, time, len)]);
+        api.apply();
+    }
+
+    fn remote_ins(str_id: Ts, after: Ts) -> Patch {
+        Patch {
+            ops: vec![Op::InsStr {
+                id: Ts::new(B, 100),
+                obj: str_id,
+                after,
+                data: "!".into(),
+            }],
+            meta: None,
+        }
+    }
+
+    fn chunk_count(model: &Model, id: Ts) -> usize {
+        match model.index.get(&TsKey::from(id)) {
+            Some(CrdtNode::Str(node)) => node.rga.iter().count(),
+            _ => panic!("expected a str node"),
+        }
+    }
+
+    #[test]
+    fn gc_collects_tombstones_and_keeps_view() {
+        let (mut model, str_id) = text_model();
+        let first = str_id.time + 1;
+        del(&mut model, str_id, first + 5, 6);
+        assert_eq!(model.view(), json!("hello"));
+        assert_eq!(chunk_count(&model, str_id), 2);
+        let before = model.to_binary().len();
+
+        let horizon = model.clock.clone();
+        assert_eq!(model.gc(&horizon), 1);
+        assert_eq!(model.view(), json!("hello"));
+        assert_eq!(chunk_count(&model, str_id), 1);
+        // The node tree shrinks; the snapshot also gains the horizon.
+        let mut tree_only = model.clone();
+        tree_only.gc_horizon = None;
+        assert!(tree_only.to_binary().len() < before);
+    }
+
+    #[test]
+    fn tombstone_survives_until_its_delete_is_below_the_horizon() {
+        let (mut model, str_id) = text_model();
+        let first = str_id.time + 1;
+        // Peer B has reached this horizon but has not seen the delete yet.
+        let horizon = model.clock.clone();
+        del(&mut model, str_id, first + 5, 6);
+        assert_eq!(model.gc(&horizon), 0);
+
+        let late = remote_ins(str_id, Ts::new(A, first + 8));
+        assert_eq!(model.apply_patch_checked(&late), Ok(()));
+        assert_eq!(model.view(), json!("hello!"));
+    }
+
+    #[test]
+    fn insert_after_collected_item_is_rejected() {
+        let (mut model, str_id) = text_model();
+        let first = str_id.time + 1;
+        del(&mut model, str_id, first + 5, 6);
+        let horizon = model.clock.clone();
+        model.gc(&horizon);
+
+        // Every replica has seen the delete, so no well-behaved peer can
+        // still reference the collected chars.
+        let bad = remote_ins(str_id, Ts::new(A, first + 8));
+        assert_eq!(
+            model.apply_patch_checked(&bad),
+            Err(GcError::Collected(Ts::new(A, first + 8)))
+        );
+        assert_eq!(model.view(), json!("hello"));
+
+        let ok = remote_ins(str_id, Ts::new(A, first + 4));
+        assert_eq!(model.apply_patch_checked(&ok), Ok(()));
+        assert_eq!(model.view(), json!("hello!"));
+    }
+
+    #[test]
+    fn move_anchored_on_collected_item_is_rejected() {
+        let mut model = Model::new(A);
+        ModelApi::new(&mut model)
+            .set_root(&json!([1, 2, 3]))
+            .unwrap();
+        let arr_id = model.root.val;
+        let first = match model.index.get(&TsKey::from(arr_id)) {
+            Some(CrdtNode::Arr(node)) => node.rga.iter().next().unwrap().id,
+            _ => panic!("expected an arr"),
+        };
+        let second = Ts::new(first.sid, first.time + 1);
+        let mut api = ModelApi::new(&mut model);
+        api.builder
+            .del(arr_id, vec![Tss::new(second.sid, second.time, 1)]);
+        api.apply();
+        let horizon = model.clock.clone();
+        assert_eq!(model.gc(&horizon), 1);
+
+        let mov = |after: Ts, elem: Ts| Patch {
+            ops: vec![Op::MovArr {
+                id: Ts::new(B, 100),
+                obj: arr_id,
+                after,
+                elem,
+            }],
+            meta: None,
+        };
+        assert_eq!(
+            model.apply_patch_checked(&mov(second, first)),
+            Err(GcError::Collected(second))
+        );
+        assert_eq!(
+            model.check_patch(&mov(arr_id, second)),
+            Err(GcError::Collected(second))
+        );
+        assert_eq!(model.view(), json!([1, 3]));
+    }
+
+    #[test]
+    fn check_peer_reports_first_missing_entry() {
+        let mut horizon = ClockVector::new(A, 10);
+        horizon.observe(Ts::new(B, 4), 1);
+        let mut peer = ClockVector::new(B, 5);
+        assert_eq!(
+            check_peer(&horizon, &peer),
+            Err(GcError::BehindHorizon { sid: A, time: 9 })
+        );
+        peer.observe(Ts::new(A, 9), 1);
+        assert_eq!(check_peer(&horizon, &peer), Ok(()));
+    }
+
+    #[test]
+    fn snapshots_preserve_horizon() {
+        use crate::json_crdt::codec::{indexed, sidecar, structural};
+
+        let (mut model, str_id) = text_model();
+        del(&mut model, str_id, str_id.time + 6, 6);
+        assert!(Model::from_binary(&model.to_binary())
+            .unwrap()
+            .gc_horizon
+            .is_none());
+
+        let mut horizon = model.clock.clone();
+        horizon.observe(Ts::new(B, 7), 1);
+        model.gc(&horizon);
+        let (view, meta) = sidecar::binary::encode(&model);
+        let decoded = [
+            Model::from_binary(&model.to_binary()).unwrap(),
+            structural::compact::decode(&structural::compact::encode(&model)).unwrap(),
+            structural::verbose::decode(&structural::verbose::encode(&model)).unwrap(),
+            indexed::binary::decode(&indexed::binary::encode(&model)).unwrap(),
+            sidecar::binary::decode(&view, &meta).unwrap(),
+        ];
+        for decoded in decoded {
+            assert_eq!(decoded.view(), model.view());
+            let restored = decoded.gc_horizon.clone().expect("horizon restored");
+            assert_eq!(restored.sid, horizon.sid);
+            assert_eq!(restored.time, horizon.time);
+            assert_eq!(restored.peers.get(&B).map(|p| p.time), Some(7));
+
+            // The restored replica still rejects references to collected items.
+            let late = remote_ins(str_id, Ts::new(A, str_id.time + 9));
+            assert!(decoded.check_patch(&late).is_err());
+        }
+    }
+
+    #[test]
+    fn failed_transaction_restores_collected_state() {
+        let (mut model, str_id) = text_model();
+        del(&mut model, str_id, str_id.time + 6, 6);
+        let horizon = model.clock.clone();
+        let result: Result<((), _), ()> = model.transaction(|api| {
+            assert_eq!(api.model.gc(&horizon), 1);
+            Err(())
+        });
+        assert!(result.is_err());
+        assert!(model.gc_horizon.is_none());
+        assert_eq!(chunk_count(&model, str_id), 2);
+        let late = remote_ins(str_id, Ts::new(A, str_id.time + 9));
+        assert_eq!(model.apply_patch_checked(&late), Ok(()));
+        assert_eq!(model.view(), json!("hello!"));
+    }
+
+    #[test]
+    fn server_snapshot_preserves_horizon() {
+        let mut model = Model::new_server(5);
+        let mut api = ModelApi::new(&mut model);
+        api.set_root(&json!("abc")).unwrap();
+        let horizon = model.clock.clone();
+        model.gc(&horizon);
+        let decoded = Model::from_binary(&model.to_binary()).unwrap();
+        assert_eq!(decoded.view(), json!("abc"));
+        assert_eq!(decoded.gc_horizon.map(|h| h.time), Some(horizon.time));
+    }
+}
@@ -10,19 +10,32 @@
 //!
 //! Operations are applied via [`Model::apply_patch`] or
 //! [`Model::apply_operation`].  The resulting JSON view can be obtained with
-//! [`Model::view`].
+//! [`Model::view`].  Changes can be observed by subscribing listeners (see
+//! [`events`]). Multi-step local edits can be grouped into a single atomic
+//! patch with [`Model::transaction`]. [`Model::cached_view`] keeps the view
+//! materialized and re-renders only changed nodes (see [`view`]).
 
 pub mod api;
+pub mod events;
+pub mod gc;
+pub mod transaction;
 pub mod util;
+pub mod view;
 
 pub use api::ModelApi;
+pub use events::{ChangeEvent, ChangeOrigin, ListenerId};
+pub use gc::GcError;
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
 
 use serde_json::Value;
 
 use super::constants::ORIGIN;
+use super::extensions::Extensions;
 use super::nodes::{
-    ArrNode, BinNode, ConNode, CrdtNode, IndexExt, NodeIndex, ObjNode, RootNode, StrNode, ValNode,
-    VecNode,
+    ArrNode, BinNode, ConNode, CrdtNode, IndexExt, NodeIndex, ObjNode, RootNode, StrNode, TsKey,
+    ValNode, VecNode,
 };
 use crate::json_crdt_patch::clock::{ClockVector, Ts};
 use crate::json_crdt_patch::enums::SESSION;
@@ -49,6 +62,21 @@ pub struct Model {
     ///
     /// Mirrors `Model.tick` in the upstream TypeScript.
     pub tick: u64,
+    /// Clock up to which RGA tombstones have been collected, if any (see
+    /// [`gc`]).
+    pub gc_horizon: Option<ClockVector>,
+    /// IDs of nodes removed from the index because a newer value overwrote
+    /// the subtree holding them (see [`causal`](crate::json_crdt::causal)).
+    /// Kept in memory only; snapshots do not store it.
+    pub removed_nodes: BTreeSet<TsKey>,
+    /// Extensions used to render extension nodes in [`Model::view`].
+    pub extensions: Arc<Extensions>,
+    /// Change listeners registered on this model instance.
+    listeners: events::Listeners,
+    /// View kept by [`Model::cached_view`].
+    view_cache: view::ViewCache,
+    /// Undo log of the running [`Model::transaction`], if any.
+    journal: transaction::Journal,
 }
 
 impl Model {
@@ -62,6 +90,12 @@ impl Model {
             index: NodeIndex::default(),
             clock: ClockVector::new(sid, 1),
             tick: 0,
+            gc_horizon: None,
+            removed_nodes: BTreeSet::new(),
+            extensions: Arc::default(),
+            listeners: events::Listeners::default(),
+            view_cache: view::ViewCache::default(),
+            journal: transaction::Journal::default(),
         }
     }
 
@@ -73,8 +107,14 @@ impl Model {
     }
 
     /// Return the JSON view of the current document state.
+    ///
+    /// Extension nodes registered in [`Model::extensions`] are rendered
+    /// through their extension's view function.
     pub fn view(&self) -> Value {
-        self.root.view(&self.index)
+        if self.extensions.size() == 0 {
+            return self.root.view(&self.index);
+        }
+        self.extensions.view_node(&self.index, self.root.val)
     }
 
     /// Serialize this model using structural binary encoding.
@@ -96,11 +136,42 @@ impl Model {
     /// Increments `self.tick` after all operations are applied, mirroring
     /// `Model.applyPatch` in the upstream TypeScript which does `this.tick++`
     /// at the end of each patch application.
+    ///
+    /// Registered listeners (see [`events`]) are notified before and after
+    /// the patch is applied.
     pub fn apply_patch(&mut self, patch: &Patch) {
+        self.journal.record_patch(patch);
+        if self.listeners.is_empty() {
+            for op in &patch.ops {
+                if let Some(id) = self.apply_op(op) {
+                    self.view_cache.invalidate(id);
+                }
+            }
+            self.tick += 1;
+            return;
+        }
+        // Detach the listeners so they can borrow the model while running.
+        let mut listeners = std::mem::take(&mut self.listeners);
+        let origin = ChangeOrigin::of(patch, self.clock.sid);
+        listeners.emit_before(self, patch, origin);
+        let mut seen = HashSet::new();
+        let mut changed = Vec::new();
         for op in &patch.ops {
-            self.apply_operation(op);
+            if let Some(id) = self.apply_op(op) {
+                self.view_cache.invalidate(id);
+                if seen.insert(id) {
+                    changed.push(id);
+                }
+            }
         }
         self.tick += 1;
+        let event = ChangeEvent {
+            patch,
+            origin,
+            changed: &changed,
+        };
+        listeners.emit(self, &event);
+        self.listeners = listeners;
     }
 
     /// Recursively remove a node and its entire subtree from the index.
@@ -115,9 +186,13 @@ impl Model {
         if ts.sid == SESSION::SYSTEM {
             return;
         }
+        self.journal.record_node(&self.index, ts);
         let Some(node) = self.index.remove_node(&ts) else {
             return;
         };
+        if self.removed_nodes.insert(TsKey::from(ts)) {
+            self.journal.record_removed(ts);
+        }
         for child_id in node.child_ids() {
             self.gc_tree(child_id);
         }
@@ -131,8 +206,17 @@ impl Model {
     /// invalidation contract (e.g. the WASM layer) must go through
     /// [`apply_patch`](Self::apply_patch) instead.
     pub fn apply_operation(&mut self, op: &Op) {
+        if let Some(id) = self.apply_op(op) {
+            self.view_cache.invalidate(id);
+        }
+    }
+
+    /// Applies a single operation and returns the ID of the existing node
+    /// whose contents it mutated, if any (`ORIGIN` for the document root).
+    fn apply_op(&mut self, op: &Op) -> Option<Ts> {
         // Advance the clock by observing this operation's ID + span.
         self.clock.observe(op.id(), op.span());
+        self.journal.record_op(&self.index, op);
 
         match op {
             // ── Creation operations ────────────────────────────────────────
@@ -141,42 +225,49 @@ impl Model {
                     self.index
                         .insert_node(*id, CrdtNode::Con(ConNode::new(*id, val.clone())));
                 }
+                None
             }
             Op::NewVal { id } => {
                 if !self.index.contains_ts(id) {
                     self.index
                         .insert_node(*id, CrdtNode::Val(ValNode::new(*id)));
                 }
+                None
             }
             Op::NewObj { id } => {
                 if !self.index.contains_ts(id) {
                     self.index
                         .insert_node(*id, CrdtNode::Obj(ObjNode::new(*id)));
                 }
+                None
             }
             Op::NewVec { id } => {
                 if !self.index.contains_ts(id) {
                     self.index
                         .insert_node(*id, CrdtNode::Vec(VecNode::new(*id)));
                 }
+                None
             }
             Op::NewStr { id } => {
                 if !self.index.contains_ts(id) {
                     self.index
                         .insert_node(*id, CrdtNode::Str(StrNode::new(*id)));
                 }
+                None
             }
             Op::NewBin { id } => {
                 if !self.index.contains_ts(id) {
                     self.index
                         .insert_node(*id, CrdtNode::Bin(BinNode::new(*id)));
                 }
+                None
             }
             Op::NewArr { id } => {
                 if !self.index.contains_ts(id) {
                     self.index
                         .insert_node(*id, CrdtNode::Arr(ArrNode::new(*id)));
                 }
+                None
             }
 
             // ── Mutation operations ────────────────────────────────────────
@@ -184,50 +275,56 @@ impl Model {
             Op::InsVal { obj, val, .. } => {
                 // The root register is addressed by ORIGIN (SESSION::SYSTEM, time 0).
                 if obj.sid == SESSION::SYSTEM && obj.time == ORIGIN.time {
-                    if let Some(old) = self.root.set(*val) {
-                        self.gc_tree(old);
-                    }
+                    let old = self.root.set(*val)?;
+                    self.gc_tree(old);
+                    Some(ORIGIN)
                 } else if let Some(CrdtNode::Val(node)) = self.index.get_mut_ts(obj) {
-                    if let Some(old) = node.set(*val) {
-                        self.gc_tree(old);
-                    }
+                    let old = node.set(*val)?;
+                    self.gc_tree(old);
+                    Some(*obj)
+                } else {
+                    None
                 }
             }
 
             // Set key→value pairs in an `obj` map.
             Op::InsObj { obj, data, .. } => {
-                if let Some(CrdtNode::Obj(node)) = self.index.get_mut_ts(obj) {
-                    let mut to_gc = Vec::new();
-                    for (key, val_id) in data {
-                        if node.id.time >= val_id.time {
-                            continue;
-                        }
-                        if let Some(old) = node.put(key, *val_id) {
-                            to_gc.push(old);
-                        }
+                let Some(CrdtNode::Obj(node)) = self.index.get_mut_ts(obj) else {
+                    return None;
+                };
+                let mut to_gc = Vec::new();
+                for (key, val_id) in data {
+                    if node.id.time >= val_id.time {
+                        continue;
                     }
-                    for old in to_gc {
-                        self.gc_tree(old);
+                    if let Some(old) = node.put(key, *val_id) {
+                        to_gc.push(old);
                     }
                 }
+                for old in to_gc {
+                    self.gc_tree(old);
+                }
+                Some(*obj)
             }
 
             // Set index→value pairs in a `vec` vector.
             Op::InsVec { obj, data, .. } => {
-                if let Some(CrdtNode::Vec(node)) = self.index.get_mut_ts(obj) {
-                    let mut to_gc = Vec::new();
-                    for (idx, val_id) in data {
-                        if node.id.time >= val_id.time {
-                            continue;
-                        }
-                        if let Some(old) = node.put(*idx as usize, *val_id) {
-                            to_gc.push(old);
-                        }
+                let Some(CrdtNode::Vec(node)) = self.index.get_mut_ts(obj) else {
+                    return None;
+                };
+                let mut to_gc = Vec::new();
+                for (idx, val_id) in data {
+                    if node.id.time >= val_id.time {
+                        continue;
                     }
-                    for old in to_gc {
-                        self.gc_tree(old);
+                    if let Some(old) = node.put(*idx as usize, *val_id) {
+                        to_gc.push(old);
                     }
                 }
+                for old in to_gc {
+                    self.gc_tree(old);
+                }
+                Some(*obj)
             }
 
             // Insert text into a `str` RGA.
@@ -237,9 +334,11 @@ impl Model {
                 after,
                 data,
             } => {
-                if let Some(CrdtNode::Str(node)) = self.index.get_mut_ts(obj) {
-                    node.ins(*after, *id, data.clone());
-                }
+                let Some(CrdtNode::Str(node)) = self.index.get_mut_ts(obj) else {
+                    return None;
+                };
+                node.ins(*after, *id, data.clone());
+                Some(*obj)
             }
 
             // Insert bytes into a `bin` RGA.
@@ -249,9 +348,11 @@ impl Model {
                 after,
                 data,
             } => {
-                if let Some(CrdtNode::Bin(node)) = self.index.get_mut_ts(obj) {
-                    node.ins(*after, *id, data.clone());
-                }
+                let Some(CrdtNode::Bin(node)) = self.index.get_mut_ts(obj) else {
+                    return None;
+                };
+                node.ins(*after, *id, data.clone());
+                Some(*obj)
             }
 
             // Insert node-ID references into an `arr` RGA.
@@ -261,55 +362,78 @@ impl Model {
                 after,
                 data,
             } => {
-                if let Some(CrdtNode::Arr(node)) = self.index.get_mut_ts(obj) {
-                    // Filter out references older than the array node itself.
-                    let filtered: Vec<Ts> = data
-                        .iter()
-                        .filter(|stamp| node.id.time < stamp.time)
-                        .copied()
-                        .collect();
-                    if !filtered.is_empty() {
-                        node.ins(*after, *id, filtered);
-                    }
+                let Some(CrdtNode::Arr(node)) = self.index.get_mut_ts(obj) else {
+                    return None;
+                };
+                // Filter out references older than the array node itself.
+                let filtered: Vec<Ts> = data
+                    .iter()
+                    .filter(|stamp| node.id.time < stamp.time)
+                    .copied()
+                    .collect();
+                if filtered.is_empty() {
+                    return None;
                 }
+                node.ins(*after, *id, filtered);
+                Some(*obj)
             }
 
             // Update (replace) an existing element in an `arr` RGA.
             Op::UpdArr {
                 obj, after, val, ..
             } => {
-                if let Some(CrdtNode::Arr(node)) = self.index.get_mut_ts(obj) {
-                    if let Some(old) = node.upd(*after, *val) {
-                        self.gc_tree(old);
-                    }
-                }
+                let Some(CrdtNode::Arr(node)) = self.index.get_mut_ts(obj) else {
+                    return None;
+                };
+                let old = node.upd(*after, *val)?;
+                self.gc_tree(old);
+                Some(*obj)
+            }
+
+            // Move an element of an `arr` RGA.
+            Op::MovArr {
+                id,
+                obj,
+                after,
+                elem,
+            } => {
+                let Some(CrdtNode::Arr(node)) = self.index.get_mut_ts(obj) else {
+                    return None;
+                };
+                node.mov(*id, *after, *elem).then_some(*obj)
             }
 
             // Delete ranges in a `str`, `bin`, or `arr`.
-            Op::Del { obj, what, .. } => match self.index.get_mut_ts(obj) {
-                Some(CrdtNode::Str(node)) => node.delete(what),
-                Some(CrdtNode::Bin(node)) => node.delete(what),
-                Some(CrdtNode::Arr(node)) => {
-                    // GC the data-node IDs before tombstoning the slots.
-                    // Mirrors upstream: for each span item, getById → _gcTree.
-                    let mut to_gc = Vec::new();
-                    for span in what.iter() {
-                        for j in 0..span.span {
-                            let slot_ts = Ts::new(span.sid, span.time + j);
-                            if let Some(data_ts) = node.get_by_id(slot_ts) {
-                                to_gc.push(data_ts);
+            Op::Del { id, obj, what } => {
+                match self.index.get_mut_ts(obj) {
+                    Some(CrdtNode::Str(node)) => node.delete(*id, what),
+                    Some(CrdtNode::Bin(node)) => node.delete(*id, what),
+                    Some(CrdtNode::Arr(node)) => {
+                        // Deleting a slot an element was moved away from
+                        // deletes the element at its current slot.
+                        let what = &node.resolve_delete(what);
+                        // GC the data-node IDs before tombstoning the slots.
+                        // Mirrors upstream: for each span item, getById → _gcTree.
+                        let mut to_gc = Vec::new();
+                        for span in what.iter() {
+                            for j in 0..span.span {
+                                let slot_ts = Ts::new(span.sid, span.time + j);
+                                if let Some(data_ts) = node.get_by_id(slot_ts) {
+                                    to_gc.push(data_ts);
+                                }
                             }
                         }
+                        node.delete(*id, what);
+                        for old in to_gc {
+                            self.gc_tree(old);
+                        }
                     }
-                    node.delete(what);
-                    for old in to_gc {
-                        self.gc_tree(old);
-                    }
+                    _ => return None,
                 }
-                _ => {}
-            },
+                Some(*obj)
+            }
 
-            Op::Nop { .. } => {}
+            Op::Nop { .. } => None,
         }
     }
 
@@ -329,6 +453,12 @@ impl Model {
             index: super::nodes::NodeIndex::default(),
             clock: ClockVector::new(SESSION::SERVER, server_time),
             tick: 0,
+            gc_horizon: None,
+            removed_nodes: BTreeSet::new(),
+            extensions: Arc::default(),
+            listeners: events::Listeners::default(),
+            view_cache: view::ViewCache::default(),
+            journal: transaction::Journal::default(),
         }
     }
 
@@ -341,6 +471,12 @@ impl Model {
             index: super::nodes::NodeIndex::default(),
             clock,
             tick: 0,
+            gc_horizon: None,
+            removed_nodes: BTreeSet::new(),
+            extensions: Arc::default(),
+            listeners: events::Listeners::default(),
+            view_cache: view::ViewCache::default(),
+            journal: transaction::Journal::default(),
         }
     }
 }
@@ -809,4 +945,112 @@ mod tests {
         );
         assert!(model.index.contains_ts(&ts(s, 5)));
     }
+
+    // ── Array moves ──────────────────────────────────────────────────────
+
+    /// Three replicas sharing the array `["a", "b", "c", "d"]`.
+    fn move_replicas() -> (Vec<Model>, Ts) {
+        let mut origin = Model::new(sid());
+        let ((), base) = origin
+            .transaction(|api| api.set(&json!(["a", "b", "c", "d"])))
+            .unwrap();
+        let replicas: Vec<Model> = (1..=3)
+            .map(|i| {
+                let mut model = Model::new(sid() + i);
+                model.apply_patch(&base);
+                model
+            })
+            .collect();
+        let arr = replicas[0].root.val;
+        (replicas, arr)
+    }
+
+    /// Applies `edit` on `model` and returns the resulting patch.
+    fn local_edit(
+        model: &mut Model,
+        edit: impl FnOnce(&mut ModelApi<'_>) -> Result<(), api::ApiError>,
+    ) -> Patch {
+        model.transaction(edit).unwrap().1
+    }
+
+    /// Delivers every patch to every replica that did not produce it, in a
+    /// different order per replica, and checks that all views agree.
+    fn converge(replicas: &mut [Model], patches: &[Patch]) -> Value {
+        for (i, model) in replicas.iter_mut().enumerate() {
+            let n = patches.len();
+            for k in 0..n {
+                let j = if i % 2 == 0 { k } else { n - 1 - k };
+                if j != i {
+                    model.apply_patch(&patches[j]);
+                }
+            }
+        }
+        let view = replicas[0].view();
+        for model in replicas.iter() {
+            assert_eq!(model.view(), view);
+        }
+        view
+    }
+
+    #[test]
+    fn concurrent_moves_of_one_element_do_not_duplicate_it() {
+        let (mut r, arr) = move_replicas();
+        let p0 = local_edit(&mut r[0], |api| api.arr_move(arr, 0, 3));
+        let p1 = local_edit(&mut r[1], |api| api.arr_move(arr, 0, 1));
+        let p2 = local_edit(&mut r[2], |api| api.arr_move(arr, 0, 2));
+        assert_eq!(r[0].view(), json!(["b", "c", "d", "a"]));
+        let view = converge(&mut r, &[p0, p1, p2]);
+        // The move with the highest ID (here the highest session) wins.
+        assert_eq!(view, json!(["b", "c", "a", "d"]));
+    }
+
+    #[test]
+    fn concurrent_move_and_delete_deletes_the_element() {
+        let (mut r, arr) = move_replicas();
+        let p0 = local_edit(&mut r[0], |api| api.arr_move(arr, 1, 3));
+        let p1 = local_edit(&mut r[1], |api| api.arr_del(arr, 1, 2));
+        let p2 = local_edit(&mut r[2], |api| api.arr_move(arr, 2, 0));
+        let view = converge(&mut r, &[p0, p1, p2]);
+        assert_eq!(view, json!(["a", "d"]));
+    }
+
+    #[test]
+    fn concurrent_move_update_and_insert_converge() {
+        let (mut r, arr) = move_replicas();
+        let p0 = local_edit(&mut r[0], |api| api.arr_move(arr, 3, 0));
+        let p1 = local_edit(&mut r[1], |api| {
+            let slot = match IndexExt::get(&api.model.index, &arr) {
+                Some(CrdtNode::Arr(node)) => node.find(3),
+                _ => None,
+            };
+            let value = api.json(&json!("D"))?;
+            api.builder.upd_arr(arr, slot.unwrap(), value);
+            api.apply();
+            Ok(())
+        });
+        let p2 = local_edit(&mut r[2], |api| api.arr_ins(arr, 3, &[json!("x")]));
+        let view = converge(&mut r, &[p0, p1, p2]);
+        assert_eq!(view, json!(["D", "a", "b", "c", "x"]));
+    }
+
+    #[test]
+    fn sequential_moves_follow_the_element() {
+        let (mut r, arr) = move_replicas();
+        let p0 = local_edit(&mut r[0], |api| api.arr_move(arr, 0, 2));
+        let p1 = local_edit(&mut r[0], |api| api.arr_move(arr, 2, 3));
+        // A delete issued by a peer that only saw the first move.
+        r[1].apply_patch(&p0);
+        let p2 = local_edit(&mut r[1], |api| api.arr_del(arr, 2, 1));
+        r[1].apply_patch(&p1);
+        assert_eq!(r[1].view(), json!(["b", "c", "d"]));
+        r[0].apply_patch(&p2);
+        r[2].apply_patch(&p0);
+        r[2].apply_patch(&p1);
+        r[2].apply_patch(&p2);
+        assert_eq!(r[0].view(), r[1].view());
+        assert_eq!(r[2].view(), r[1].view());
+        // Re-applying a move is a no-op.
+        r[2].apply_patch(&p1);
+        assert_eq!(r[2].view(), json!(["b", "c", "d"]));
+    }
 }
@@ -0,0 +1,352 @@
+//! Atomic multi-step edits on a [`Model`].
+//!
+//! # Overview
+//!
+//! [`Model::transaction`] runs a closure against a [`ModelApi`] bound to the
+//! model itself. While it runs, the model keeps a journal: every patch the
+//! closure applies, and each node as it was before the transaction first
+//! changed, created or removed it. If the closure succeeds the patches are
+//! combined into a single [`Patch`], which is returned; the model's
+//! listeners are notified once, with that patch. If the closure fails, the
+//! journaled nodes, the clock, the root and the GC horizon are restored,
+//! leaving the model untouched.
+//!
+//! The journal costs a copy of each node the transaction touches (the whole
+//! `str` node for a text edit, say), not of the document. When listeners
+//! are registered, a successful transaction additionally rolls back and
+//! re-applies the combined patch so that they observe a single change.
+//!
+//! The closure is expected to make local edits through patches only:
+//! patches from other sessions must be applied outside of a transaction,
+//! and changes made with [`Model::apply_operation`] are rolled back on
+//! failure but not included in the returned patch.
+
+use std::collections::HashMap;
+
+use super::{Model, ModelApi};
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::nodes::{CrdtNode, NodeIndex, RootNode, TsKey};
+use crate::json_crdt_patch::clock::{ClockVector, Ts};
+use crate::json_crdt_patch::compaction::combine;
+use crate::json_crdt_patch::enums::SESSION;
+use crate::json_crdt_patch::operations::Op;
+use crate::json_crdt_patch::patch::Patch;
+
+impl Model {
+    /// Runs `edit` as a single atomic transaction.
+    ///
+    /// On success returns the closure's result together with the patch that
+    /// was applied to the model (empty if nothing changed). Operations left
+    /// in the builder without calling [`ModelApi::apply`] are committed too.
+    /// On error the model is unchanged and the error is returned.
+    ///
+    /// Transactions may be nested; an inner one that fails only rolls back
+    /// its own changes. Listeners registered by `edit` only observe the
+    /// patches applied while it runs.
+    pub fn transaction<T, E>(
+        &mut self,
+        edit: impl FnOnce(&mut ModelApi<'_>) -> Result<T, E>,
+    ) -> Result<(T, Patch), E> {
+        let outer = self.journal.0.replace(Entries::default());
+        // Listeners are notified once, at commit.
+        let listeners = std::mem::take(&mut self.listeners);
+        let saved = Saved {
+            clock: self.clock.clone(),
+            root: self.root.clone(),
+            tick: self.tick,
+            gc_horizon: self.gc_horizon.clone(),
+        };
+
+        let result = {
+            let mut api = ModelApi::new(self);
+            edit(&mut api).inspect(|_| api.apply())
+        };
+        let mut entries = std::mem::replace(&mut self.journal.0, outer).unwrap_or_default();
+        let value = match result {
+            Ok(value) => value,
+            Err(err) => {
+                self.rollback(entries, saved);
+                self.listeners = listeners;
+                return Err(err);
+            }
+        };
+
+        let mut patches = std::mem::take(&mut entries.patches);
+        patches.retain(|patch| !patch.ops.is_empty());
+        combine(&mut patches);
+        let patch = patches.pop().unwrap_or_default();
+        if patch.ops.is_empty() {
+            self.listeners = listeners;
+            return Ok((value, patch));
+        }
+        if listeners.is_empty() {
+            self.listeners = listeners;
+            self.tick = saved.tick + 1;
+            if let Some(outer) = &mut self.journal.0 {
+                for (key, node) in entries.nodes {
+                    outer.nodes.entry(key).or_insert(node);
+                }
+                outer.removed.extend(entries.removed);
+                outer.patches.push(patch.clone());
+            }
+        } else {
+            self.rollback(entries, saved);
+            self.listeners = listeners;
+            self.apply_patch(&patch);
+        }
+        Ok((value, patch))
+    }
+
+    /// Restores the journaled nodes and the `saved` state.
+    fn rollback(&mut self, entries: Entries, saved: Saved) {
+        for key in entries.removed {
+            self.removed_nodes.remove(&key);
+        }
+        for (key, node) in entries.nodes {
+            match node {
+                Some(node) => {
+                    self.index.insert(key, node);
+                }
+                None => {
+                    self.index.remove(&key);
+                }
+            }
+        }
+        self.clock = saved.clock;
+        self.root = saved.root;
+        self.tick = saved.tick;
+        self.gc_horizon = saved.gc_horizon;
+        self.view_cache.invalidate(ORIGIN);
+    }
+}
+
+/// Model state outside the index, as it was when a transaction started.
+struct Saved {
+    clock: ClockVector,
+    root: RootNode,
+    tick: u64,
+    gc_horizon: Option<ClockVector>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Nodes as they were before the transaction first touched them;
+    /// `None` for nodes that did not exist.
+    nodes: HashMap<TsKey, Option<CrdtNode>>,
+    /// Nodes added to [`Model::removed_nodes`].
+    removed: Vec<TsKey>,
+    /// Patches applied so far.
+    patches: Vec<Patch>,
+}
+
+/// Undo log of the running [`Model::transaction`], if any.
+///
+/// Bound to a model instance and not copied by clones.
+#[derive(Debug, Default)]
+pub(super) struct Journal(Option<Entries>);
+
+impl Clone for Journal {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Journal {
+    /// Records `patch` as applied.
+    pub(super) fn record_patch(&mut self, patch: &Patch) {
+        if let Some(entries) = &mut self.0 {
+            entries.patches.push(patch.clone());
+        }
+    }
+
+    /// Records the node `op` creates or changes before it is applied.
+    pub(super) fn record_op(&mut self, index: &NodeIndex, op: &Op) {
+        let id = match op {
+            Op::NewCon { id, .. }
+            | Op::NewVal { id }
+            | Op::NewObj { id }
+            | Op::NewVec { id }
+            | Op::NewStr { id }
+            | Op::NewBin { id }
+            | Op::NewArr { id } => *id,
+            Op::InsVal { obj, .. }
+            | Op::InsObj { obj, .. }
+            | Op::InsVec { obj, .. }
+            | Op::InsStr { obj, .. }
+            | Op::InsBin { obj, .. }
+            | Op::InsArr { obj, .. }
+            | Op::UpdArr { obj, .. }
+            | Op::MovArr { obj, .. }
+            | Op::Del { obj, .. } => *obj,
+            Op::Nop { .. } => return,
+        };
+        self.record_node(index, id);
+    }
+
+    /// Records that node `id` was added to [`Model::removed_nodes`].
+    pub(super) fn record_removed(&mut self, id: Ts) {
+        if let Some(entries) = &mut self.0 {
+            entries.removed.push(TsKey::from(id));
+        }
+    }
+
+    /// Records `node` as it was before it was changed, unless the
+    /// transaction already holds an earlier copy.
+    pub(super) fn record_copy(&mut self, node: CrdtNode) {
+        if let Some(entries) = &mut self.0 {
+            entries
+                .nodes
+                .entry(TsKey::from(node.id()))
+                .or_insert(Some(node));
+        }
+    }
+
+    /// Whether a transaction is running.
+    pub(super) fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Records node `id` before it is changed or removed.
+    pub(super) fn record_node(&mut self, index: &NodeIndex, id: Ts) {
+        // The root register is restored separately.
+        if id.sid == SESSION::SYSTEM {
+            return;
+        }
+        if let Some(entries) = &mut self.0 {
+            entries
+                .nodes
+                .entry(TsKey::from(id))
+                .or_insert_with(|| index.get(&TsKey::from(id)).cloned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_crdt::model::api::{find_path, ApiError};
+    use crate::json_crdt::model::Model;
+    use crate::json_crdt_patch::clock::Ts;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    fn make_model() -> (Model, Ts) {
+        let mut model = Model::new(400_001);
+        model
+            .transaction(|api| api.set(&json!({"title": "ab", "items": []})))
+            .unwrap();
+        let root = model.root.val;
+        (model, root)
+    }
+
+    #[test]
+    fn commit_emits_one_patch() {
+        let (mut model, root) = make_model();
+        let patches = Arc::new(Mutex::new(0));
+        let count = patches.clone();
+        model.on_patch(move |_, _| *count.lock().unwrap() += 1);
+
+        let ((), patch) = model
+            .transaction(|api| {
+                let title = api.find(root, &[json!("title")])?;
+                api.str_ins(title, 2, "c")?;
+                api.str_ins(title, 3, "d")?;
+                let items = api.find(root, &[json!("items")])?;
+                api.arr_ins(items, 0, &[json!(1), json!(2)])?;
+                api.obj_set(root, &[("done".into(), json!(true))])
+            })
+            .unwrap();
+
+        assert_eq!(*patches.lock().unwrap(), 1);
+        assert_eq!(
+            model.view(),
+            json!({"title": "abcd", "items": [1, 2], "done": true})
+        );
+        // Replaying the emitted patch on a fresh copy gives the same document.
+        let (mut replica, _) = make_model();
+        replica.apply_patch(&patch);
+        assert_eq!(replica.view(), model.view());
+    }
+
+    #[test]
+    fn error_rolls_back_model_and_clock() {
+        let (mut model, root) = make_model();
+        let view = model.view();
+        let time = model.clock.time;
+        let tick = model.tick;
+
+        let result: Result<((), _), ApiError> = model.transaction(|api| {
+            api.obj_set(root, &[("a".into(), json!(1))])?;
+            let title = api.find(root, &[json!("title")])?;
+            api.str_ins(title, 0, "x")?;
+            api.find(root, &[json!("missing")])?;
+            unreachable!()
+        });
+
+        assert_eq!(result.unwrap_err(), ApiError::NotFound);
+        assert_eq!(model.view(), view);
+        assert_eq!(model.clock.time, time);
+        assert_eq!(model.tick, tick);
+    }
+
+    #[test]
+    fn error_restores_overwritten_subtrees() {
+        let (mut model, root) = make_model();
+        let title = find_path(&model, root, &[json!("title")]).unwrap();
+        let result: Result<((), _), ApiError> = model.transaction(|api| {
+            api.obj_set(root, &[("title".into(), json!(5))])?;
+            Err(ApiError::NotFound)
+        });
+        assert!(result.is_err());
+        assert!(model.index.contains_key(&title.into()));
+        assert!(model.removed_nodes.is_empty());
+        model.transaction(|api| api.str_ins(title, 2, "c")).unwrap();
+        assert_eq!(model.view()["title"], json!("abc"));
+    }
+
+    #[test]
+    fn failed_nested_transaction_keeps_outer_edits() {
+        let (mut model, root) = make_model();
+        let patches = Arc::new(Mutex::new(0));
+        let count = patches.clone();
+        model.on_patch(move |_, _| *count.lock().unwrap() += 1);
+
+        let ((), patch) = model
+            .transaction(|api| {
+                api.obj_set(root, &[("a".into(), json!(1))])?;
+                let inner: Result<((), _), ApiError> = api.model.transaction(|api| {
+                    api.obj_set(root, &[("b".into(), json!(2))])?;
+                    Err(ApiError::NotFound)
+                });
+                assert!(inner.is_err());
+                api.model
+                    .transaction(|api| api.obj_set(root, &[("c".into(), json!(3))]))?;
+                Ok::<_, ApiError>(())
+            })
+            .unwrap();
+
+        assert_eq!(*patches.lock().unwrap(), 1);
+        assert_eq!(model.view()["a"], json!(1));
+        assert_eq!(model.view().get("b"), None);
+        assert_eq!(model.view()["c"], json!(3));
+        let (mut replica, _) = make_model();
+        replica.apply_patch(&patch);
+        assert_eq!(replica.view(), model.view());
+    }
+
+    #[test]
+    fn pending_builder_ops_are_committed() {
+        let (mut model, root) = make_model();
+        let (id, patch) = model
+            .transaction(|api| {
+                let id = api.builder.con_val(json!(5).into());
+                api.builder.ins_obj(root, vec![("n".into(), id)]);
+                Ok::<_, ApiError>(id)
+            })
+            .unwrap();
+        assert_eq!(patch.get_id(), Some(id));
+        assert_eq!(model.view()["n"], json!(5));
+
+        let ((), empty) = model.transaction(|_| Ok::<_, ApiError>(())).unwrap();
+        assert!(empty.ops.is_empty());
+    }
+}
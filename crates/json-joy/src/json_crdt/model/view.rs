@@ -0,0 +1,489 @@
+//! Incremental view cache for a JSON CRDT [`Model`].
+//!
+//! Not part of the upstream TypeScript, where each node memoizes its own
+//! `view()` and re-checks its children on every call.
+//!
+//! # Overview
+//!
+//! [`Model::cached_view`] keeps the document's JSON view materialized
+//! between calls, together with a map from each node to its parent
+//! container. Every operation applied through [`Model::apply_patch`] or
+//! [`Model::apply_operation`] marks the node whose contents it mutated as
+//! dirty. On the next read only dirty nodes are updated, in place at their
+//! path in the cached tree: a changed `str`, `bin` or `con` is re-rendered,
+//! while a changed container keeps the cached views of the children it
+//! still holds and renders only the new ones. Reading a large document
+//! after a one-character edit or a new key therefore costs the depth of the
+//! edited node (plus the width of its ancestors, to locate it) rather than
+//! the size of the document.
+//!
+//! Edits that bypass those methods (writing to [`Model::index`] directly)
+//! are not seen; call [`Model::invalidate_view`] after them. Documents with
+//! registered [`extensions`](Model::extensions) are re-rendered in full
+//! after any change.
+
+use std::collections::{HashMap, HashSet};
+
+use json_joy_json_pack::PackValue;
+use serde_json::{Map, Value};
+
+use super::Model;
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::nodes::{ArrNode, CrdtNode, NodeIndex, RootNode, TsKey};
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::operations::ConValue;
+
+/// Materialized view of a model, updated along the paths of changed nodes.
+#[derive(Debug, Clone, Default)]
+pub struct ViewCache {
+    /// The document view, or `None` when it must be rendered in full.
+    value: Option<Value>,
+    /// Container holding each rendered node.
+    parents: HashMap<Ts, Ts>,
+    /// Live elements of each rendered `arr` node, in view order.
+    elements: HashMap<Ts, Vec<Ts>>,
+    /// Nodes whose contents changed since the last read.
+    dirty: HashSet<Ts>,
+}
+
+/// One step from a container to a child in the view tree.
+enum Step {
+    /// A `val` register is transparent in the view.
+    Through,
+    Key(String),
+    Index(usize),
+}
+
+impl ViewCache {
+    /// Record that the contents of node `id` changed.
+    pub(crate) fn invalidate(&mut self, id: Ts) {
+        if self.value.is_some() {
+            self.dirty.insert(id);
+        }
+    }
+
+    /// Drop the cached view; the next read renders it in full.
+    pub(crate) fn clear(&mut self) {
+        self.value = None;
+        self.parents.clear();
+        self.elements.clear();
+        self.dirty.clear();
+    }
+
+    /// Bring the view up to date with `root` and `index`.
+    fn refresh(&mut self, root: &RootNode, index: &NodeIndex) {
+        let dirty = std::mem::take(&mut self.dirty);
+        if dirty.contains(&ORIGIN) {
+            self.clear();
+        }
+        for id in dirty {
+            if self.value.is_none() {
+                break;
+            }
+            if !self.update(root, index, id) {
+                self.clear();
+            }
+        }
+        if self.value.is_none() {
+            self.value = Some(root.view(index));
+            self.parents.clear();
+            self.elements.clear();
+            self.link(index, root.val);
+        }
+    }
+
+    /// Update the cached view of node `id` in place. Returns `false` if the
+    /// cached tree does not have the shape the parent map expects.
+    fn update(&mut self, root: &RootNode, index: &NodeIndex, id: Ts) -> bool {
+        let Some(node) = index.get(&TsKey::from(id)) else {
+            // Collected along with a replaced subtree.
+            return true;
+        };
+        let Some(path) = self.path(root, index, id) else {
+            // Not (yet) attached: its container renders it if needed.
+            return true;
+        };
+        let mut value = self.value.take().expect("checked");
+        let patched = match locate(&mut value, &path) {
+            Some(slot) => self.patch(index, node, slot),
+            None => false,
+        };
+        self.value = Some(value);
+        patched
+    }
+
+    /// Rewrite `slot`, the cached view of `node`, reusing the cached views
+    /// of the children `node` still holds.
+    fn patch(&mut self, index: &NodeIndex, node: &CrdtNode, slot: &mut Value) -> bool {
+        let id = node.id();
+        match node {
+            CrdtNode::Obj(obj) => {
+                let Value::Object(old) = slot else {
+                    return false;
+                };
+                let mut old = std::mem::take(old);
+                let mut map = Map::new();
+                for (key, &child) in &obj.keys {
+                    let held = self.parents.get(&child) == Some(&id);
+                    let cached = if held { old.remove(key) } else { None };
+                    let view = match cached {
+                        Some(view) => view,
+                        // Upstream omits object keys whose winning value is
+                        // `con(undefined)`, and so does `ObjNode::view`.
+                        None => match index.get(&TsKey::from(child)) {
+                            Some(CrdtNode::Con(con))
+                                if matches!(con.val, ConValue::Val(PackValue::Undefined)) =>
+                            {
+                                continue
+                            }
+                            _ => match self.render(index, id, child) {
+                                Some(view) => view,
+                                None => continue,
+                            },
+                        },
+                    };
+                    map.insert(key.clone(), view);
+                }
+                *slot = Value::Object(map);
+            }
+            CrdtNode::Vec(vec) => {
+                let Value::Array(old) = slot else {
+                    return false;
+                };
+                let mut old = std::mem::take(old);
+                let items = vec
+                    .elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, element)| match *element {
+                        Some(child) if self.parents.get(&child) == Some(&id) && i < old.len() => {
+                            std::mem::take(&mut old[i])
+                        }
+                        Some(child) => self.render(index, id, child).unwrap_or(Value::Null),
+                        None => Value::Null,
+                    })
+                    .collect();
+                *slot = Value::Array(items);
+            }
+            CrdtNode::Arr(arr) => {
+                let Value::Array(old) = slot else {
+                    return false;
+                };
+                let mut old = std::mem::take(old);
+                let positions: HashMap<Ts, usize> = self
+                    .elements
+                    .remove(&id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, child)| (child, i))
+                    .collect();
+                let live = live_elements(arr);
+                let items = live
+                    .iter()
+                    .map(|child| match positions.get(child) {
+                        Some(&i) if i < old.len() => std::mem::take(&mut old[i]),
+                        _ => self.render(index, id, *child).unwrap_or(Value::Null),
+                    })
+                    .collect();
+                self.elements.insert(id, live);
+                *slot = Value::Array(items);
+            }
+            CrdtNode::Val(val) => {
+                *slot = self.render(index, id, val.val).unwrap_or(Value::Null);
+            }
+            CrdtNode::Con(_) | CrdtNode::Str(_) | CrdtNode::Bin(_) => {
+                *slot = node.view(index);
+            }
+        }
+        true
+    }
+
+    /// Render `child`, newly held by `parent`, and record its subtree.
+    /// Returns `None` if `child` is not in the index.
+    fn render(&mut self, index: &NodeIndex, parent: Ts, child: Ts) -> Option<Value> {
+        self.parents.insert(child, parent);
+        let view = index.get(&TsKey::from(child))?.view(index);
+        self.link(index, child);
+        Some(view)
+    }
+
+    /// Steps from the document root down to `id`.
+    fn path(&self, root: &RootNode, index: &NodeIndex, id: Ts) -> Option<Vec<Step>> {
+        let mut path = Vec::new();
+        let mut curr = id;
+        while curr != root.val {
+            if path.len() > self.parents.len() {
+                return None;
+            }
+            let parent = *self.parents.get(&curr)?;
+            path.push(self.step(index.get(&TsKey::from(parent))?, curr)?);
+            curr = parent;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The step from `container` to its child `id` in the cached tree, if it
+    /// holds it.
+    fn step(&self, container: &CrdtNode, id: Ts) -> Option<Step> {
+        match container {
+            CrdtNode::Val(node) => (node.val == id).then_some(Step::Through),
+            CrdtNode::Obj(node) => node
+                .keys
+                .iter()
+                .find(|(_, child)| **child == id)
+                .map(|(key, _)| Step::Key(key.clone())),
+            CrdtNode::Vec(node) => node
+                .elements
+                .iter()
+                .position(|child| *child == Some(id))
+                .map(Step::Index),
+            CrdtNode::Arr(node) => self
+                .elements
+                .get(&node.id)?
+                .iter()
+                .position(|child| *child == id)
+                .map(Step::Index),
+            CrdtNode::Con(_) | CrdtNode::Str(_) | CrdtNode::Bin(_) => None,
+        }
+    }
+
+    /// Record the parent of every node in the subtree of `id`, and the
+    /// elements of every `arr` node in it.
+    fn link(&mut self, index: &NodeIndex, id: Ts) {
+        let mut stack = vec![id];
+        while let Some(parent) = stack.pop() {
+            let Some(node) = index.get(&TsKey::from(parent)) else {
+                continue;
+            };
+            if let CrdtNode::Arr(arr) = node {
+                self.elements.insert(parent, live_elements(arr));
+            }
+            for child in node.child_ids() {
+                self.parents.insert(child, parent);
+                stack.push(child);
+            }
+        }
+    }
+}
+
+/// The value at `path` in `value`.
+fn locate<'v>(mut value: &'v mut Value, path: &[Step]) -> Option<&'v mut Value> {
+    for step in path {
+        value = match step {
+            Step::Through => value,
+            Step::Key(key) => value.as_object_mut()?.get_mut(key)?,
+            Step::Index(index) => value.as_array_mut()?.get_mut(*index)?,
+        };
+    }
+    Some(value)
+}
+
+/// Live elements of `arr`, in view order.
+fn live_elements(arr: &ArrNode) -> Vec<Ts> {
+    arr.rga
+        .iter_live()
+        .filter_map(|chunk| chunk.data.as_ref())
+        .flatten()
+        .copied()
+        .collect()
+}
+
+impl Model {
+    /// Return the JSON view of the document, updated incrementally.
+    ///
+    /// Equal to [`Model::view`], but only the nodes changed since the
+    /// previous call are re-rendered; see [`view`](self) for details.
+    pub fn cached_view(&mut self) -> &Value {
+        if self.extensions.size() > 0 {
+            if !self.view_cache.dirty.is_empty() {
+                self.view_cache.clear();
+            }
+            if self.view_cache.value.is_none() {
+                self.view_cache.value = Some(self.view());
+            }
+            return self.view_cache.value.as_ref().expect("rendered");
+        }
+        self.view_cache.refresh(&self.root, &self.index);
+        self.view_cache.value.as_ref().expect("rendered")
+    }
+
+    /// Discard the view kept by [`Model::cached_view`], after editing
+    /// [`Model::index`] or [`Model::root`] directly.
+    pub fn invalidate_view(&mut self) {
+        self.view_cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_crdt::constants::ORIGIN;
+    use crate::json_crdt::model::Model;
+    use crate::json_crdt_patch::clock::{ts, tss};
+    use crate::json_crdt_patch::patch::Patch;
+    use crate::json_crdt_patch::patch_builder::PatchBuilder;
+    use json_joy_json_pack::PackValue;
+    use serde_json::json;
+
+    fn edit(model: &mut Model, f: impl FnOnce(&mut PatchBuilder)) -> Patch {
+        let mut builder = PatchBuilder::new(model.clock.sid, model.clock.time);
+        f(&mut builder);
+        let patch = builder.flush();
+        model.apply_patch(&patch);
+        let expected = model.view();
+        assert_eq!(*model.cached_view(), expected);
+        patch
+    }
+
+    #[test]
+    fn follows_nested_edits() {
+        let mut model = Model::new(7);
+        assert_eq!(*model.cached_view(), json!(null));
+        let [mut obj, mut text, mut ab, mut list, mut reg] = [ORIGIN; 5];
+        edit(&mut model, |b| {
+            obj = b.obj();
+            text = b.str_node();
+            ab = b.ins_str(text, text, "ab".into());
+            list = b.arr();
+            let inner = b.obj();
+            reg = b.val();
+            let one = b.con_val(PackValue::Integer(1));
+            b.set_val(reg, one);
+            b.ins_obj(inner, vec![("reg".into(), reg)]);
+            b.ins_arr(list, list, vec![inner]);
+            b.ins_obj(obj, vec![("text".into(), text), ("list".into(), list)]);
+            b.root(obj);
+        });
+        assert_eq!(
+            *model.cached_view(),
+            json!({"text": "ab", "list": [{"reg": 1}]})
+        );
+        edit(&mut model, |b| {
+            b.ins_str(text, ts(7, ab.time + 1), "c".into());
+        });
+        edit(&mut model, |b| {
+            let two = b.con_val(PackValue::Integer(2));
+            b.set_val(reg, two);
+        });
+        let mut slot = ORIGIN;
+        edit(&mut model, |b| {
+            let x = b.con_val(PackValue::Str("x".into()));
+            slot = b.ins_arr(list, list, vec![x]);
+        });
+        assert_eq!(
+            *model.cached_view(),
+            json!({"text": "abc", "list": ["x", {"reg": 2}]})
+        );
+        edit(&mut model, |b| {
+            b.del(list, vec![tss(7, slot.time, 1)]);
+        });
+
+        edit(&mut model, |b| {
+            let other = b.str_node();
+            b.ins_obj(obj, vec![("text".into(), other)]);
+        });
+        edit(&mut model, |b| {
+            let s = b.str_node();
+            b.root(s);
+        });
+        assert_eq!(*model.cached_view(), json!(""));
+    }
+
+    #[test]
+    fn remote_patches_and_operations_invalidate() {
+        let mut model = Model::new(7);
+        let [mut text, mut hi] = [ORIGIN; 2];
+        edit(&mut model, |b| {
+            let obj = b.obj();
+            text = b.str_node();
+            hi = b.ins_str(text, text, "hi".into());
+            b.ins_obj(obj, vec![("t".into(), text)]);
+            b.root(obj);
+        });
+        let mut peer = model.clone();
+        peer.clock.sid = 8;
+        let mut builder = PatchBuilder::new(8, peer.clock.time);
+        builder.ins_str(text, ts(7, hi.time + 1), "!".into());
+        let patch = builder.flush();
+        for op in &patch.ops {
+            model.apply_operation(op);
+        }
+        assert_eq!(*model.cached_view(), json!({"t": "hi!"}));
+    }
+
+    #[test]
+    fn cloned_models_keep_their_own_view() {
+        let mut model = Model::new(7);
+        let mut text = ORIGIN;
+        edit(&mut model, |b| {
+            text = b.str_node();
+            b.root(text);
+        });
+        let mut copy = model.clone();
+        edit(&mut copy, |b| {
+            b.ins_str(text, text, "copy".into());
+        });
+        assert_eq!(*model.cached_view(), json!(""));
+        assert_eq!(*copy.cached_view(), json!("copy"));
+    }
+
+    #[test]
+    fn unchanged_nodes_are_not_re_rendered() {
+        let mut model = Model::new(7);
+        let [mut a, mut b_text] = [ORIGIN; 2];
+        edit(&mut model, |b| {
+            let obj = b.obj();
+            a = b.str_node();
+            b_text = b.str_node();
+            b.ins_obj(obj, vec![("a".into(), a), ("b".into(), b_text)]);
+            b.root(obj);
+        });
+        // An edit outside the patch pipeline is not seen...
+        let hidden = ts(7, model.clock.time);
+        if let Some(crate::json_crdt::nodes::CrdtNode::Str(node)) = model
+            .index
+            .get_mut(&crate::json_crdt::nodes::TsKey::from(a))
+        {
+            node.ins(a, hidden, "hidden".into());
+        }
+        model.clock.observe(hidden, 6);
+        let mut builder = PatchBuilder::new(7, model.clock.time);
+        builder.ins_str(b_text, b_text, "seen".into());
+        model.apply_patch(&builder.flush());
+        assert_eq!(*model.cached_view(), json!({"a": "", "b": "seen"}));
+        // ...until the view is invalidated.
+        model.invalidate_view();
+        assert_eq!(*model.cached_view(), json!({"a": "hidden", "b": "seen"}));
+    }
+
+    #[test]
+    fn containers_keep_the_views_of_unchanged_children() {
+        let mut model = Model::new(7);
+        let [mut obj, mut list, mut text] = [ORIGIN; 3];
+        edit(&mut model, |b| {
+            obj = b.obj();
+            list = b.arr();
+            text = b.str_node();
+            b.ins_arr(list, list, vec![text]);
+            b.ins_obj(obj, vec![("list".into(), list)]);
+            b.root(obj);
+        });
+        // Hidden from the cache, so re-rendering `text` would reveal it.
+        let hidden = ts(7, model.clock.time);
+        if let Some(crate::json_crdt::nodes::CrdtNode::Str(node)) = model
+            .index
+            .get_mut(&crate::json_crdt::nodes::TsKey::from(text))
+        {
+            node.ins(text, hidden, "hidden".into());
+        }
+        model.clock.observe(hidden, 6);
+        let mut builder = PatchBuilder::new(7, model.clock.time);
+        let one = builder.con_val(PackValue::Integer(1));
+        builder.ins_obj(obj, vec![("one".into(), one)]);
+        let two = builder.con_val(PackValue::Integer(2));
+        builder.ins_arr(list, list, vec![two]);
+        model.apply_patch(&builder.flush());
+        assert_eq!(*model.cached_view(), json!({"list": [2, ""], "one": 1}));
+    }
+}
@@ -215,8 +215,9 @@ impl StrNode {
         self.rga.insert(after, id, span, data);
     }
 
-    pub fn delete(&mut self, spans: &[Tss]) {
-        self.rga.delete(spans);
+    /// Delete the items covered by `spans` on behalf of operation `by`.
+    pub fn delete(&mut self, by: Ts, spans: &[Tss]) {
+        self.rga.delete_by(by, spans);
     }
 
     pub fn view(&self) -> Value {
@@ -306,8 +307,9 @@ impl BinNode {
         self.rga.insert(after, id, span, data);
     }
 
-    pub fn delete(&mut self, spans: &[Tss]) {
-        self.rga.delete(spans);
+    /// Delete the items covered by `spans` on behalf of operation `by`.
+    pub fn delete(&mut self, by: Ts, spans: &[Tss]) {
+        self.rga.delete_by(by, spans);
     }
 
     pub fn view(&self) -> Vec<u8> {
@@ -328,10 +330,50 @@ impl BinNode {
 // ── ArrNode ───────────────────────────────────────────────────────────────
 
 /// RGA array of node-ID references.
+///
+/// Elements can be moved with [`ArrNode::mov`]: a move inserts a fresh slot
+/// at the destination and the element's position is a LWW register over
+/// the slots created for it, so only the slot written by the latest move is
+/// live. Concurrent moves of one element therefore never duplicate it.
 #[derive(Debug, Clone)]
 pub struct ArrNode {
     pub id: Ts,
     pub rga: Rga<Vec<Ts>>,
+    /// Move bookkeeping, empty until the first move.
+    pub moves: ArrMoves,
+}
+
+/// Position registers of moved [`ArrNode`] elements.
+///
+/// An element is identified by the slot it was first inserted into (its
+/// *origin*). Snapshot codecs persist the registers outside the upstream
+/// node formats (see [`extras`](crate::json_crdt::codec::extras)).
+#[derive(Debug, Clone, Default)]
+pub struct ArrMoves {
+    /// Slot created by a move → origin slot of the moved element.
+    pub origin: BTreeMap<TsKey, Ts>,
+    /// Origin slot → slot currently holding the element (the LWW winner).
+    pub position: BTreeMap<TsKey, Ts>,
+}
+
+impl ArrMoves {
+    pub fn is_empty(&self) -> bool {
+        self.position.is_empty()
+    }
+
+    /// Origin slot of the element that `slot` belongs to.
+    pub fn element_of(&self, slot: Ts) -> Ts {
+        self.origin.get(&TsKey::from(slot)).copied().unwrap_or(slot)
+    }
+
+    /// Slot currently holding the element that `slot` belongs to.
+    pub fn current(&self, slot: Ts) -> Ts {
+        let origin = self.element_of(slot);
+        self.position
+            .get(&TsKey::from(origin))
+            .copied()
+            .unwrap_or(origin)
+    }
 }
 
 impl ArrNode {
@@ -339,7 +381,69 @@ impl ArrNode {
         Self {
             id,
             rga: Rga::new(),
+            moves: ArrMoves::default(),
+        }
+    }
+
+    /// Moves the element held by slot `elem` (or by any slot it was moved
+    /// through) to a new slot `id` inserted after `after`.
+    ///
+    /// The new slot wins if `id` is newer than the element's current slot;
+    /// otherwise it is inserted as a tombstone. Moving a deleted element
+    /// also leaves a tombstone, so a move never resurrects a delete.
+    /// Returns `false` if `elem` is unknown.
+    pub fn mov(&mut self, id: Ts, after: Ts, elem: Ts) -> bool {
+        let origin = self.moves.element_of(elem);
+        let current = self.moves.current(elem);
+        if self.rga.find_by_id(current).is_none() || self.rga.find_by_id(id).is_some() {
+            return false;
+        }
+        let data = self.get_by_id(current);
+        self.rga.insert(after, id, 1, vec![data.unwrap_or(ORIGIN)]);
+        self.moves.origin.insert(TsKey::from(id), origin);
+        let loser = match data {
+            Some(_) if compare(id, current) > 0 => {
+                self.moves.position.insert(TsKey::from(origin), id);
+                current
+            }
+            _ => id,
+        };
+        self.rga
+            .delete_by(id, &[Tss::new(loser.sid, loser.time, 1)]);
+        true
+    }
+
+    /// Redirects a delete of `spans` to the slots currently holding the
+    /// elements they cover, so deleting a moved-away slot still deletes the
+    /// element.
+    pub fn resolve_delete(&self, spans: &[Tss]) -> Vec<Tss> {
+        let mut resolved = spans.to_vec();
+        if self.moves.is_empty() {
+            return resolved;
+        }
+        let covers = |slot: Ts| {
+            spans
+                .iter()
+                .any(|s| s.sid == slot.sid && slot.time >= s.time && slot.time < s.time + s.span)
+        };
+        let moved = self
+            .moves
+            .origin
+            .iter()
+            .map(|(slot, origin)| (Ts::new(slot.sid, slot.time), *origin))
+            .chain(self.moves.position.keys().map(|o| {
+                let origin = Ts::new(o.sid, o.time);
+                (origin, origin)
+            }));
+        for (slot, origin) in moved {
+            if covers(slot) {
+                let current = self.moves.current(origin);
+                if !covers(current) {
+                    resolved.push(Tss::new(current.sid, current.time, 1));
+                }
+            }
         }
+        resolved
     }
 
     /// Insert node IDs after `after`.
@@ -363,7 +467,10 @@ impl ArrNode {
     ///
     /// Mirrors `ArrNode.upd` in the upstream TypeScript.
     /// Only replaces the current value if `val` has a higher timestamp.
+    /// A slot the element has since been moved away from resolves to the
+    /// element's current slot.
     pub fn upd(&mut self, ref_id: Ts, val: Ts) -> Option<Ts> {
+        let ref_id = self.moves.current(ref_id);
         let idx = self.rga.find_by_id(ref_id)?;
         let chunk = self.rga.slot_mut(idx);
         if let Some(data) = &mut chunk.data {
@@ -381,8 +488,9 @@ impl ArrNode {
         None
     }
 
-    pub fn delete(&mut self, spans: &[Tss]) {
-        self.rga.delete(spans);
+    /// Delete the items covered by `spans` on behalf of operation `by`.
+    pub fn delete(&mut self, by: Ts, spans: &[Tss]) {
+        self.rga.delete_by(by, spans);
     }
 
     /// Number of live elements in this array.
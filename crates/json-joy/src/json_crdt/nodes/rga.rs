@@ -12,7 +12,7 @@
 //! Chunks also carry a `s` (split-link) pointer that threads together
 //! consecutive pieces of the same original insertion operation.
 
-use crate::json_crdt_patch::clock::{compare, Ts, Tss};
+use crate::json_crdt_patch::clock::{compare, ClockVector, Ts, Tss};
 use sonic_forest::{Node, Node2};
 
 // ── ChunkData ─────────────────────────────────────────────────────────────
@@ -83,6 +83,10 @@ pub struct Chunk<T: Clone> {
     pub deleted: bool,
     /// Actual content. `None` if the chunk is a deleted tombstone.
     pub data: Option<T>,
+    /// ID of the operation that deleted this tombstone, used by
+    /// [`Rga::gc`]. `None` when unknown: the tombstone was decoded from a
+    /// snapshot, or merged from deletes by different sessions.
+    pub deleted_by: Option<Ts>,
     /// Aggregated live (non-deleted) content length in this subtree.
     pub len: u64,
     // Position tree links
@@ -104,6 +108,7 @@ impl<T: Clone> Chunk<T> {
             span,
             deleted: false,
             data: Some(data),
+            deleted_by: None,
             len: span,
             p: None,
             l: None,
@@ -121,6 +126,7 @@ impl<T: Clone> Chunk<T> {
             span,
             deleted: true,
             data: None,
+            deleted_by: None,
             len: 0,
             p: None,
             l: None,
@@ -494,7 +500,10 @@ fn alloc_split_chunk<T: Clone + ChunkData>(rga: &mut Rga<T>, idx: u32, ticks: us
     let right_span = span - ticks as u64;
 
     let new_chunk = if del {
-        Chunk::new_deleted(right_id, right_span)
+        Chunk {
+            deleted_by: rga.chunks[idx as usize].deleted_by,
+            ..Chunk::new_deleted(right_id, right_span)
+        }
     } else {
         match right_data {
             Some(d) => Chunk::new(right_id, right_span, d),
@@ -813,12 +822,26 @@ fn merge_tombstones<T: Clone>(rga: &mut Rga<T>, ch1: u32, ch2: u32) -> bool {
     }
     let s2 = rga.chunks[ch2 as usize].s;
     let ch2_span = rga.chunks[ch2 as usize].span;
+    let by = merge_deleters(
+        rga.chunks[ch1 as usize].deleted_by,
+        rga.chunks[ch2 as usize].deleted_by,
+    );
     rga.chunks[ch1 as usize].s = s2;
     rga.chunks[ch1 as usize].span += ch2_span;
+    rga.chunks[ch1 as usize].deleted_by = by;
     delete_chunk(rga, ch2);
     true
 }
 
+/// Deleter of a tombstone merged from tombstones deleted by `a` and `b`:
+/// the later delete if both come from one session, otherwise unknown.
+fn merge_deleters(a: Option<Ts>, b: Option<Ts>) -> Option<Ts> {
+    match (a, b) {
+        (Some(a), Some(b)) if a.sid == b.sid => Some(if a.time >= b.time { a } else { b }),
+        _ => None,
+    }
+}
+
 /// Try to merge tombstones around the deletion range `[start, end]`.
 /// Mirrors `AbstractRga.mergeTombstones2()`.
 fn merge_tombstones2<T: Clone>(rga: &mut Rga<T>, start: u32, end: u32) {
@@ -849,7 +872,7 @@ fn merge_tombstones2<T: Clone>(rga: &mut Rga<T>, start: u32, end: u32) {
 
 /// Delete all items in a single timestamp span.
 /// Mirrors `AbstractRga.deleteSpan()`.
-fn delete_span<T: Clone + ChunkData>(rga: &mut Rga<T>, tss: Tss) {
+fn delete_span<T: Clone + ChunkData>(rga: &mut Rga<T>, tss: Tss, by: Option<Ts>) {
     let t1 = tss.time;
     let t2 = t1 + tss.span - 1;
 
@@ -886,6 +909,7 @@ fn delete_span<T: Clone + ChunkData>(rga: &mut Rga<T>, tss: Tss) {
                 // Delete the whole chunk.
                 rga.chunks[ci as usize].deleted = true;
                 rga.chunks[ci as usize].data = None;
+                rga.chunks[ci as usize].deleted_by = by;
                 d_len(&mut rga.chunks, Some(ci), -(c_span as i64));
                 if t2 <= c2 {
                     break;
@@ -898,6 +922,7 @@ fn delete_span<T: Clone + ChunkData>(rga: &mut Rga<T>, tss: Tss) {
                 let del_span = rga.chunks[ci as usize].span;
                 rga.chunks[ci as usize].deleted = true;
                 rga.chunks[ci as usize].data = None;
+                rga.chunks[ci as usize].deleted_by = by;
                 update_len_one(&mut rga.chunks, _new_ci);
                 d_len(&mut rga.chunks, Some(ci), -(del_span as i64));
                 break;
@@ -911,6 +936,7 @@ fn delete_span<T: Clone + ChunkData>(rga: &mut Rga<T>, tss: Tss) {
                 let new_span = rga.chunks[new_ci as usize].span;
                 rga.chunks[new_ci as usize].deleted = true;
                 rga.chunks[new_ci as usize].data = None;
+                rga.chunks[new_ci as usize].deleted_by = by;
                 rga.chunks[new_ci as usize].len = rga.chunks[new_ci as usize]
                     .r
                     .map(|r| rga.chunks[r as usize].len)
@@ -926,6 +952,7 @@ fn delete_span<T: Clone + ChunkData>(rga: &mut Rga<T>, tss: Tss) {
                 let mid_span = rga.chunks[mid as usize].span;
                 rga.chunks[mid as usize].deleted = true;
                 rga.chunks[mid as usize].data = None;
+                rga.chunks[mid as usize].deleted_by = by;
                 update_len_one(&mut rga.chunks, right);
                 update_len_one(&mut rga.chunks, mid);
                 d_len(&mut rga.chunks, Some(ci), -(mid_span as i64));
@@ -1028,6 +1055,24 @@ impl<T: Clone + ChunkData> Rga<T> {
         Some(chunk)
     }
 
+    /// Number of live items before chunk `idx` in document order.
+    ///
+    /// Walks up the position tree, adding the aggregated length of every
+    /// subtree to the left. Mirrors `AbstractRga.pos()`.
+    pub fn pos(&self, idx: u32) -> u64 {
+        let chunk = &self.chunks[idx as usize];
+        let mut pos = chunk.l.map_or(0, |l| self.chunks[l as usize].len);
+        let mut curr = idx;
+        while let Some(p) = self.chunks[curr as usize].p {
+            let parent = &self.chunks[p as usize];
+            if parent.r == Some(curr) {
+                pos += parent.len - self.chunks[curr as usize].len;
+            }
+            curr = p;
+        }
+        pos
+    }
+
     // ── Insert ────────────────────────────────────────────────────────────
 
     /// Insert `data` (with timestamp `id`, logical span `span`) after the
@@ -1143,12 +1188,86 @@ impl<T: Clone + ChunkData> Rga<T> {
     // ── Deletion ─────────────────────────────────────────────────────────
 
     /// Delete all items covered by the given timestamp spans.
+    ///
+    /// The deleting operation is not recorded, so [`Rga::gc`] treats the
+    /// tombstones as deleted at an unknown time; prefer [`Rga::delete_by`].
     pub fn delete(&mut self, spans: &[Tss]) {
         for &tss in spans {
-            delete_span(self, tss);
+            delete_span(self, tss, None);
+        }
+    }
+
+    /// Delete all items covered by `spans` on behalf of operation `by`.
+    ///
+    /// Items that are already deleted keep their original deleter.
+    pub fn delete_by(&mut self, by: Ts, spans: &[Tss]) {
+        for &tss in spans {
+            delete_span(self, tss, Some(by));
         }
     }
 
+    // ── Garbage collection ───────────────────────────────────────────────
+
+    /// Drops tombstones whose deleting operation `horizon` has seen, merges
+    /// the remaining adjacent tombstones with contiguous IDs, and returns
+    /// the number of chunks removed.
+    ///
+    /// `horizon` must be causally stable: every replica has reached it and
+    /// no operation concurrent with it is still in flight. A replica that
+    /// has seen a delete no longer references the deleted items, so once
+    /// the delete is below the horizon nothing can reference the tombstone
+    /// any more. Tombstones whose deleter is unknown (see
+    /// [`Chunk::deleted_by`]) are dropped only if `horizon` has reached
+    /// `clock`, the clock of the replica holding this sequence, which has
+    /// seen every delete applied to it.
+    ///
+    /// The sequence is rebuilt the same way codec decoders build it, so
+    /// split links are not preserved.
+    pub fn gc(&mut self, horizon: &ClockVector, clock: &ClockVector) -> usize {
+        let all_stable = horizon.has_reached(clock);
+        let mut kept: Vec<Chunk<T>> = Vec::new();
+        let mut total = 0;
+        for chunk in self.iter() {
+            total += 1;
+            if !chunk.deleted {
+                if let Some(data) = &chunk.data {
+                    kept.push(Chunk::new(chunk.id, chunk.span, data.clone()));
+                }
+                continue;
+            }
+            let stable = match chunk.deleted_by {
+                Some(by) => horizon.has_seen(by),
+                None => all_stable,
+            };
+            if stable {
+                continue;
+            }
+            match kept.last_mut() {
+                Some(prev)
+                    if prev.deleted
+                        && prev.id.sid == chunk.id.sid
+                        && prev.id.time + prev.span == chunk.id.time =>
+                {
+                    prev.span += chunk.span;
+                    prev.deleted_by = merge_deleters(prev.deleted_by, chunk.deleted_by);
+                }
+                _ => kept.push(Chunk {
+                    deleted_by: chunk.deleted_by,
+                    ..Chunk::new_deleted(chunk.id, chunk.span)
+                }),
+            }
+        }
+        let removed = total - kept.len();
+        if removed > 0 {
+            let mut rga = Rga::new();
+            for chunk in kept {
+                rga.push_chunk(chunk);
+            }
+            *self = rga;
+        }
+        removed
+    }
+
     // ── Iteration ─────────────────────────────────────────────────────────
 
     /// Iterator over all chunks in document order (in-order position tree).
@@ -1334,4 +1453,107 @@ mod tests {
         assert!(rga.find_by_id(ts(1, 3)).is_some());
         assert!(rga.find_by_id(ts(2, 1)).is_none());
     }
+
+    #[test]
+    fn pos_counts_live_items_before_a_chunk() {
+        let mut rga: Rga<String> = Rga::new();
+        rga.insert(origin(), ts(sid(), 1), 5, "hello".to_string());
+        rga.insert(ts(sid(), 5), ts(sid(), 6), 3, "abc".to_string());
+        rga.insert(ts(sid(), 2), ts(2, 1), 2, "xy".to_string());
+        rga.delete(&[tss(sid(), 1, 1)]);
+        let mut live = 0;
+        for chunk in rga.iter() {
+            let idx = rga.find_by_id(chunk.id).unwrap();
+            assert_eq!(rga.pos(idx), live);
+            live += chunk.len();
+        }
+    }
+
+    #[test]
+    fn gc_drops_tombstones_below_horizon() {
+        let mut rga: Rga<String> = Rga::new();
+        rga.insert(origin(), ts(1, 1), 5, "hello".to_string());
+        rga.insert(ts(1, 5), ts(2, 6), 6, " world".to_string());
+        rga.delete_by(ts(1, 12), &[tss(1, 2, 2)]);
+        rga.delete_by(ts(2, 13), &[tss(2, 7, 2)]);
+        assert_eq!(rga.iter().count(), 6);
+
+        // Peer 2's delete is beyond the horizon, so its tombstone survives.
+        let horizon = ClockVector::new(1, 13);
+        let mut clock = ClockVector::new(1, 14);
+        clock.observe(ts(2, 13), 1);
+        assert_eq!(rga.gc(&horizon, &clock), 1);
+        let ids: Vec<(Ts, bool)> = rga.iter().map(|c| (c.id, c.deleted)).collect();
+        assert_eq!(
+            ids,
+            vec![
+                (ts(1, 1), false),
+                (ts(1, 4), false),
+                (ts(2, 6), false),
+                (ts(2, 7), true),
+                (ts(2, 9), false),
+            ]
+        );
+        let s: String = rga.iter_live().filter_map(|c| c.data.as_deref()).collect();
+        assert_eq!(s, "hlo rld");
+
+        // The rebuilt sequence still accepts inserts after live items.
+        rga.insert(ts(1, 4), ts(1, 20), 1, "L".to_string());
+        let s: String = rga.iter_live().filter_map(|c| c.data.as_deref()).collect();
+        assert_eq!(s, "hlLo rld");
+    }
+
+    #[test]
+    fn gc_keeps_old_text_deleted_after_horizon() {
+        let mut rga: Rga<String> = Rga::new();
+        rga.insert(origin(), ts(1, 1), 5, "hello".to_string());
+        rga.delete_by(ts(1, 20), &[tss(1, 1, 5)]);
+
+        // The text predates the horizon but its delete does not: a replica
+        // at the horizon may still insert after it.
+        let horizon = ClockVector::new(1, 11);
+        let clock = ClockVector::new(1, 21);
+        assert_eq!(rga.gc(&horizon, &clock), 0);
+        rga.insert(ts(1, 4), ts(2, 30), 1, "!".to_string());
+        let s: String = rga.iter_live().filter_map(|c| c.data.as_deref()).collect();
+        assert_eq!(s, "!");
+        assert_eq!(rga.gc(&ClockVector::new(1, 21), &clock), 2);
+    }
+
+    #[test]
+    fn gc_waits_for_the_full_clock_when_the_deleter_is_unknown() {
+        let mut rga: Rga<String> = Rga::new();
+        rga.insert(origin(), ts(1, 1), 5, "hello".to_string());
+        rga.delete(&[tss(1, 1, 2)]);
+        let mut clock = ClockVector::new(1, 6);
+        clock.observe(ts(2, 3), 1);
+        assert_eq!(rga.gc(&ClockVector::new(1, 6), &clock), 0);
+        let mut horizon = ClockVector::new(1, 6);
+        horizon.observe(ts(2, 3), 1);
+        assert_eq!(rga.gc(&horizon, &clock), 1);
+    }
+
+    #[test]
+    fn gc_merges_contiguous_tombstones() {
+        let mut rga: Rga<String> = Rga::new();
+        rga.insert(origin(), ts(1, 1), 5, "hello".to_string());
+        rga.insert(ts(1, 5), ts(2, 10), 2, "ab".to_string());
+        rga.insert(ts(2, 10), ts(1, 20), 1, "X".to_string());
+        rga.delete_by(ts(1, 21), &[tss(1, 20, 1)]);
+        rga.delete_by(ts(2, 30), &[tss(2, 10, 2)]);
+        assert_eq!(rga.iter().count(), 4);
+
+        // Dropping "X" leaves the two halves of peer 2's tombstone adjacent.
+        let horizon = ClockVector::new(1, 22);
+        let mut clock = ClockVector::new(1, 22);
+        clock.observe(ts(2, 30), 1);
+        assert_eq!(rga.gc(&horizon, &clock), 2);
+        let chunks: Vec<(Ts, u64, Option<Ts>)> =
+            rga.iter().map(|c| (c.id, c.span, c.deleted_by)).collect();
+        assert_eq!(
+            chunks,
+            vec![(ts(1, 1), 5, None), (ts(2, 10), 2, Some(ts(2, 30)))]
+        );
+        assert_eq!(rga.gc(&horizon, &clock), 0);
+    }
 }
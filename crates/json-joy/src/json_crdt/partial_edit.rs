@@ -302,6 +302,7 @@ fn op_obj(op: &Op) -> Option<Ts> {
         Op::InsBin { obj, .. } => Some(*obj),
         Op::InsArr { obj, .. } => Some(*obj),
         Op::UpdArr { obj, .. } => Some(*obj),
+        Op::MovArr { obj, .. } => Some(*obj),
         Op::Del { obj, .. } => Some(*obj),
         // Creation ops and Nop don't reference an existing node.
         _ => None,
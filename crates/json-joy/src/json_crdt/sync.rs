@@ -0,0 +1,302 @@
+//! State-vector based synchronisation between two replicas.
+//!
+//! # Overview
+//!
+//! Each replica summarises everything it has seen as a *state vector* — its
+//! [`ClockVector`] encoded with [`encode_state_vector`]. A sync round between
+//! two peers is:
+//!
+//! 1. Peer A sends its state vector to peer B.
+//! 2. B answers with [`encode_update`]: every patch in its [`Log`] that A has
+//!    not seen (see [`Log::patches_since`]), each encoded with the binary
+//!    patch codec.
+//! 3. A applies the update with [`apply_update`], which skips patches it has
+//!    already seen.
+//!
+//! Running the same round in the other direction makes both replicas
+//! converge.
+//!
+//! If the serving model has been garbage collected (see
+//! [`gc`](crate::json_crdt::model::gc)), peers whose state vector is behind
+//! its GC horizon are refused with [`SyncError::Gc`], and incoming patches
+//! that reference collected items are rejected before being applied.
+//!
+//! # Wire format
+//!
+//! State vector (same layout as the indexed codec's clock table; the first
+//! entry is the local session at its last issued time):
+//!
+//! ```text
+//! vu57(count)  [vu57(sid) vu57(time)] × count
+//! ```
+//!
+//! Update:
+//!
+//! ```text
+//! vu57(count)  [vu57(length) binary_patch] × count
+//! ```
+
+use crate::json_crdt::log::Log;
+use crate::json_crdt::model::gc::{check_peer, GcError};
+use crate::json_crdt_patch::clock::{ClockVector, Ts};
+use crate::json_crdt_patch::codec::binary::DecodeError;
+use crate::json_crdt_patch::codec::clock::ClockTable;
+use crate::json_crdt_patch::patch::Patch;
+use crate::json_crdt_patch::util::binary::{CrdtReader, CrdtWriter};
+
+/// Errors produced while decoding sync messages.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SyncError {
+    /// The state vector payload is truncated or empty.
+    #[error("invalid state vector")]
+    InvalidStateVector,
+    /// The update payload is truncated.
+    #[error("invalid update")]
+    InvalidUpdate,
+    /// A patch inside the update could not be decoded.
+    #[error("invalid patch: {0}")]
+    Patch(#[from] DecodeError),
+    /// The exchange conflicts with the garbage-collected state.
+    #[error(transparent)]
+    Gc(#[from] GcError),
+}
+
+/// Encodes `clock` as a state vector.
+pub fn encode_state_vector(clock: &ClockVector) -> Vec<u8> {
+    let table = ClockTable::from_clock(clock);
+    let mut w = CrdtWriter::new();
+    w.vu57(table.by_idx.len() as u64);
+    for entry in &table.by_idx {
+        w.vu57(entry.sid);
+        w.vu57(entry.time);
+    }
+    w.flush()
+}
+
+/// Decodes a state vector produced by [`encode_state_vector`].
+pub fn decode_state_vector(data: &[u8]) -> Result<ClockVector, SyncError> {
+    let mut r = CrdtReader::new(data);
+    let count = r.vu57();
+    if count == 0 {
+        return Err(SyncError::InvalidStateVector);
+    }
+    let sid = r.vu57();
+    let time = r.vu57();
+    let mut clock = ClockVector::new(sid, time + 1);
+    for _ in 1..count {
+        let sid = r.vu57();
+        let time = r.vu57();
+        if r.x > data.len() {
+            return Err(SyncError::InvalidStateVector);
+        }
+        clock.observe(Ts::new(sid, time), 1);
+    }
+    if r.x > data.len() {
+        return Err(SyncError::InvalidStateVector);
+    }
+    Ok(clock)
+}
+
+/// Encodes a sequence of patches as an update payload.
+pub fn encode_patches<'a>(patches: impl IntoIterator<Item = &'a Patch>) -> Vec<u8> {
+    let encoded: Vec<Vec<u8>> = patches.into_iter().map(Patch::to_binary).collect();
+    let mut w = CrdtWriter::new();
+    w.vu57(encoded.len() as u64);
+    for bytes in &encoded {
+        w.vu57(bytes.len() as u64);
+        w.buf(bytes);
+    }
+    w.flush()
+}
+
+/// Decodes an update payload produced by [`encode_patches`].
+pub fn decode_patches(data: &[u8]) -> Result<Vec<Patch>, SyncError> {
+    let mut r = CrdtReader::new(data);
+    let count = r.vu57();
+    let mut patches = Vec::new();
+    for _ in 0..count {
+        let len = r.vu57() as usize;
+        if r.x + len > data.len() {
+            return Err(SyncError::InvalidUpdate);
+        }
+        patches.push(Patch::from_binary(r.buf(len))?);
+    }
+    if r.x > data.len() {
+        return Err(SyncError::InvalidUpdate);
+    }
+    Ok(patches)
+}
+
+/// Answers a remote state vector with the patches the remote is missing.
+pub fn encode_update(log: &Log, remote_state: &[u8]) -> Result<Vec<u8>, SyncError> {
+    let remote = decode_state_vector(remote_state)?;
+    if let Some(horizon) = &log.end.gc_horizon {
+        check_peer(horizon, &remote)?;
+    }
+    Ok(encode_patches(log.patches_since(&remote)))
+}
+
+/// Applies an update payload to `log`, skipping patches it has already seen.
+///
+/// Patches are applied in `(time, sid)` order, which respects causality.
+/// Returns the IDs of the patches that were applied. A patch referencing a
+/// garbage-collected item aborts the update; patches before it stay applied.
+pub fn apply_update(log: &mut Log, update: &[u8]) -> Result<Vec<Ts>, SyncError> {
+    let mut patches = decode_patches(update)?;
+    patches.retain(|patch| patch.get_id().is_some());
+    patches.sort_by_key(|patch| patch.get_id().map(|id| (id.time, id.sid)));
+    let mut applied = Vec::new();
+    for patch in patches {
+        let Some(id) = patch.get_id() else {
+            continue;
+        };
+        let last = Ts::new(id.sid, id.time + patch.span() - 1);
+        if log.end.clock.has_seen(last) {
+            continue;
+        }
+        log.end.check_patch(&patch)?;
+        log.apply(patch);
+        applied.push(id);
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::model::{Model, ModelApi};
+    use serde_json::json;
+
+    const A: u64 = 200_001;
+    const B: u64 = 200_002;
+
+    fn make_pair() -> (Log, Log) {
+        let mut a = Log::from_new_model(Model::new(A));
+        let mut model = Model::new(A);
+        let patch = {
+            let mut api = ModelApi::new(&mut model);
+            let id = api.json(&json!({"text": "ab"})).unwrap();
+            api.builder.root(id);
+            api.flush()
+        };
+        a.apply(patch.clone());
+        let mut b = Log::from_new_model(Model::new(B));
+        b.apply(patch);
+        (a, b)
+    }
+
+    fn text_id(log: &Log) -> Ts {
+        let root = log.end.root.val;
+        match crate::json_crdt::nodes::IndexExt::get(&log.end.index, &root) {
+            Some(crate::json_crdt::nodes::CrdtNode::Obj(obj)) => obj.keys["text"],
+            _ => panic!("root should be an obj"),
+        }
+    }
+
+    fn ins(log: &mut Log, index: usize, text: &str) {
+        let id = text_id(log);
+        let mut model = log.end.clone();
+        let patch = {
+            let mut api = ModelApi::new(&mut model);
+            let after = if index == 0 {
+                id
+            } else {
+                api.node(id)
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .find(index - 1)
+                    .unwrap()
+            };
+            api.builder.ins_str(id, after, text.to_string());
+            api.flush()
+        };
+        log.apply(patch);
+    }
+
+    /// Performs a full two-way sync round between `a` and `b`.
+    fn sync(a: &mut Log, b: &mut Log) -> (usize, usize) {
+        let a_state = encode_state_vector(&a.end.clock);
+        let b_state = encode_state_vector(&b.end.clock);
+        let to_a = encode_update(b, &a_state).unwrap();
+        let to_b = encode_update(a, &b_state).unwrap();
+        let applied_a = apply_update(a, &to_a).unwrap().len();
+        let applied_b = apply_update(b, &to_b).unwrap().len();
+        (applied_a, applied_b)
+    }
+
+    #[test]
+    fn state_vector_round_trip() {
+        let mut clock = ClockVector::new(A, 10);
+        clock.observe(Ts::new(B, 7), 1);
+        let decoded = decode_state_vector(&encode_state_vector(&clock)).unwrap();
+        assert_eq!(decoded.sid, A);
+        assert_eq!(decoded.time, 10);
+        assert_eq!(decoded.peers[&B].time, 7);
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        assert!(matches!(
+            decode_state_vector(&[]),
+            Err(SyncError::InvalidStateVector)
+        ));
+        assert_eq!(decode_patches(&[1, 200]), Err(SyncError::InvalidUpdate));
+    }
+
+    #[test]
+    fn two_way_sync_converges() {
+        let (mut a, mut b) = make_pair();
+        ins(&mut a, 2, "c");
+        ins(&mut a, 3, "d");
+        ins(&mut b, 0, "z");
+        assert_eq!(sync(&mut a, &mut b), (1, 2));
+        assert_eq!(a.end.view(), b.end.view());
+        assert_eq!(a.end.view(), json!({"text": "zabcd"}));
+        // A second round has nothing left to exchange.
+        assert_eq!(sync(&mut a, &mut b), (0, 0));
+    }
+
+    #[test]
+    fn update_only_contains_missing_patches() {
+        let (mut a, mut b) = make_pair();
+        ins(&mut a, 2, "c");
+        sync(&mut a, &mut b);
+        ins(&mut a, 3, "d");
+        let b_state = encode_state_vector(&b.end.clock);
+        let update = decode_patches(&encode_update(&a, &b_state).unwrap()).unwrap();
+        assert_eq!(update.len(), 1);
+        assert_eq!(
+            update[0].get_id(),
+            a.patches.values().last().unwrap().get_id()
+        );
+    }
+
+    #[test]
+    fn peer_behind_gc_horizon_is_refused() {
+        let (mut a, mut b) = make_pair();
+        let b_state = encode_state_vector(&b.end.clock);
+        ins(&mut a, 2, "c");
+        let horizon = a.end.clock.clone();
+        a.end.gc(&horizon);
+        assert!(matches!(
+            encode_update(&a, &b_state),
+            Err(SyncError::Gc(GcError::BehindHorizon { .. }))
+        ));
+        // Once B has caught up through another path it is served again.
+        let update = encode_patches(a.patches.values());
+        apply_update(&mut b, &update).unwrap();
+        let b_state = encode_state_vector(&b.end.clock);
+        assert!(encode_update(&a, &b_state).is_ok());
+    }
+
+    #[test]
+    fn applying_an_update_twice_is_a_noop() {
+        let (mut a, mut b) = make_pair();
+        ins(&mut a, 2, "c");
+        let update = encode_update(&a, &encode_state_vector(&b.end.clock)).unwrap();
+        assert_eq!(apply_update(&mut b, &update).unwrap().len(), 1);
+        assert!(apply_update(&mut b, &update).unwrap().is_empty());
+        assert_eq!(b.end.view(), json!({"text": "abc"}));
+    }
+}
@@ -0,0 +1,434 @@
+//! Typed mapping between Rust values and JSON CRDT nodes.
+//!
+//! # Overview
+//!
+//! [`CrdtValue`] describes how a Rust value is stored in a document: the
+//! schema used to create it and its JSON view. It is implemented for
+//! strings, booleans, numbers, `Option<T>` and `Vec<T>`.
+//!
+//! [`CrdtSchema`] is implemented by `#[derive(CrdtSchema)]` for structs with
+//! named fields. The derive also generates a typed handle, `<Name>Node`,
+//! with a getter, a `<field>_id` lookup and a `set_<field>` setter per field:
+//!
+//! ```ignore
+//! #[derive(CrdtSchema)]
+//! struct Todo {
+//!     title: String,           // str (the default for `String`)
+//!     #[crdt(con)]
+//!     done: bool,
+//!     #[crdt(rename = "tags")]
+//!     labels: Vec<String>,     // arr
+//! }
+//!
+//! let node = todo.write_root(&mut api);
+//! node.set_done(&mut api, &true)?;
+//! let todo = node.read(api.model)?;
+//! ```
+//!
+//! Struct attributes: `#[crdt(obj)]` (default) stores fields as object keys,
+//! `#[crdt(vec)]` stores them in vector slots in declaration order.
+//!
+//! Field attributes choose the node kind: `con`, `val`, `str`, `bin`, `arr`,
+//! and `obj` for a nested `CrdtSchema` struct (which also generates a
+//! `<field>_node` accessor). `rename = "..."` changes the object key; it is
+//! rejected on `vec` structs, which have no keys.
+//!
+//! Setters replace the field's node with a new one, except for `str` nodes
+//! which are merged so that concurrent edits to the text are preserved, and
+//! `val` fields, whose existing register is written to.
+
+use serde_json::Map;
+
+use crate::json_crdt::model::api::{find_path, ApiError};
+use crate::json_crdt::model::{Model, ModelApi};
+use crate::json_crdt::nodes::{CrdtNode, IndexExt};
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::schema::{
+    ArrNode, BinNode, ConNode, NodeBuilder, ObjNode, StrNode, ValNode, VecNode,
+};
+use json_joy_json_pack::PackValue;
+
+pub use json_joy_derive::CrdtSchema;
+pub use serde_json::Value;
+
+// ── Traits ─────────────────────────────────────────────────────────────────
+
+/// A Rust value that can be stored in a JSON CRDT document.
+pub trait CrdtValue: Sized {
+    /// Schema that creates a node holding this value.
+    fn schema(&self) -> Box<dyn NodeBuilder>;
+
+    /// JSON view of this value, as produced by [`Model::view`].
+    fn to_json(&self) -> Value;
+
+    /// Reads a value back from its JSON view.
+    fn from_json(value: &Value) -> Option<Self>;
+}
+
+/// A struct mapped onto a JSON CRDT `obj` or `vec` node.
+///
+/// Implemented by `#[derive(CrdtSchema)]`.
+pub trait CrdtSchema: CrdtValue {
+    /// Typed handle to a node holding this struct.
+    type Node;
+
+    /// Wraps the node `id` in a typed handle.
+    fn node(id: Ts) -> Self::Node;
+
+    /// Handle to the document root.
+    fn root(model: &Model) -> Self::Node {
+        Self::node(model.root.val)
+    }
+
+    /// Creates the document from `self` and sets it as the root.
+    fn write_root(&self, api: &mut ModelApi<'_>) -> Self::Node {
+        let id = self.schema().build(&mut api.builder);
+        api.builder.root(id);
+        api.apply();
+        Self::node(id)
+    }
+
+    /// Reads the struct stored at node `id`.
+    fn read(model: &Model, id: Ts) -> Result<Self, ApiError> {
+        read(model, id)
+    }
+}
+
+// ── Schema helpers ─────────────────────────────────────────────────────────
+
+/// Stores `value` as a `con` node.
+pub fn con_schema<T: CrdtValue>(value: &T) -> Box<dyn NodeBuilder> {
+    Box::new(ConNode {
+        raw: PackValue::from(value.to_json()),
+    })
+}
+
+/// Wraps the default schema of `value` in a `val` register.
+pub fn val_schema<T: CrdtValue>(value: &T) -> Box<dyn NodeBuilder> {
+    Box::new(ValNode {
+        value: value.schema(),
+    })
+}
+
+/// Stores `value` as a `str` node.
+pub fn str_schema(value: &impl AsRef<str>) -> Box<dyn NodeBuilder> {
+    Box::new(StrNode {
+        raw: value.as_ref().to_owned(),
+    })
+}
+
+/// Stores `value` as a `bin` node.
+pub fn bin_schema(value: &impl AsRef<[u8]>) -> Box<dyn NodeBuilder> {
+    Box::new(BinNode {
+        raw: value.as_ref().to_vec(),
+    })
+}
+
+/// Stores `items` as an `arr` node.
+pub fn arr_schema<T: CrdtValue>(items: &[T]) -> Box<dyn NodeBuilder> {
+    Box::new(ArrNode {
+        items: items.iter().map(CrdtValue::schema).collect(),
+    })
+}
+
+/// Builds an `obj` schema from `(key, schema)` entries.
+pub fn obj_schema(entries: Vec<(&str, Box<dyn NodeBuilder>)>) -> Box<dyn NodeBuilder> {
+    Box::new(ObjNode {
+        entries: entries
+            .into_iter()
+            .map(|(key, schema)| (key.to_owned(), schema))
+            .collect(),
+    })
+}
+
+/// Builds a `vec` schema with one slot per entry.
+pub fn vec_schema(slots: Vec<Box<dyn NodeBuilder>>) -> Box<dyn NodeBuilder> {
+    Box::new(VecNode {
+        value: slots.into_iter().map(Some).collect(),
+    })
+}
+
+// ── View helpers ───────────────────────────────────────────────────────────
+
+/// Builds the JSON view of an `obj`-mapped struct.
+pub fn obj_json(entries: Vec<(&str, Value)>) -> Value {
+    let map: Map<String, Value> = entries
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value))
+        .collect();
+    Value::Object(map)
+}
+
+/// Builds the JSON view of a `vec`-mapped struct.
+pub fn vec_json(slots: Vec<Value>) -> Value {
+    Value::Array(slots)
+}
+
+/// Returns the field `key` of an object view; missing keys read as `null`.
+pub fn obj_field<'v>(view: &'v Value, key: &str) -> Option<&'v Value> {
+    Some(view.as_object()?.get(key).unwrap_or(&Value::Null))
+}
+
+/// Returns slot `index` of a vector view; missing slots read as `null`.
+pub fn vec_field(view: &Value, index: usize) -> Option<&Value> {
+    Some(view.as_array()?.get(index).unwrap_or(&Value::Null))
+}
+
+// ── Model access ───────────────────────────────────────────────────────────
+
+/// Reads the value stored at node `id`.
+pub fn read<T: CrdtValue>(model: &Model, id: Ts) -> Result<T, ApiError> {
+    let node = IndexExt::get(&model.index, &id).ok_or(ApiError::NotFound)?;
+    T::from_json(&node.view(&model.index)).ok_or(ApiError::WrongType)
+}
+
+/// Returns the ID of the node stored under `key` in the `obj` at `id`.
+pub fn obj_child(model: &Model, id: Ts, key: &str) -> Result<Ts, ApiError> {
+    find_path(model, id, &[Value::from(key)])
+}
+
+/// Returns the ID of the node stored in slot `index` of the `vec` at `id`.
+pub fn vec_child(model: &Model, id: Ts, index: u8) -> Result<Ts, ApiError> {
+    find_path(model, id, &[Value::from(index)])
+}
+
+/// Writes `value` under `key` in the `obj` at `id`.
+pub fn set_obj_child<T: CrdtValue>(
+    api: &mut ModelApi<'_>,
+    id: Ts,
+    key: &str,
+    value: &T,
+    schema: Box<dyn NodeBuilder>,
+) -> Result<(), ApiError> {
+    if let Ok(child) = obj_child(api.model, id, key) {
+        if merge_str(api, child, value) {
+            return Ok(());
+        }
+    }
+    let obj = container(api.model, id);
+    if !matches!(
+        IndexExt::get(&api.model.index, &obj),
+        Some(CrdtNode::Obj(_))
+    ) {
+        return Err(ApiError::WrongType);
+    }
+    let child = schema.build(&mut api.builder);
+    api.builder.ins_obj(obj, vec![(key.to_owned(), child)]);
+    api.apply();
+    Ok(())
+}
+
+/// Writes `value` into slot `index` of the `vec` at `id`.
+pub fn set_vec_child<T: CrdtValue>(
+    api: &mut ModelApi<'_>,
+    id: Ts,
+    index: u8,
+    value: &T,
+    schema: Box<dyn NodeBuilder>,
+) -> Result<(), ApiError> {
+    if let Ok(child) = vec_child(api.model, id, index) {
+        if merge_str(api, child, value) {
+            return Ok(());
+        }
+    }
+    let vec = container(api.model, id);
+    if !matches!(
+        IndexExt::get(&api.model.index, &vec),
+        Some(CrdtNode::Vec(_))
+    ) {
+        return Err(ApiError::WrongType);
+    }
+    let child = schema.build(&mut api.builder);
+    api.builder.ins_vec(vec, vec![(index, child)]);
+    api.apply();
+    Ok(())
+}
+
+/// Writes `value` into the `val` register under `key` in the `obj` at `id`,
+/// creating the register if the key holds anything else.
+pub fn set_obj_val<T: CrdtValue>(
+    api: &mut ModelApi<'_>,
+    id: Ts,
+    key: &str,
+    value: &T,
+) -> Result<(), ApiError> {
+    if let Ok(child) = obj_child(api.model, id, key) {
+        if set_register(api, child, value) {
+            return Ok(());
+        }
+    }
+    set_obj_child(api, id, key, value, val_schema(value))
+}
+
+/// Writes `value` into the `val` register in slot `index` of the `vec` at
+/// `id`, creating the register if the slot holds anything else.
+pub fn set_vec_val<T: CrdtValue>(
+    api: &mut ModelApi<'_>,
+    id: Ts,
+    index: u8,
+    value: &T,
+) -> Result<(), ApiError> {
+    if let Ok(child) = vec_child(api.model, id, index) {
+        if set_register(api, child, value) {
+            return Ok(());
+        }
+    }
+    set_vec_child(api, id, index, value, val_schema(value))
+}
+
+/// Sets the existing `val` register `child` to a new node holding `value`.
+/// Returns `false` if `child` is not a `val` node.
+fn set_register<T: CrdtValue>(api: &mut ModelApi<'_>, child: Ts, value: &T) -> bool {
+    if !matches!(
+        IndexExt::get(&api.model.index, &child),
+        Some(CrdtNode::Val(_))
+    ) {
+        return false;
+    }
+    let inner = value.schema().build(&mut api.builder);
+    api.builder.set_val(child, inner);
+    api.apply();
+    true
+}
+
+/// Merges a string value into an existing `str` node instead of replacing
+/// it. Returns `false` if `child` is not a `str` node.
+fn merge_str<T: CrdtValue>(api: &mut ModelApi<'_>, child: Ts, value: &T) -> bool {
+    let json = value.to_json();
+    let is_str = matches!(
+        IndexExt::get(&api.model.index, &child),
+        Some(CrdtNode::Str(_))
+    );
+    if !is_str || !json.is_string() {
+        return false;
+    }
+    api.merge(child, &json);
+    true
+}
+
+/// Unwraps `val` registers around the container at `id`.
+fn container(model: &Model, mut id: Ts) -> Ts {
+    while let Some(CrdtNode::Val(node)) = IndexExt::get(&model.index, &id) {
+        id = node.val;
+    }
+    id
+}
+
+// ── CrdtValue implementations ──────────────────────────────────────────────
+
+impl CrdtValue for String {
+    fn schema(&self) -> Box<dyn NodeBuilder> {
+        str_schema(self)
+    }
+
+    fn to_json(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        value.as_str().map(str::to_owned)
+    }
+}
+
+impl CrdtValue for bool {
+    fn schema(&self) -> Box<dyn NodeBuilder> {
+        con_schema(self)
+    }
+
+    fn to_json(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        value.as_bool()
+    }
+}
+
+macro_rules! impl_int {
+    ($($ty:ty => $as:ident),*) => {$(
+        impl CrdtValue for $ty {
+            fn schema(&self) -> Box<dyn NodeBuilder> {
+                con_schema(self)
+            }
+
+            fn to_json(&self) -> Value {
+                Value::from(*self)
+            }
+
+            fn from_json(value: &Value) -> Option<Self> {
+                value.$as()?.try_into().ok()
+            }
+        }
+    )*};
+}
+
+impl_int!(
+    i8 => as_i64, i16 => as_i64, i32 => as_i64, i64 => as_i64, isize => as_i64,
+    u8 => as_u64, u16 => as_u64, u32 => as_u64, u64 => as_u64, usize => as_u64
+);
+
+impl CrdtValue for f64 {
+    fn schema(&self) -> Box<dyn NodeBuilder> {
+        con_schema(self)
+    }
+
+    fn to_json(&self) -> Value {
+        Value::from(*self)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        value.as_f64()
+    }
+}
+
+impl CrdtValue for f32 {
+    fn schema(&self) -> Box<dyn NodeBuilder> {
+        con_schema(self)
+    }
+
+    fn to_json(&self) -> Value {
+        Value::from(*self)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        value.as_f64().map(|f| f as f32)
+    }
+}
+
+/// `None` is stored as a `null` constant.
+impl<T: CrdtValue> CrdtValue for Option<T> {
+    fn schema(&self) -> Box<dyn NodeBuilder> {
+        match self {
+            Some(value) => value.schema(),
+            None => Box::new(ConNode {
+                raw: PackValue::Null,
+            }),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        self.as_ref().map_or(Value::Null, CrdtValue::to_json)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            _ => T::from_json(value).map(Some),
+        }
+    }
+}
+
+/// Stored as an `arr` node. Use `#[crdt(bin)]` for byte buffers.
+impl<T: CrdtValue> CrdtValue for Vec<T> {
+    fn schema(&self) -> Box<dyn NodeBuilder> {
+        arr_schema(self)
+    }
+
+    fn to_json(&self) -> Value {
+        Value::Array(self.iter().map(CrdtValue::to_json).collect())
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        value.as_array()?.iter().map(T::from_json).collect()
+    }
+}
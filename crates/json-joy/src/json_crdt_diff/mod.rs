@@ -8,6 +8,12 @@
 //! - `ConNode` matching uses value equality (no `Timestamp` reference comparison).
 //! - Destination values are plain JSON (`serde_json::Value`), so upstream
 //!   NodeBuilder wrapper variants are not represented directly in this API.
+//! - Peritext slices are not diffed here; see [`peritext`] for a diff that
+//!   also syncs formatting.
+
+pub mod peritext;
+
+pub use peritext::{diff_peritext, TargetSlice};
 
 use serde_json::Value;
 use std::cell::RefCell;
@@ -0,0 +1,297 @@
+//! Peritext-aware diff — produce a patch that makes a Peritext document
+//! match a target plain text and set of formatted ranges.
+//!
+//! Not part of the upstream TypeScript. Used to sync edits made in a
+//! non-CRDT editor back into a Peritext document: the text is diffed as by
+//! [`JsonCrdtDiff`](super::JsonCrdtDiff), keeping the IDs of unchanged
+//! characters, then slices are compared by their positions in the new text.
+//! Slices that already cover a target range are kept; the others are
+//! deleted and the missing ones inserted.
+//!
+//! Cursor slices are local state, not formatting: they are neither compared
+//! nor deleted.
+
+use serde_json::Value;
+
+use super::diff_node;
+use crate::json_crdt::model::Model;
+use crate::json_crdt::nodes::{StrNode, TsKey};
+use crate::json_crdt_extensions::peritext::{
+    command, Peritext, Point, Range, Slice, SliceStacking, SliceType,
+};
+use crate::json_crdt_patch::compaction::combine;
+use crate::json_crdt_patch::patch::Patch;
+
+// ── TargetSlice ───────────────────────────────────────────────────────────
+
+/// A formatted range of the target text, in UTF-16 code units.
+///
+/// An inline slice covers `start..end`; a block-split marker sits on the
+/// `\n` at `start` and has `end == start`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetSlice {
+    pub start: usize,
+    pub end: usize,
+    pub stacking: SliceStacking,
+    pub slice_type: SliceType,
+    pub data: Option<Value>,
+}
+
+impl TargetSlice {
+    /// An inline slice over `start..end`, stacking as `One` if it carries
+    /// `data` and as `Many` otherwise (like
+    /// [`Peritext::toggle_inline`]).
+    pub fn inline(
+        start: usize,
+        end: usize,
+        slice_type: impl Into<SliceType>,
+        data: Option<Value>,
+    ) -> Self {
+        let stacking = if data.is_some() {
+            SliceStacking::One
+        } else {
+            SliceStacking::Many
+        };
+        Self {
+            start,
+            end,
+            stacking,
+            slice_type: slice_type.into(),
+            data,
+        }
+    }
+
+    /// A block-split marker on the `\n` at `pos`.
+    pub fn marker(pos: usize, slice_type: impl Into<SliceType>, data: Option<Value>) -> Self {
+        Self {
+            start: pos,
+            end: pos,
+            stacking: SliceStacking::Marker,
+            slice_type: slice_type.into(),
+            data,
+        }
+    }
+
+    /// The formatting of `peritext` as target slices, in slice order.
+    pub fn read(peritext: &Peritext, model: &Model) -> Vec<Self> {
+        let Some(node) = Peritext::str_node(model, peritext.str_id) else {
+            return Vec::new();
+        };
+        peritext
+            .saved_slices
+            .iter_slices(model)
+            .into_iter()
+            .filter(|slice| slice.stacking != SliceStacking::Cursor)
+            .map(|slice| Self {
+                start: slice.start.view_pos(node),
+                end: slice.end.view_pos(node),
+                stacking: slice.stacking,
+                slice_type: slice.slice_type,
+                data: slice.data,
+            })
+            .collect()
+    }
+
+    fn matches(&self, slice: &Slice, node: &StrNode) -> bool {
+        self.stacking == slice.stacking
+            && self.slice_type == slice.slice_type
+            && self.data == slice.data
+            && is_live(slice.start, node)
+            && is_live(slice.end, node)
+            && self.start == slice.start.view_pos(node)
+            && self.end == slice.end.view_pos(node)
+    }
+}
+
+// ── Diff ──────────────────────────────────────────────────────────────────
+
+/// Compute a patch that makes `peritext` in `model` hold `text` formatted
+/// by exactly `slices`.
+///
+/// Text edits come first in the patch, then slice deletions and
+/// insertions. Targets outside the new text are skipped. Returns `None` if
+/// the document already matches.
+///
+/// Slices are compared against the new text, so the text patch is applied
+/// to a clone of `model` first: each call costs O(document) time and
+/// memory, however small the change.
+pub fn diff_peritext(
+    peritext: &Peritext,
+    model: &Model,
+    text: &str,
+    slices: &[TargetSlice],
+) -> Option<Patch> {
+    let node = model.index.get(&TsKey::from(peritext.str_id))?;
+    let dst = Value::String(text.to_string());
+    let text_patch = diff_node(node, &model.index, model.clock.sid, model.clock.time, &dst);
+
+    let mut draft = model.clone();
+    if let Some(patch) = &text_patch {
+        draft.apply_patch(patch);
+    }
+    let node = Peritext::str_node(&draft, peritext.str_id)?;
+    let mut missing: Vec<&TargetSlice> = slices.iter().collect();
+    let mut stale = Vec::new();
+    for slice in peritext.saved_slices.iter_slices(&draft) {
+        if slice.stacking == SliceStacking::Cursor {
+            continue;
+        }
+        match missing
+            .iter()
+            .position(|target| target.matches(&slice, node))
+        {
+            Some(index) => {
+                missing.remove(index);
+            }
+            None => stale.push(slice.id),
+        }
+    }
+    let slice_patch = command(&mut draft, |model| {
+        for id in stale {
+            peritext.saved_slices.del(model, id);
+        }
+        for target in missing {
+            if let Some(range) = target_range(peritext, model, target) {
+                peritext.saved_slices.ins(
+                    model,
+                    &range,
+                    target.stacking,
+                    target.slice_type.clone(),
+                    target.data.clone(),
+                );
+            }
+        }
+    });
+
+    let mut patches: Vec<Patch> = text_patch
+        .into_iter()
+        .chain([slice_patch])
+        .filter(|patch| !patch.ops.is_empty())
+        .collect();
+    combine(&mut patches);
+    patches.pop()
+}
+
+/// Points for `target` in the current text, as [`Peritext::range_at`]
+/// builds them; a marker gets a collapsed range on its character.
+fn target_range(peritext: &Peritext, model: &Model, target: &TargetSlice) -> Option<Range> {
+    if target.stacking == SliceStacking::Marker || target.end <= target.start {
+        let range = peritext.range_at(model, target.start, 1)?;
+        return Some(Range::new(range.start, range.start));
+    }
+    peritext.range_at(model, target.start, target.end - target.start)
+}
+
+/// Whether `point` is attached to a visible character (or the origin).
+fn is_live(point: Point, node: &StrNode) -> bool {
+    if point.is_origin() {
+        return true;
+    }
+    node.rga
+        .find_by_id(point.id)
+        .is_some_and(|idx| !node.rga.slot(idx).deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt_extensions::peritext::slice::constants::{
+        TYPE_BOLD, TYPE_H1, TYPE_ITALIC, TYPE_LINK, TYPE_P,
+    };
+    use crate::json_crdt_extensions::peritext::test_util::setup;
+    use crate::json_crdt_extensions::peritext::{transfer, TagMap};
+    use crate::json_crdt_patch::operations::Op;
+    use serde_json::json;
+
+    fn html(pt: &Peritext, model: &Model) -> String {
+        transfer::to_html(&pt.fragment(model), &TagMap::default())
+    }
+
+    fn sync(pt: &Peritext, model: &mut Model, text: &str, slices: &[TargetSlice]) -> Patch {
+        let patch = diff_peritext(pt, model, text, slices).unwrap();
+        model.apply_patch(&patch);
+        patch
+    }
+
+    #[test]
+    fn matching_document_needs_no_patch() {
+        let (mut model, pt) = setup("hello world");
+        pt.toggle_inline(&mut model, 6, 5, TYPE_BOLD, None);
+        let targets = TargetSlice::read(&pt, &model);
+        assert_eq!(targets, [TargetSlice::inline(6, 11, TYPE_BOLD, None)]);
+        assert!(diff_peritext(&pt, &model, "hello world", &targets).is_none());
+    }
+
+    #[test]
+    fn keeps_slices_that_follow_the_text() {
+        let (mut model, pt) = setup("hello world");
+        pt.toggle_inline(&mut model, 6, 5, TYPE_BOLD, None);
+        let bold = pt.saved_slices.iter_slices(&model)[0].id;
+        let patch = sync(
+            &pt,
+            &mut model,
+            "well, hello world",
+            &[TargetSlice::inline(12, 17, TYPE_BOLD, None)],
+        );
+        assert!(patch.ops.iter().all(|op| !matches!(op, Op::Del { .. })));
+        assert_eq!(pt.saved_slices.iter_slices(&model)[0].id, bold);
+        assert_eq!(html(&pt, &model), "<p>well, hello <b>world</b></p>");
+    }
+
+    #[test]
+    fn replaces_changed_formatting() {
+        let (mut model, pt) = setup("hello world");
+        pt.toggle_inline(&mut model, 0, 5, TYPE_BOLD, None);
+        pt.toggle_inline(&mut model, 6, 5, TYPE_ITALIC, None);
+        let link = json!({"href": "/w"});
+        sync(
+            &pt,
+            &mut model,
+            "hello brave world",
+            &[
+                TargetSlice::inline(0, 5, TYPE_BOLD, None),
+                TargetSlice::inline(6, 11, TYPE_LINK, Some(link)),
+            ],
+        );
+        assert_eq!(
+            html(&pt, &model),
+            "<p><b>hello</b> <a href=\"/w\">brave</a> world</p>"
+        );
+        assert_eq!(pt.saved_slices.size(&model), 2);
+    }
+
+    #[test]
+    fn syncs_block_markers() {
+        let (mut model, pt) = setup("titlebody");
+        pt.split_block(&mut model, 5, None);
+        sync(
+            &pt,
+            &mut model,
+            "\ntitle\nbody",
+            &[
+                TargetSlice::marker(0, TYPE_H1, None),
+                TargetSlice::marker(6, TYPE_P, None),
+            ],
+        );
+        assert_eq!(html(&pt, &model), "<h1>title</h1><p>body</p>");
+    }
+
+    #[test]
+    fn patch_applies_on_a_concurrent_replica() {
+        let (mut model, pt) = setup("hello world");
+        let mut peer = model.clone();
+        peer.clock = peer.clock.fork(43);
+        let patch = diff_peritext(
+            &pt,
+            &model,
+            "hello big world",
+            &[TargetSlice::inline(6, 9, TYPE_BOLD, None)],
+        )
+        .unwrap();
+        model.apply_patch(&patch);
+        pt.ins_at(&mut peer, 0, "so ");
+        peer.apply_patch(&patch);
+        assert_eq!(html(&pt, &model), "<p>hello <b>big</b> world</p>");
+        assert_eq!(html(&pt, &peer), "<p>so hello <b>big</b> world</p>");
+    }
+}
@@ -0,0 +1,426 @@
+//! Bounded counter that never drops below zero.
+//!
+//! Uses escrow-style quotas: every session may only spend (decrement) the
+//! rights it holds. Incrementing grants the incrementing session rights for
+//! the same amount and rights can be transferred to other sessions. Since no
+//! session ever spends more than it holds, the counter stays non-negative
+//! however concurrent decrements interleave.
+//!
+//! The payload is an [`ObjNode`](crate::json_crdt::nodes::ObjNode) of `con`
+//! integers, each a cumulative total written only by its owning session:
+//!
+//! ```text
+//! p:<sid>        total incremented by <sid>
+//! n:<sid>        total decremented by <sid>
+//! t:<from>:<to>  total transferred from <from> to <to>
+//! ```
+//!
+//! Session IDs are written in base 36, as in the plain counter.
+
+use json_joy_json_pack::PackValue;
+use serde_json::Value;
+
+use super::{con_int, to_base36};
+use crate::json_crdt::extensions::{AnyExtension, ExtHandle, ExtRef, Extensions};
+use crate::json_crdt::model::api::ApiError;
+use crate::json_crdt::model::{Model, ModelApi};
+use crate::json_crdt::nodes::{CrdtNode, IndexExt, NodeIndex, ObjNode};
+use crate::json_crdt_extensions::ExtensionId;
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::patch_builder::PatchBuilder;
+use crate::json_crdt_patch::schema::NodeBuilder;
+
+// ── Errors ────────────────────────────────────────────────────────────────
+
+/// Errors returned by [`BoundedCntApi`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BoundedCntError {
+    /// The counter node could not be edited.
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    /// The session holds fewer rights than the operation needs.
+    #[error("INSUFFICIENT_QUOTA: requested {requested}, available {available}")]
+    InsufficientQuota { requested: u64, available: i64 },
+    /// The amount, or the session total it would produce, does not fit in
+    /// the `i64` range of the payload.
+    #[error("OVERFLOW: amount {0} is out of range")]
+    Overflow(u64),
+}
+
+// ── BoundedCntNode ────────────────────────────────────────────────────────
+
+/// A non-negative counter backed by an [`ObjNode`] of per-session totals.
+#[derive(Debug, Clone, Copy)]
+pub struct BoundedCntNode {
+    pub obj_id: Ts,
+}
+
+impl BoundedCntNode {
+    pub fn new(obj_id: Ts) -> Self {
+        Self { obj_id }
+    }
+
+    /// Current value: all increments minus all decrements.
+    pub fn view(&self, model: &Model) -> i64 {
+        self.value(&model.index)
+    }
+
+    /// Rights held by session `sid`: what it may still decrement or
+    /// transfer.
+    pub fn quota(&self, model: &Model, sid: u64) -> i64 {
+        let sid = to_base36(sid);
+        let mut quota: i64 = 0;
+        for (key, amount) in self.totals(&model.index) {
+            quota = match Key::parse(&key) {
+                Some(Key::Inc(s)) if s == sid => quota.saturating_add(amount),
+                Some(Key::Dec(s)) if s == sid => quota.saturating_sub(amount),
+                Some(Key::Transfer(from, _)) if from == sid => quota.saturating_sub(amount),
+                Some(Key::Transfer(_, to)) if to == sid => quota.saturating_add(amount),
+                _ => quota,
+            };
+        }
+        quota
+    }
+
+    fn value(&self, index: &NodeIndex) -> i64 {
+        self.totals(index)
+            .into_iter()
+            .fold(0i64, |value, (key, amount)| match Key::parse(&key) {
+                Some(Key::Inc(_)) => value.saturating_add(amount),
+                Some(Key::Dec(_)) => value.saturating_sub(amount),
+                _ => value,
+            })
+    }
+
+    fn total(&self, index: &NodeIndex, key: &str) -> i64 {
+        obj(index, self.obj_id)
+            .and_then(|obj| obj.keys.get(key))
+            .map_or(0, |id| con_int(index, *id))
+    }
+
+    fn totals(&self, index: &NodeIndex) -> Vec<(String, i64)> {
+        let Some(obj) = obj(index, self.obj_id) else {
+            return Vec::new();
+        };
+        obj.keys
+            .iter()
+            .map(|(key, id)| (key.clone(), con_int(index, *id)))
+            .collect()
+    }
+}
+
+fn obj(index: &NodeIndex, id: Ts) -> Option<&ObjNode> {
+    match IndexExt::get(index, &id) {
+        Some(CrdtNode::Obj(obj)) => Some(obj),
+        _ => None,
+    }
+}
+
+/// A parsed payload key.
+enum Key<'k> {
+    Inc(&'k str),
+    Dec(&'k str),
+    Transfer(&'k str, &'k str),
+}
+
+impl<'k> Key<'k> {
+    fn parse(key: &'k str) -> Option<Self> {
+        let (kind, rest) = key.split_once(':')?;
+        match kind {
+            "p" => Some(Key::Inc(rest)),
+            "n" => Some(Key::Dec(rest)),
+            "t" => rest
+                .split_once(':')
+                .map(|(from, to)| Key::Transfer(from, to)),
+            _ => None,
+        }
+    }
+}
+
+// ── BoundedCntExt ─────────────────────────────────────────────────────────
+
+/// Extension descriptor for [`BoundedCntNode`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoundedCntExt;
+
+impl AnyExtension for BoundedCntExt {
+    fn id(&self) -> u32 {
+        ExtensionId::BoundedCnt as u32
+    }
+
+    fn name(&self) -> &str {
+        "bcnt"
+    }
+
+    /// A counter starting at `value` (if it is a positive `i64`), with the
+    /// rights to it held by the creating session.
+    fn schema(&self, value: &Value) -> Box<dyn NodeBuilder> {
+        Box::new(BoundedCntSchema {
+            start: value.as_i64().filter(|start| *start > 0).unwrap_or(0),
+        })
+    }
+
+    fn view(&self, _: &Extensions, index: &NodeIndex, data: Ts) -> Value {
+        Value::from(BoundedCntNode::new(data).value(index))
+    }
+}
+
+/// Payload schema of a new bounded counter; the initial increment is keyed
+/// by the session building the patch.
+#[derive(Debug)]
+struct BoundedCntSchema {
+    start: i64,
+}
+
+impl NodeBuilder for BoundedCntSchema {
+    fn build(&self, builder: &mut PatchBuilder) -> Ts {
+        let obj = builder.obj();
+        if self.start > 0 {
+            let key = format!("p:{}", to_base36(builder.clock.sid()));
+            let con = builder.con_val(PackValue::Integer(self.start));
+            builder.ins_obj(obj, vec![(key, con)]);
+        }
+        obj
+    }
+}
+
+// ── BoundedCntApi ─────────────────────────────────────────────────────────
+
+/// Editing API for a bounded counter, acting as the model's session.
+///
+/// Obtained with `api.ext::<BoundedCntApi>(path)`.
+pub struct BoundedCntApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    node: BoundedCntNode,
+}
+
+impl<'m, 'a> ExtHandle<'m, 'a> for BoundedCntApi<'m, 'a> {
+    const EXT_ID: u8 = ExtensionId::BoundedCnt as u8;
+
+    fn wrap(api: &'m mut ModelApi<'a>, node: ExtRef) -> Self {
+        Self {
+            api,
+            node: BoundedCntNode::new(node.data),
+        }
+    }
+}
+
+impl BoundedCntApi<'_, '_> {
+    /// The underlying counter node.
+    pub fn node(&self) -> BoundedCntNode {
+        self.node
+    }
+
+    /// Current value of the counter.
+    pub fn view(&self) -> i64 {
+        self.node.view(self.api.model)
+    }
+
+    /// Rights held by this session.
+    pub fn quota(&self) -> i64 {
+        self.node.quota(self.api.model, self.api.model.clock.sid)
+    }
+
+    /// Increments the counter by `amount`, granting this session as many
+    /// rights.
+    pub fn inc(&mut self, amount: u64) -> Result<(), BoundedCntError> {
+        let key = format!("p:{}", self.me());
+        self.add(key, amount)
+    }
+
+    /// Decrements the counter by `amount` out of this session's rights.
+    pub fn dec(&mut self, amount: u64) -> Result<(), BoundedCntError> {
+        self.spend(amount)?;
+        let key = format!("n:{}", self.me());
+        self.add(key, amount)
+    }
+
+    /// Transfers `amount` of this session's rights to session `to`.
+    /// Transferring to this session itself changes nothing.
+    pub fn transfer(&mut self, to: u64, amount: u64) -> Result<(), BoundedCntError> {
+        self.spend(amount)?;
+        if to == self.api.model.clock.sid {
+            return Ok(());
+        }
+        let key = format!("t:{}:{}", self.me(), to_base36(to));
+        self.add(key, amount)
+    }
+
+    fn me(&self) -> String {
+        to_base36(self.api.model.clock.sid)
+    }
+
+    fn spend(&self, amount: u64) -> Result<(), BoundedCntError> {
+        let available = self.quota();
+        if available < to_i64(amount)? {
+            return Err(BoundedCntError::InsufficientQuota {
+                requested: amount,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    /// Raises this session's total at `key` by `amount`.
+    fn add(&mut self, key: String, amount: u64) -> Result<(), BoundedCntError> {
+        match IndexExt::get(&self.api.model.index, &self.node.obj_id) {
+            Some(CrdtNode::Obj(_)) => {}
+            Some(_) => return Err(ApiError::WrongType.into()),
+            None => return Err(ApiError::NotFound.into()),
+        }
+        if amount == 0 {
+            return Ok(());
+        }
+        let total = self
+            .node
+            .total(&self.api.model.index, &key)
+            .checked_add(to_i64(amount)?)
+            .ok_or(BoundedCntError::Overflow(amount))?;
+        let con = self.api.builder.con_val(PackValue::Integer(total));
+        self.api.builder.ins_obj(self.node.obj_id, vec![(key, con)]);
+        self.api.apply();
+        Ok(())
+    }
+}
+
+fn to_i64(amount: u64) -> Result<i64, BoundedCntError> {
+    i64::try_from(amount).map_err(|_| BoundedCntError::Overflow(amount))
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::json_crdt::codec::structural::verbose;
+    use crate::json_crdt_patch::patch::Patch;
+    use serde_json::json;
+
+    fn registry() -> Arc<Extensions> {
+        let mut extensions = Extensions::new();
+        extensions.register(Box::new(BoundedCntExt)).unwrap();
+        Arc::new(extensions)
+    }
+
+    /// Two replicas whose root is a counter at `start`, held by `a`.
+    fn replicas(start: u64) -> (Model, Model) {
+        let mut a = Model::new(100);
+        a.extensions = registry();
+        let ((), base) = a
+            .transaction(|api| {
+                let cnt = api.ext_node(&BoundedCntExt, &json!(start));
+                api.builder.root(cnt);
+                Ok::<_, ApiError>(())
+            })
+            .unwrap();
+        let mut b = Model::new(200);
+        b.extensions = registry();
+        b.apply_patch(&base);
+        (a, b)
+    }
+
+    fn edit(
+        model: &mut Model,
+        f: impl FnOnce(&mut BoundedCntApi) -> Result<(), BoundedCntError>,
+    ) -> Result<Patch, BoundedCntError> {
+        model
+            .transaction(|api| f(&mut api.ext::<BoundedCntApi>(&[])?))
+            .map(|((), patch)| patch)
+    }
+
+    #[test]
+    fn decrements_are_limited_by_quota() {
+        let (mut a, _) = replicas(3);
+        edit(&mut a, |cnt| {
+            cnt.inc(2)?;
+            cnt.dec(4)
+        })
+        .unwrap();
+        assert_eq!(a.view(), json!(1));
+        let err = edit(&mut a, |cnt| cnt.dec(2)).unwrap_err();
+        assert_eq!(
+            err,
+            BoundedCntError::InsufficientQuota {
+                requested: 2,
+                available: 1
+            }
+        );
+        assert_eq!(a.view(), json!(1));
+    }
+
+    #[test]
+    fn rights_move_with_transfers() {
+        let (mut a, mut b) = replicas(5);
+        assert!(edit(&mut b, |cnt| cnt.dec(1)).is_err());
+        let pa = edit(&mut a, |cnt| cnt.transfer(200, 3)).unwrap();
+        b.apply_patch(&pa);
+        let node = BoundedCntNode::new(match IndexExt::get(&b.index, &b.root.val) {
+            Some(CrdtNode::Vec(vec)) => vec.elements[1].unwrap(),
+            _ => panic!("expected an ext node"),
+        });
+        assert_eq!((node.quota(&b, 100), node.quota(&b, 200)), (2, 3));
+        edit(&mut b, |cnt| cnt.dec(3)).unwrap();
+        assert_eq!(b.view(), json!(2));
+    }
+
+    #[test]
+    fn concurrent_decrements_never_go_below_zero() {
+        let (mut a, mut b) = replicas(4);
+        let pa = edit(&mut a, |cnt| cnt.transfer(200, 2)).unwrap();
+        b.apply_patch(&pa);
+        let pa = edit(&mut a, |cnt| cnt.dec(2)).unwrap();
+        let pb = edit(&mut b, |cnt| cnt.dec(2)).unwrap();
+        assert!(edit(&mut b, |cnt| cnt.dec(1)).is_err());
+        a.apply_patch(&pb);
+        b.apply_patch(&pa);
+        assert_eq!(a.view(), json!(0));
+        assert_eq!(b.view(), json!(0));
+    }
+
+    #[test]
+    fn out_of_range_amounts_are_rejected() {
+        let (mut a, _) = replicas(3);
+        for amount in [u64::MAX, i64::MAX as u64 + 1] {
+            assert_eq!(
+                edit(&mut a, |cnt| cnt.dec(amount)),
+                Err(BoundedCntError::Overflow(amount))
+            );
+            assert_eq!(
+                edit(&mut a, |cnt| cnt.transfer(200, amount)),
+                Err(BoundedCntError::Overflow(amount))
+            );
+            assert_eq!(
+                edit(&mut a, |cnt| cnt.inc(amount)),
+                Err(BoundedCntError::Overflow(amount))
+            );
+        }
+        // The session total would overflow.
+        let max = i64::MAX as u64;
+        assert_eq!(
+            edit(&mut a, |cnt| cnt.inc(max)),
+            Err(BoundedCntError::Overflow(max))
+        );
+        assert_eq!(a.view(), json!(3));
+        assert_eq!(edit(&mut a, |cnt| cnt.dec(3)).map(|_| ()), Ok(()));
+        assert_eq!(a.view(), json!(0));
+    }
+
+    #[test]
+    fn out_of_range_start_is_ignored() {
+        let (a, _) = replicas(u64::MAX);
+        assert_eq!(a.view(), json!(0));
+    }
+
+    #[test]
+    fn round_trips_through_structural_verbose() {
+        let (mut a, _) = replicas(7);
+        edit(&mut a, |cnt| cnt.dec(2)).unwrap();
+        let mut decoded = verbose::decode(&verbose::encode(&a)).unwrap();
+        decoded.extensions = registry();
+        assert_eq!(decoded.view(), json!(5));
+        let mut api = ModelApi::new(&mut decoded);
+        assert_eq!(api.ext::<BoundedCntApi>(&[]).unwrap().quota(), 5);
+    }
+}
@@ -1,13 +1,21 @@
 //! Counter extension (`cnt`).
 //!
 //! Mirrors `packages/json-joy/src/json-crdt-extensions/cnt/`.
+//!
+//! Two variants that are not part of the upstream TypeScript live in
+//! submodules: [`resettable`] (a counter that can be reset to zero without
+//! losing concurrent increments) and [`bounded`] (a counter that never drops
+//! below zero, using per-session escrow quotas).
+
+pub mod bounded;
+pub mod resettable;
 
 use json_joy_json_pack::PackValue;
 
 use crate::json_crdt::model::Model;
 #[cfg(test)]
 use crate::json_crdt::nodes::IndexExt;
-use crate::json_crdt::nodes::{CrdtNode, TsKey};
+use crate::json_crdt::nodes::{CrdtNode, NodeIndex, TsKey};
 use crate::json_crdt_patch::clock::Ts;
 use crate::json_crdt_patch::operations::{ConValue, Op};
 
@@ -88,6 +96,18 @@ impl CntNode {
 
 // ── Helpers ───────────────────────────────────────────────────────────────
 
+/// Integer held by the `con` node `id`, or `0`.
+fn con_int(index: &NodeIndex, id: Ts) -> i64 {
+    match index.get(&TsKey::from(id)) {
+        Some(CrdtNode::Con(con)) => match &con.val {
+            ConValue::Val(PackValue::Integer(n)) => *n,
+            ConValue::Val(PackValue::UInteger(n)) => *n as i64,
+            _ => 0,
+        },
+        _ => 0,
+    }
+}
+
 fn to_base36(mut n: u64) -> String {
     if n == 0 {
         return "0".to_string();
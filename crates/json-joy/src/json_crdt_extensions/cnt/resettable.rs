@@ -0,0 +1,239 @@
+//! Resettable counter with observed-reset semantics.
+//!
+//! The payload is an [`ArrNode`](crate::json_crdt::nodes::ArrNode) of `con`
+//! integer deltas and the counter's value is their sum. An increment appends
+//! a delta; a reset deletes every delta it has observed. Increments made
+//! concurrently with a reset were not observed by it and survive, so no
+//! replica loses an increment it has not seen reset.
+//!
+//! Deltas accumulate until the next reset.
+
+use json_joy_json_pack::PackValue;
+use serde_json::Value;
+
+use super::con_int;
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::extensions::{AnyExtension, ExtHandle, ExtRef, Extensions};
+use crate::json_crdt::model::api::ApiError;
+use crate::json_crdt::model::{Model, ModelApi};
+use crate::json_crdt::nodes::{ArrNode, CrdtNode, IndexExt, NodeIndex};
+use crate::json_crdt_extensions::ExtensionId;
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::schema::{self, NodeBuilder};
+
+// ── ResettableCntNode ─────────────────────────────────────────────────────
+
+/// A counter that can be reset, backed by an [`ArrNode`] of deltas.
+#[derive(Debug, Clone, Copy)]
+pub struct ResettableCntNode {
+    pub arr_id: Ts,
+}
+
+impl ResettableCntNode {
+    pub fn new(arr_id: Ts) -> Self {
+        Self { arr_id }
+    }
+
+    /// Sum of all deltas since the last observed reset.
+    pub fn view(&self, model: &Model) -> i64 {
+        self.sum(&model.index)
+    }
+
+    fn sum(&self, index: &NodeIndex) -> i64 {
+        let Some(CrdtNode::Arr(arr)) = IndexExt::get(index, &self.arr_id) else {
+            return 0;
+        };
+        arr.rga
+            .iter_live()
+            .filter_map(|chunk| chunk.data.as_ref())
+            .flatten()
+            .map(|id| con_int(index, *id))
+            .fold(0i64, i64::saturating_add)
+    }
+}
+
+// ── ResettableCntExt ──────────────────────────────────────────────────────
+
+/// Extension descriptor for [`ResettableCntNode`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResettableCntExt;
+
+impl AnyExtension for ResettableCntExt {
+    fn id(&self) -> u32 {
+        ExtensionId::ResettableCnt as u32
+    }
+
+    fn name(&self) -> &str {
+        "rcnt"
+    }
+
+    /// A counter starting at `value` (if it is an integer), else at zero.
+    fn schema(&self, value: &Value) -> Box<dyn NodeBuilder> {
+        let items = match value.as_i64() {
+            Some(start) if start != 0 => vec![Box::new(schema::ConNode {
+                raw: PackValue::Integer(start),
+            }) as Box<dyn NodeBuilder>],
+            _ => Vec::new(),
+        };
+        Box::new(schema::ArrNode { items })
+    }
+
+    fn view(&self, _: &Extensions, index: &NodeIndex, data: Ts) -> Value {
+        Value::from(ResettableCntNode::new(data).sum(index))
+    }
+}
+
+// ── ResettableCntApi ──────────────────────────────────────────────────────
+
+/// Editing API for a resettable counter.
+///
+/// Obtained with `api.ext::<ResettableCntApi>(path)`.
+pub struct ResettableCntApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    node: ResettableCntNode,
+}
+
+impl<'m, 'a> ExtHandle<'m, 'a> for ResettableCntApi<'m, 'a> {
+    const EXT_ID: u8 = ExtensionId::ResettableCnt as u8;
+
+    fn wrap(api: &'m mut ModelApi<'a>, node: ExtRef) -> Self {
+        Self {
+            api,
+            node: ResettableCntNode::new(node.data),
+        }
+    }
+}
+
+impl ResettableCntApi<'_, '_> {
+    /// The underlying counter node.
+    pub fn node(&self) -> ResettableCntNode {
+        self.node
+    }
+
+    /// Current value of the counter.
+    pub fn view(&self) -> i64 {
+        self.node.view(self.api.model)
+    }
+
+    /// Adds `delta` (which may be negative) to the counter.
+    pub fn inc(&mut self, delta: i64) -> Result<(), ApiError> {
+        let after = self.arr()?.rga.last_chunk().map_or(ORIGIN, |chunk| {
+            Ts::new(chunk.id.sid, chunk.id.time + chunk.span - 1)
+        });
+        if delta == 0 {
+            return Ok(());
+        }
+        let con = self.api.builder.con_val(PackValue::Integer(delta));
+        self.api.builder.ins_arr(self.node.arr_id, after, vec![con]);
+        self.api.apply();
+        Ok(())
+    }
+
+    /// Resets the counter to zero, discarding every observed delta.
+    pub fn reset(&mut self) -> Result<(), ApiError> {
+        let arr = self.arr()?;
+        let size = arr.size();
+        if size == 0 {
+            return Ok(());
+        }
+        let spans = arr.find_interval(0, size);
+        self.api.builder.del(self.node.arr_id, spans);
+        self.api.apply();
+        Ok(())
+    }
+
+    fn arr(&self) -> Result<&ArrNode, ApiError> {
+        match IndexExt::get(&self.api.model.index, &self.node.arr_id) {
+            Some(CrdtNode::Arr(arr)) => Ok(arr),
+            Some(_) => Err(ApiError::WrongType),
+            None => Err(ApiError::NotFound),
+        }
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::json_crdt::codec::structural::binary;
+    use crate::json_crdt_patch::patch::Patch;
+    use serde_json::json;
+
+    fn registry() -> Arc<Extensions> {
+        let mut extensions = Extensions::new();
+        extensions.register(Box::new(ResettableCntExt)).unwrap();
+        Arc::new(extensions)
+    }
+
+    /// Two replicas whose root is a counter starting at `start`.
+    fn replicas(start: i64) -> (Model, Model) {
+        let mut a = Model::new(100);
+        a.extensions = registry();
+        let ((), base) = a
+            .transaction(|api| {
+                let cnt = api.ext_node(&ResettableCntExt, &json!(start));
+                api.builder.root(cnt);
+                Ok::<_, ApiError>(())
+            })
+            .unwrap();
+        let mut b = Model::new(200);
+        b.extensions = registry();
+        b.apply_patch(&base);
+        (a, b)
+    }
+
+    fn edit(
+        model: &mut Model,
+        f: impl FnOnce(&mut ResettableCntApi) -> Result<(), ApiError>,
+    ) -> Patch {
+        model
+            .transaction(|api| f(&mut api.ext::<ResettableCntApi>(&[])?))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn inc_and_reset() {
+        let (mut a, _) = replicas(5);
+        assert_eq!(a.view(), json!(5));
+        edit(&mut a, |cnt| {
+            cnt.inc(3)?;
+            cnt.inc(-1)
+        });
+        assert_eq!(a.view(), json!(7));
+        edit(&mut a, |cnt| cnt.reset());
+        assert_eq!(a.view(), json!(0));
+        edit(&mut a, |cnt| cnt.inc(2));
+        assert_eq!(a.view(), json!(2));
+    }
+
+    #[test]
+    fn sum_saturates() {
+        let (mut a, _) = replicas(i64::MAX);
+        edit(&mut a, |cnt| cnt.inc(1));
+        assert_eq!(a.view(), json!(i64::MAX));
+    }
+
+    #[test]
+    fn concurrent_increment_survives_reset() {
+        let (mut a, mut b) = replicas(10);
+        let pa = edit(&mut a, |cnt| cnt.reset());
+        let pb = edit(&mut b, |cnt| cnt.inc(4));
+        a.apply_patch(&pb);
+        b.apply_patch(&pa);
+        assert_eq!(a.view(), json!(4));
+        assert_eq!(b.view(), json!(4));
+    }
+
+    #[test]
+    fn round_trips_through_structural_binary() {
+        let (mut a, _) = replicas(1);
+        edit(&mut a, |cnt| cnt.inc(2));
+        let mut decoded = binary::decode(&binary::encode(&a)).unwrap();
+        decoded.extensions = registry();
+        assert_eq!(decoded.view(), json!(3));
+    }
+}
@@ -0,0 +1,307 @@
+//! Last-write-wins map extension (`lwwmap`).
+//!
+//! Not part of the upstream TypeScript; built on the same `ext` node layout
+//! as the upstream extensions (see [`crate::json_crdt::extensions`]).
+//!
+//! The payload is an [`ObjNode`] whose keys hold `con` values. Every write —
+//! a set or a delete — replaces the key's `con` with a new one, and the
+//! write with the highest timestamp wins. A delete writes a `con(undefined)`
+//! tombstone, so each deleted key keeps the timestamp of its deletion and a
+//! concurrent set only survives it if it is newer.
+
+use json_joy_json_pack::PackValue;
+use serde_json::{Map, Value};
+
+use super::ExtensionId;
+use crate::json_crdt::extensions::{AnyExtension, ExtHandle, ExtRef, Extensions};
+use crate::json_crdt::model::api::ApiError;
+use crate::json_crdt::model::{Model, ModelApi};
+use crate::json_crdt::nodes::{CrdtNode, IndexExt, NodeIndex, ObjNode};
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::operations::ConValue;
+use crate::json_crdt_patch::schema::{self, NodeBuilder};
+
+// ── LwwMapNode ────────────────────────────────────────────────────────────
+
+/// A map with per-key last-write-wins semantics, backed by an [`ObjNode`].
+#[derive(Debug, Clone, Copy)]
+pub struct LwwMapNode {
+    pub obj_id: Ts,
+}
+
+impl LwwMapNode {
+    pub fn new(obj_id: Ts) -> Self {
+        Self { obj_id }
+    }
+
+    /// Live entries of the map.
+    pub fn view(&self, model: &Model) -> Map<String, Value> {
+        self.entries(&model.index)
+    }
+
+    /// Value of `key`, if it is set.
+    pub fn get(&self, model: &Model, key: &str) -> Option<Value> {
+        match self.slot(&model.index, key)? {
+            (_, Some(value)) => Some(value),
+            (_, None) => None,
+        }
+    }
+
+    /// Timestamp of the winning write to `key`, whether a set or a delete.
+    pub fn timestamp(&self, model: &Model, key: &str) -> Option<Ts> {
+        self.slot(&model.index, key).map(|(id, _)| id)
+    }
+
+    /// Deleted keys, each with the timestamp of its winning delete.
+    pub fn tombstones(&self, model: &Model) -> Vec<(String, Ts)> {
+        let Some(obj) = obj(&model.index, self.obj_id) else {
+            return Vec::new();
+        };
+        obj.keys
+            .iter()
+            .filter(|(key, _)| matches!(self.slot(&model.index, key), Some((_, None))))
+            .map(|(key, &id)| (key.clone(), id))
+            .collect()
+    }
+
+    fn entries(&self, index: &NodeIndex) -> Map<String, Value> {
+        let Some(obj) = obj(index, self.obj_id) else {
+            return Map::new();
+        };
+        obj.keys
+            .keys()
+            .filter_map(|key| match self.slot(index, key)? {
+                (_, Some(value)) => Some((key.clone(), value)),
+                (_, None) => None,
+            })
+            .collect()
+    }
+
+    /// The winning write to `key`: its ID and value (`None` if deleted).
+    fn slot(&self, index: &NodeIndex, key: &str) -> Option<(Ts, Option<Value>)> {
+        let id = *obj(index, self.obj_id)?.keys.get(key)?;
+        let value = match IndexExt::get(index, &id) {
+            Some(CrdtNode::Con(con)) if matches!(con.val, ConValue::Val(PackValue::Undefined)) => {
+                None
+            }
+            Some(node) => Some(node.view(index)),
+            None => None,
+        };
+        Some((id, value))
+    }
+}
+
+fn obj(index: &NodeIndex, id: Ts) -> Option<&ObjNode> {
+    match IndexExt::get(index, &id) {
+        Some(CrdtNode::Obj(obj)) => Some(obj),
+        _ => None,
+    }
+}
+
+// ── LwwMapExt ─────────────────────────────────────────────────────────────
+
+/// Extension descriptor for [`LwwMapNode`]; register it on
+/// [`Model::extensions`] to render maps in [`Model::view`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LwwMapExt;
+
+impl AnyExtension for LwwMapExt {
+    fn id(&self) -> u32 {
+        ExtensionId::LwwMap as u32
+    }
+
+    fn name(&self) -> &str {
+        "lwwmap"
+    }
+
+    /// An object with one `con` per entry of `value` (if it is an object).
+    fn schema(&self, value: &Value) -> Box<dyn NodeBuilder> {
+        Box::new(schema::ObjNode {
+            entries: value
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(key, value)| {
+                    let con = schema::ConNode {
+                        raw: PackValue::from(value.clone()),
+                    };
+                    (key.clone(), Box::new(con) as Box<dyn NodeBuilder>)
+                })
+                .collect(),
+        })
+    }
+
+    fn view(&self, _: &Extensions, index: &NodeIndex, data: Ts) -> Value {
+        Value::Object(LwwMapNode::new(data).entries(index))
+    }
+}
+
+// ── LwwMapApi ─────────────────────────────────────────────────────────────
+
+/// Editing API for an `lwwmap` extension node.
+///
+/// Obtained with `api.ext::<LwwMapApi>(path)`.
+pub struct LwwMapApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    node: LwwMapNode,
+}
+
+impl<'m, 'a> ExtHandle<'m, 'a> for LwwMapApi<'m, 'a> {
+    const EXT_ID: u8 = ExtensionId::LwwMap as u8;
+
+    fn wrap(api: &'m mut ModelApi<'a>, node: ExtRef) -> Self {
+        Self {
+            api,
+            node: LwwMapNode::new(node.data),
+        }
+    }
+}
+
+impl LwwMapApi<'_, '_> {
+    /// The underlying map node.
+    pub fn node(&self) -> LwwMapNode {
+        self.node
+    }
+
+    /// Live entries of the map.
+    pub fn view(&self) -> Map<String, Value> {
+        self.node.view(self.api.model)
+    }
+
+    /// Value of `key`, if it is set.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.node.get(self.api.model, key)
+    }
+
+    /// Deleted keys, each with the timestamp of its winning delete.
+    pub fn tombstones(&self) -> Vec<(String, Ts)> {
+        self.node.tombstones(self.api.model)
+    }
+
+    /// Sets `key` to `value`, stored as a single atomic constant.
+    pub fn set(&mut self, key: &str, value: &Value) -> Result<(), ApiError> {
+        self.write(key, PackValue::from(value.clone()))
+    }
+
+    /// Deletes `key`, leaving a timestamped tombstone.
+    pub fn del(&mut self, key: &str) -> Result<(), ApiError> {
+        self.write(key, PackValue::Undefined)
+    }
+
+    fn write(&mut self, key: &str, value: PackValue) -> Result<(), ApiError> {
+        match IndexExt::get(&self.api.model.index, &self.node.obj_id) {
+            Some(CrdtNode::Obj(_)) => {}
+            Some(_) => return Err(ApiError::WrongType),
+            None => return Err(ApiError::NotFound),
+        }
+        let con = self.api.builder.con_val(value);
+        self.api
+            .builder
+            .ins_obj(self.node.obj_id, vec![(key.to_string(), con)]);
+        self.api.apply();
+        Ok(())
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::json_crdt_patch::clock::compare;
+    use crate::json_crdt_patch::patch::Patch;
+    use serde_json::json;
+
+    fn registry() -> Arc<Extensions> {
+        let mut extensions = Extensions::new();
+        extensions.register(Box::new(LwwMapExt)).unwrap();
+        Arc::new(extensions)
+    }
+
+    /// Two replicas whose root is the map `initial`.
+    fn replicas(initial: Value) -> (Model, Model) {
+        let mut a = Model::new(100);
+        a.extensions = registry();
+        let ((), base) = a
+            .transaction(|api| {
+                let map = api.ext_node(&LwwMapExt, &initial);
+                api.builder.root(map);
+                Ok::<_, ApiError>(())
+            })
+            .unwrap();
+        let mut b = Model::new(200);
+        b.extensions = registry();
+        b.apply_patch(&base);
+        (a, b)
+    }
+
+    fn edit(model: &mut Model, f: impl FnOnce(&mut LwwMapApi) -> Result<(), ApiError>) -> Patch {
+        model
+            .transaction(|api| f(&mut api.ext::<LwwMapApi>(&[])?))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn set_get_and_del() {
+        let (mut a, _) = replicas(json!({"a": 1}));
+        let mut api = ModelApi::new(&mut a);
+        let mut map = api.ext::<LwwMapApi>(&[]).unwrap();
+        map.set("b", &json!([1, 2])).unwrap();
+        map.set("a", &json!("one")).unwrap();
+        assert_eq!(map.get("a"), Some(json!("one")));
+        map.del("b").unwrap();
+        assert_eq!(map.get("b"), None);
+        assert_eq!(map.view(), json!({"a": "one"}).as_object().unwrap().clone());
+        assert_eq!(a.view(), json!({"a": "one"}));
+    }
+
+    #[test]
+    fn deletes_leave_timestamped_tombstones() {
+        let (mut a, _) = replicas(json!({"a": 1, "b": 2}));
+        edit(&mut a, |map| map.del("a"));
+        let node = LwwMapNode::new(match IndexExt::get(&a.index, &a.root.val) {
+            Some(CrdtNode::Vec(vec)) => vec.elements[1].unwrap(),
+            _ => panic!("expected an ext node"),
+        });
+        let tombstones = node.tombstones(&a);
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(tombstones[0].0, "a");
+        assert_eq!(node.timestamp(&a, "a"), Some(tombstones[0].1));
+        let (deleted, set) = (node.timestamp(&a, "a"), node.timestamp(&a, "b"));
+        assert!(compare(deleted.unwrap(), set.unwrap()) > 0);
+        assert_eq!(node.timestamp(&a, "c"), None);
+    }
+
+    #[test]
+    fn newer_delete_beats_older_set() {
+        let (mut a, mut b) = replicas(json!({"k": 0}));
+        let pa = edit(&mut a, |map| map.set("k", &json!(1)));
+        // `b` has seen `a`'s set before deleting, so its delete is newer.
+        b.apply_patch(&pa);
+        let pb = edit(&mut b, |map| map.del("k"));
+        a.apply_patch(&pb);
+        assert_eq!(a.view(), json!({}));
+        assert_eq!(b.view(), json!({}));
+    }
+
+    #[test]
+    fn concurrent_writes_converge() {
+        let (mut a, mut b) = replicas(json!({"k": 0, "x": 0}));
+        let pa = edit(&mut a, |map| {
+            map.del("k")?;
+            map.set("x", &json!("a"))
+        });
+        let pb = edit(&mut b, |map| {
+            map.set("k", &json!("b"))?;
+            map.del("x")
+        });
+        a.apply_patch(&pb);
+        b.apply_patch(&pa);
+        assert_eq!(a.view(), b.view());
+        // Equal clock times: the higher session ID (`b`) wins both keys.
+        assert_eq!(a.view(), json!({"k": "b"}));
+    }
+}
@@ -5,9 +5,15 @@
 //! Extensions add higher-level semantics on top of the base CRDT node types.
 //! Each extension wraps one or more underlying CRDT nodes and exposes a
 //! domain-specific API (counters, multi-value registers, rich text, …).
+//!
+//! [`or_set`], [`lww_map`] and the counter variants in [`cnt`] are not part
+//! of the upstream TypeScript; they are registered through the custom
+//! extension API of [`crate::json_crdt::extensions`].
 
 pub mod cnt;
+pub mod lww_map;
 pub mod mval;
+pub mod or_set;
 pub mod peritext;
 
 /// Numeric IDs for each registered extension.
@@ -22,4 +28,12 @@ pub enum ExtensionId {
     Quill = 3,
     Prosemirror = 4,
     Slate = 5,
+    /// Observed-remove set (not part of upstream).
+    OrSet = 6,
+    /// Last-write-wins map (not part of upstream).
+    LwwMap = 7,
+    /// Counter with observed-reset semantics (not part of upstream).
+    ResettableCnt = 8,
+    /// Escrow-style non-negative counter (not part of upstream).
+    BoundedCnt = 9,
 }
@@ -1,6 +1,9 @@
 //! Multi-value register extension (`mval`).
 //!
 //! Mirrors `packages/json-joy/src/json-crdt-extensions/mval/`.
+//!
+//! Conflict helpers ([`MvalNode::conflicts`], [`MvalNode::resolve`]) are not
+//! part of the upstream TypeScript.
 
 use json_joy_json_pack::PackValue;
 use serde_json::Value;
@@ -8,9 +11,20 @@ use serde_json::Value;
 use crate::json_crdt::constants::ORIGIN;
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{CrdtNode, TsKey};
-use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::clock::{Ts, Tss};
 use crate::json_crdt_patch::operations::{ConValue, Op};
 
+// ── MvalEntry ─────────────────────────────────────────────────────────────
+
+/// One of the concurrent values held by an [`MvalNode`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MvalEntry {
+    /// ID of the write that stored the value; pass it to
+    /// [`MvalNode::resolve`] to supersede it.
+    pub id: Ts,
+    pub value: Value,
+}
+
 // ── MvalNode ──────────────────────────────────────────────────────────────
 
 /// A multi-value register backed by an [`ArrNode`].
@@ -43,6 +57,69 @@ impl MvalNode {
             .collect()
     }
 
+    /// Return the live values together with the writes that stored them.
+    ///
+    /// More than one entry means concurrent writes are in conflict.
+    pub fn conflicts(&self, model: &Model) -> Vec<MvalEntry> {
+        let Some(CrdtNode::Arr(arr)) = model.index.get(&TsKey::from(self.arr_id)) else {
+            return Vec::new();
+        };
+        let mut entries = Vec::new();
+        for chunk in arr.rga.iter_live() {
+            let Some(ids) = &chunk.data else { continue };
+            for (offset, data_id) in ids.iter().enumerate() {
+                let id = Ts::new(chunk.id.sid, chunk.id.time + offset as u64);
+                let value = match model.index.get(&TsKey::from(*data_id)) {
+                    Some(node) => node.view(&model.index),
+                    None => Value::Null,
+                };
+                entries.push(MvalEntry { id, value });
+            }
+        }
+        entries
+    }
+
+    /// Write `value` so that it supersedes only the entries in `supersede`
+    /// (IDs taken from [`MvalNode::conflicts`]).
+    ///
+    /// Entries that are not chosen stay alongside the resolved value, and so
+    /// do writes made concurrently with the resolution. IDs that are not
+    /// live values of this register are ignored.
+    pub fn resolve(&self, model: &mut Model, value: Value, supersede: &[Ts]) {
+        if !model.index.contains_key(&TsKey::from(self.arr_id)) {
+            return;
+        }
+        let live: Vec<Ts> = self.conflicts(model).iter().map(|e| e.id).collect();
+        let what: Vec<Tss> = supersede
+            .iter()
+            .filter(|id| live.contains(id))
+            .map(|id| Tss::new(id.sid, id.time, 1))
+            .collect();
+
+        if !what.is_empty() {
+            let del_id = model.next_ts();
+            model.apply_operation(&Op::Del {
+                id: del_id,
+                obj: self.arr_id,
+                what,
+            });
+        }
+
+        let con_id = model.next_ts();
+        model.apply_operation(&Op::NewCon {
+            id: con_id,
+            val: ConValue::Val(PackValue::from(value)),
+        });
+
+        let ins_id = model.next_ts();
+        model.apply_operation(&Op::InsArr {
+            id: ins_id,
+            obj: self.arr_id,
+            after: ORIGIN,
+            data: vec![con_id],
+        });
+    }
+
     /// Replace the current value with `value`.
     pub fn set(&self, model: &mut Model, value: Value) {
         let (size, spans) = {
@@ -98,6 +175,35 @@ mod tests {
         100
     }
 
+    fn sid2() -> u64 {
+        200
+    }
+
+    /// Operations writing `value` from another session.
+    fn concurrent_write(mval: MvalNode, time: u64, value: i64) -> Vec<Op> {
+        let con_id = ts(sid2(), time);
+        vec![
+            Op::NewCon {
+                id: con_id,
+                val: ConValue::Val(PackValue::Integer(value)),
+            },
+            Op::InsArr {
+                id: ts(sid2(), time + 1),
+                obj: mval.arr_id,
+                after: ORIGIN,
+                data: vec![con_id],
+            },
+        ]
+    }
+
+    /// Inserts `value` as a write by another session.
+    fn concurrent_set(model: &mut Model, mval: MvalNode, time: u64, value: i64) -> Ts {
+        for op in concurrent_write(mval, time, value) {
+            model.apply_operation(&op);
+        }
+        ts(sid2(), time + 1)
+    }
+
     fn setup(sid: u64) -> (Model, MvalNode) {
         let mut model = Model::new(sid);
         let arr_id = ts(sid, 1);
@@ -154,4 +260,66 @@ mod tests {
         mval.set(&mut model, json!(4));
         assert_eq!(mval.view(&model), vec![json!(4)]);
     }
+
+    #[test]
+    fn conflicts_report_authors_and_times() {
+        let (mut model, mval) = setup(sid1());
+        mval.set(&mut model, json!(1));
+        let theirs = concurrent_set(&mut model, mval, 50, 2);
+        let conflicts = mval.conflicts(&model);
+        assert_eq!(conflicts.len(), 2);
+        let other = conflicts.iter().find(|e| e.id.sid == sid2()).unwrap();
+        assert_eq!(
+            (other.id, other.id.time, &other.value),
+            (theirs, 51, &json!(2))
+        );
+        let mine = conflicts.iter().find(|e| e.id.sid == sid1()).unwrap();
+        assert_eq!(mine.value, json!(1));
+    }
+
+    #[test]
+    fn resolve_supersedes_only_the_chosen_entries() {
+        let (mut model, mval) = setup(sid1());
+        mval.set(&mut model, json!(1));
+        let a = concurrent_set(&mut model, mval, 50, 2);
+        let b = concurrent_set(&mut model, mval, 60, 3);
+        let mine = mval
+            .conflicts(&model)
+            .into_iter()
+            .find(|e| e.id.sid == sid1())
+            .unwrap();
+
+        mval.resolve(&mut model, json!(12), &[mine.id, a]);
+        let mut view = mval.view(&model);
+        view.sort_by_key(|v| v.as_i64());
+        assert_eq!(view, vec![json!(3), json!(12)]);
+        assert!(mval.conflicts(&model).iter().any(|e| e.id == b));
+
+        mval.resolve(&mut model, json!(4), &[b, a]);
+        assert_eq!(mval.view(&model), vec![json!(4), json!(12)]);
+    }
+
+    #[test]
+    fn write_concurrent_with_resolution_survives() {
+        let (mut model, mval) = setup(sid1());
+        mval.set(&mut model, json!(1));
+        let a = concurrent_set(&mut model, mval, 50, 2);
+        let chosen: Vec<Ts> = mval.conflicts(&model).iter().map(|e| e.id).collect();
+        // Session 2 writes again on a replica that has not seen the
+        // resolution; its write arrives after the resolution.
+        let write = concurrent_write(mval, 70, 5);
+        let mut replica = model.clone();
+        for op in &write {
+            replica.apply_operation(op);
+        }
+        assert_eq!(mval.view(&replica).len(), 3);
+        mval.resolve(&mut model, json!(9), &chosen);
+        for op in &write {
+            model.apply_operation(op);
+        }
+        let mut view = mval.view(&model);
+        view.sort_by_key(|v| v.as_i64());
+        assert_eq!(view, vec![json!(5), json!(9)]);
+        assert!(!mval.conflicts(&model).iter().any(|e| e.id == a));
+    }
 }
@@ -0,0 +1,318 @@
+//! Observed-remove set extension (`orset`).
+//!
+//! Not part of the upstream TypeScript; built on the same `ext` node layout
+//! as the upstream extensions (see [`crate::json_crdt::extensions`]).
+//!
+//! The payload is an [`ArrNode`] of `con` elements. Every add inserts a new
+//! element, whose slot ID serves as the add's unique tag, and a remove
+//! deletes only the tags it has observed. A remove concurrent with an add of
+//! the same value therefore leaves the new tag alive: adds win.
+
+use json_joy_json_pack::PackValue;
+use serde_json::Value;
+
+use super::ExtensionId;
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::extensions::{AnyExtension, ExtHandle, ExtRef, Extensions};
+use crate::json_crdt::model::api::ApiError;
+use crate::json_crdt::model::{Model, ModelApi};
+use crate::json_crdt::nodes::{ArrNode, CrdtNode, IndexExt, NodeIndex};
+use crate::json_crdt_patch::clock::{Ts, Tss};
+use crate::json_crdt_patch::schema::{self, NodeBuilder};
+
+// ── OrSetNode ─────────────────────────────────────────────────────────────
+
+/// An add-wins set backed by an [`ArrNode`].
+#[derive(Debug, Clone, Copy)]
+pub struct OrSetNode {
+    pub arr_id: Ts,
+}
+
+impl OrSetNode {
+    pub fn new(arr_id: Ts) -> Self {
+        Self { arr_id }
+    }
+
+    /// Distinct members, in order of their first live tag.
+    pub fn view(&self, model: &Model) -> Vec<Value> {
+        self.values(&model.index)
+    }
+
+    /// Whether `value` is a member of the set.
+    pub fn has(&self, model: &Model, value: &Value) -> bool {
+        self.entries(&model.index).iter().any(|(_, v)| v == value)
+    }
+
+    fn values(&self, index: &NodeIndex) -> Vec<Value> {
+        let mut values: Vec<Value> = Vec::new();
+        for (_, value) in self.entries(index) {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        values
+    }
+
+    /// Live `(tag, value)` pairs, where the tag is the element's slot ID.
+    fn entries(&self, index: &NodeIndex) -> Vec<(Ts, Value)> {
+        let Some(CrdtNode::Arr(arr)) = IndexExt::get(index, &self.arr_id) else {
+            return Vec::new();
+        };
+        let mut entries = Vec::new();
+        for chunk in arr.rga.iter_live() {
+            let Some(ids) = &chunk.data else { continue };
+            for (offset, id) in ids.iter().enumerate() {
+                let tag = Ts::new(chunk.id.sid, chunk.id.time + offset as u64);
+                let value = match IndexExt::get(index, id) {
+                    Some(node) => node.view(index),
+                    None => Value::Null,
+                };
+                entries.push((tag, value));
+            }
+        }
+        entries
+    }
+}
+
+// ── OrSetExt ──────────────────────────────────────────────────────────────
+
+/// Extension descriptor for [`OrSetNode`]; register it on
+/// [`Model::extensions`] to render sets in [`Model::view`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrSetExt;
+
+impl AnyExtension for OrSetExt {
+    fn id(&self) -> u32 {
+        ExtensionId::OrSet as u32
+    }
+
+    fn name(&self) -> &str {
+        "orset"
+    }
+
+    /// An array of the distinct members of `value` (if it is an array).
+    fn schema(&self, value: &Value) -> Box<dyn NodeBuilder> {
+        let mut members: Vec<&Value> = Vec::new();
+        for member in value.as_array().into_iter().flatten() {
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        }
+        Box::new(schema::ArrNode {
+            items: members
+                .into_iter()
+                .map(|member| {
+                    Box::new(schema::ConNode {
+                        raw: PackValue::from(member.clone()),
+                    }) as Box<dyn NodeBuilder>
+                })
+                .collect(),
+        })
+    }
+
+    fn view(&self, _: &Extensions, index: &NodeIndex, data: Ts) -> Value {
+        Value::Array(OrSetNode::new(data).values(index))
+    }
+}
+
+// ── OrSetApi ──────────────────────────────────────────────────────────────
+
+/// Editing API for an `orset` extension node.
+///
+/// Obtained with `api.ext::<OrSetApi>(path)`.
+pub struct OrSetApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    node: OrSetNode,
+}
+
+impl<'m, 'a> ExtHandle<'m, 'a> for OrSetApi<'m, 'a> {
+    const EXT_ID: u8 = ExtensionId::OrSet as u8;
+
+    fn wrap(api: &'m mut ModelApi<'a>, node: ExtRef) -> Self {
+        Self {
+            api,
+            node: OrSetNode::new(node.data),
+        }
+    }
+}
+
+impl OrSetApi<'_, '_> {
+    /// The underlying set node.
+    pub fn node(&self) -> OrSetNode {
+        self.node
+    }
+
+    /// Distinct members of the set.
+    pub fn view(&self) -> Vec<Value> {
+        self.node.view(self.api.model)
+    }
+
+    /// Whether `value` is a member of the set.
+    pub fn has(&self, value: &Value) -> bool {
+        self.node.has(self.api.model, value)
+    }
+
+    /// Adds `value` under a fresh tag.
+    ///
+    /// Tags of `value` seen so far are replaced by the new one, so the set
+    /// does not grow on repeated adds while a concurrent remove — which can
+    /// only have observed the older tags — still leaves `value` in the set.
+    pub fn add(&mut self, value: &Value) -> Result<(), ApiError> {
+        let after = {
+            let arr = self.arr()?;
+            arr.rga.last_chunk().map_or(ORIGIN, |chunk| {
+                Ts::new(chunk.id.sid, chunk.id.time + chunk.span - 1)
+            })
+        };
+        let stale = self.tags(value);
+        if !stale.is_empty() {
+            self.api.builder.del(self.node.arr_id, stale);
+        }
+        let con = self.api.builder.con_val(PackValue::from(value.clone()));
+        self.api.builder.ins_arr(self.node.arr_id, after, vec![con]);
+        self.api.apply();
+        Ok(())
+    }
+
+    /// Removes every observed tag of `value`. Returns whether `value` was
+    /// a member.
+    pub fn del(&mut self, value: &Value) -> Result<bool, ApiError> {
+        self.arr()?;
+        let tags = self.tags(value);
+        if tags.is_empty() {
+            return Ok(false);
+        }
+        self.api.builder.del(self.node.arr_id, tags);
+        self.api.apply();
+        Ok(true)
+    }
+
+    fn arr(&self) -> Result<&ArrNode, ApiError> {
+        match IndexExt::get(&self.api.model.index, &self.node.arr_id) {
+            Some(CrdtNode::Arr(arr)) => Ok(arr),
+            Some(_) => Err(ApiError::WrongType),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    fn tags(&self, value: &Value) -> Vec<Tss> {
+        self.node
+            .entries(&self.api.model.index)
+            .into_iter()
+            .filter(|(_, v)| v == value)
+            .map(|(tag, _)| Tss::new(tag.sid, tag.time, 1))
+            .collect()
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::json_crdt_patch::patch::Patch;
+    use serde_json::json;
+
+    fn registry() -> Arc<Extensions> {
+        let mut extensions = Extensions::new();
+        extensions.register(Box::new(OrSetExt)).unwrap();
+        Arc::new(extensions)
+    }
+
+    /// Two replicas whose root is the set `initial`.
+    fn replicas(initial: Value) -> (Model, Model) {
+        let mut a = Model::new(100);
+        a.extensions = registry();
+        let ((), base) = a
+            .transaction(|api| {
+                let set = api.ext_node(&OrSetExt, &initial);
+                api.builder.root(set);
+                Ok::<_, ApiError>(())
+            })
+            .unwrap();
+        let mut b = Model::new(200);
+        b.extensions = registry();
+        b.apply_patch(&base);
+        (a, b)
+    }
+
+    fn edit(model: &mut Model, f: impl FnOnce(&mut OrSetApi) -> Result<(), ApiError>) -> Patch {
+        model
+            .transaction(|api| f(&mut api.ext::<OrSetApi>(&[])?))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn schema_deduplicates_initial_members() {
+        let (a, _) = replicas(json!([1, "x", 1]));
+        assert_eq!(a.view(), json!([1, "x"]));
+    }
+
+    #[test]
+    fn add_and_del_update_membership() {
+        let (mut a, _) = replicas(json!([]));
+        let mut api = ModelApi::new(&mut a);
+        let mut set = api.ext::<OrSetApi>(&[]).unwrap();
+        set.add(&json!("a")).unwrap();
+        set.add(&json!({"b": 1})).unwrap();
+        set.add(&json!("a")).unwrap();
+        assert_eq!(set.view(), vec![json!({"b": 1}), json!("a")]);
+        assert!(set.del(&json!("a")).unwrap());
+        assert!(!set.del(&json!("a")).unwrap());
+        assert!(!set.has(&json!("a")));
+        assert!(set.has(&json!({"b": 1})));
+        assert_eq!(a.view(), json!([{"b": 1}]));
+    }
+
+    #[test]
+    fn repeated_adds_do_not_grow_the_set() {
+        let (mut a, _) = replicas(json!(["a"]));
+        for _ in 0..3 {
+            edit(&mut a, |set| set.add(&json!("a")));
+        }
+        let Some(CrdtNode::Vec(vec)) = IndexExt::get(&a.index, &a.root.val) else {
+            panic!("expected an ext node");
+        };
+        let arr = vec.elements[1].unwrap();
+        let Some(CrdtNode::Arr(arr)) = IndexExt::get(&a.index, &arr) else {
+            panic!("expected the payload array");
+        };
+        assert_eq!(arr.size(), 1);
+    }
+
+    #[test]
+    fn concurrent_add_wins_over_remove() {
+        let (mut a, mut b) = replicas(json!(["x", "y"]));
+        let pa = edit(&mut a, |set| set.del(&json!("x")).map(drop));
+        let pb = edit(&mut b, |set| set.add(&json!("x")));
+        a.apply_patch(&pb);
+        b.apply_patch(&pa);
+        assert_eq!(a.view(), b.view());
+        assert_eq!(a.view(), json!(["y", "x"]));
+    }
+
+    #[test]
+    fn concurrent_removes_converge() {
+        let (mut a, mut b) = replicas(json!(["x", "y", "z"]));
+        let pa = edit(&mut a, |set| set.del(&json!("x")).map(drop));
+        let pb = edit(&mut b, |set| {
+            set.del(&json!("x"))?;
+            set.del(&json!("z")).map(drop)
+        });
+        a.apply_patch(&pb);
+        b.apply_patch(&pa);
+        assert_eq!(a.view(), json!(["y"]));
+        assert_eq!(b.view(), json!(["y"]));
+    }
+
+    #[test]
+    fn wrong_extension_is_rejected() {
+        let mut model = Model::new(300);
+        let mut api = ModelApi::new(&mut model);
+        api.set(&json!([1])).unwrap();
+        assert_eq!(api.ext::<OrSetApi>(&[]).err(), Some(ApiError::WrongType));
+    }
+}
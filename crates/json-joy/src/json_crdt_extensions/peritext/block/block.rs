@@ -0,0 +1,87 @@
+//! `Block` — a node of the Peritext block tree.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/block/Block.ts`
+//! and `LeafBlock.ts`.
+
+use serde_json::Value;
+
+use crate::json_crdt_extensions::peritext::overlay::InlineRun;
+use crate::json_crdt_extensions::peritext::slice::TypeTag;
+use crate::json_crdt_patch::clock::Ts;
+
+// ── Block ─────────────────────────────────────────────────────────────────
+
+/// A block of the document: either a container of other blocks or a leaf
+/// holding inline content.
+///
+/// Upstream distinguishes `Block` and `LeafBlock`; here a leaf is a block
+/// without children.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    /// Tags from the root down to this block; empty for the root.
+    pub path: Vec<TypeTag>,
+    /// ID of the split marker that starts this leaf block, if any. The
+    /// first block of a document without a leading marker has none, as do
+    /// container blocks.
+    pub marker: Option<Ts>,
+    /// Data of the split marker.
+    pub data: Option<Value>,
+    /// Start of the block's content in the visible text (inclusive). For a
+    /// leaf this is just after its marker character.
+    pub start: usize,
+    /// End of the block's content in the visible text (exclusive).
+    pub end: usize,
+    /// Child blocks of a container.
+    pub children: Vec<Block>,
+    /// Inline content of a leaf.
+    pub inlines: Vec<InlineRun>,
+}
+
+impl Block {
+    pub(crate) fn container(path: Vec<TypeTag>) -> Self {
+        Self {
+            path,
+            marker: None,
+            data: None,
+            start: 0,
+            end: 0,
+            children: Vec::new(),
+            inlines: Vec::new(),
+        }
+    }
+
+    /// The innermost tag of the block, `None` for the root.
+    pub fn tag(&self) -> Option<&TypeTag> {
+        self.path.last()
+    }
+
+    /// `true` if the block holds inline content rather than other blocks.
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty() && !self.path.is_empty()
+    }
+
+    /// Plain text of the block; blocks of a container are concatenated.
+    pub fn text(&self) -> String {
+        if self.is_leaf() {
+            self.inlines.iter().map(|run| run.text.as_str()).collect()
+        } else {
+            self.children.iter().map(Block::text).collect()
+        }
+    }
+
+    /// Leaf blocks under (or equal to) this block, in document order.
+    pub fn leaves(&self) -> Vec<&Block> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(&mut leaves);
+        leaves
+    }
+
+    fn collect_leaves<'b>(&'b self, leaves: &mut Vec<&'b Block>) {
+        if self.is_leaf() {
+            leaves.push(self);
+        }
+        for child in &self.children {
+            child.collect_leaves(leaves);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! `Fragment` — the block tree of a Peritext document.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/block/Fragment.ts`.
+
+use serde_json::Value;
+
+use super::Block;
+use crate::json_crdt::model::Model;
+use crate::json_crdt_extensions::peritext::overlay::Overlay;
+use crate::json_crdt_extensions::peritext::slice::constants::TYPE_P;
+use crate::json_crdt_extensions::peritext::slice::{SliceType, TypeTag};
+use crate::json_crdt_extensions::peritext::Peritext;
+use crate::json_crdt_patch::clock::Ts;
+
+// ── Fragment ──────────────────────────────────────────────────────────────
+
+/// The document split into blocks by its markers.
+///
+/// Each marker starts a leaf block whose content runs from just after the
+/// marker character to the next marker (or the end of the text). Text
+/// before the first marker forms a leading paragraph, omitted if empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+    /// The root block; its children are the top-level blocks.
+    pub root: Block,
+}
+
+impl Fragment {
+    /// Build the block tree from an up-to-date [`Overlay`].
+    ///
+    /// Mirrors `Fragment.refresh()` in the upstream TypeScript.
+    pub fn build(overlay: &Overlay) -> Self {
+        let len = overlay.runs().last().map_or(0, |run| run.end);
+        let mut leaves: Vec<Leaf> = Vec::new();
+        for point in overlay.points().filter(|p| p.is_marker()) {
+            // Of several markers at one position the newest wins.
+            let Some(slice) = point.markers.last().and_then(|id| overlay.slice(*id)) else {
+                continue;
+            };
+            if let Some(prev) = leaves.last_mut() {
+                prev.end = point.pos;
+            }
+            leaves.push(Leaf {
+                path: marker_path(&slice.slice_type),
+                marker: Some(slice.id),
+                data: slice.data.clone(),
+                start: (point.pos + 1).min(len),
+                end: len,
+            });
+        }
+        let first_end = leaves.first().map_or(len, |leaf| leaf.marker_pos());
+        if first_end > 0 || leaves.is_empty() {
+            leaves.insert(
+                0,
+                Leaf {
+                    path: vec![TypeTag::Int(TYPE_P)],
+                    marker: None,
+                    data: None,
+                    start: 0,
+                    end: first_end,
+                },
+            );
+        }
+
+        // Stack of open containers; the root is at the bottom.
+        let mut stack = vec![Block::container(Vec::new())];
+        for leaf in leaves {
+            let parents = &leaf.path[..leaf.path.len() - 1];
+            let mut shared = 0;
+            while shared < parents.len()
+                && shared + 1 < stack.len()
+                && stack[shared + 1].tag() == Some(&parents[shared])
+            {
+                shared += 1;
+            }
+            while stack.len() > shared + 1 {
+                close(&mut stack);
+            }
+            for depth in shared..parents.len() {
+                stack.push(Block::container(parents[..=depth].to_vec()));
+            }
+            let inlines = overlay
+                .runs()
+                .iter()
+                .filter_map(|run| run.clip(leaf.start, leaf.end))
+                .collect();
+            let block = Block {
+                path: leaf.path,
+                marker: leaf.marker,
+                data: leaf.data,
+                start: leaf.start,
+                end: leaf.end,
+                children: Vec::new(),
+                inlines,
+            };
+            stack.last_mut().unwrap().children.push(block);
+        }
+        while stack.len() > 1 {
+            close(&mut stack);
+        }
+        let mut root = stack.pop().unwrap();
+        root.end = len;
+        Self { root }
+    }
+
+    /// Top-level blocks.
+    pub fn blocks(&self) -> &[Block] {
+        &self.root.children
+    }
+
+    /// Leaf blocks in document order.
+    pub fn leaves(&self) -> Vec<&Block> {
+        self.root.leaves()
+    }
+
+    /// The leaf block containing visible position `pos`; a marker character
+    /// belongs to the block it starts.
+    pub fn leaf_at(&self, pos: usize) -> Option<&Block> {
+        self.leaves()
+            .into_iter()
+            .rev()
+            .find(|leaf| leaf.start <= pos + usize::from(leaf.marker.is_some()))
+    }
+}
+
+impl Peritext {
+    /// Build the block tree of this document.
+    pub fn fragment(&self, model: &Model) -> Fragment {
+        Fragment::build(&self.overlay(model))
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// A leaf block before it is placed into the tree.
+struct Leaf {
+    path: Vec<TypeTag>,
+    marker: Option<Ts>,
+    data: Option<Value>,
+    start: usize,
+    end: usize,
+}
+
+impl Leaf {
+    /// Position of the marker character, i.e. where the previous block
+    /// ends.
+    fn marker_pos(&self) -> usize {
+        self.start
+            .saturating_sub(usize::from(self.marker.is_some()))
+    }
+}
+
+/// Block path of a marker type; an empty path defaults to a paragraph.
+fn marker_path(slice_type: &SliceType) -> Vec<TypeTag> {
+    match slice_type {
+        SliceType::Simple(tag) => vec![tag.clone()],
+        SliceType::Steps(steps) if !steps.is_empty() => steps.clone(),
+        SliceType::Steps(_) => vec![TypeTag::Int(TYPE_P)],
+    }
+}
+
+/// Pop the innermost open container into its parent, spanning its children.
+fn close(stack: &mut Vec<Block>) {
+    let mut block = stack.pop().unwrap();
+    block.start = block.children.first().map_or(0, |child| child.start);
+    block.end = block.children.last().map_or(0, |child| child.end);
+    stack.last_mut().unwrap().children.push(block);
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt_extensions::peritext::slice::constants::*;
+    use crate::json_crdt_extensions::peritext::test_util::setup;
+    use crate::json_crdt_extensions::peritext::Range;
+    use serde_json::json;
+
+    /// Place a marker of type `steps` on the character at `pos`.
+    fn marker(model: &mut Model, pt: &Peritext, pos: usize, steps: &[i64]) -> Ts {
+        let start = pt.range_at(model, pos, 1).unwrap().start;
+        let slice_type = match steps {
+            [tag] => SliceType::from(*tag),
+            _ => SliceType::Steps(steps.iter().map(|t| TypeTag::Int(*t)).collect()),
+        };
+        pt.saved_slices
+            .ins_marker(model, &Range::new(start, start), slice_type, None)
+    }
+
+    fn tags(block: &Block) -> Vec<i64> {
+        block
+            .path
+            .iter()
+            .map(|tag| match tag {
+                TypeTag::Int(n) => *n,
+                TypeTag::Str(_) => panic!("string tag"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn text_without_markers_is_one_paragraph() {
+        let (model, pt) = setup("hello");
+        let fragment = pt.fragment(&model);
+        assert_eq!(fragment.blocks().len(), 1);
+        let block = &fragment.blocks()[0];
+        assert_eq!(tags(block), vec![TYPE_P]);
+        assert!(block.is_leaf());
+        assert_eq!(block.text(), "hello");
+        assert_eq!((block.start, block.end), (0, 5));
+    }
+
+    #[test]
+    fn markers_split_leaf_blocks() {
+        let (mut model, pt) = setup("intro\ntitle\nbody");
+        let h1 = marker(&mut model, &pt, 5, &[TYPE_H1]);
+        marker(&mut model, &pt, 11, &[TYPE_P]);
+        let fragment = pt.fragment(&model);
+        let leaves = fragment.leaves();
+        let summary: Vec<(Vec<i64>, String)> = leaves
+            .iter()
+            .map(|leaf| (tags(leaf), leaf.text()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (vec![TYPE_P], "intro".into()),
+                (vec![TYPE_H1], "title".into()),
+                (vec![TYPE_P], "body".into()),
+            ]
+        );
+        assert_eq!(leaves[1].marker, Some(h1));
+        assert_eq!((leaves[1].start, leaves[1].end), (6, 11));
+        assert_eq!(fragment.leaf_at(5).unwrap().marker, Some(h1));
+        assert_eq!(fragment.leaf_at(4).unwrap().marker, None);
+    }
+
+    #[test]
+    fn leading_marker_omits_empty_paragraph() {
+        let (mut model, pt) = setup("\nquote");
+        marker(&mut model, &pt, 0, &[TYPE_BLOCKQUOTE, TYPE_P]);
+        let fragment = pt.fragment(&model);
+        assert_eq!(fragment.blocks().len(), 1);
+        let quote = &fragment.blocks()[0];
+        assert_eq!(tags(quote), vec![TYPE_BLOCKQUOTE]);
+        assert!(!quote.is_leaf());
+        assert_eq!(tags(&quote.children[0]), vec![TYPE_BLOCKQUOTE, TYPE_P]);
+        assert_eq!(quote.text(), "quote");
+    }
+
+    #[test]
+    fn consecutive_items_share_containers() {
+        let (mut model, pt) = setup("\na\nb\nc\nd");
+        marker(&mut model, &pt, 0, &[TYPE_UL, TYPE_LI]);
+        marker(&mut model, &pt, 2, &[TYPE_UL, TYPE_LI]);
+        marker(&mut model, &pt, 4, &[TYPE_UL, TYPE_LI, TYPE_UL, TYPE_LI]);
+        marker(&mut model, &pt, 6, &[TYPE_P]);
+        let fragment = pt.fragment(&model);
+        let blocks = fragment.blocks();
+        assert_eq!(blocks.len(), 2);
+
+        let list = &blocks[0];
+        assert_eq!(tags(list), vec![TYPE_UL]);
+        assert_eq!((list.start, list.end), (1, 6));
+        let items: Vec<Vec<i64>> = list.children.iter().map(tags).collect();
+        assert_eq!(
+            items,
+            vec![
+                vec![TYPE_UL, TYPE_LI],
+                vec![TYPE_UL, TYPE_LI],
+                vec![TYPE_UL, TYPE_LI]
+            ]
+        );
+        // The last item is a container holding the nested list.
+        let nested = &list.children[2];
+        assert!(!nested.is_leaf());
+        assert_eq!(tags(&nested.children[0]), vec![TYPE_UL, TYPE_LI, TYPE_UL]);
+        assert_eq!(nested.text(), "c");
+
+        assert_eq!(tags(&blocks[1]), vec![TYPE_P]);
+        assert_eq!(blocks[1].text(), "d");
+    }
+
+    #[test]
+    fn leaf_inlines_keep_formatting() {
+        let (mut model, pt) = setup("ab\ncd");
+        marker(&mut model, &pt, 2, &[TYPE_P]);
+        let range = pt.range_at(&model, 1, 3).unwrap();
+        pt.saved_slices
+            .ins_stack(&mut model, &range, TYPE_BOLD, Some(json!(1)));
+        let fragment = pt.fragment(&model);
+        let leaves = fragment.leaves();
+        let bold = SliceType::from(TYPE_BOLD);
+        let runs: Vec<(String, bool)> = leaves
+            .iter()
+            .flat_map(|leaf| &leaf.inlines)
+            .map(|run| (run.text.clone(), run.attrs.has(&bold)))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                ("a".into(), false),
+                ("b".into(), true),
+                ("c".into(), true),
+                ("d".into(), false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,16 @@
+//! Peritext block structure.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/block/`.
+//!
+//! Split markers (slices with `Marker` stacking) divide the text into leaf
+//! blocks. The type of each marker is a path of tags — e.g. `[ul, li]` or
+//! `[blockquote, p]` — whose leading steps name the container blocks the
+//! leaf is nested in. A [`Fragment`] assembles the leaves into a tree of
+//! [`Block`]s, merging consecutive leaves that share container steps.
+
+#[allow(clippy::module_inception)]
+pub mod block;
+pub mod fragment;
+
+pub use block::Block;
+pub use fragment::Fragment;
@@ -0,0 +1,378 @@
+//! High-level rich-text editing commands.
+//!
+//! Mirrors the formatting and block commands of
+//! `packages/json-joy/src/json-crdt-extensions/peritext/editor/Editor.ts`.
+//!
+//! Every command runs as a [`Model::transaction`] and returns the single
+//! [`Patch`] it applied (empty if it changed nothing), so that it can be
+//! sent to peers and undone as one step.
+
+use serde_json::Value;
+
+use crate::json_crdt::model::Model;
+use crate::json_crdt_extensions::peritext::rga::Range;
+use crate::json_crdt_extensions::peritext::slice::constants::{TYPE_LI, TYPE_OL, TYPE_P, TYPE_UL};
+use crate::json_crdt_extensions::peritext::slice::{Slice, SliceStacking, SliceType, TypeTag};
+use crate::json_crdt_extensions::peritext::{command, Peritext};
+use crate::json_crdt_patch::clock::Ts;
+use crate::json_crdt_patch::patch::Patch;
+
+impl Peritext {
+    // ── Inline formatting ─────────────────────────────────────────────────
+
+    /// Toggle the inline format `slice_type` over `len` characters from
+    /// `start`.
+    ///
+    /// If every character of the range already has the format, it is
+    /// removed: slices of the type are split so that only their parts
+    /// outside the range remain. Otherwise the format is applied: slices of
+    /// the type that erase it inside the range are split likewise, and the
+    /// new slice absorbs overlapping or adjacent slices with the same data.
+    /// The new slice stacks as `One` if it carries `data`, else as `Many`.
+    ///
+    /// Mirrors `Editor.toggleExclFmt()` in the upstream TypeScript.
+    pub fn toggle_inline(
+        &self,
+        model: &mut Model,
+        start: usize,
+        len: usize,
+        slice_type: impl Into<SliceType>,
+        data: Option<Value>,
+    ) -> Patch {
+        let slice_type = slice_type.into();
+        let end = start.saturating_add(len);
+        command(model, |model| {
+            if len == 0 {
+                return;
+            }
+            let overlay = self.overlay(model);
+            let active = overlay
+                .runs()
+                .iter()
+                .filter(|run| run.end > start && run.start < end)
+                .all(|run| run.attrs.has(&slice_type));
+            let stacking = if data.is_some() {
+                SliceStacking::One
+            } else {
+                SliceStacking::Many
+            };
+            let Some(node) = Self::str_node(model, self.str_id) else {
+                return;
+            };
+            let spans: Vec<(Slice, usize, usize)> = overlay
+                .slices()
+                .iter()
+                .filter(|slice| slice.slice_type == slice_type)
+                .filter(|slice| {
+                    !matches!(
+                        slice.stacking,
+                        SliceStacking::Marker | SliceStacking::Cursor
+                    )
+                })
+                .map(|slice| {
+                    (
+                        slice.clone(),
+                        slice.start.view_pos(node),
+                        slice.end.view_pos(node),
+                    )
+                })
+                .filter(|(_, s, e)| s < e)
+                .collect();
+            let (mut from, mut to) = (start, end);
+            for (slice, s, e) in spans {
+                let overlaps = s < end && e > start;
+                if active || slice.stacking == SliceStacking::Erase {
+                    if overlaps {
+                        self.saved_slices.del(model, slice.id);
+                        self.reinsert(model, &slice, s, start);
+                        self.reinsert(model, &slice, end, e);
+                    }
+                } else if s <= end && e >= start && slice.stacking == stacking && slice.data == data
+                {
+                    self.saved_slices.del(model, slice.id);
+                    from = from.min(s);
+                    to = to.max(e);
+                }
+            }
+            if !active {
+                if let Some(range) = self.range_at(model, from, to - from) {
+                    self.saved_slices
+                        .ins(model, &range, stacking, slice_type, data);
+                }
+            }
+        })
+    }
+
+    /// Re-insert the part `[from, to)` of a removed slice, if non-empty.
+    fn reinsert(&self, model: &mut Model, slice: &Slice, from: usize, to: usize) {
+        if from >= to {
+            return;
+        }
+        if let Some(range) = self.range_at(model, from, to - from) {
+            let slice_type = slice.slice_type.clone();
+            let data = slice.data.clone();
+            self.saved_slices
+                .ins(model, &range, slice.stacking, slice_type, data);
+        }
+    }
+
+    // ── Blocks ────────────────────────────────────────────────────────────
+
+    /// Break the block containing `pos` in two by inserting a `\n` with a
+    /// split marker at `pos`.
+    ///
+    /// The new block has type `slice_type`, or else the type of the block
+    /// being split (so that splitting a list item starts a new item).
+    ///
+    /// Mirrors `Editor.insMarker()` in the upstream TypeScript.
+    pub fn split_block(
+        &self,
+        model: &mut Model,
+        pos: usize,
+        slice_type: Option<SliceType>,
+    ) -> Patch {
+        command(model, |model| {
+            let pos = pos.min(self.len(model));
+            let slice_type = slice_type.unwrap_or_else(|| path_type(self.block_at(model, pos).0));
+            self.ins_at(model, pos, "\n");
+            self.mark(model, pos, slice_type, None);
+        })
+    }
+
+    /// Merge the block starting at `pos` into the previous one, as a
+    /// backspace at the start of a block does: the `\n` before `pos` and its
+    /// markers are deleted. Elsewhere, deletes the character before `pos`.
+    ///
+    /// Mirrors `Editor.delete()` at a block boundary in the upstream
+    /// TypeScript.
+    pub fn merge_block(&self, model: &mut Model, pos: usize) -> Patch {
+        command(model, |model| {
+            if pos == 0 {
+                return;
+            }
+            let overlay = self.overlay(model);
+            if let Some(point) = overlay.point_at(pos - 1) {
+                for id in &point.markers {
+                    self.saved_slices.del(model, *id);
+                }
+            }
+            self.del_at(model, pos - 1, 1);
+        })
+    }
+
+    /// Change the type of the leaf block containing `pos`, keeping its data.
+    ///
+    /// A leading block without a marker gets one, on a `\n` inserted at the
+    /// start of the text.
+    ///
+    /// Mirrors `Editor.setBlockType()` in the upstream TypeScript.
+    pub fn set_block_type(
+        &self,
+        model: &mut Model,
+        pos: usize,
+        slice_type: impl Into<SliceType>,
+    ) -> Patch {
+        let slice_type = slice_type.into();
+        command(model, |model| self.retype(model, pos, |_| Some(slice_type)))
+    }
+
+    /// Nest the list item containing `pos` one level deeper, in a list of
+    /// the same kind. Does nothing outside list items.
+    ///
+    /// Mirrors `Editor.indent()` in the upstream TypeScript.
+    pub fn indent(&self, model: &mut Model, pos: usize) -> Patch {
+        command(model, |model| {
+            self.retype(model, pos, |path| {
+                let list = list_of(path)?;
+                let mut path = path.to_vec();
+                path.extend([list, TypeTag::Int(TYPE_LI)]);
+                Some(path_type(path))
+            })
+        })
+    }
+
+    /// Move the list item containing `pos` one level up; a top-level item
+    /// becomes a paragraph. Does nothing outside list items.
+    ///
+    /// Mirrors `Editor.outdent()` in the upstream TypeScript.
+    pub fn outdent(&self, model: &mut Model, pos: usize) -> Patch {
+        command(model, |model| {
+            self.retype(model, pos, |path| {
+                list_of(path)?;
+                let path = &path[..path.len() - 2];
+                Some(match path {
+                    [] => SliceType::from(TYPE_P),
+                    _ => path_type(path.to_vec()),
+                })
+            })
+        })
+    }
+
+    /// Replace the marker of the leaf at `pos` with one of the type `retype`
+    /// derives from the leaf's path.
+    fn retype(
+        &self,
+        model: &mut Model,
+        pos: usize,
+        retype: impl FnOnce(&[TypeTag]) -> Option<SliceType>,
+    ) {
+        let (path, marker) = self.block_at(model, pos);
+        let Some(slice_type) = retype(&path) else {
+            return;
+        };
+        if path_type(path) == slice_type {
+            return;
+        }
+        match marker.and_then(|id| self.saved_slices.get(model, id)) {
+            Some(old) => {
+                self.saved_slices.del(model, old.id);
+                let point = old.start;
+                let range = Range::new(point, point);
+                self.saved_slices
+                    .ins_marker(model, &range, slice_type, old.data);
+            }
+            None => {
+                self.ins_at(model, 0, "\n");
+                self.mark(model, 0, slice_type, None);
+            }
+        }
+    }
+
+    /// Path and marker of the leaf block holding a caret at `pos`. Text
+    /// before the first marker is an unmarked paragraph.
+    fn block_at(&self, model: &Model, pos: usize) -> (Vec<TypeTag>, Option<Ts>) {
+        let fragment = self.fragment(model);
+        let leaves = fragment.leaves();
+        let leaf = leaves
+            .iter()
+            .rev()
+            .find(|leaf| leaf.start <= pos)
+            .or(leaves.first());
+        match leaf {
+            Some(leaf) => (leaf.path.clone(), leaf.marker),
+            None => (vec![TypeTag::Int(TYPE_P)], None),
+        }
+    }
+
+    /// Place a split marker on the character at `pos`.
+    fn mark(&self, model: &mut Model, pos: usize, slice_type: SliceType, data: Option<Value>) {
+        if let Some(range) = self.range_at(model, pos, 1) {
+            let point = range.start;
+            self.saved_slices
+                .ins_marker(model, &Range::new(point, point), slice_type, data);
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// Marker type of a block path.
+fn path_type(path: Vec<TypeTag>) -> SliceType {
+    match <[TypeTag; 1]>::try_from(path) {
+        Ok([tag]) => SliceType::Simple(tag),
+        Err(path) => SliceType::Steps(path),
+    }
+}
+
+/// The list tag of a list item path ending in `[list, li]`.
+fn list_of(path: &[TypeTag]) -> Option<TypeTag> {
+    match path {
+        [.., list @ TypeTag::Int(TYPE_UL | TYPE_OL), TypeTag::Int(TYPE_LI)] => Some(list.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt_extensions::peritext::slice::constants::{TYPE_BOLD, TYPE_H1, TYPE_H2};
+    use crate::json_crdt_extensions::peritext::test_util::setup;
+    use crate::json_crdt_extensions::peritext::TagMap;
+
+    fn html(pt: &Peritext, model: &Model) -> String {
+        crate::json_crdt_extensions::peritext::transfer::to_html(
+            &pt.fragment(model),
+            &TagMap::default(),
+        )
+    }
+
+    fn bold_count(pt: &Peritext, model: &Model) -> usize {
+        pt.saved_slices
+            .iter_slices(model)
+            .iter()
+            .filter(|slice| slice.slice_type == SliceType::from(TYPE_BOLD))
+            .count()
+    }
+
+    #[test]
+    fn toggle_splits_and_merges_slices() {
+        let (mut model, pt) = setup("hello world");
+        pt.toggle_inline(&mut model, 0, 11, TYPE_BOLD, None);
+        assert_eq!(html(&pt, &model), "<p><b>hello world</b></p>");
+        pt.toggle_inline(&mut model, 4, 3, TYPE_BOLD, None);
+        assert_eq!(html(&pt, &model), "<p><b>hell</b>o w<b>orld</b></p>");
+        assert_eq!(bold_count(&pt, &model), 2);
+        pt.toggle_inline(&mut model, 3, 2, TYPE_BOLD, None);
+        assert_eq!(html(&pt, &model), "<p><b>hello</b> w<b>orld</b></p>");
+        pt.toggle_inline(&mut model, 5, 2, TYPE_BOLD, None);
+        assert_eq!(html(&pt, &model), "<p><b>hello world</b></p>");
+        assert_eq!(bold_count(&pt, &model), 1);
+    }
+
+    #[test]
+    fn toggle_past_the_end_does_not_overflow() {
+        let (mut model, pt) = setup("hello");
+        pt.toggle_inline(&mut model, 0, 5, TYPE_BOLD, None);
+        pt.toggle_inline(&mut model, 2, usize::MAX, TYPE_BOLD, None);
+        assert_eq!(html(&pt, &model), "<p><b>he</b>llo</p>");
+    }
+
+    #[test]
+    fn command_is_a_single_replayable_patch() {
+        let (mut model, pt) = setup("hello world");
+        pt.toggle_inline(&mut model, 0, 11, TYPE_BOLD, None);
+        let mut replica = model.clone();
+        let patch = pt.toggle_inline(&mut model, 2, 5, TYPE_BOLD, None);
+        assert!(!patch.ops.is_empty());
+        replica.apply_patch(&patch);
+        assert_eq!(html(&pt, &replica), html(&pt, &model));
+        assert_eq!(html(&pt, &model), "<p><b>he</b>llo w<b>orld</b></p>");
+    }
+
+    #[test]
+    fn splits_merges_and_retypes_blocks() {
+        let (mut model, pt) = setup("helloworld");
+        pt.split_block(&mut model, 5, None);
+        assert_eq!(html(&pt, &model), "<p>hello</p><p>world</p>");
+        pt.set_block_type(&mut model, 8, TYPE_H1);
+        assert_eq!(html(&pt, &model), "<p>hello</p><h1>world</h1>");
+        pt.set_block_type(&mut model, 0, TYPE_H2);
+        assert_eq!(html(&pt, &model), "<h2>hello</h2><h1>world</h1>");
+        assert_eq!(pt.text(&model), "\nhello\nworld");
+        pt.merge_block(&mut model, 7);
+        assert_eq!(html(&pt, &model), "<h2>helloworld</h2>");
+        pt.merge_block(&mut model, 6);
+        assert_eq!(pt.text(&model), "\nhellworld");
+    }
+
+    #[test]
+    fn indents_and_outdents_list_items() {
+        let (mut model, pt) = setup("ab");
+        let item = SliceType::Steps(vec![TypeTag::Int(TYPE_UL), TypeTag::Int(TYPE_LI)]);
+        pt.split_block(&mut model, 1, Some(item.clone()));
+        pt.set_block_type(&mut model, 0, item);
+        assert_eq!(html(&pt, &model), "<ul><li>a</li><li>b</li></ul>");
+        pt.split_block(&mut model, 4, None);
+        pt.ins_at(&mut model, 5, "c");
+        pt.indent(&mut model, 6);
+        assert_eq!(
+            html(&pt, &model),
+            "<ul><li>a</li><li>b</li><li><ul><li>c</li></ul></li></ul>"
+        );
+        pt.outdent(&mut model, 6);
+        assert_eq!(html(&pt, &model), "<ul><li>a</li><li>b</li><li>c</li></ul>");
+        pt.outdent(&mut model, 1);
+        assert_eq!(html(&pt, &model), "<p>a</p><ul><li>b</li><li>c</li></ul>");
+        assert!(pt.outdent(&mut model, 1).ops.is_empty());
+    }
+}
@@ -0,0 +1,410 @@
+//! `Cursor` — a caret or selection that survives concurrent edits.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/editor/Cursor.ts`.
+//!
+//! Both ends are [`Point`]s attached to characters: a caret at visible
+//! position `p > 0` sits after character `p - 1`, and a caret at `0` on the
+//! start of the string. Text inserted or deleted elsewhere therefore shifts
+//! the cursor along with the characters it is attached to.
+
+use serde_json::{json, Value};
+
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::model::Model;
+use crate::json_crdt::nodes::StrNode;
+use crate::json_crdt_extensions::peritext::rga::{Anchor, Point, Range};
+use crate::json_crdt_extensions::peritext::Peritext;
+use crate::json_crdt_patch::clock::Ts;
+
+// ── CursorAnchor ──────────────────────────────────────────────────────────
+
+/// Which end of a selection stays put while the other (the *focus*) moves.
+///
+/// Mirrors `CursorAnchor` in the upstream TypeScript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u8)]
+pub enum CursorAnchor {
+    /// The anchor is the start; the focus is the end.
+    #[default]
+    Start = 0,
+    /// The anchor is the end; the focus is the start.
+    End = 1,
+}
+
+// ── TextUnit ──────────────────────────────────────────────────────────────
+
+/// Distance unit for cursor movement.
+///
+/// Mirrors `TextRangeUnit` in the upstream TypeScript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextUnit {
+    /// One character (a surrogate pair counts as one).
+    Char,
+    /// To the end (or start) of a word; non-word characters before it are
+    /// skipped first.
+    Word,
+    /// To the end (or start) of a `\n`-delimited line.
+    Line,
+    /// To the end (or start) of the text.
+    All,
+}
+
+// ── Cursor ────────────────────────────────────────────────────────────────
+
+/// A caret (collapsed) or selection in a Peritext string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    /// ID of the `StrNode` the cursor is in.
+    pub str_id: Ts,
+    /// Start of the selection.
+    pub start: Point,
+    /// End of the selection; equal to `start` for a caret.
+    pub end: Point,
+    /// Which end is the anchor.
+    pub anchor: CursorAnchor,
+}
+
+impl Cursor {
+    /// A caret at visible position `pos` (clamped to the text length).
+    pub fn at(peritext: &Peritext, model: &Model, pos: usize) -> Self {
+        Self::select(peritext, model, pos, pos)
+    }
+
+    /// A selection from `anchor` to `focus` (visible positions, in either
+    /// order).
+    pub fn select(peritext: &Peritext, model: &Model, anchor: usize, focus: usize) -> Self {
+        let mut cursor = Self {
+            str_id: peritext.str_id,
+            start: caret(None, 0),
+            end: caret(None, 0),
+            anchor: CursorAnchor::Start,
+        };
+        cursor.set(model, anchor, focus);
+        cursor
+    }
+
+    /// Move both ends: the anchor to `anchor` and the focus to `focus`.
+    pub fn set(&mut self, model: &Model, anchor: usize, focus: usize) {
+        let node = Peritext::str_node(model, self.str_id);
+        let (start, end) = (anchor.min(focus), anchor.max(focus));
+        self.start = caret(node, start);
+        self.end = caret(node, end);
+        self.anchor = if anchor <= focus {
+            CursorAnchor::Start
+        } else {
+            CursorAnchor::End
+        };
+    }
+
+    /// The end of the selection that stays put.
+    pub fn anchor_point(&self) -> Point {
+        match self.anchor {
+            CursorAnchor::Start => self.start,
+            CursorAnchor::End => self.end,
+        }
+    }
+
+    /// The end of the selection that moves.
+    pub fn focus_point(&self) -> Point {
+        match self.anchor {
+            CursorAnchor::Start => self.end,
+            CursorAnchor::End => self.start,
+        }
+    }
+
+    /// The selection as a [`Range`].
+    pub fn range(&self) -> Range {
+        Range::new(self.start, self.end)
+    }
+
+    /// Visible positions of the start and end.
+    pub fn positions(&self, model: &Model) -> (usize, usize) {
+        let Some(node) = Peritext::str_node(model, self.str_id) else {
+            return (0, 0);
+        };
+        let start = self.start.view_pos(node);
+        (start, self.end.view_pos(node).max(start))
+    }
+
+    /// Visible position of the anchor.
+    pub fn anchor_pos(&self, model: &Model) -> usize {
+        let (start, end) = self.positions(model);
+        match self.anchor {
+            CursorAnchor::Start => start,
+            CursorAnchor::End => end,
+        }
+    }
+
+    /// Visible position of the focus.
+    pub fn focus_pos(&self, model: &Model) -> usize {
+        let (start, end) = self.positions(model);
+        match self.anchor {
+            CursorAnchor::Start => end,
+            CursorAnchor::End => start,
+        }
+    }
+
+    /// `true` if the cursor selects no text — including a selection whose
+    /// text has been deleted.
+    pub fn is_collapsed(&self, model: &Model) -> bool {
+        let (start, end) = self.positions(model);
+        start == end
+    }
+
+    /// The selected text.
+    pub fn text(&self, model: &Model) -> String {
+        let Some(node) = Peritext::str_node(model, self.str_id) else {
+            return String::new();
+        };
+        let (start, end) = self.positions(model);
+        let units: Vec<u16> = node.view_str().encode_utf16().collect();
+        String::from_utf16_lossy(&units[start.min(units.len())..end.min(units.len())])
+    }
+
+    /// Collapse the selection onto its focus.
+    pub fn collapse(&mut self, model: &Model) {
+        let focus = self.focus_pos(model);
+        self.set(model, focus, focus);
+    }
+
+    /// Move the focus by `steps` units (backwards if negative), extending or
+    /// shrinking the selection; the anchor stays put.
+    pub fn move_focus(&mut self, model: &Model, unit: TextUnit, steps: isize) {
+        let anchor = self.anchor_pos(model);
+        let focus = self.skip(model, self.focus_pos(model), unit, steps);
+        self.set(model, anchor, focus);
+    }
+
+    /// Move the caret by `steps` units (backwards if negative), collapsing
+    /// the selection.
+    ///
+    /// A selection moved by characters collapses onto its start or end
+    /// first, like in text editors; the first step is spent doing so.
+    pub fn move_by(&mut self, model: &Model, unit: TextUnit, steps: isize) {
+        let (start, end) = self.positions(model);
+        let pos = if unit == TextUnit::Char && start != end && steps != 0 {
+            let edge = if steps < 0 { start } else { end };
+            self.skip(model, edge, unit, steps - steps.signum())
+        } else {
+            self.skip(model, self.focus_pos(model), unit, steps)
+        };
+        self.set(model, pos, pos);
+    }
+
+    fn skip(&self, model: &Model, pos: usize, unit: TextUnit, steps: isize) -> usize {
+        let text = Peritext::str_node(model, self.str_id)
+            .map(StrNode::view_str)
+            .unwrap_or_default();
+        skip(&text, pos, unit, steps)
+    }
+
+    // ── Awareness payload ─────────────────────────────────────────────────
+
+    /// Compact JSON for sharing with peers:
+    /// `[[start.sid, start.time, start.anchor], [end.sid, end.time,
+    /// end.anchor], anchor]`.
+    pub fn to_json(&self) -> Value {
+        let point = |p: &Point| json!([p.id.sid, p.id.time, p.anchor as u8]);
+        json!([point(&self.start), point(&self.end), self.anchor as u8])
+    }
+
+    /// Decode [`Cursor::to_json`] output for the string `str_id`.
+    pub fn from_json(str_id: Ts, value: &Value) -> Option<Self> {
+        let point = |v: &Value| -> Option<Point> {
+            let [sid, time, anchor] = v.as_array()?.as_slice() else {
+                return None;
+            };
+            let anchor = match anchor.as_u64()? {
+                0 => Anchor::Before,
+                1 => Anchor::After,
+                _ => return None,
+            };
+            Some(Point::new(Ts::new(sid.as_u64()?, time.as_u64()?), anchor))
+        };
+        let [start, end, anchor] = value.as_array()?.as_slice() else {
+            return None;
+        };
+        let anchor = match anchor.as_u64()? {
+            0 => CursorAnchor::Start,
+            1 => CursorAnchor::End,
+            _ => return None,
+        };
+        Some(Self {
+            str_id,
+            start: point(start)?,
+            end: point(end)?,
+            anchor,
+        })
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// The caret point at visible position `pos`, clamped to the text.
+fn caret(node: Option<&StrNode>, pos: usize) -> Point {
+    let before = pos
+        .min(node.map_or(0, StrNode::size))
+        .checked_sub(1)
+        .and_then(|last| node?.find(last));
+    match before {
+        Some(id) => Point::new(id, Anchor::After),
+        None => Point::new(ORIGIN, Anchor::After),
+    }
+}
+
+fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Position reached from `pos` after `steps` units of `text` (in UTF-16
+/// code units).
+fn skip(text: &str, pos: usize, unit: TextUnit, steps: isize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut offsets = Vec::with_capacity(chars.len() + 1);
+    let mut offset = 0;
+    for ch in &chars {
+        offsets.push(offset);
+        offset += ch.len_utf16();
+    }
+    offsets.push(offset);
+    let n = chars.len();
+    let mut i = offsets.partition_point(|o| *o < pos).min(n);
+    let forward = steps > 0;
+    for _ in 0..steps.unsigned_abs() {
+        i = match (unit, forward) {
+            (TextUnit::Char, true) => (i + 1).min(n),
+            (TextUnit::Char, false) => i.saturating_sub(1),
+            (TextUnit::Word, true) => {
+                while i < n && !is_word(chars[i]) {
+                    i += 1;
+                }
+                while i < n && is_word(chars[i]) {
+                    i += 1;
+                }
+                i
+            }
+            (TextUnit::Word, false) => {
+                while i > 0 && !is_word(chars[i - 1]) {
+                    i -= 1;
+                }
+                while i > 0 && is_word(chars[i - 1]) {
+                    i -= 1;
+                }
+                i
+            }
+            (TextUnit::Line, true) => {
+                if i < n && chars[i] == '\n' {
+                    i += 1;
+                }
+                while i < n && chars[i] != '\n' {
+                    i += 1;
+                }
+                i
+            }
+            (TextUnit::Line, false) => {
+                if i > 0 && chars[i - 1] == '\n' {
+                    i -= 1;
+                }
+                while i > 0 && chars[i - 1] != '\n' {
+                    i -= 1;
+                }
+                i
+            }
+            (TextUnit::All, true) => n,
+            (TextUnit::All, false) => 0,
+        };
+    }
+    offsets[i]
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt_extensions::peritext::test_util::setup;
+
+    #[test]
+    fn caret_follows_its_character() {
+        let (mut model, pt) = setup("hello world");
+        let caret = Cursor::at(&pt, &model, 6);
+        let selection = Cursor::select(&pt, &model, 6, 11);
+        pt.ins_at(&mut model, 0, ">> ");
+        assert_eq!(caret.focus_pos(&model), 9);
+        assert_eq!(selection.text(&model), "world");
+        pt.del_at(&mut model, 0, 5);
+        assert_eq!(caret.focus_pos(&model), 4);
+        assert_eq!(selection.positions(&model), (4, 9));
+    }
+
+    #[test]
+    fn deleting_the_selection_collapses_it() {
+        let (mut model, pt) = setup("abcdef");
+        let selection = Cursor::select(&pt, &model, 2, 4);
+        assert!(!selection.is_collapsed(&model));
+        pt.del_at(&mut model, 1, 4);
+        assert!(selection.is_collapsed(&model));
+        assert_eq!(selection.positions(&model), (1, 1));
+    }
+
+    #[test]
+    fn caret_at_start_stays_at_start() {
+        let (mut model, pt) = setup("abc");
+        let caret = Cursor::at(&pt, &model, 0);
+        pt.ins_at(&mut model, 0, "xyz");
+        assert_eq!(caret.focus_pos(&model), 0);
+        let clamped = Cursor::at(&pt, &model, 99);
+        assert_eq!(clamped.focus_pos(&model), 6);
+    }
+
+    #[test]
+    fn moves_by_char_word_and_line() {
+        let (model, pt) = setup("one two, three\nfour 😀 five");
+        let mut cursor = Cursor::at(&pt, &model, 0);
+        cursor.move_by(&model, TextUnit::Word, 2);
+        assert_eq!(cursor.focus_pos(&model), 7);
+        cursor.move_by(&model, TextUnit::Word, 1);
+        assert_eq!(cursor.focus_pos(&model), 14);
+        cursor.move_by(&model, TextUnit::Line, 1);
+        assert_eq!(cursor.focus_pos(&model), 27);
+        cursor.move_by(&model, TextUnit::Word, -1);
+        assert_eq!(cursor.focus_pos(&model), 23);
+        // The emoji is two UTF-16 code units but one character.
+        cursor.move_by(&model, TextUnit::Char, -2);
+        assert_eq!(cursor.focus_pos(&model), 20);
+        cursor.move_by(&model, TextUnit::Line, -1);
+        assert_eq!(cursor.focus_pos(&model), 15);
+        cursor.move_by(&model, TextUnit::Line, -1);
+        assert_eq!(cursor.focus_pos(&model), 0);
+        cursor.move_by(&model, TextUnit::All, 1);
+        assert_eq!(cursor.focus_pos(&model), 27);
+    }
+
+    #[test]
+    fn move_focus_extends_selection_from_anchor() {
+        let (model, pt) = setup("hello world");
+        let mut cursor = Cursor::at(&pt, &model, 5);
+        cursor.move_focus(&model, TextUnit::Word, -1);
+        assert_eq!(cursor.anchor, CursorAnchor::End);
+        assert_eq!(cursor.text(&model), "hello");
+        cursor.move_focus(&model, TextUnit::All, 1);
+        assert_eq!(cursor.anchor, CursorAnchor::Start);
+        assert_eq!(cursor.text(&model), " world");
+        // Moving by a character collapses onto the edge first.
+        cursor.move_by(&model, TextUnit::Char, -1);
+        assert_eq!(cursor.positions(&model), (5, 5));
+    }
+
+    #[test]
+    fn awareness_payload_round_trips() {
+        let (mut model, pt) = setup("hello");
+        let cursor = Cursor::select(&pt, &model, 4, 1);
+        let payload = cursor.to_json();
+        let decoded = Cursor::from_json(pt.str_id, &payload).unwrap();
+        assert_eq!(decoded, cursor);
+        pt.ins_at(&mut model, 0, "__");
+        assert_eq!(decoded.text(&model), "ell");
+        assert_eq!(decoded.anchor_pos(&model), 6);
+        assert!(Cursor::from_json(pt.str_id, &json!([[1, 2, 3], [1, 2, 0], 0])).is_none());
+    }
+}
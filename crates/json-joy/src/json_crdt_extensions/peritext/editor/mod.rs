@@ -0,0 +1,14 @@
+//! Editor-agnostic editing state for Peritext documents.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/editor/`.
+//!
+//! A [`Cursor`] is a caret or selection anchored to character IDs rather
+//! than offsets, so it stays on the same text while remote edits are
+//! merged, and can be shared with peers through [`Cursor::to_json`].
+//! The formatting and block commands in [`commands`] each apply one
+//! [`Patch`](crate::json_crdt_patch::patch::Patch), so they undo as a unit.
+
+pub mod commands;
+pub mod cursor;
+
+pub use cursor::{Cursor, CursorAnchor, TextUnit};
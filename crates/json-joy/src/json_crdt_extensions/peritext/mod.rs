@@ -22,21 +22,42 @@
 //! let range = peritext.range_at(&model, 6, 5).unwrap(); // "world"
 //! peritext.saved_slices.ins_stack(&mut model, &range, "bold", None);
 //! assert_eq!(peritext.text(&model), "hello world");
+//!
+//! // Formatted runs: "hello " (plain) and "world" (bold).
+//! let overlay = peritext.overlay(&model);
+//! assert_eq!(overlay.runs().len(), 2);
+//!
+//! // Block tree: without split markers, a single paragraph.
+//! let fragment = peritext.fragment(&model);
+//! assert_eq!(fragment.blocks().len(), 1);
+//! assert_eq!(
+//!     transfer::to_html(&fragment, &TagMap::default()),
+//!     "<p>hello <bold>world</bold></p>"
+//! );
 //! ```
 
+pub mod block;
+pub mod editor;
+pub mod overlay;
 pub mod rga;
 pub mod slice;
+pub mod transfer;
 
+pub use block::{Block, Fragment};
+pub use editor::{Cursor, CursorAnchor, TextUnit};
+pub use overlay::{InlineAttrs, InlineRun, Overlay};
 pub use rga::{Anchor, Point, Range};
 pub use slice::{Slice, SliceStacking, SliceType, Slices};
+pub use transfer::TagMap;
 
 use serde_json::Value;
 
 use crate::json_crdt::constants::ORIGIN;
 use crate::json_crdt::model::Model;
-use crate::json_crdt::nodes::{CrdtNode, TsKey};
+use crate::json_crdt::nodes::{CrdtNode, StrNode, TsKey};
 use crate::json_crdt_patch::clock::Ts;
 use crate::json_crdt_patch::operations::Op;
+use crate::json_crdt_patch::patch::Patch;
 
 // ── Peritext ──────────────────────────────────────────────────────────────
 
@@ -44,8 +65,9 @@ use crate::json_crdt_patch::operations::Op;
 /// collection (annotations).
 ///
 /// Construct with the IDs of an existing `StrNode` and `ArrNode` in a
-/// [`Model`].  All mutation methods apply operations to the model via
-/// [`Model::apply_operation`].
+/// [`Model`].  Each mutation method applies its operations to the model as
+/// one patch via [`Model::apply_patch`], so edits notify change listeners
+/// and are recorded by [`Model::transaction`].
 #[derive(Debug, Clone, Copy)]
 pub struct Peritext {
     /// ID of the `StrNode` holding the text content.
@@ -66,6 +88,14 @@ impl Peritext {
 
     // ── Text queries ──────────────────────────────────────────────────────
 
+    /// The `StrNode` with ID `str_id`, if `model` holds one.
+    pub(crate) fn str_node(model: &Model, str_id: Ts) -> Option<&StrNode> {
+        match model.index.get(&TsKey::from(str_id)) {
+            Some(CrdtNode::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
     /// Return the current text content as a plain `String`.
     pub fn text(&self, model: &Model) -> String {
         match model.index.get(&TsKey::from(self.str_id)) {
@@ -104,12 +134,15 @@ impl Peritext {
             }
         };
         let id = model.next_ts();
-        model.apply_operation(&Op::InsStr {
-            id,
-            obj: self.str_id,
-            after,
-            data: text.to_string(),
-        });
+        commit(
+            model,
+            vec![Op::InsStr {
+                id,
+                obj: self.str_id,
+                after,
+                data: text.to_string(),
+            }],
+        );
     }
 
     /// Delete `len` visible characters starting at position `pos`.
@@ -127,11 +160,14 @@ impl Peritext {
             return;
         }
         let id = model.next_ts();
-        model.apply_operation(&Op::Del {
-            id,
-            obj: self.str_id,
-            what: spans,
-        });
+        commit(
+            model,
+            vec![Op::Del {
+                id,
+                obj: self.str_id,
+                what: spans,
+            }],
+        );
     }
 
     // ── Position helpers ──────────────────────────────────────────────────
@@ -188,6 +224,46 @@ impl Peritext {
     }
 }
 
+/// Apply `ops` to `model` as a single local patch.
+pub(crate) fn commit(model: &mut Model, ops: Vec<Op>) {
+    model.apply_patch(&Patch { ops, meta: None });
+}
+
+/// Run `edit` on `model` as one [`Model::transaction`] and return the patch
+/// it applied (empty if it changed nothing).
+pub(crate) fn command(model: &mut Model, edit: impl FnOnce(&mut Model)) -> Patch {
+    let result = model.transaction(|api| {
+        edit(api.model);
+        Ok::<_, std::convert::Infallible>(())
+    });
+    match result {
+        Ok(((), patch)) => patch,
+    }
+}
+
+/// Shared test fixture.
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::Peritext;
+    use crate::json_crdt::model::Model;
+    use crate::json_crdt_patch::clock::ts;
+    use crate::json_crdt_patch::operations::Op;
+
+    /// A model with a Peritext document at `42.1` (text) and `42.2`
+    /// (slices) holding `text`.
+    pub(crate) fn setup(text: &str) -> (Model, Peritext) {
+        let mut model = Model::new(42);
+        let str_id = ts(42, 1);
+        let arr_id = ts(42, 2);
+        model.apply_operation(&Op::NewStr { id: str_id });
+        model.apply_operation(&Op::NewArr { id: arr_id });
+        model.clock.observe(arr_id, 1);
+        let peritext = Peritext::new(str_id, arr_id);
+        peritext.ins_at(&mut model, 0, text);
+        (model, peritext)
+    }
+}
+
 // ── Tests ─────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
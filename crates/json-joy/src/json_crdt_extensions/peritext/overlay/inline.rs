@@ -0,0 +1,141 @@
+//! Inline runs — stretches of text sharing the same formatting.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/block/Inline.ts`.
+
+use serde_json::Value;
+
+use crate::json_crdt_extensions::peritext::slice::{Slice, SliceStacking, SliceType};
+use crate::json_crdt_patch::clock::Ts;
+
+// ── InlineAttr ────────────────────────────────────────────────────────────
+
+/// One slice contributing to an attribute of an [`InlineRun`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineAttr {
+    /// ID of the contributing slice.
+    pub slice: Ts,
+    /// Stacking of the contributing slice.
+    pub stacking: SliceStacking,
+    /// Data of the contributing slice.
+    pub data: Option<Value>,
+}
+
+// ── InlineAttrs ───────────────────────────────────────────────────────────
+
+/// Attributes active over an [`InlineRun`], keyed by slice type.
+///
+/// Each type maps to the slices that contribute to it: several for `Many`
+/// stacking, exactly one for `One` (and `Cursor`). Types are kept in the
+/// order in which they first became active.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InlineAttrs {
+    entries: Vec<(SliceType, Vec<InlineAttr>)>,
+}
+
+impl InlineAttrs {
+    /// Resolve the attributes produced by `layers`, which must be ordered
+    /// by slice ID (oldest first) so that later slices override earlier
+    /// ones.
+    ///
+    /// Mirrors `Inline.attr()` in the upstream TypeScript.
+    pub fn resolve<'s>(layers: impl IntoIterator<Item = &'s Slice>) -> Self {
+        let mut attrs = Self::default();
+        for slice in layers {
+            let attr = InlineAttr {
+                slice: slice.id,
+                stacking: slice.stacking,
+                data: slice.data.clone(),
+            };
+            match slice.stacking {
+                SliceStacking::Many => attrs.entry(&slice.slice_type).push(attr),
+                SliceStacking::One | SliceStacking::Cursor => {
+                    *attrs.entry(&slice.slice_type) = vec![attr];
+                }
+                SliceStacking::Erase => attrs.remove(&slice.slice_type),
+                SliceStacking::Marker => {}
+            }
+        }
+        attrs
+    }
+
+    /// Contributions to the attribute of type `slice_type`, if active.
+    pub fn get(&self, slice_type: &SliceType) -> Option<&[InlineAttr]> {
+        self.entries
+            .iter()
+            .find(|(t, _)| t == slice_type)
+            .map(|(_, attrs)| attrs.as_slice())
+    }
+
+    /// `true` if an attribute of type `slice_type` is active.
+    pub fn has(&self, slice_type: &SliceType) -> bool {
+        self.get(slice_type).is_some()
+    }
+
+    /// Iterate over the active attribute types and their contributions.
+    pub fn iter(&self) -> impl Iterator<Item = (&SliceType, &[InlineAttr])> {
+        self.entries.iter().map(|(t, attrs)| (t, attrs.as_slice()))
+    }
+
+    /// `true` if no attribute is active.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn entry(&mut self, slice_type: &SliceType) -> &mut Vec<InlineAttr> {
+        let index = match self.entries.iter().position(|(t, _)| t == slice_type) {
+            Some(index) => index,
+            None => {
+                self.entries.push((slice_type.clone(), Vec::new()));
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[index].1
+    }
+
+    fn remove(&mut self, slice_type: &SliceType) {
+        self.entries.retain(|(t, _)| t != slice_type);
+    }
+}
+
+// ── InlineRun ─────────────────────────────────────────────────────────────
+
+/// A maximal stretch of text between two overlay points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineRun {
+    /// Start position in the visible text (inclusive).
+    pub start: usize,
+    /// End position in the visible text (exclusive).
+    pub end: usize,
+    /// The text of the run.
+    pub text: String,
+    /// Formatting active over the whole run.
+    pub attrs: InlineAttrs,
+}
+
+impl InlineRun {
+    /// Length of the run in UTF-16 code units.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// `true` if the run covers no text.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The part of this run within `[start, end)`, if non-empty.
+    pub fn clip(&self, start: usize, end: usize) -> Option<InlineRun> {
+        let start = start.max(self.start);
+        let end = end.min(self.end);
+        if start >= end {
+            return None;
+        }
+        let units: Vec<u16> = self.text.encode_utf16().collect();
+        Some(InlineRun {
+            start,
+            end,
+            text: String::from_utf16_lossy(&units[start - self.start..end - self.start]),
+            attrs: self.attrs.clone(),
+        })
+    }
+}
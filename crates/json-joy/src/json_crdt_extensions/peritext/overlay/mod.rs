@@ -0,0 +1,569 @@
+//! Peritext overlay — the formatting that applies at each text position.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/overlay/`.
+//!
+//! # Overview
+//!
+//! The [`Overlay`] sorts the endpoints of all slices into a tree of
+//! [`OverlayPoint`]s keyed by visible position. Sweeping the tree from left
+//! to right yields the slices covering each stretch between two points,
+//! which [`InlineAttrs::resolve`] folds into the active attributes according
+//! to each slice's [`SliceStacking`](super::slice::SliceStacking):
+//!
+//! - `Many` — every slice of the type contributes;
+//! - `One` (and `Cursor`) — the newest slice of the type replaces older ones;
+//! - `Erase` — removes the type as contributed by older slices;
+//! - `Marker` — splits blocks and contributes no inline attribute.
+//!
+//! The result is a sequence of [`InlineRun`]s. [`Overlay::refresh`] is keyed
+//! on [`Model::tick`]: it does nothing until another patch is applied.
+//!
+//! Unlike upstream, positions are plain visible offsets (UTF-16 code units)
+//! rather than IDs, so only formatting changes are laid out incrementally.
+//! A refresh re-reads the slices, matching them to the previous ones by ID,
+//! and hashes the layout of the text's chunks. If the text is unchanged,
+//! only the points of added, removed or edited slices move and only the
+//! runs between them are rebuilt; a text edit shifts positions, so the text
+//! is re-encoded and every endpoint resolved again (in `O(log n)` each).
+//! Keep one overlay and refresh it rather than calling
+//! [`Peritext::overlay`] for every read.
+
+pub mod inline;
+pub mod point;
+
+pub use inline::{InlineAttr, InlineAttrs, InlineRun};
+pub use point::OverlayPoint;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use super::slice::Slice;
+use super::Peritext;
+use crate::json_crdt::model::Model;
+use crate::json_crdt::nodes::StrNode;
+use crate::json_crdt_patch::clock::{compare, Ts};
+
+// ── Overlay ───────────────────────────────────────────────────────────────
+
+/// Sorted slice endpoints of a [`Peritext`] and the inline runs they
+/// delimit.
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    peritext: Peritext,
+    /// [`Model::tick`] the layout was computed at.
+    tick: Option<u64>,
+    /// Hash of the text's chunks the layout was computed for.
+    text_hash: Option<u64>,
+    /// The text, in UTF-16 code units.
+    units: Vec<u16>,
+    /// Live slices, ordered by ID (oldest first).
+    slices: Vec<Slice>,
+    /// Visible `(start, end)` of each laid-out slice.
+    spans: HashMap<Ts, (usize, usize)>,
+    points: BTreeMap<usize, OverlayPoint>,
+    runs: Vec<InlineRun>,
+}
+
+impl Overlay {
+    /// Create an empty overlay for `peritext`; call [`Overlay::refresh`] to
+    /// populate it.
+    pub fn new(peritext: Peritext) -> Self {
+        Self {
+            peritext,
+            tick: None,
+            text_hash: None,
+            units: Vec::new(),
+            slices: Vec::new(),
+            spans: HashMap::new(),
+            points: BTreeMap::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Bring the overlay up to date with `model`.
+    ///
+    /// Returns `false` if no patch was applied to `model` since the last
+    /// refresh, in which case nothing is recomputed. Operations applied
+    /// without [`Model::apply_patch`] do not advance the tick and are not
+    /// picked up.
+    ///
+    /// Mirrors `Overlay.refresh()` in the upstream TypeScript.
+    pub fn refresh(&mut self, model: &Model) -> bool {
+        if self.tick == Some(model.tick) {
+            return false;
+        }
+        self.tick = Some(model.tick);
+        let changed = self.update_slices(model);
+        let Some(node) = Peritext::str_node(model, self.peritext.str_id) else {
+            self.text_hash = None;
+            self.units.clear();
+            self.spans.clear();
+            self.points.clear();
+            self.runs.clear();
+            return true;
+        };
+        let hash = text_hash(node);
+        if self.text_hash != Some(hash) {
+            self.text_hash = Some(hash);
+            self.units = node.view_str().encode_utf16().collect();
+            self.layout(node);
+        } else if !changed.is_empty() {
+            self.relayout(node, &changed);
+        }
+        true
+    }
+
+    /// Overlay points in position order.
+    pub fn points(&self) -> impl Iterator<Item = &OverlayPoint> {
+        self.points.values()
+    }
+
+    /// The overlay point at visible position `pos`, if any.
+    pub fn point_at(&self, pos: usize) -> Option<&OverlayPoint> {
+        self.points.get(&pos)
+    }
+
+    /// Inline runs covering the whole text, in order.
+    pub fn runs(&self) -> &[InlineRun] {
+        &self.runs
+    }
+
+    /// Formatting of the character at visible position `pos`.
+    pub fn attrs_at(&self, pos: usize) -> Option<&InlineAttrs> {
+        let index = self.runs.partition_point(|run| run.end <= pos);
+        self.runs
+            .get(index)
+            .filter(|run| run.start <= pos)
+            .map(|run| &run.attrs)
+    }
+
+    /// Live slices, ordered by ID.
+    pub fn slices(&self) -> &[Slice] {
+        &self.slices
+    }
+
+    /// Live slice with ID `id`.
+    pub fn slice(&self, id: Ts) -> Option<&Slice> {
+        self.index(id).map(|index| &self.slices[index])
+    }
+
+    /// Position of the slice with ID `id` in [`Overlay::slices`].
+    fn index(&self, id: Ts) -> Option<usize> {
+        self.slices
+            .binary_search_by(|slice| compare(slice.id, id).cmp(&0))
+            .ok()
+    }
+
+    /// Merge the live slices of `model` into `slices`, keeping ID order.
+    /// Returns the IDs of the slices added, removed or edited.
+    fn update_slices(&mut self, model: &Model) -> Vec<Ts> {
+        let current = self.peritext.saved_slices.iter_slices(model);
+        let live: HashSet<Ts> = current.iter().map(|slice| slice.id).collect();
+        let mut changed = Vec::new();
+        self.slices.retain(|slice| {
+            let keep = live.contains(&slice.id);
+            if !keep {
+                changed.push(slice.id);
+            }
+            keep
+        });
+        for slice in current {
+            match self
+                .slices
+                .binary_search_by(|old| compare(old.id, slice.id).cmp(&0))
+            {
+                Ok(index) => {
+                    if !same(&self.slices[index], &slice) {
+                        changed.push(slice.id);
+                        self.slices[index] = slice;
+                    }
+                }
+                Err(index) => {
+                    changed.push(slice.id);
+                    self.slices.insert(index, slice);
+                }
+            }
+        }
+        changed
+    }
+
+    // ── Layout ────────────────────────────────────────────────────────────
+
+    /// Lay out every slice from scratch.
+    fn layout(&mut self, node: &StrNode) {
+        self.spans.clear();
+        self.points.clear();
+        for index in 0..self.slices.len() {
+            self.place(index, node);
+        }
+        self.runs = self.runs_between(0, self.units.len());
+    }
+
+    /// Move the points of the `changed` slices and rebuild the runs around
+    /// them.
+    fn relayout(&mut self, node: &StrNode, changed: &[Ts]) {
+        let (mut lo, mut hi) = (usize::MAX, 0);
+        for id in changed {
+            if let Some((start, end)) = self.spans.remove(id) {
+                self.unplace(*id, start, end);
+                (lo, hi) = (lo.min(start), hi.max(end));
+            }
+            if let Some(index) = self.index(*id) {
+                let (start, end) = self.place(index, node);
+                (lo, hi) = (lo.min(start), hi.max(end));
+            }
+        }
+        if lo > hi {
+            return;
+        }
+        // The runs touching `lo..=hi` are bounded by points that did not
+        // move (or by the ends of the text).
+        let first = self.runs.partition_point(|run| run.end < lo);
+        let last = self.runs.partition_point(|run| run.start <= hi);
+        let (from, to) = match self.runs.get(first..last) {
+            Some([head, .., tail]) => (head.start, tail.end),
+            Some([run]) => (run.start, run.end),
+            _ => (0, self.units.len()),
+        };
+        let runs = self.runs_between(from, to);
+        self.runs.splice(first..last.max(first), runs);
+    }
+
+    /// Resolve the slice at `index` and add its endpoints to the points.
+    fn place(&mut self, index: usize, node: &StrNode) -> (usize, usize) {
+        let len = self.units.len();
+        let slice = &self.slices[index];
+        let id = slice.id;
+        let start = slice.start.view_pos(node).min(len);
+        if slice.is_marker() {
+            point(&mut self.points, start).markers.push(id);
+            self.spans.insert(id, (start, start));
+            return (start, start);
+        }
+        let end = slice.end.view_pos(node).min(len);
+        if start < end {
+            point(&mut self.points, start).starts.push(id);
+            point(&mut self.points, end).ends.push(id);
+        }
+        self.spans.insert(id, (start, end));
+        (start, end)
+    }
+
+    /// Remove slice `id`'s endpoints at `start` and `end`.
+    fn unplace(&mut self, id: Ts, start: usize, end: usize) {
+        for pos in [start, end] {
+            if let Some(point) = self.points.get_mut(&pos) {
+                point.starts.retain(|other| *other != id);
+                point.ends.retain(|other| *other != id);
+                point.markers.retain(|other| *other != id);
+                if point.starts.is_empty() && point.ends.is_empty() && !point.is_marker() {
+                    self.points.remove(&pos);
+                }
+            }
+        }
+    }
+
+    /// Inline runs between `from` and `to`, both run boundaries.
+    fn runs_between(&self, from: usize, to: usize) -> Vec<InlineRun> {
+        if from >= to {
+            return Vec::new();
+        }
+        let mut active: Vec<usize> = (0..self.slices.len())
+            .filter(|index| {
+                let slice = &self.slices[*index];
+                !slice.is_marker()
+                    && self
+                        .spans
+                        .get(&slice.id)
+                        .is_some_and(|(start, end)| *start <= from && from < *end)
+            })
+            .collect();
+        let mut bounds = vec![from];
+        bounds.extend(self.points.range(from + 1..to).map(|(pos, _)| *pos));
+        bounds.push(to);
+
+        let mut runs = Vec::with_capacity(bounds.len() - 1);
+        for pair in bounds.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            if let Some(point) = self.points.get(&start).filter(|_| start != from) {
+                active.retain(|i| !point.ends.contains(&self.slices[*i].id));
+                active.extend(point.starts.iter().filter_map(|id| self.index(*id)));
+                active.sort_unstable();
+            }
+            runs.push(InlineRun {
+                start,
+                end,
+                text: String::from_utf16_lossy(&self.units[start..end]),
+                attrs: InlineAttrs::resolve(active.iter().map(|i| &self.slices[*i])),
+            });
+        }
+        runs
+    }
+}
+
+/// The overlay point at `pos`, created if missing.
+fn point(points: &mut BTreeMap<usize, OverlayPoint>, pos: usize) -> &mut OverlayPoint {
+    points.entry(pos).or_insert_with(|| OverlayPoint::new(pos))
+}
+
+/// Whether `a` and `b` lay out and format the same.
+fn same(a: &Slice, b: &Slice) -> bool {
+    a.stacking == b.stacking
+        && a.slice_type == b.slice_type
+        && a.start == b.start
+        && a.end == b.end
+        && a.data == b.data
+}
+
+/// Hash of the IDs, spans and deletion state of the text's chunks, which
+/// change with every edit of the text.
+fn text_hash(node: &StrNode) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for chunk in node.rga.iter() {
+        (chunk.id, chunk.span, chunk.deleted).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl Peritext {
+    /// Build an up-to-date [`Overlay`] of this document.
+    ///
+    /// Every call lays the document out from scratch; to read it repeatedly,
+    /// keep the overlay and call [`Overlay::refresh`].
+    pub fn overlay(&self, model: &Model) -> Overlay {
+        let mut overlay = Overlay::new(*self);
+        overlay.refresh(model);
+        overlay
+    }
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt_extensions::peritext::rga::Range;
+    use crate::json_crdt_extensions::peritext::slice::constants::*;
+    use crate::json_crdt_extensions::peritext::slice::{SliceStacking, SliceType};
+    use crate::json_crdt_extensions::peritext::test_util::setup;
+    use serde_json::json;
+
+    fn ins(
+        model: &mut Model,
+        pt: &Peritext,
+        start: usize,
+        len: usize,
+        stacking: SliceStacking,
+        slice_type: i64,
+        data: Option<serde_json::Value>,
+    ) -> Ts {
+        let range = pt.range_at(model, start, len).unwrap();
+        pt.ins_slice(model, &range, stacking, slice_type, data)
+    }
+
+    /// `(text, [types])` for each run.
+    fn runs(overlay: &Overlay) -> Vec<(String, Vec<SliceType>)> {
+        overlay
+            .runs()
+            .iter()
+            .map(|run| {
+                let types = run.attrs.iter().map(|(t, _)| t.clone()).collect();
+                (run.text.clone(), types)
+            })
+            .collect()
+    }
+
+    fn bold() -> SliceType {
+        SliceType::from(TYPE_BOLD)
+    }
+
+    fn italic() -> SliceType {
+        SliceType::from(TYPE_ITALIC)
+    }
+
+    #[test]
+    fn unformatted_text_is_one_run() {
+        let (model, pt) = setup("hello");
+        let overlay = pt.overlay(&model);
+        assert_eq!(runs(&overlay), vec![("hello".into(), vec![])]);
+        assert_eq!(overlay.points().count(), 0);
+    }
+
+    #[test]
+    fn overlapping_slices_split_runs() {
+        let (mut model, pt) = setup("hello world");
+        ins(&mut model, &pt, 0, 5, SliceStacking::Many, TYPE_BOLD, None);
+        ins(
+            &mut model,
+            &pt,
+            3,
+            5,
+            SliceStacking::Many,
+            TYPE_ITALIC,
+            None,
+        );
+        let overlay = pt.overlay(&model);
+        assert_eq!(
+            runs(&overlay),
+            vec![
+                ("hel".into(), vec![bold()]),
+                ("lo".into(), vec![bold(), italic()]),
+                (" wo".into(), vec![italic()]),
+                ("rld".into(), vec![]),
+            ]
+        );
+        assert!(overlay.attrs_at(4).unwrap().has(&italic()));
+        assert!(overlay.attrs_at(11).is_none());
+    }
+
+    #[test]
+    fn stacking_rules_are_applied_in_id_order() {
+        let (mut model, pt) = setup("abcdef");
+        ins(
+            &mut model,
+            &pt,
+            0,
+            6,
+            SliceStacking::Many,
+            TYPE_BOLD,
+            Some(json!(1)),
+        );
+        ins(
+            &mut model,
+            &pt,
+            0,
+            3,
+            SliceStacking::Many,
+            TYPE_BOLD,
+            Some(json!(2)),
+        );
+        ins(
+            &mut model,
+            &pt,
+            0,
+            6,
+            SliceStacking::One,
+            TYPE_LINK,
+            Some(json!("a")),
+        );
+        ins(
+            &mut model,
+            &pt,
+            2,
+            2,
+            SliceStacking::One,
+            TYPE_LINK,
+            Some(json!("b")),
+        );
+        ins(&mut model, &pt, 4, 2, SliceStacking::Erase, TYPE_BOLD, None);
+        let overlay = pt.overlay(&model);
+        let link = SliceType::from(TYPE_LINK);
+        let data = |pos: usize, t: &SliceType| -> Vec<serde_json::Value> {
+            overlay
+                .attrs_at(pos)
+                .unwrap()
+                .get(t)
+                .map_or(vec![], |attrs| {
+                    attrs.iter().map(|a| a.data.clone().unwrap()).collect()
+                })
+        };
+        assert_eq!(data(0, &bold()), vec![json!(1), json!(2)]);
+        assert_eq!(data(3, &bold()), vec![json!(1)]);
+        assert_eq!(data(4, &bold()), Vec::<serde_json::Value>::new());
+        assert_eq!(data(1, &link), vec![json!("a")]);
+        assert_eq!(data(2, &link), vec![json!("b")]);
+        assert_eq!(data(5, &link), vec![json!("a")]);
+    }
+
+    #[test]
+    fn markers_split_runs_without_attributes() {
+        let (mut model, pt) = setup("ab\ncd");
+        let range = pt.range_at(&model, 2, 1).unwrap();
+        let marker = pt.saved_slices.ins_marker(
+            &mut model,
+            &Range::new(range.start, range.start),
+            TYPE_P,
+            None,
+        );
+        let overlay = pt.overlay(&model);
+        assert_eq!(
+            runs(&overlay),
+            vec![("ab".into(), vec![]), ("\ncd".into(), vec![])]
+        );
+        assert_eq!(overlay.point_at(2).unwrap().markers, vec![marker]);
+    }
+
+    #[test]
+    fn refresh_tracks_text_and_slice_edits() {
+        let (mut model, pt) = setup("hello");
+        let mut overlay = pt.overlay(&model);
+        assert!(!overlay.refresh(&model));
+
+        let slice = ins(&mut model, &pt, 1, 3, SliceStacking::Many, TYPE_BOLD, None);
+        assert!(overlay.refresh(&model));
+        assert_eq!(overlay.runs().len(), 3);
+
+        // Text inserted inside the slice extends it.
+        pt.ins_at(&mut model, 2, "XY");
+        assert!(overlay.refresh(&model));
+        assert_eq!(overlay.runs()[1].text, "eXYll");
+        assert!(!overlay.refresh(&model));
+
+        pt.saved_slices.del(&mut model, slice);
+        assert!(overlay.refresh(&model));
+        assert_eq!(runs(&overlay), vec![("heXYllo".into(), vec![])]);
+    }
+
+    #[test]
+    fn incremental_refresh_matches_a_fresh_layout() {
+        let (mut model, pt) = setup("ab\ncd efgh");
+        let mut overlay = pt.overlay(&model);
+        let check = |overlay: &mut Overlay, model: &Model| {
+            assert!(overlay.refresh(model));
+            let fresh = pt.overlay(model);
+            assert_eq!(overlay.runs(), fresh.runs());
+            let positions = |o: &Overlay| o.points().map(|p| p.pos).collect::<Vec<_>>();
+            assert_eq!(positions(overlay), positions(&fresh));
+        };
+
+        let bold = ins(&mut model, &pt, 0, 6, SliceStacking::Many, TYPE_BOLD, None);
+        check(&mut overlay, &model);
+        let range = pt.range_at(&model, 2, 1).unwrap();
+        let marker = pt.saved_slices.ins_marker(
+            &mut model,
+            &Range::new(range.start, range.start),
+            TYPE_P,
+            None,
+        );
+        check(&mut overlay, &model);
+        let italic = ins(
+            &mut model,
+            &pt,
+            4,
+            5,
+            SliceStacking::Many,
+            TYPE_ITALIC,
+            None,
+        );
+        ins(
+            &mut model,
+            &pt,
+            5,
+            2,
+            SliceStacking::Erase,
+            TYPE_ITALIC,
+            None,
+        );
+        check(&mut overlay, &model);
+        pt.saved_slices.del(&mut model, bold);
+        check(&mut overlay, &model);
+        pt.saved_slices.del(&mut model, marker);
+        check(&mut overlay, &model);
+        pt.ins_at(&mut model, 5, "XYZ");
+        check(&mut overlay, &model);
+        pt.saved_slices.del(&mut model, italic);
+        check(&mut overlay, &model);
+        assert_eq!(overlay.slices().len(), 1);
+    }
+}
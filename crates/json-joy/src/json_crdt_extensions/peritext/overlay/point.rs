@@ -0,0 +1,37 @@
+//! `OverlayPoint` — a boundary in the overlay tree.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/overlay/OverlayPoint.ts`.
+
+use crate::json_crdt_patch::clock::Ts;
+
+// ── OverlayPoint ──────────────────────────────────────────────────────────
+
+/// A visible position at which at least one slice starts, ends or places a
+/// block marker.
+///
+/// Slices are referenced by their IDs (the IDs of their backing `VecNode`s).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverlayPoint {
+    /// Position in the visible text (UTF-16 code units before the point).
+    pub pos: usize,
+    /// Inline slices whose range starts here.
+    pub starts: Vec<Ts>,
+    /// Inline slices whose range ends here.
+    pub ends: Vec<Ts>,
+    /// Block-split markers placed here.
+    pub markers: Vec<Ts>,
+}
+
+impl OverlayPoint {
+    pub fn new(pos: usize) -> Self {
+        Self {
+            pos,
+            ..Self::default()
+        }
+    }
+
+    /// `true` if a block marker is placed at this point.
+    pub fn is_marker(&self) -> bool {
+        !self.markers.is_empty()
+    }
+}
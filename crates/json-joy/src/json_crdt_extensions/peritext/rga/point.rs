@@ -53,29 +53,22 @@ impl Point {
             return 0;
         }
 
-        let mut live = 0usize;
-        for chunk in str_node.rga.iter() {
-            // Is this the chunk containing our character ID?
-            if chunk.id.sid == self.id.sid
-                && chunk.id.time <= self.id.time
-                && self.id.time < chunk.id.time + chunk.span
-            {
-                if chunk.deleted {
-                    // The character is deleted; return where it would be.
-                    return live;
-                }
-                let char_offset = (self.id.time - chunk.id.time) as usize;
-                return match self.anchor {
-                    Anchor::Before => live + char_offset,
-                    Anchor::After => live + char_offset + 1,
-                };
-            }
-            // Accumulate live characters from this chunk.
-            live += chunk.len() as usize;
+        let rga = &str_node.rga;
+        let Some(idx) = rga.find_by_id(self.id) else {
+            // Character not found — treat as absolute end.
+            return str_node.size();
+        };
+        let chunk = rga.slot(idx);
+        let live = rga.pos(idx) as usize;
+        if chunk.deleted {
+            // The character is deleted; return where it would be.
+            return live;
+        }
+        let char_offset = (self.id.time - chunk.id.time) as usize;
+        match self.anchor {
+            Anchor::Before => live + char_offset,
+            Anchor::After => live + char_offset + 1,
         }
-
-        // Character not found — treat as absolute end.
-        live
     }
 
     /// Compare two points by their visual position in `str_node`.
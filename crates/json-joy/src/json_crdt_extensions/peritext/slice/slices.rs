@@ -12,6 +12,7 @@ use super::{
 use crate::json_crdt::constants::ORIGIN;
 use crate::json_crdt::model::Model;
 use crate::json_crdt::nodes::{CrdtNode, TsKey};
+use crate::json_crdt_extensions::peritext::commit;
 use crate::json_crdt_extensions::peritext::rga::{Anchor, Point, Range};
 use crate::json_crdt_patch::clock::{Ts, Tss};
 use crate::json_crdt_patch::operations::{ConValue, Op};
@@ -67,17 +68,17 @@ impl Slices {
         let type_id = model.next_ts();
 
         // Create the VecNode container first (so its ID is lowest).
-        model.apply_operation(&Op::NewVec { id: vec_id });
+        let mut ops = vec![Op::NewVec { id: vec_id }];
 
-        model.apply_operation(&Op::NewCon {
+        ops.push(Op::NewCon {
             id: header_id,
             val: ConValue::Val(PackValue::UInteger(header_bits)),
         });
-        model.apply_operation(&Op::NewCon {
+        ops.push(Op::NewCon {
             id: x1_id,
             val: ConValue::Val(ts_to_pack(range.start.id)),
         });
-        model.apply_operation(&Op::NewCon {
+        ops.push(Op::NewCon {
             id: x2_id,
             val: ConValue::Val(if same_point {
                 PackValue::Integer(0)
@@ -85,14 +86,14 @@ impl Slices {
                 ts_to_pack(range.end.id)
             }),
         });
-        model.apply_operation(&Op::NewCon {
+        ops.push(Op::NewCon {
             id: type_id,
             val: ConValue::Val(slice_type.to_pack()),
         });
 
         let data_id = data.map(|d| {
             let id = model.next_ts();
-            model.apply_operation(&Op::NewCon {
+            ops.push(Op::NewCon {
                 id,
                 val: ConValue::Val(PackValue::from(d)),
             });
@@ -109,19 +110,20 @@ impl Slices {
             vec_data.push((tuple_index::DATA as u8, d_id));
         }
         let ins_vec_id = model.next_ts();
-        model.apply_operation(&Op::InsVec {
+        ops.push(Op::InsVec {
             id: ins_vec_id,
             obj: vec_id,
             data: vec_data,
         });
 
         let ins_arr_id = model.next_ts();
-        model.apply_operation(&Op::InsArr {
+        ops.push(Op::InsArr {
             id: ins_arr_id,
             obj: self.arr_id,
             after: ORIGIN,
             data: vec![vec_id],
         });
+        commit(model, ops);
 
         vec_id
     }
@@ -186,11 +188,14 @@ impl Slices {
 
         if let Some(tss) = slot_tss {
             let del_id = model.next_ts();
-            model.apply_operation(&Op::Del {
-                id: del_id,
-                obj: self.arr_id,
-                what: vec![tss],
-            });
+            commit(
+                model,
+                vec![Op::Del {
+                    id: del_id,
+                    obj: self.arr_id,
+                    what: vec![tss],
+                }],
+            );
         }
     }
 
@@ -0,0 +1,489 @@
+//! Export of Peritext documents to JsonML, HTML, Markdown and plain text.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/transfer/export-html.ts`
+//! and `export-markdown.ts`.
+//!
+//! All exporters take the [`Fragment`] of a document. Block element names
+//! come from the [`TagMap`]; the Markdown exporter also uses them to pick
+//! the syntax of each block and inline mark (`h2` → `## `, `b` → `**`, …),
+//! dropping formatting Markdown cannot express.
+
+use serde_json::Value;
+
+use super::TagMap;
+use crate::json_crdt_extensions::peritext::block::{Block, Fragment};
+use crate::json_crdt_extensions::peritext::overlay::InlineRun;
+use crate::json_crdt_extensions::peritext::slice::{SliceStacking, SliceType, TypeTag};
+use crate::json_ml::{self, JsonMlElement, JsonMlNode, Tag};
+
+// ── JsonML / HTML ─────────────────────────────────────────────────────────
+
+/// Render `fragment` as a JsonML tree whose root is a fragment element.
+///
+/// Mirrors `toJsonMl()` in the upstream TypeScript.
+pub fn to_json_ml(fragment: &Fragment, tags: &TagMap) -> JsonMlNode {
+    element(Tag::Fragment, None, block_nodes(&fragment.root, tags))
+}
+
+/// Render `fragment` as compact HTML.
+///
+/// Mirrors `toHtml()` in the upstream TypeScript.
+pub fn to_html(fragment: &Fragment, tags: &TagMap) -> String {
+    json_ml::to_html(&to_json_ml(fragment, tags), "", "")
+}
+
+fn block_nodes(block: &Block, tags: &TagMap) -> Vec<JsonMlNode> {
+    block
+        .children
+        .iter()
+        .map(|child| {
+            let children = if child.is_leaf() {
+                inline_nodes(&child.inlines, tags)
+            } else {
+                block_nodes(child, tags)
+            };
+            let tag = match child.tag().and_then(|tag| tags.name(tag)) {
+                Some(name) => Tag::Named(name.to_string()),
+                None => Tag::Fragment,
+            };
+            element(tag, attrs(child.data.as_ref()), children)
+        })
+        .collect()
+}
+
+/// Inline content with adjacent runs sharing the wrappers of their common
+/// leading marks.
+fn inline_nodes(runs: &[InlineRun], tags: &TagMap) -> Vec<JsonMlNode> {
+    let mut stack: Vec<(Option<Mark>, Vec<JsonMlNode>)> = vec![(None, Vec::new())];
+    for run in runs {
+        let marks = marks(run, tags);
+        let open = stack.len() - 1;
+        let shared = (0..open.min(marks.len()))
+            .take_while(|i| stack[i + 1].0.as_ref() == Some(&marks[*i]))
+            .count();
+        for _ in shared..open {
+            close_mark(&mut stack);
+        }
+        for mark in &marks[shared..] {
+            stack.push((Some(mark.clone()), Vec::new()));
+        }
+        stack
+            .last_mut()
+            .unwrap()
+            .1
+            .push(JsonMlNode::Text(run.text.clone()));
+    }
+    while stack.len() > 1 {
+        close_mark(&mut stack);
+    }
+    stack.pop().unwrap().1
+}
+
+fn close_mark(stack: &mut Vec<(Option<Mark>, Vec<JsonMlNode>)>) {
+    let (mark, children) = stack.pop().unwrap();
+    let mark = mark.unwrap();
+    let node = element(Tag::Named(mark.name), mark.attrs, children);
+    stack.last_mut().unwrap().1.push(node);
+}
+
+fn element(
+    tag: Tag,
+    attrs: Option<Vec<(String, String)>>,
+    children: Vec<JsonMlNode>,
+) -> JsonMlNode {
+    JsonMlNode::Element(JsonMlElement {
+        tag,
+        attrs,
+        children,
+    })
+}
+
+// ── Markdown ──────────────────────────────────────────────────────────────
+
+/// Render `fragment` as Markdown.
+///
+/// Mirrors `toMarkdown()` in the upstream TypeScript.
+pub fn to_markdown(fragment: &Fragment, tags: &TagMap) -> String {
+    md_blocks(&fragment.root.children, tags, "\n\n")
+}
+
+fn md_blocks(blocks: &[Block], tags: &TagMap, separator: &str) -> String {
+    blocks
+        .iter()
+        .map(|block| md_block(block, tags))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn md_block(block: &Block, tags: &TagMap) -> String {
+    let name = block.tag().and_then(|tag| tags.name(tag)).unwrap_or("");
+    if block.is_leaf() {
+        return match name {
+            "pre" => {
+                let text = block.text();
+                let fence = "`".repeat(longest_run(&text, '`').max(2) + 1);
+                format!("{}\n{}\n{}", fence, text, fence)
+            }
+            _ => {
+                let text = md_inline(&block.inlines, tags);
+                match heading_level(name) {
+                    Some(level) => format!("{} {}", "#".repeat(level), text),
+                    None => text
+                        .split('\n')
+                        .map(md_escape_line)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                }
+            }
+        };
+    }
+    match name {
+        "ul" | "ol" => block
+            .children
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let bullet = match name {
+                    "ol" => format!("{}. ", i + 1),
+                    _ => "- ".to_string(),
+                };
+                let indent = " ".repeat(bullet.len());
+                let body = md_block(item, tags);
+                let mut lines = body.lines();
+                let first = lines.next().unwrap_or("");
+                let mut out = format!("{}{}", bullet, first);
+                for line in lines {
+                    out.push('\n');
+                    if !line.is_empty() {
+                        out.push_str(&indent);
+                        out.push_str(line);
+                    }
+                }
+                out
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "li" => md_blocks(&block.children, tags, "\n"),
+        "blockquote" => md_blocks(&block.children, tags, "\n\n")
+            .lines()
+            .map(|line| match line {
+                "" => ">".to_string(),
+                _ => format!("> {}", line),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => md_blocks(&block.children, tags, "\n\n"),
+    }
+}
+
+fn heading_level(name: &str) -> Option<usize> {
+    let level = name.strip_prefix('h')?.parse().ok()?;
+    (1..=6).contains(&level).then_some(level)
+}
+
+/// Inline Markdown, keeping marks shared by adjacent runs open across them.
+fn md_inline(runs: &[InlineRun], tags: &TagMap) -> String {
+    let run_marks: Vec<Vec<(String, String)>> = runs
+        .iter()
+        .map(|run| marks(run, tags).iter().filter_map(md_mark).collect())
+        .collect();
+    let mut out = String::new();
+    // Open marks with the syntax that closes them.
+    let mut open: Vec<(&(String, String), String)> = Vec::new();
+    for (i, (run, marks)) in runs.iter().zip(&run_marks).enumerate() {
+        let shared = open
+            .iter()
+            .zip(marks)
+            .take_while(|((a, _), b)| *a == *b)
+            .count();
+        for (_, close) in open.drain(shared..).rev() {
+            out.push_str(&close);
+        }
+        for (depth, mark) in marks.iter().enumerate().skip(shared) {
+            if mark.0 != "`" {
+                out.push_str(&mark.0);
+                open.push((mark, mark.1.clone()));
+                continue;
+            }
+            // The code span lasts as long as the marks up to it stay open.
+            let code: String = runs[i..]
+                .iter()
+                .zip(&run_marks[i..])
+                .take_while(|(_, next)| next.get(..=depth) == Some(&marks[..=depth]))
+                .map(|(run, _)| run.text.as_str())
+                .collect();
+            let (start, close) = code_fence(&code);
+            out.push_str(&start);
+            open.push((mark, close));
+        }
+        if open.iter().any(|((start, _), _)| start == "`") {
+            out.push_str(&run.text);
+        } else {
+            out.push_str(&md_escape(&run.text));
+        }
+    }
+    for (_, close) in open.into_iter().rev() {
+        out.push_str(&close);
+    }
+    out
+}
+
+/// Opening and closing syntax of a code span holding `code`: a backtick
+/// fence longer than any backtick run inside, padded with spaces if `code`
+/// starts or ends with a backtick or a space.
+fn code_fence(code: &str) -> (String, String) {
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    let padded = !code.trim_matches(' ').is_empty()
+        && (code.starts_with(['`', ' ']) || code.ends_with(['`', ' ']));
+    if padded {
+        (format!("{} ", fence), format!(" {}", fence))
+    } else {
+        (fence.clone(), fence)
+    }
+}
+
+/// Length of the longest run of `ch` in `text`.
+fn longest_run(text: &str, ch: char) -> usize {
+    text.split(|c| c != ch).map(str::len).max().unwrap_or(0)
+}
+
+/// Opening and closing syntax of an inline mark.
+fn md_mark(mark: &Mark) -> Option<(String, String)> {
+    let (start, end) = match mark.name.as_str() {
+        "b" | "strong" => ("**", "**".to_string()),
+        "i" | "em" => ("_", "_".to_string()),
+        "s" | "del" | "strike" => ("~~", "~~".to_string()),
+        "code" => ("`", "`".to_string()),
+        "a" => {
+            let href = mark
+                .attrs
+                .iter()
+                .flatten()
+                .find(|(key, _)| key == "href")
+                .map_or("", |(_, value)| value.as_str());
+            ("[", format!("]({})", md_escape_href(href)))
+        }
+        _ => return None,
+    };
+    Some((start.to_string(), end))
+}
+
+fn md_escape(text: &str) -> String {
+    escape_chars(text, |ch| {
+        matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']' | '~')
+    })
+}
+
+/// Escapes a link destination so that it ends at the closing `)`.
+fn md_escape_href(href: &str) -> String {
+    escape_chars(href, |ch| matches!(ch, '\\' | '(' | ')'))
+}
+
+/// Escapes the start of a paragraph line that would otherwise begin a
+/// heading, block quote or list item.
+fn md_escape_line(line: &str) -> String {
+    let text = line.trim_start();
+    let lead = &line[..line.len() - text.len()];
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let at = if text.starts_with(['#', '>', '-', '+']) {
+        0
+    } else if digits > 0 && text[digits..].starts_with(['.', ')']) {
+        digits
+    } else {
+        return line.to_string();
+    };
+    format!("{}{}\\{}", lead, &text[..at], &text[at..])
+}
+
+fn escape_chars(text: &str, special: impl Fn(char) -> bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if special(ch) {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+// ── Plain text ────────────────────────────────────────────────────────────
+
+/// Render `fragment` as plain text, one line per leaf block.
+pub fn to_text(fragment: &Fragment) -> String {
+    fragment
+        .leaves()
+        .iter()
+        .map(|leaf| leaf.text())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// An inline element wrapping the text of a run.
+#[derive(Debug, Clone, PartialEq)]
+struct Mark {
+    name: String,
+    attrs: Option<Vec<(String, String)>>,
+}
+
+/// Inline marks of `run` in attribute order, outermost first. Cursors and
+/// types without an element name are skipped.
+fn marks(run: &InlineRun, tags: &TagMap) -> Vec<Mark> {
+    run.attrs
+        .iter()
+        .filter_map(|(slice_type, contributions)| {
+            let last = contributions.last()?;
+            if last.stacking == SliceStacking::Cursor {
+                return None;
+            }
+            let name = tags.name(inline_tag(slice_type)?)?;
+            Some(Mark {
+                name: name.to_string(),
+                attrs: attrs(last.data.as_ref()),
+            })
+        })
+        .collect()
+}
+
+fn inline_tag(slice_type: &SliceType) -> Option<&TypeTag> {
+    match slice_type {
+        SliceType::Simple(tag) => Some(tag),
+        SliceType::Steps(steps) => steps.last(),
+    }
+}
+
+/// Element attributes from slice data: the entries of a JSON object.
+fn attrs(data: Option<&Value>) -> Option<Vec<(String, String)>> {
+    let Value::Object(map) = data? else {
+        return None;
+    };
+    let attrs = map
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect();
+    Some(attrs)
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt::model::Model;
+    use crate::json_crdt_extensions::peritext::slice::constants::*;
+    use crate::json_crdt_extensions::peritext::test_util::setup;
+    use crate::json_crdt_extensions::peritext::{Peritext, Range};
+    use crate::json_crdt_patch::clock::Ts;
+    use serde_json::json;
+
+    fn marker(model: &mut Model, pt: &Peritext, pos: usize, steps: &[i64]) -> Ts {
+        let start = pt.range_at(model, pos, 1).unwrap().start;
+        let slice_type = SliceType::Steps(steps.iter().map(|t| TypeTag::Int(*t)).collect());
+        pt.saved_slices
+            .ins_marker(model, &Range::new(start, start), slice_type, None)
+    }
+
+    fn format(
+        model: &mut Model,
+        pt: &Peritext,
+        pos: usize,
+        len: usize,
+        slice_type: impl Into<SliceType>,
+        data: Option<Value>,
+    ) {
+        let range = pt.range_at(model, pos, len).unwrap();
+        pt.saved_slices.ins_stack(model, &range, slice_type, data);
+    }
+
+    /// "Title\nSome bold and linked text\none\ntwo", with the heading,
+    /// paragraph and list items marked.
+    fn document() -> (Model, Peritext) {
+        let (mut model, pt) = setup("\nTitle\nSome bold and linked text\none\ntwo");
+        marker(&mut model, &pt, 0, &[TYPE_H2]);
+        marker(&mut model, &pt, 6, &[TYPE_P]);
+        marker(&mut model, &pt, 32, &[TYPE_UL, TYPE_LI]);
+        marker(&mut model, &pt, 36, &[TYPE_UL, TYPE_LI]);
+        format(&mut model, &pt, 12, 15, TYPE_BOLD, None);
+        let link = json!({"href": "https://x.io"});
+        format(&mut model, &pt, 21, 6, TYPE_LINK, Some(link));
+        (model, pt)
+    }
+
+    #[test]
+    fn exports_html() {
+        let (model, pt) = document();
+        let html = to_html(&pt.fragment(&model), &TagMap::default());
+        assert_eq!(
+            html,
+            "<h2>Title</h2><p>Some <b>bold and <a href=\"https://x.io\">linked</a></b> \
+             text</p><ul><li>one</li><li>two</li></ul>"
+        );
+    }
+
+    #[test]
+    fn exports_markdown() {
+        let (model, pt) = document();
+        let md = to_markdown(&pt.fragment(&model), &TagMap::default());
+        assert_eq!(
+            md,
+            "## Title\n\nSome **bold and [linked](https://x.io)** text\n\n- one\n- two"
+        );
+    }
+
+    #[test]
+    fn exports_plain_text() {
+        let (model, pt) = document();
+        assert_eq!(
+            to_text(&pt.fragment(&model)),
+            "Title\nSome bold and linked text\none\ntwo"
+        );
+    }
+
+    #[test]
+    fn tag_map_is_configurable() {
+        let (mut model, pt) = setup("hi there");
+        format(&mut model, &pt, 0, 2, TYPE_BOLD, None);
+        format(&mut model, &pt, 3, 5, "mark", None);
+        let tags = TagMap::default()
+            .with(TYPE_BOLD, "strong")
+            .with(TYPE_P, "div");
+        assert_eq!(
+            to_html(&pt.fragment(&model), &tags),
+            "<div><strong>hi</strong> <mark>there</mark></div>"
+        );
+        // Unmapped integer tags leave their content unwrapped.
+        let tags = TagMap::new();
+        assert_eq!(
+            to_html(&pt.fragment(&model), &tags),
+            "hi <mark>there</mark>"
+        );
+    }
+
+    #[test]
+    fn markdown_nests_blockquotes_and_code() {
+        let (mut model, pt) = setup("\nquote *me*\nlet x = 1;");
+        marker(&mut model, &pt, 0, &[TYPE_BLOCKQUOTE, TYPE_P]);
+        marker(&mut model, &pt, 11, &[TYPE_CODEBLOCK]);
+        let md = to_markdown(&pt.fragment(&model), &TagMap::default());
+        assert_eq!(md, "> quote \\*me\\*\n\n```\nlet x = 1;\n```");
+    }
+
+    #[test]
+    fn json_ml_root_is_a_fragment() {
+        let (model, pt) = setup("plain");
+        let node = to_json_ml(&pt.fragment(&model), &TagMap::default());
+        let JsonMlNode::Element(root) = node else {
+            panic!("expected element");
+        };
+        assert!(root.tag.is_fragment());
+        assert_eq!(root.children.len(), 1);
+    }
+}
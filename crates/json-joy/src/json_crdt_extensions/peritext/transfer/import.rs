@@ -0,0 +1,511 @@
+//! Import of JsonML, HTML and Markdown into Peritext documents.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/transfer/import-html.ts`
+//! and `import-markdown.ts`.
+//!
+//! Content is flattened into text, inline slices and split markers, then
+//! inserted at a position of the document. Element names are resolved to
+//! slice types through [`TagMap::tag`]; numeric JsonML tags are used as
+//! slice types directly.
+//!
+//! - Block elements (tags `>= 0`, or HTML block names) end the current leaf
+//!   block. `blockquote`, `ul`, `ol`, and `li` elements holding blocks
+//!   become containers: their tag is a leading step of the marker paths of
+//!   the leaves inside them. Every other block becomes a leaf starting with
+//!   a marker on a `\n` character.
+//! - Inline elements with a tag become slices over their text: `One`
+//!   stacking if the element has attributes (kept as the slice data), else
+//!   `Many`. Unknown inline elements are unwrapped.
+//! - Whitespace is collapsed as in HTML, except inside `pre` blocks.
+
+use serde_json::{Map, Value};
+
+use super::{markdown, TagMap};
+use crate::json_crdt::model::Model;
+use crate::json_crdt_extensions::peritext::slice::constants::{TYPE_CODEBLOCK, TYPE_P};
+use crate::json_crdt_extensions::peritext::slice::{SliceType, TypeTag};
+use crate::json_crdt_extensions::peritext::{Peritext, Range};
+use crate::json_ml::{self, JsonMlElement, JsonMlNode, Tag, MAX_DEPTH};
+
+/// Block elements without a slice type; they only end the current block.
+const TRANSPARENT_BLOCKS: &[&str] = &[
+    "address", "article", "aside", "body", "div", "figure", "footer", "form", "header", "html",
+    "main", "nav", "section", "table", "tbody", "td", "th", "thead", "tr",
+];
+
+/// HTML elements treated as blocks when mapped to a string tag.
+const BLOCKS: &[&str] = &[
+    "blockquote",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "ul",
+];
+
+/// Elements whose content is dropped.
+const SKIPPED: &[&str] = &["head", "script", "style", "title"];
+
+/// Insert `node` at visible position `pos`; returns the length of the
+/// inserted text.
+///
+/// Mirrors `fromJsonMl()` in the upstream TypeScript.
+pub fn from_json_ml(
+    peritext: &Peritext,
+    model: &mut Model,
+    pos: usize,
+    node: &JsonMlNode,
+    tags: &TagMap,
+) -> usize {
+    let mut importer = Importer::new(tags);
+    importer.node(node);
+    importer.end_leaf();
+    importer.apply(peritext, model, pos)
+}
+
+/// Insert the content of `html` at visible position `pos`; returns the
+/// length of the inserted text.
+///
+/// Mirrors `fromHtml()` in the upstream TypeScript.
+pub fn from_html(
+    peritext: &Peritext,
+    model: &mut Model,
+    pos: usize,
+    html: &str,
+    tags: &TagMap,
+) -> usize {
+    from_json_ml(peritext, model, pos, &json_ml::from_html(html), tags)
+}
+
+/// Insert the content of `markdown` at visible position `pos`; returns the
+/// length of the inserted text.
+///
+/// Mirrors `fromMarkdown()` in the upstream TypeScript.
+pub fn from_markdown(
+    peritext: &Peritext,
+    model: &mut Model,
+    pos: usize,
+    markdown: &str,
+    tags: &TagMap,
+) -> usize {
+    from_json_ml(peritext, model, pos, &markdown::parse(markdown), tags)
+}
+
+// ── Importer ──────────────────────────────────────────────────────────────
+
+/// An inline slice, in UTF-16 offsets into the imported text.
+struct Span {
+    start: usize,
+    end: usize,
+    tag: TypeTag,
+    data: Option<Value>,
+}
+
+struct Importer<'t> {
+    tags: &'t TagMap,
+    text: String,
+    /// Length of `text` in UTF-16 code units.
+    len: usize,
+    spans: Vec<Span>,
+    /// Marker positions and block paths.
+    markers: Vec<(usize, Vec<TypeTag>)>,
+    /// Tags of the open containers.
+    path: Vec<TypeTag>,
+    /// Start of the open leaf block, if any.
+    leaf: Option<usize>,
+    pre: bool,
+    /// Number of elements being imported.
+    depth: usize,
+}
+
+impl<'t> Importer<'t> {
+    fn new(tags: &'t TagMap) -> Self {
+        Self {
+            tags,
+            text: String::new(),
+            len: 0,
+            spans: Vec::new(),
+            markers: Vec::new(),
+            path: Vec::new(),
+            leaf: None,
+            pre: false,
+            depth: 0,
+        }
+    }
+
+    fn node(&mut self, node: &JsonMlNode) {
+        match node {
+            JsonMlNode::Text(text) => self.text(text),
+            JsonMlNode::Element(el) => self.element(el),
+        }
+    }
+
+    fn children(&mut self, el: &JsonMlElement) {
+        for child in &el.children {
+            self.node(child);
+        }
+    }
+
+    fn element(&mut self, el: &JsonMlElement) {
+        if self.depth >= MAX_DEPTH {
+            return self.flatten(el);
+        }
+        self.depth += 1;
+        self.import(el);
+        self.depth -= 1;
+    }
+
+    /// Import only the text inside `el`, without recursion.
+    fn flatten(&mut self, el: &JsonMlElement) {
+        let mut stack: Vec<&JsonMlNode> = el.children.iter().rev().collect();
+        while let Some(node) = stack.pop() {
+            match node {
+                JsonMlNode::Text(text) => self.text(text),
+                JsonMlNode::Element(el) => stack.extend(el.children.iter().rev()),
+            }
+        }
+    }
+
+    fn import(&mut self, el: &JsonMlElement) {
+        let (name, tag) = match &el.tag {
+            Tag::Fragment => return self.children(el),
+            Tag::Numeric(n) => (String::new(), Some(TypeTag::Int(*n))),
+            Tag::Named(name) => {
+                let name = name.to_ascii_lowercase();
+                let tag = self.tags.tag(&name);
+                (name, tag)
+            }
+        };
+        if SKIPPED.contains(&name.as_str()) {
+            return;
+        }
+        if name == "br" {
+            return self.push("\n");
+        }
+        match tag {
+            Some(tag) if self.is_block(&tag, &name) => {
+                self.end_leaf();
+                if self.is_container(el, &name) {
+                    self.path.push(tag);
+                    self.children(el);
+                    self.end_leaf();
+                    self.path.pop();
+                } else {
+                    let pre = self.pre;
+                    self.pre = name == "pre" || tag == TypeTag::Int(TYPE_CODEBLOCK);
+                    self.open_leaf(tag);
+                    self.children(el);
+                    self.end_leaf();
+                    self.pre = pre;
+                }
+            }
+            _ if TRANSPARENT_BLOCKS.contains(&name.as_str()) => {
+                self.end_leaf();
+                self.children(el);
+                self.end_leaf();
+            }
+            Some(tag) if !self.pre => {
+                // Outer spans precede inner ones, so they get older slice IDs.
+                let (start, index) = (self.len, self.spans.len());
+                self.children(el);
+                if self.len > start {
+                    self.spans.insert(
+                        index,
+                        Span {
+                            start,
+                            end: self.len,
+                            tag,
+                            data: data(el.attrs.as_deref()),
+                        },
+                    );
+                }
+            }
+            _ => self.children(el),
+        }
+    }
+
+    fn is_block(&self, tag: &TypeTag, name: &str) -> bool {
+        match tag {
+            TypeTag::Int(n) => *n >= 0,
+            TypeTag::Str(_) => BLOCKS.contains(&name),
+        }
+    }
+
+    fn is_container(&self, el: &JsonMlElement, name: &str) -> bool {
+        match name {
+            "blockquote" | "ul" | "ol" => true,
+            "li" => el.children.iter().any(|child| match child {
+                JsonMlNode::Element(child) => match &child.tag {
+                    Tag::Named(name) => {
+                        let name = name.to_ascii_lowercase();
+                        BLOCKS.contains(&name.as_str())
+                            || TRANSPARENT_BLOCKS.contains(&name.as_str())
+                    }
+                    Tag::Numeric(n) => *n >= 0,
+                    Tag::Fragment => false,
+                },
+                JsonMlNode::Text(_) => false,
+            }),
+            _ => false,
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre {
+            if self.leaf.is_none() {
+                self.open_leaf(TypeTag::Int(TYPE_CODEBLOCK));
+            }
+            return self.push(text);
+        }
+        let mut collapsed = String::with_capacity(text.len());
+        for word in text.split_ascii_whitespace() {
+            if !collapsed.is_empty() {
+                collapsed.push(' ');
+            }
+            collapsed.push_str(word);
+        }
+        let lead = text.starts_with(|c: char| c.is_ascii_whitespace());
+        let trail = text.ends_with(|c: char| c.is_ascii_whitespace());
+        if collapsed.is_empty() {
+            if self.leaf.is_some() && lead {
+                self.space();
+            }
+            return;
+        }
+        if self.leaf.is_none() && (!self.path.is_empty() || !self.markers.is_empty()) {
+            self.open_leaf(TypeTag::Int(TYPE_P));
+        }
+        if lead {
+            self.space();
+        }
+        self.push(&collapsed);
+        if trail {
+            self.space();
+        }
+    }
+
+    /// A collapsed space, unless at the start of a block or after another.
+    fn space(&mut self) {
+        let at_start = self.leaf.map_or(self.len == 0, |start| start == self.len);
+        if !at_start && !self.text.ends_with([' ', '\n']) {
+            self.push(" ");
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.len += text.encode_utf16().count();
+    }
+
+    fn open_leaf(&mut self, tag: TypeTag) {
+        let mut path = self.path.clone();
+        path.push(tag);
+        self.markers.push((self.len, path));
+        self.push("\n");
+        self.leaf = Some(self.len);
+    }
+
+    /// Close the open leaf block, dropping a trailing collapsed space.
+    fn end_leaf(&mut self) {
+        if self.leaf.take().is_some() && !self.pre && self.text.ends_with(' ') {
+            self.text.pop();
+            self.len -= 1;
+            for span in &mut self.spans {
+                span.end = span.end.min(self.len);
+            }
+            self.spans.retain(|span| span.start < span.end);
+        }
+    }
+
+    fn apply(self, peritext: &Peritext, model: &mut Model, pos: usize) -> usize {
+        if self.text.is_empty() {
+            return 0;
+        }
+        peritext.ins_at(model, pos, &self.text);
+        let slices = &peritext.saved_slices;
+        for (at, path) in self.markers {
+            let Some(range) = peritext.range_at(model, pos + at, 1) else {
+                continue;
+            };
+            let slice_type = match <[TypeTag; 1]>::try_from(path) {
+                Ok([tag]) => SliceType::Simple(tag),
+                Err(path) => SliceType::Steps(path),
+            };
+            let point = range.start;
+            slices.ins_marker(model, &Range::new(point, point), slice_type, None);
+        }
+        for span in self.spans {
+            let Some(range) = peritext.range_at(model, pos + span.start, span.end - span.start)
+            else {
+                continue;
+            };
+            if span.data.is_some() {
+                slices.ins_one(model, &range, span.tag, span.data);
+            } else {
+                slices.ins_stack(model, &range, span.tag, None);
+            }
+        }
+        self.len
+    }
+}
+
+/// Slice data from element attributes: an object of their string values.
+fn data(attrs: Option<&[(String, String)]>) -> Option<Value> {
+    let attrs = attrs.filter(|attrs| !attrs.is_empty())?;
+    let map: Map<String, Value> = attrs
+        .iter()
+        .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+        .collect();
+    Some(Value::Object(map))
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt_extensions::peritext::slice::constants::*;
+    use crate::json_crdt_extensions::peritext::test_util::setup;
+    use crate::json_crdt_extensions::peritext::transfer::{to_html, to_markdown};
+    use serde_json::json;
+
+    fn html(model: &Model, pt: &Peritext) -> String {
+        to_html(&pt.fragment(model), &TagMap::default())
+    }
+
+    #[test]
+    fn imports_html_blocks_and_marks() {
+        let (mut model, pt) = setup("");
+        let source =
+            "<h1>Title</h1>\n<p>Some <b>bold and <a href=\"/x\">linked</a></b>\n   text</p>\
+                      <ul><li>one</li><li>two</li></ul>";
+        let len = from_html(&pt, &mut model, 0, source, &TagMap::default());
+        assert_eq!(
+            pt.text(&model),
+            "\nTitle\nSome bold and linked text\none\ntwo"
+        );
+        assert_eq!(len, 40);
+        assert_eq!(
+            html(&model, &pt),
+            "<h1>Title</h1><p>Some <b>bold and <a href=\"/x\">linked</a></b> text</p>\
+             <ul><li>one</li><li>two</li></ul>"
+        );
+        let link = pt
+            .saved_slices
+            .iter_slices(&model)
+            .into_iter()
+            .find(|slice| slice.slice_type == SliceType::from(TYPE_LINK))
+            .unwrap();
+        assert_eq!(link.data, Some(json!({"href": "/x"})));
+    }
+
+    #[test]
+    fn inline_html_adds_no_markers() {
+        let (mut model, pt) = setup("[]");
+        from_html(&pt, &mut model, 1, "a <i>b</i> &amp; c", &TagMap::default());
+        assert_eq!(pt.text(&model), "[a b & c]");
+        assert_eq!(html(&model, &pt), "<p>[a <i>b</i> &#38; c]</p>");
+    }
+
+    #[test]
+    fn imports_nested_containers() {
+        let (mut model, pt) = setup("");
+        let source = "<blockquote><p>q</p></blockquote>\
+                      <ul><li>a<ul><li>b</li></ul></li></ul><pre>x  <b>y</b></pre>";
+        from_html(&pt, &mut model, 0, source, &TagMap::default());
+        assert_eq!(
+            html(&model, &pt),
+            "<blockquote><p>q</p></blockquote><ul><li><p>a</p><ul><li>b</li></ul></li></ul>\
+             <pre>x  y</pre>"
+        );
+    }
+
+    #[test]
+    fn markdown_round_trips() {
+        let source = "## Title\n\nSome **bold and [linked](https://x.io)** _text_\n\n\
+                      > quoted `code`\n\n- one\n  1. nested\n- two\n\n```\nlet x = 1;\n```";
+        let (mut model, pt) = setup("");
+        from_markdown(&pt, &mut model, 0, source, &TagMap::default());
+        let md = to_markdown(&pt.fragment(&model), &TagMap::default());
+        assert_eq!(md, source);
+    }
+
+    #[test]
+    fn escaped_markdown_round_trips() {
+        let source = "\\# hash\n\n1\\. one\n\n\\> quote\n\n\\- dash\n\n\
+                      [link](/a\\(b\\)) ``a`b`` `` `x ``";
+        let (mut model, pt) = setup("");
+        from_markdown(&pt, &mut model, 0, source, &TagMap::default());
+        assert_eq!(
+            html(&model, &pt),
+            "<p># hash</p><p>1. one</p><p>&#62; quote</p><p>- dash</p>\
+             <p><a href=\"/a(b)\">link</a> <code>a`b</code> <code>`x</code></p>"
+        );
+        let md = to_markdown(&pt.fragment(&model), &TagMap::default());
+        assert_eq!(md, source);
+    }
+
+    #[test]
+    fn code_blocks_with_fences_round_trip() {
+        let source = "````\n```\nnested\n```\n````\n\n~~~~ rust\nlet x = 1;\n~~~\n~~~~";
+        let (mut model, pt) = setup("");
+        from_markdown(&pt, &mut model, 0, source, &TagMap::default());
+        assert_eq!(
+            html(&model, &pt),
+            "<pre>```\nnested\n```</pre><pre>let x = 1;\n~~~</pre>"
+        );
+        let md = to_markdown(&pt.fragment(&model), &TagMap::default());
+        assert_eq!(
+            md,
+            "````\n```\nnested\n```\n````\n\n```\nlet x = 1;\n~~~\n```"
+        );
+    }
+
+    #[test]
+    fn deep_html_is_flattened() {
+        let n = 100_000;
+        let (mut model, pt) = setup("");
+        let source = format!("<p>{}x{}</p>", "<b>".repeat(n), "</b>".repeat(n));
+        from_html(&pt, &mut model, 0, &source, &TagMap::default());
+        assert_eq!(html(&model, &pt), "<p><b>x</b></p>");
+
+        // Hand-built JsonML is not capped by the HTML parser.
+        let mut node = JsonMlNode::Text("y".into());
+        for _ in 0..2 * MAX_DEPTH {
+            node = JsonMlNode::Element(JsonMlElement {
+                tag: Tag::Named("i".into()),
+                attrs: None,
+                children: vec![node],
+            });
+        }
+        let (mut model, pt) = setup("");
+        from_json_ml(&pt, &mut model, 0, &node, &TagMap::default());
+        assert_eq!(html(&model, &pt), "<p><i>y</i></p>");
+    }
+
+    #[test]
+    fn json_ml_numeric_tags_are_slice_types() {
+        let (mut model, pt) = setup("");
+        let node = JsonMlNode::Element(JsonMlElement {
+            tag: Tag::Numeric(TYPE_H3),
+            attrs: None,
+            children: vec![JsonMlNode::Element(JsonMlElement {
+                tag: Tag::Numeric(TYPE_CODE),
+                attrs: None,
+                children: vec![JsonMlNode::Text("x".into())],
+            })],
+        });
+        from_json_ml(&pt, &mut model, 0, &node, &TagMap::new());
+        let tags = TagMap::default();
+        assert_eq!(
+            to_html(&pt.fragment(&model), &tags),
+            "<h3><code>x</code></h3>"
+        );
+    }
+}
@@ -0,0 +1,421 @@
+//! Markdown to JsonML parser used by the importer.
+//!
+//! Not part of the upstream TypeScript. Covers the subset of Markdown the
+//! exporter produces: ATX headings, paragraphs, block quotes, fenced code
+//! blocks, nested bullet and ordered lists, and the `**`, `__`, `*`, `_`,
+//! `~~`, `` ` `` and `[label](href)` inline forms with backslash escapes.
+//! Elements are named as in HTML (`h2`, `b`, `a`, …).
+//!
+//! Block quotes, list items and inline spans nest by recursion, so input
+//! nested deeper than `MAX_DEPTH` is kept as plain text instead.
+
+use crate::json_ml::{JsonMlElement, JsonMlNode, Tag, MAX_DEPTH};
+
+/// Parse `markdown` into a fragment element holding its blocks.
+pub(super) fn parse(markdown: &str) -> JsonMlNode {
+    let lines: Vec<&str> = markdown.lines().collect();
+    element(Tag::Fragment, None, blocks(&lines, 0))
+}
+
+// ── Blocks ────────────────────────────────────────────────────────────────
+
+fn blocks(lines: &[&str], depth: usize) -> Vec<JsonMlNode> {
+    if depth > MAX_DEPTH {
+        let body = lines.iter().map(|line| line.trim()).collect::<Vec<_>>();
+        return vec![named("p", vec![text(body.join(" ").trim().to_string())])];
+    }
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if line.is_empty() {
+            i += 1;
+        } else if let Some(fence) = fence(line) {
+            let mut body = Vec::new();
+            i += 1;
+            while i < lines.len() && !closes(lines[i], fence) {
+                body.push(lines[i]);
+                i += 1;
+            }
+            i += 1;
+            out.push(named("pre", vec![text(body.join("\n"))]));
+        } else if let Some((level, content)) = heading(line) {
+            out.push(named(&format!("h{}", level), inline(content, depth)));
+            i += 1;
+        } else if line.starts_with('>') {
+            let mut body = Vec::new();
+            while i < lines.len() {
+                let Some(quoted) = lines[i].trim_start().strip_prefix('>') else {
+                    break;
+                };
+                body.push(quoted.strip_prefix(' ').unwrap_or(quoted));
+                i += 1;
+            }
+            out.push(named("blockquote", blocks(&body, depth + 1)));
+        } else if let Some(first) = list_item(lines[i]) {
+            let mut items = Vec::new();
+            while i < lines.len() {
+                let Some(item) = list_item(lines[i]) else {
+                    break;
+                };
+                if item.indent != first.indent || item.ordered != first.ordered {
+                    break;
+                }
+                let mut body = vec![item.content];
+                i += 1;
+                while i < lines.len() {
+                    let line = lines[i];
+                    if line.trim().is_empty() {
+                        let continues = lines
+                            .get(i + 1)
+                            .is_some_and(|next| indent(next) >= item.content_indent);
+                        if !continues {
+                            break;
+                        }
+                        body.push("");
+                    } else if indent(line) >= item.content_indent {
+                        body.push(&line[item.content_indent..]);
+                    } else {
+                        break;
+                    }
+                    i += 1;
+                }
+                let mut children = blocks(&body, depth + 1);
+                if let [JsonMlNode::Element(p)] = children.as_mut_slice() {
+                    if p.tag == Tag::Named("p".into()) {
+                        children = std::mem::take(&mut p.children);
+                    }
+                }
+                items.push(named("li", children));
+            }
+            out.push(named(if first.ordered { "ol" } else { "ul" }, items));
+        } else {
+            let mut para = Vec::new();
+            while i < lines.len() {
+                let line = lines[i].trim_start();
+                let starts_block = fence(line).is_some()
+                    || heading(line).is_some()
+                    || line.starts_with('>')
+                    || list_item(lines[i]).is_some();
+                if line.is_empty() || (starts_block && !para.is_empty()) {
+                    break;
+                }
+                para.push(line.trim_end());
+                i += 1;
+            }
+            out.push(named("p", inline(&para.join(" "), depth)));
+        }
+    }
+    out
+}
+
+/// The opening run of three or more backticks or tildes of a code fence.
+fn fence(line: &str) -> Option<&str> {
+    let ch = line.chars().next().filter(|ch| matches!(ch, '`' | '~'))?;
+    let len = line.len() - line.trim_start_matches(ch).len();
+    (len >= 3).then(|| &line[..len])
+}
+
+/// Whether `line` closes a code block opened by `open`: a run of the same
+/// character at least as long, followed by nothing but whitespace.
+fn closes(line: &str, open: &str) -> bool {
+    let line = line.trim();
+    fence(line).is_some_and(|run| {
+        run.len() == line.len() && run.len() >= open.len() && run[..1] == open[..1]
+    })
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level, rest.trim()))
+}
+
+struct ListItem<'s> {
+    indent: usize,
+    ordered: bool,
+    /// Column at which the item's content starts.
+    content_indent: usize,
+    content: &'s str,
+}
+
+fn list_item(line: &str) -> Option<ListItem<'_>> {
+    let indent = indent(line);
+    let rest = &line[indent..];
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let (ordered, marker_len) = if digits > 0 {
+        let after = &rest[digits..];
+        if !(after.starts_with(". ") || after.starts_with(") ")) {
+            return None;
+        }
+        (true, digits + 2)
+    } else if ["- ", "* ", "+ "].iter().any(|m| rest.starts_with(m)) {
+        (false, 2)
+    } else {
+        return None;
+    };
+    Some(ListItem {
+        indent,
+        ordered,
+        content_indent: indent + marker_len,
+        content: rest[marker_len..].trim(),
+    })
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+// ── Inlines ───────────────────────────────────────────────────────────────
+
+const DELIMITERS: &[(&str, &str)] = &[
+    ("**", "b"),
+    ("__", "b"),
+    ("~~", "s"),
+    ("*", "i"),
+    ("_", "i"),
+];
+
+fn inline(s: &str, depth: usize) -> Vec<JsonMlNode> {
+    if depth > MAX_DEPTH {
+        return vec![text(s.to_string())];
+    }
+    let mut out = Vec::new();
+    let mut buf = String::new();
+    let mut i = 0;
+    'scan: while i < s.len() {
+        let rest = &s[i..];
+        let ch = rest.chars().next().unwrap();
+        if ch == '\\' {
+            if let Some(escaped) = rest[1..].chars().next().filter(char::is_ascii_punctuation) {
+                buf.push(escaped);
+                i += 1 + escaped.len_utf8();
+                continue;
+            }
+        }
+        if ch == '`' {
+            let fence = rest.len() - rest.trim_start_matches('`').len();
+            match code_span(&rest[fence..], fence) {
+                Some((code, len)) => {
+                    flush(&mut buf, &mut out);
+                    out.push(named("code", vec![text(code.to_string())]));
+                    i += fence + len;
+                }
+                None => {
+                    buf.push_str(&rest[..fence]);
+                    i += fence;
+                }
+            }
+            continue;
+        }
+        for (delim, name) in DELIMITERS {
+            if !rest.starts_with(delim) {
+                continue;
+            }
+            let intraword = delim.starts_with('_')
+                && s[..i]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_alphanumeric);
+            if intraword {
+                break;
+            }
+            if let Some(end) = closing(&rest[delim.len()..], delim) {
+                flush(&mut buf, &mut out);
+                let inner = &rest[delim.len()..delim.len() + end];
+                out.push(named(name, inline(inner, depth + 1)));
+                i += 2 * delim.len() + end;
+                continue 'scan;
+            }
+        }
+        if ch == '[' {
+            if let Some((label, href, len)) = link(rest) {
+                flush(&mut buf, &mut out);
+                let attrs = vec![("href".to_string(), href)];
+                out.push(element(
+                    Tag::Named("a".into()),
+                    Some(attrs),
+                    inline(label, depth + 1),
+                ));
+                i += len;
+                continue;
+            }
+        }
+        buf.push(ch);
+        i += ch.len_utf8();
+    }
+    flush(&mut buf, &mut out);
+    out
+}
+
+/// Offset of the delimiter closing a span of `s`, which starts just after
+/// the opening one. The span must not start or end with whitespace, and a
+/// single-character delimiter never matches part of a doubled one.
+fn closing(s: &str, delim: &str) -> Option<usize> {
+    if s.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let bytes = s.as_bytes();
+    let d = delim.as_bytes()[0];
+    let mut from = 0;
+    while let Some(offset) = s[from..].find(delim) {
+        let at = from + offset;
+        from = at + 1;
+        let escaped = at > 0 && bytes[at - 1] == b'\\';
+        let doubled =
+            delim.len() == 1 && ((at > 0 && bytes[at - 1] == d) || bytes.get(at + 1) == Some(&d));
+        let spaced = at == 0 || s[..at].ends_with(char::is_whitespace);
+        if !escaped && !doubled && !spaced {
+            return Some(at);
+        }
+        if delim.len() == 1 && bytes.get(at + 1) == Some(&d) {
+            from = at + 2;
+        }
+    }
+    None
+}
+
+/// The code of a span that follows an opening run of `fence` backticks,
+/// and the length of the rest of the span. The span ends at the next run of
+/// exactly `fence` backticks; one space is stripped from each side if the
+/// code has both.
+fn code_span(s: &str, fence: usize) -> Option<(&str, usize)> {
+    let mut from = 0;
+    while let Some(offset) = s[from..].find('`') {
+        let at = from + offset;
+        let run = s[at..].len() - s[at..].trim_start_matches('`').len();
+        if run == fence {
+            let code = &s[..at];
+            let padded = code.len() >= 2
+                && code.starts_with(' ')
+                && code.ends_with(' ')
+                && !code.trim_matches(' ').is_empty();
+            let code = if padded {
+                &code[1..code.len() - 1]
+            } else {
+                code
+            };
+            return Some((code, at + run));
+        }
+        from = at + run;
+    }
+    None
+}
+
+/// `[label](href)` at the start of `s`: the label, the unescaped href and
+/// the length of the whole link.
+fn link(s: &str) -> Option<(&str, String, usize)> {
+    let close = unescaped(s, "](")?;
+    let label = &s[1..close];
+    let rest = &s[close + 2..];
+    let end = unescaped(rest, ")")?;
+    Some((label, unescape(rest[..end].trim()), close + 3 + end))
+}
+
+/// Offset of the first occurrence of `pat` in `s` that is not
+/// backslash-escaped.
+fn unescaped(s: &str, pat: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(pat.as_bytes()) {
+            return Some(i);
+        }
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    None
+}
+
+/// `s` with its backslash escapes resolved.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let escaped = chars.next_if(|next| ch == '\\' && next.is_ascii_punctuation());
+        out.push(escaped.unwrap_or(ch));
+    }
+    out
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+fn flush(buf: &mut String, out: &mut Vec<JsonMlNode>) {
+    if !buf.is_empty() {
+        out.push(text(std::mem::take(buf)));
+    }
+}
+
+fn text(s: String) -> JsonMlNode {
+    JsonMlNode::Text(s)
+}
+
+fn named(name: &str, children: Vec<JsonMlNode>) -> JsonMlNode {
+    element(Tag::Named(name.to_string()), None, children)
+}
+
+fn element(
+    tag: Tag,
+    attrs: Option<Vec<(String, String)>>,
+    children: Vec<JsonMlNode>,
+) -> JsonMlNode {
+    JsonMlNode::Element(JsonMlElement {
+        tag,
+        attrs,
+        children,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_ml::to_html;
+
+    fn html(markdown: &str) -> String {
+        to_html(&parse(markdown), "", "")
+    }
+
+    #[test]
+    fn parses_blocks() {
+        assert_eq!(
+            html("# Title\n\nfirst\nline\n\n> quoted\n\n```\nlet x = *1*;\n```"),
+            "<h1>Title</h1><p>first line</p><blockquote><p>quoted</p></blockquote>\
+             <pre>let x = *1*;</pre>"
+        );
+    }
+
+    #[test]
+    fn parses_nested_lists() {
+        assert_eq!(
+            html("- one\n  1. a\n  2. b\n- two"),
+            "<ul><li><p>one</p><ol><li>a</li><li>b</li></ol></li><li>two</li></ul>"
+        );
+    }
+
+    #[test]
+    fn parses_inline_marks() {
+        assert_eq!(
+            html("a **b _c_** `*d*` ~~e~~ [f](/g) \\*h\\* snake_case_name 2 * 3 * 4"),
+            "<p>a <b>b <i>c</i></b> <code>*d*</code> <s>e</s> <a href=\"/g\">f</a> \
+             *h* snake_case_name 2 * 3 * 4</p>"
+        );
+    }
+
+    #[test]
+    fn single_delimiters_skip_doubled_ones() {
+        assert_eq!(html("*a **b** c*"), "<p><i>a <b>b</b> c</i></p>");
+    }
+
+    #[test]
+    fn deep_nesting_is_kept_as_text() {
+        let n = 100_000;
+        let quoted = html(&format!("{} x", ">".repeat(n)));
+        assert_eq!(quoted.matches("<blockquote>").count(), MAX_DEPTH + 1);
+        assert!(quoted.contains(&format!("{} x", "&#62;".repeat(n - MAX_DEPTH - 1))));
+
+        let listed = html(&format!("{}x", "- ".repeat(n)));
+        assert_eq!(listed.matches("<ul>").count(), MAX_DEPTH + 1);
+    }
+}
@@ -0,0 +1,109 @@
+//! Peritext import and export.
+//!
+//! Mirrors `packages/json-joy/src/json-crdt-extensions/peritext/transfer/`.
+//!
+//! A [`TagMap`] names the element used for each slice type tag; the
+//! exporters in [`export`] render a [`Fragment`](super::Fragment) through it
+//! and the importers in [`import`] map elements back to slice types.
+
+pub mod export;
+pub mod import;
+mod markdown;
+
+pub use export::{to_html, to_json_ml, to_markdown, to_text};
+pub use import::{from_html, from_json_ml, from_markdown};
+
+use std::collections::HashMap;
+
+use super::slice::constants::*;
+use super::slice::TypeTag;
+
+// ── TagMap ────────────────────────────────────────────────────────────────
+
+/// Mapping from slice type tags to element names.
+///
+/// String tags without an entry are used as element names verbatim, so a
+/// slice of type `"mark"` renders as `<mark>`. Integer tags without an entry
+/// render their content unwrapped.
+///
+/// The [`Default`] map covers the common slice types (`TYPE_BOLD` → `b`,
+/// `TYPE_H1` → `h1`, …); start from [`TagMap::new`] for an empty one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagMap {
+    names: HashMap<TypeTag, String>,
+}
+
+impl TagMap {
+    /// An empty map.
+    pub fn new() -> Self {
+        Self {
+            names: HashMap::new(),
+        }
+    }
+
+    /// Map `tag` to the element `name`, replacing any previous entry.
+    pub fn with(mut self, tag: impl Into<TypeTag>, name: impl Into<String>) -> Self {
+        self.insert(tag, name);
+        self
+    }
+
+    /// Map `tag` to the element `name`, replacing any previous entry.
+    pub fn insert(&mut self, tag: impl Into<TypeTag>, name: impl Into<String>) {
+        self.names.insert(tag.into(), name.into());
+    }
+
+    /// Element name for `tag`, if any.
+    pub fn name<'a>(&'a self, tag: &'a TypeTag) -> Option<&'a str> {
+        match self.names.get(tag) {
+            Some(name) => Some(name),
+            None => match tag {
+                TypeTag::Str(name) if !name.is_empty() => Some(name),
+                _ => None,
+            },
+        }
+    }
+
+    /// The tag mapped to element `name`, if any. When several tags map to
+    /// the same name, the integer tag closest to zero is preferred (so `b`
+    /// yields `TYPE_BOLD` rather than `TYPE_BOLD2`).
+    pub fn tag(&self, name: &str) -> Option<TypeTag> {
+        self.names
+            .iter()
+            .filter(|(_, n)| n.as_str() == name)
+            .map(|(tag, _)| tag)
+            .min_by_key(|tag| match tag {
+                TypeTag::Int(n) => (0, n.unsigned_abs(), String::new()),
+                TypeTag::Str(s) => (1, 0, s.clone()),
+            })
+            .cloned()
+    }
+}
+
+impl Default for TagMap {
+    fn default() -> Self {
+        Self::new()
+            .with(TYPE_BOLD, "b")
+            .with(TYPE_BOLD2, "b")
+            .with(TYPE_STRONG, "strong")
+            .with(TYPE_ITALIC, "i")
+            .with(TYPE_ITALIC2, "i")
+            .with(TYPE_EM, "em")
+            .with(TYPE_UNDERLINE, "u")
+            .with(TYPE_STRIKETHROUGH, "s")
+            .with(TYPE_CODE, "code")
+            .with(TYPE_LINK, "a")
+            .with(TYPE_P, "p")
+            .with(TYPE_BLOCKQUOTE, "blockquote")
+            .with(TYPE_CODEBLOCK, "pre")
+            .with(TYPE_UL, "ul")
+            .with(TYPE_OL, "ol")
+            .with(TYPE_TL, "ul")
+            .with(TYPE_LI, "li")
+            .with(TYPE_H1, "h1")
+            .with(TYPE_H2, "h2")
+            .with(TYPE_H3, "h3")
+            .with(TYPE_H4, "h4")
+            .with(TYPE_H5, "h5")
+            .with(TYPE_H6, "h6")
+    }
+}
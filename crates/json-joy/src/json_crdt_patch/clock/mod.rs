@@ -215,6 +215,33 @@ impl ClockVector {
         }
     }
 
+    /// Returns `true` if this clock has observed the timestamp `id`.
+    ///
+    /// System-session timestamps (e.g. `ORIGIN`) are always considered seen.
+    /// Local timestamps are seen if they precede the current local time; peer
+    /// timestamps are seen if they do not exceed the peer's latest observed
+    /// time.
+    pub fn has_seen(&self, id: Ts) -> bool {
+        if id.sid == SESSION::SYSTEM {
+            return true;
+        }
+        if id.sid == self.sid {
+            return id.time < self.time;
+        }
+        self.peers
+            .get(&id.sid)
+            .is_some_and(|peer| id.time <= peer.time)
+    }
+
+    /// Returns `true` if this clock has seen everything `other` has seen.
+    ///
+    /// The local entry of `other` is taken at `other.time - 1`, its last
+    /// issued time; a clock that has issued nothing yet is always reached.
+    pub fn has_reached(&self, other: &ClockVector) -> bool {
+        (other.time <= 1 || self.has_seen(Ts::new(other.sid, other.time - 1)))
+            && other.peers.values().all(|peer| self.has_seen(*peer))
+    }
+
     /// Deep clone with the same session ID.
     pub fn clone_same(&self) -> ClockVector {
         self.fork(self.sid)
@@ -367,6 +394,30 @@ mod tests {
         assert_eq!(cv.peers[&2].time, 5);
     }
 
+    #[test]
+    fn clock_vector_has_seen() {
+        let mut cv = ClockVector::new(1, 10);
+        cv.observe(ts(2, 5), 3);
+        assert!(cv.has_seen(ts(SESSION::SYSTEM, 0)));
+        assert!(cv.has_seen(ts(1, 9)));
+        assert!(!cv.has_seen(ts(1, 10)));
+        assert!(cv.has_seen(ts(2, 7)));
+        assert!(!cv.has_seen(ts(2, 8)));
+        assert!(!cv.has_seen(ts(3, 1)));
+    }
+
+    #[test]
+    fn clock_vector_has_reached() {
+        let mut a = ClockVector::new(1, 10);
+        a.observe(ts(2, 5), 3);
+        let mut b = ClockVector::new(2, 8);
+        b.observe(ts(1, 4), 1);
+        assert!(a.has_reached(&b));
+        assert!(!b.has_reached(&a));
+        assert!(b.has_reached(&ClockVector::new(3, 1)));
+        assert!(!b.has_reached(&ClockVector::new(3, 2)));
+    }
+
     #[test]
     fn print_ts_server() {
         assert_eq!(print_ts(ts(SESSION::SERVER, 42)), ".42");
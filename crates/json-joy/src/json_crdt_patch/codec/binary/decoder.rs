@@ -193,6 +193,12 @@ impl Decoder {
                 let val = self.decode_id(r, patch_sid);
                 builder.upd_arr(obj, after, val);
             }
+            Some(JsonCrdtPatchOpcode::MovArr) => {
+                let obj = self.decode_id(r, patch_sid);
+                let after = self.decode_id(r, patch_sid);
+                let elem = self.decode_id(r, patch_sid);
+                builder.mov_arr(obj, after, elem);
+            }
             Some(JsonCrdtPatchOpcode::Del) => {
                 let length = if inline == 0 { r.vu57() } else { inline } as usize;
                 let obj = self.decode_id(r, patch_sid);
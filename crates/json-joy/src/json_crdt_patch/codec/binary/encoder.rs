@@ -219,6 +219,17 @@ impl Encoder {
                 self.encode_id(after);
                 self.encode_id(val);
             }
+            Op::MovArr {
+                obj, after, elem, ..
+            } => {
+                self.writer.u8(OpcodeOverlay::MOV_ARR);
+                let obj = *obj;
+                let after = *after;
+                let elem = *elem;
+                self.encode_id(obj);
+                self.encode_id(after);
+                self.encode_id(elem);
+            }
             Op::Del { obj, what, .. } => {
                 let length = what.len();
                 if length <= 0b111 {
@@ -252,6 +252,18 @@ mod tests {
         assert_eq!(out.ops, ops);
     }
 
+    #[test]
+    fn mov_arr() {
+        let ops = vec![Op::MovArr {
+            id: t(10),
+            obj: t(1),
+            after: other(4),
+            elem: t(5),
+        }];
+        let out = roundtrip(ops.clone());
+        assert_eq!(out.ops, ops);
+    }
+
     #[test]
     fn del_single_range() {
         // interval(stamp, tick_offset, span): deletes 2 ticks starting from t(3)+0
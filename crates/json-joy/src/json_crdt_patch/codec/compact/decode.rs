@@ -187,6 +187,12 @@ pub fn decode(data: &[Value]) -> Patch {
                 let val = decode_id(arr.get(3).unwrap_or(&Value::Null), patch_sid);
                 builder.upd_arr(obj, after, val);
             }
+            Some(JsonCrdtPatchOpcode::MovArr) => {
+                let obj = decode_id(arr.get(1).unwrap_or(&Value::Null), patch_sid);
+                let after = decode_id(arr.get(2).unwrap_or(&Value::Null), patch_sid);
+                let elem = decode_id(arr.get(3).unwrap_or(&Value::Null), patch_sid);
+                builder.mov_arr(obj, after, elem);
+            }
             Some(JsonCrdtPatchOpcode::Del) => {
                 let obj = decode_id(arr.get(1).unwrap_or(&Value::Null), patch_sid);
                 let what: Vec<crate::json_crdt_patch::clock::Tss> = arr
@@ -375,6 +381,20 @@ mod tests {
         assert!(matches!(&decoded.ops[4], Op::UpdArr { .. }));
     }
 
+    #[test]
+    fn roundtrip_mov_arr() {
+        let mut b = PatchBuilder::new(1, 0);
+        let arr = b.arr();
+        let c1 = b.con_val(PackValue::Integer(1));
+        let c2 = b.con_val(PackValue::Integer(2));
+        b.ins_arr(arr, arr, vec![c1, c2]);
+        let slot = Ts::new(1, 4);
+        b.mov_arr(arr, Ts::new(1, 5), slot);
+        let patch = b.flush();
+        let decoded = roundtrip(&patch);
+        assert_eq!(decoded.ops[4], patch.ops[4]);
+    }
+
     #[test]
     fn roundtrip_del() {
         let mut b = PatchBuilder::new(1, 0);
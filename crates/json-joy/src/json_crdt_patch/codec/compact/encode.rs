@@ -94,6 +94,14 @@ pub fn encode(patch: &Patch) -> Vec<Value> {
                 encode_ts(*obj, patch_sid),
                 encode_ts(*val, patch_sid),
             ]),
+            Op::MovArr {
+                obj, after, elem, ..
+            } => json!([
+                JsonCrdtPatchOpcode::MovArr as u8,
+                encode_ts(*obj, patch_sid),
+                encode_ts(*after, patch_sid),
+                encode_ts(*elem, patch_sid),
+            ]),
             Op::InsObj { obj, data, .. } => {
                 let tuples: Vec<Value> = data
                     .iter()
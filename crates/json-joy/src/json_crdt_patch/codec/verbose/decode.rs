@@ -175,6 +175,12 @@ pub fn decode(data: &Value) -> Patch {
                 let val = decode_id(op_obj.get("value").unwrap_or(&Value::Null));
                 builder.upd_arr(obj, after, val);
             }
+            "mov_arr" => {
+                let obj = decode_id(op_obj.get("obj").unwrap_or(&Value::Null));
+                let after = op_obj.get("after").map(decode_id).unwrap_or(obj);
+                let elem = decode_id(op_obj.get("ref").unwrap_or(&Value::Null));
+                builder.mov_arr(obj, after, elem);
+            }
             "del" => {
                 let obj = decode_id(op_obj.get("obj").unwrap_or(&Value::Null));
                 let what_arr = op_obj
@@ -372,6 +378,20 @@ mod tests {
         assert!(matches!(&decoded.ops[4], Op::UpdArr { .. }));
     }
 
+    #[test]
+    fn roundtrip_mov_arr() {
+        let mut b = PatchBuilder::new(1, 0);
+        let arr_id = b.arr();
+        let c1 = b.con_val(PackValue::Integer(1));
+        let c2 = b.con_val(PackValue::Integer(2));
+        b.ins_arr(arr_id, arr_id, vec![c1, c2]);
+        let slot = Ts::new(1, 4);
+        b.mov_arr(arr_id, Ts::new(1, 5), slot);
+        let patch = b.flush();
+        let decoded = roundtrip(&patch);
+        assert_eq!(decoded.ops[4], patch.ops[4]);
+    }
+
     #[test]
     fn roundtrip_del() {
         let mut b = PatchBuilder::new(1, 0);
@@ -74,6 +74,14 @@ pub fn encode(patch: &Patch) -> Value {
                 "obj": encode_ts(*obj),
                 "value": encode_ts(*val),
             }),
+            Op::MovArr {
+                obj, after, elem, ..
+            } => json!({
+                "op": "mov_arr",
+                "obj": encode_ts(*obj),
+                "after": encode_ts(*after),
+                "ref": encode_ts(*elem),
+            }),
             Op::InsObj { obj, data, .. } => {
                 let vals: Vec<Value> = data
                     .iter()
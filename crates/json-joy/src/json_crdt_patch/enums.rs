@@ -57,6 +57,7 @@ pub enum JsonCrdtPatchOpcode {
     UpdArr = 0b01000 | (JsonCrdtDataType::Arr as u8 + 1), // 15
     Del = 0b10000,                                        // 16
     Nop = 0b10001,                                        // 17
+    MovArr = 0b10010,                                     // 18
 }
 
 impl JsonCrdtPatchOpcode {
@@ -78,6 +79,7 @@ impl JsonCrdtPatchOpcode {
             15 => Some(Self::UpdArr),
             16 => Some(Self::Del),
             17 => Some(Self::Nop),
+            18 => Some(Self::MovArr),
             _ => None,
         }
     }
@@ -105,4 +107,5 @@ pub mod OpcodeOverlay {
     pub const UPD_ARR: u8 = (O::UpdArr as u8) << 3;
     pub const DEL: u8 = (O::Del as u8) << 3;
     pub const NOP: u8 = (O::Nop as u8) << 3;
+    pub const MOV_ARR: u8 = (O::MovArr as u8) << 3;
 }
@@ -1,7 +1,8 @@
 //! All 16 JSON CRDT Patch operations as a single Rust enum.
 //!
 //! Mirrors the 16 operation classes in
-//! `packages/json-joy/src/json-crdt-patch/operations.ts`.
+//! `packages/json-joy/src/json-crdt-patch/operations.ts`, plus `MovArr`
+//! (array element move), which has no upstream counterpart.
 
 use crate::json_crdt_patch::clock::{print_ts, Ts, Tss};
 use json_joy_json_pack::PackValue;
@@ -93,6 +94,16 @@ pub enum Op {
     Del { id: Ts, obj: Ts, what: Vec<Tss> },
     /// No-op — skips clock cycles without performing any CRDT action.
     Nop { id: Ts, len: u64 },
+    /// Move the element held by slot `elem` of an `arr` to a new slot
+    /// (identified by `id`) inserted after `after`.
+    ///
+    /// Not part of the upstream protocol.
+    MovArr {
+        id: Ts,
+        obj: Ts,
+        after: Ts,
+        elem: Ts,
+    },
 }
 
 impl Op {
@@ -114,7 +125,8 @@ impl Op {
             | Op::InsArr { id, .. }
             | Op::UpdArr { id, .. }
             | Op::Del { id, .. }
-            | Op::Nop { id, .. } => *id,
+            | Op::Nop { id, .. }
+            | Op::MovArr { id, .. } => *id,
         }
     }
 
@@ -129,6 +141,52 @@ impl Op {
         }
     }
 
+    /// Timestamps this operation depends on: target nodes, referenced values,
+    /// RGA insertion anchors and deleted ranges. Single IDs are reported as
+    /// spans of length 1.
+    pub fn refs(&self) -> Vec<Tss> {
+        let one = |id: &Ts| Tss::new(id.sid, id.time, 1);
+        match self {
+            Op::NewCon {
+                val: ConValue::Ref(id),
+                ..
+            } => vec![one(id)],
+            Op::NewCon { .. }
+            | Op::NewVal { .. }
+            | Op::NewObj { .. }
+            | Op::NewVec { .. }
+            | Op::NewStr { .. }
+            | Op::NewBin { .. }
+            | Op::NewArr { .. }
+            | Op::Nop { .. } => Vec::new(),
+            Op::InsVal { obj, val, .. } => vec![one(obj), one(val)],
+            Op::InsObj { obj, data, .. } => std::iter::once(one(obj))
+                .chain(data.iter().map(|(_, id)| one(id)))
+                .collect(),
+            Op::InsVec { obj, data, .. } => std::iter::once(one(obj))
+                .chain(data.iter().map(|(_, id)| one(id)))
+                .collect(),
+            Op::InsStr { obj, after, .. } | Op::InsBin { obj, after, .. } => {
+                vec![one(obj), one(after)]
+            }
+            Op::InsArr {
+                obj, after, data, ..
+            } => [one(obj), one(after)]
+                .into_iter()
+                .chain(data.iter().map(one))
+                .collect(),
+            Op::UpdArr {
+                obj, after, val, ..
+            } => vec![one(obj), one(after), one(val)],
+            Op::MovArr {
+                obj, after, elem, ..
+            } => vec![one(obj), one(after), one(elem)],
+            Op::Del { obj, what, .. } => std::iter::once(one(obj))
+                .chain(what.iter().copied())
+                .collect(),
+        }
+    }
+
     /// Short mnemonic name of this operation (used in verbose JSON codec).
     pub fn name(&self) -> &'static str {
         match self {
@@ -148,6 +206,7 @@ impl Op {
             Op::UpdArr { .. } => "upd_arr",
             Op::Del { .. } => "del",
             Op::Nop { .. } => "nop",
+            Op::MovArr { .. } => "mov_arr",
         }
     }
 }
@@ -189,6 +248,16 @@ impl std::fmt::Display for Op {
                 print_ts(*after),
                 data
             ),
+            Op::MovArr {
+                obj, after, elem, ..
+            } => write!(
+                f,
+                "{}, obj = {} {{ {} ← {} }}",
+                base,
+                print_ts(*obj),
+                print_ts(*after),
+                print_ts(*elem)
+            ),
             Op::Del { obj, what, .. } => {
                 let spans: Vec<_> = what
                     .iter()
@@ -232,6 +301,34 @@ mod tests {
         assert_eq!(op.span(), 5);
     }
 
+    #[test]
+    fn refs_of_mutation_ops() {
+        use crate::json_crdt_patch::clock::tss;
+        let op = Op::InsStr {
+            id: ts(1, 10),
+            obj: ts(1, 2),
+            after: ts(2, 5),
+            data: "x".into(),
+        };
+        assert_eq!(op.refs(), vec![tss(1, 2, 1), tss(2, 5, 1)]);
+        let op = Op::Del {
+            id: ts(1, 10),
+            obj: ts(1, 2),
+            what: vec![tss(2, 5, 3)],
+        };
+        assert_eq!(op.refs(), vec![tss(1, 2, 1), tss(2, 5, 3)]);
+        assert!(Op::NewStr { id: ts(1, 1) }.refs().is_empty());
+        let op = Op::MovArr {
+            id: ts(1, 10),
+            obj: ts(1, 2),
+            after: ts(1, 3),
+            elem: ts(2, 4),
+        };
+        assert_eq!(op.refs(), vec![tss(1, 2, 1), tss(1, 3, 1), tss(2, 4, 1)]);
+        assert_eq!(op.span(), 1);
+        assert_eq!(op.name(), "mov_arr");
+    }
+
     #[test]
     fn span_of_creation_op() {
         let op = Op::NewObj { id: ts(1, 0) };
@@ -204,6 +204,17 @@ where
             after: f(*after),
             val: f(*val),
         },
+        Op::MovArr {
+            id,
+            obj,
+            after,
+            elem,
+        } => Op::MovArr {
+            id: f(*id),
+            obj: f(*obj),
+            after: f(*after),
+            elem: f(*elem),
+        },
         Op::Del { id, obj, what } => Op::Del {
             id: f(*id),
             obj: f(*obj),
@@ -295,6 +295,19 @@ impl PatchBuilder {
         id
     }
 
+    /// Move the element held by slot `elem` of an `arr` after `after`.
+    pub fn mov_arr(&mut self, arr: Ts, after: Ts, elem: Ts) -> Ts {
+        self.pad();
+        let id = self.clock.tick(1);
+        self.patch.ops.push(Op::MovArr {
+            id,
+            obj: arr,
+            after,
+            elem,
+        });
+        id
+    }
+
     /// Delete spans of operations in an object.
     pub fn del(&mut self, obj: Ts, what: Vec<Tss>) -> Ts {
         self.pad();
@@ -3,7 +3,12 @@
 //! Mirrors `packages/json-joy/src/json-crdt-peritext-ui/`.
 //!
 //! React/RxJS components are skipped (not portable to Rust).
-//! Only the portable model types from `types.ts` are ported.
+//! Only the portable model types from `types.ts` are ported, plus
+//! [`PeritextUndo`], a concrete [`UndoManager`] for Peritext documents.
+
+pub mod undo;
+
+pub use undo::PeritextUndo;
 
 // ── Undo / Redo framework ─────────────────────────────────────────────────
 
@@ -0,0 +1,719 @@
+//! Peritext-aware undo manager.
+//!
+//! Not part of the upstream TypeScript, which only defines the
+//! [`UndoManager`] interface for its web UI.
+//!
+//! [`PeritextUndo`] owns the document [`Model`] and records each local edit
+//! as the list of Peritext changes it made: text inserted or deleted, and
+//! slices inserted or deleted. Inverse patches are built only when an entry
+//! is undone, against the current document, so they apply correctly after
+//! remote concurrent edits: inserted text is deleted by character ID, and
+//! deleted text is re-inserted after the character that preceded it.
+//!
+//! Deleted text and slices are read from the document just before each
+//! patch of an edit is applied, so recording an edit costs no copy of the
+//! document.
+//!
+//! Restored text gets new character IDs. The manager remembers which IDs
+//! replaced which, and re-anchors slices and cursors that pointed at the
+//! deleted characters, so formatting, block markers and selections come back
+//! together with the text. Replacements no history entry refers to any more
+//! are forgotten.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{RedoCallback, UndoCallback, UndoManager};
+use crate::json_crdt::constants::ORIGIN;
+use crate::json_crdt::model::Model;
+use crate::json_crdt::nodes::{CrdtNode, TsKey};
+use crate::json_crdt_extensions::peritext::{
+    command, commit, Cursor, Peritext, Point, Range, Slice,
+};
+use crate::json_crdt_patch::clock::{Ts, Tss};
+use crate::json_crdt_patch::operations::Op;
+use crate::json_crdt_patch::patch::Patch;
+
+/// Default time window within which consecutive typing is grouped.
+pub const DEFAULT_DELAY: Duration = Duration::from_millis(1000);
+
+// ── PeritextUndo ──────────────────────────────────────────────────────────
+
+/// Undo/redo history of local edits to one Peritext document.
+///
+/// Local edits go through [`edit`](Self::edit); remote patches through
+/// [`apply_remote`](Self::apply_remote). Consecutive edits that only insert
+/// text, or only delete it, are grouped into one entry while they follow
+/// each other within [`delay`](Self::with_delay); typing after whitespace
+/// starts a new entry, so words undo one at a time.
+///
+/// Each entry remembers [`cursor`](Self::cursor) as it was before the edit,
+/// and undoing it restores that cursor.
+pub struct PeritextUndo {
+    /// The document.
+    pub model: Model,
+    /// The Peritext document edited.
+    pub peritext: Peritext,
+    /// The local cursor; set it after each edit and selection change.
+    pub cursor: Option<Cursor>,
+    delay: Duration,
+    undo: Vec<Item>,
+    redo: Vec<Item>,
+    /// Character and slice IDs replaced by restored copies.
+    aliases: HashMap<Ts, Ts>,
+}
+
+impl PeritextUndo {
+    /// A manager with empty history for `peritext` in `model`.
+    pub fn new(model: Model, peritext: Peritext) -> Self {
+        Self {
+            model,
+            peritext,
+            cursor: None,
+            delay: DEFAULT_DELAY,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Set the grouping window; `Duration::ZERO` disables grouping.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Number of entries that can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Number of entries that can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Run the local edit `f` and record it; clears the redo history.
+    ///
+    /// `f` may make any number of changes (typically a Peritext method or
+    /// editing command); they form one entry, or extend the previous one.
+    /// Returns the patch applied, for sending to peers.
+    pub fn edit(&mut self, f: impl FnOnce(&mut Model, &Peritext)) -> Patch {
+        let peritext = self.peritext;
+        let (patch, changes) = record(&mut self.model, &peritext, |model| f(model, &peritext));
+        if patch.ops.is_empty() {
+            return patch;
+        }
+        let entry = Entry::new(changes, &patch, self.cursor);
+        if !self.redo.is_empty() {
+            self.redo.clear();
+            self.prune_aliases();
+        }
+        match self.undo.last_mut() {
+            Some(Item::Entry(last)) if last.absorbs(&entry, self.delay) => last.extend(entry),
+            _ => self.undo.push(Item::Entry(entry)),
+        }
+        patch
+    }
+
+    /// Apply a patch received from a peer. History is kept: later undos
+    /// build their inverse against the merged document.
+    pub fn apply_remote(&mut self, patch: &Patch) {
+        self.model.apply_patch(patch);
+    }
+
+    /// Undo the latest entry, restoring its cursor. Returns the inverse
+    /// patch applied, or `None` if there is nothing to undo.
+    pub fn undo_patch(&mut self) -> Option<Patch> {
+        match self.undo.pop()? {
+            Item::Entry(entry) => {
+                let (patch, redo) = self.revert(entry);
+                self.redo.push(Item::Entry(redo));
+                Some(patch)
+            }
+            Item::Custom(state, callback) => {
+                let (state, callback) = callback(state);
+                self.redo.push(Item::CustomRedo(state, callback));
+                Some(Patch::new())
+            }
+            Item::CustomRedo(..) => None,
+        }
+    }
+
+    /// Redo the latest undone entry. Returns the patch applied, or `None`
+    /// if there is nothing to redo.
+    pub fn redo_patch(&mut self) -> Option<Patch> {
+        match self.redo.pop()? {
+            Item::Entry(entry) => {
+                let (patch, undo) = self.revert(entry);
+                self.undo.push(Item::Entry(undo));
+                Some(patch)
+            }
+            Item::CustomRedo(state, callback) => {
+                callback(state);
+                Some(Patch::new())
+            }
+            Item::Custom(..) => None,
+        }
+    }
+
+    /// Apply the inverse of `entry`, returning the patch and an entry that
+    /// reverts it in turn.
+    fn revert(&mut self, entry: Entry) -> (Patch, Entry) {
+        let peritext = self.peritext;
+        let aliases = &mut self.aliases;
+        let (patch, changes) = record(&mut self.model, &peritext, |model| {
+            for change in entry.changes.iter().rev() {
+                invert(&peritext, model, aliases, change);
+            }
+        });
+        let inverse = Entry::new(changes, &patch, self.cursor);
+        self.cursor = entry.cursor.map(|cursor| Cursor {
+            start: alias_point(&self.aliases, cursor.start),
+            end: alias_point(&self.aliases, cursor.end),
+            ..cursor
+        });
+        (patch, inverse)
+    }
+
+    /// Drop the aliases of IDs no history entry refers to, and shorten
+    /// chains of aliases to their latest ID.
+    fn prune_aliases(&mut self) {
+        let mut aliases = HashMap::new();
+        for item in self.undo.iter().chain(&self.redo) {
+            let Item::Entry(entry) = item else {
+                continue;
+            };
+            for id in entry.refs() {
+                let latest = alias(&self.aliases, id);
+                if latest != id {
+                    aliases.insert(id, latest);
+                }
+            }
+        }
+        self.aliases = aliases;
+    }
+}
+
+/// Interleaves caller-defined steps with recorded edits.
+///
+/// A pushed callback runs when its step is undone, receiving the cursor it
+/// was pushed with; the callback it returns runs on redo. As the trait's
+/// redo callbacks return nothing, a redone custom step leaves the history.
+impl UndoManager for PeritextUndo {
+    type UndoState = Option<Cursor>;
+    type RedoState = Option<Cursor>;
+
+    fn push(
+        &mut self,
+        state: Self::UndoState,
+        callback: UndoCallback<Self::UndoState, Self::RedoState>,
+    ) {
+        if !self.redo.is_empty() {
+            self.redo.clear();
+            self.prune_aliases();
+        }
+        self.undo.push(Item::Custom(state, callback));
+    }
+
+    fn undo(&mut self) {
+        self.undo_patch();
+    }
+
+    fn redo(&mut self) {
+        self.redo_patch();
+    }
+}
+
+// ── History entries ───────────────────────────────────────────────────────
+
+enum Item {
+    Entry(Entry),
+    Custom(Option<Cursor>, UndoCallback<Option<Cursor>, Option<Cursor>>),
+    CustomRedo(Option<Cursor>, RedoCallback<Option<Cursor>>),
+}
+
+/// A change to the Peritext document, with what is needed to revert it.
+#[derive(Debug, Clone)]
+enum Change {
+    /// Text inserted by an `ins_str` of this span.
+    InsText(Tss),
+    /// Slices inserted, by `VecNode` ID.
+    InsSlices(Vec<Ts>),
+    /// A run of contiguous visible UTF-16 units deleted, with the ID of the
+    /// unit preceding it.
+    DelText {
+        after: Ts,
+        ids: Vec<Ts>,
+        units: Vec<u16>,
+    },
+    /// A slice deleted.
+    DelSlice(Slice),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Typing,
+    Deleting,
+    Other,
+}
+
+struct Entry {
+    changes: Vec<Change>,
+    /// Cursor before the edit.
+    cursor: Option<Cursor>,
+    kind: EditKind,
+    at: Instant,
+    /// Whether the latest text typed ends with whitespace.
+    ends_word: bool,
+}
+
+impl Entry {
+    /// Record the `changes` made by `patch`.
+    fn new(changes: Vec<Change>, patch: &Patch, cursor: Option<Cursor>) -> Self {
+        let ends_word = patch
+            .ops
+            .iter()
+            .rev()
+            .find_map(|op| match op {
+                Op::InsStr { data, .. } => Some(data.ends_with(char::is_whitespace)),
+                _ => None,
+            })
+            .unwrap_or(false);
+        let kind = if changes.iter().all(|c| matches!(c, Change::InsText(_))) {
+            EditKind::Typing
+        } else if changes.iter().all(|c| matches!(c, Change::DelText { .. })) {
+            EditKind::Deleting
+        } else {
+            EditKind::Other
+        };
+        Self {
+            changes,
+            cursor,
+            kind,
+            at: Instant::now(),
+            ends_word,
+        }
+    }
+
+    /// Whether `next` continues this entry.
+    fn absorbs(&self, next: &Entry, delay: Duration) -> bool {
+        self.kind == next.kind
+            && self.kind != EditKind::Other
+            && next.at.duration_since(self.at) < delay
+            && !(self.kind == EditKind::Typing && self.ends_word)
+    }
+
+    fn extend(&mut self, next: Entry) {
+        self.changes.extend(next.changes);
+        self.at = next.at;
+        self.ends_word = next.ends_word;
+    }
+
+    /// IDs this entry refers to, which may have been replaced since.
+    fn refs(&self) -> impl Iterator<Item = Ts> + '_ {
+        let cursor = self
+            .cursor
+            .into_iter()
+            .flat_map(|cursor| [cursor.start.id, cursor.end.id]);
+        let changes = self.changes.iter().flat_map(|change| -> Vec<Ts> {
+            match change {
+                Change::InsText(span) => (0..span.span)
+                    .map(|i| Ts::new(span.sid, span.time + i))
+                    .collect(),
+                Change::InsSlices(ids) => ids.clone(),
+                Change::DelText { after, .. } => vec![*after],
+                Change::DelSlice(slice) => vec![slice.id, slice.start.id, slice.end.id],
+            }
+        });
+        cursor.chain(changes)
+    }
+}
+
+// ── Capture ───────────────────────────────────────────────────────────────
+
+/// Run `f` as one transaction on `model`, returning its patch and the
+/// Peritext changes it made, each read just before its patch was applied.
+fn record(
+    model: &mut Model,
+    peritext: &Peritext,
+    f: impl FnOnce(&mut Model),
+) -> (Patch, Vec<Change>) {
+    let changes: Arc<Mutex<Vec<Change>>> = Arc::default();
+    let sink = changes.clone();
+    let peritext = *peritext;
+    let patch = command(model, |model| {
+        let listener = model.on_before_patch(move |before, patch, _| {
+            sink.lock()
+                .unwrap()
+                .extend(capture(&peritext, before, patch));
+        });
+        f(model);
+        model.off(listener);
+    });
+    let changes = std::mem::take(&mut *changes.lock().unwrap());
+    (patch, changes)
+}
+
+/// Peritext changes made by `patch`, read against the state `before` it.
+fn capture(peritext: &Peritext, before: &Model, patch: &Patch) -> Vec<Change> {
+    let str_id = peritext.str_id;
+    let arr_id = peritext.saved_slices.arr_id;
+    let mut changes = Vec::new();
+    for op in &patch.ops {
+        match op {
+            Op::InsStr { id, obj, .. } if *obj == str_id => {
+                changes.push(Change::InsText(Tss::new(id.sid, id.time, op.span())));
+            }
+            Op::InsArr { obj, data, .. } if *obj == arr_id => {
+                changes.push(Change::InsSlices(data.clone()));
+            }
+            Op::Del { obj, what, .. } if *obj == str_id => {
+                changes.extend(deleted_text(before, str_id, what));
+            }
+            Op::Del { obj, what, .. } if *obj == arr_id => {
+                changes.extend(
+                    deleted_slices(before, arr_id, what)
+                        .into_iter()
+                        .filter_map(|id| peritext.saved_slices.get(before, id))
+                        .map(Change::DelSlice),
+                );
+            }
+            _ => {}
+        }
+    }
+    changes
+}
+
+/// Runs of visible text in `what`, as they were in `before`.
+fn deleted_text(before: &Model, str_id: Ts, what: &[Tss]) -> Vec<Change> {
+    let Some(CrdtNode::Str(node)) = before.index.get(&TsKey::from(str_id)) else {
+        return Vec::new();
+    };
+    let mut runs = Vec::new();
+    let mut run: Option<Change> = None;
+    let mut prev = ORIGIN;
+    for chunk in node.rga.iter() {
+        let units: Vec<u16> = match (&chunk.data, chunk.deleted) {
+            (Some(data), false) => data.encode_utf16().collect(),
+            _ => Vec::new(),
+        };
+        for offset in 0..chunk.span {
+            let id = Ts::new(chunk.id.sid, chunk.id.time + offset);
+            let unit = units.get(offset as usize).copied();
+            match unit {
+                Some(unit) if contains(what, id) => {
+                    if let Some(Change::DelText { ids, units, .. }) = run.as_mut() {
+                        ids.push(id);
+                        units.push(unit);
+                    } else {
+                        run = Some(Change::DelText {
+                            after: prev,
+                            ids: vec![id],
+                            units: vec![unit],
+                        });
+                    }
+                }
+                Some(_) => runs.extend(run.take()),
+                None => {}
+            }
+            prev = id;
+        }
+    }
+    runs.extend(run);
+    runs
+}
+
+/// `VecNode` IDs of the visible slots in `what`, as they were in `before`.
+fn deleted_slices(before: &Model, arr_id: Ts, what: &[Tss]) -> Vec<Ts> {
+    let Some(CrdtNode::Arr(node)) = before.index.get(&TsKey::from(arr_id)) else {
+        return Vec::new();
+    };
+    let mut ids = Vec::new();
+    for chunk in node.rga.iter_live() {
+        let Some(data) = &chunk.data else {
+            continue;
+        };
+        for (offset, vec_id) in data.iter().enumerate() {
+            let slot = Ts::new(chunk.id.sid, chunk.id.time + offset as u64);
+            if contains(what, slot) {
+                ids.push(*vec_id);
+            }
+        }
+    }
+    ids
+}
+
+fn contains(spans: &[Tss], id: Ts) -> bool {
+    spans
+        .iter()
+        .any(|s| s.sid == id.sid && s.time <= id.time && id.time < s.time + s.span)
+}
+
+// ── Inversion ─────────────────────────────────────────────────────────────
+
+/// Apply the inverse of `change` to `model`.
+fn invert(peritext: &Peritext, model: &mut Model, aliases: &mut HashMap<Ts, Ts>, change: &Change) {
+    match change {
+        Change::InsText(span) => {
+            let what =
+                spans((0..span.span).map(|i| alias(aliases, Ts::new(span.sid, span.time + i))));
+            let id = model.next_ts();
+            let obj = peritext.str_id;
+            commit(model, vec![Op::Del { id, obj, what }]);
+        }
+        Change::InsSlices(ids) => {
+            for id in ids {
+                peritext.saved_slices.del(model, alias(aliases, *id));
+            }
+        }
+        Change::DelText { after, ids, units } => {
+            let id = model.next_ts();
+            let data = String::from_utf16_lossy(units);
+            let (obj, after) = (peritext.str_id, alias(aliases, *after));
+            commit(
+                model,
+                vec![Op::InsStr {
+                    id,
+                    obj,
+                    after,
+                    data,
+                }],
+            );
+            for (offset, old) in ids.iter().enumerate() {
+                aliases.insert(*old, Ts::new(id.sid, id.time + offset as u64));
+            }
+            // Re-anchor the slices which pointed at the deleted text.
+            for slice in peritext.saved_slices.iter_slices(model) {
+                let moved = |point: Point| ids.contains(&point.id);
+                if moved(slice.start) || moved(slice.end) {
+                    peritext.saved_slices.del(model, slice.id);
+                    restore(peritext, model, aliases, &slice);
+                }
+            }
+        }
+        Change::DelSlice(slice) => restore(peritext, model, aliases, slice),
+    }
+}
+
+/// Insert a copy of `slice` with its points re-anchored.
+fn restore(peritext: &Peritext, model: &mut Model, aliases: &mut HashMap<Ts, Ts>, slice: &Slice) {
+    let range = Range::new(
+        alias_point(aliases, slice.start),
+        alias_point(aliases, slice.end),
+    );
+    let data = slice.data.clone();
+    let id = peritext.saved_slices.ins(
+        model,
+        &range,
+        slice.stacking,
+        slice.slice_type.clone(),
+        data,
+    );
+    aliases.insert(slice.id, id);
+}
+
+/// The ID now standing for `id`.
+fn alias(aliases: &HashMap<Ts, Ts>, mut id: Ts) -> Ts {
+    while let Some(next) = aliases.get(&id) {
+        id = *next;
+    }
+    id
+}
+
+fn alias_point(aliases: &HashMap<Ts, Ts>, point: Point) -> Point {
+    Point {
+        id: alias(aliases, point.id),
+        ..point
+    }
+}
+
+/// Group IDs into spans of consecutive times.
+fn spans(ids: impl Iterator<Item = Ts>) -> Vec<Tss> {
+    let mut spans: Vec<Tss> = Vec::new();
+    for id in ids {
+        match spans.last_mut() {
+            Some(last) if last.sid == id.sid && last.time + last.span == id.time => last.span += 1,
+            _ => spans.push(Tss::new(id.sid, id.time, 1)),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_crdt_extensions::peritext::slice::constants::TYPE_BOLD;
+    use crate::json_crdt_extensions::peritext::test_util;
+    use crate::json_crdt_extensions::peritext::{transfer, TagMap};
+    use std::sync::{Arc, Mutex};
+
+    fn setup(text: &str) -> PeritextUndo {
+        let (model, peritext) = test_util::setup(text);
+        PeritextUndo::new(model, peritext).with_delay(Duration::from_secs(3600))
+    }
+
+    fn html(undo: &PeritextUndo) -> String {
+        let fragment = undo.peritext.fragment(&undo.model);
+        transfer::to_html(&fragment, &TagMap::default())
+    }
+
+    fn text(undo: &PeritextUndo) -> String {
+        undo.peritext.text(&undo.model)
+    }
+
+    fn type_text(undo: &mut PeritextUndo, text: &str) {
+        for ch in text.chars() {
+            let pos = undo.peritext.len(&undo.model);
+            undo.edit(|model, pt| pt.ins_at(model, pos, &ch.to_string()));
+        }
+    }
+
+    #[test]
+    fn typing_is_grouped_by_word() {
+        let mut undo = setup("");
+        type_text(&mut undo, "hello world");
+        assert_eq!(undo.undo_len(), 2);
+        undo.undo_patch();
+        assert_eq!(text(&undo), "hello ");
+        undo.undo_patch();
+        assert_eq!(text(&undo), "");
+        assert!(undo.undo_patch().is_none());
+        undo.redo_patch();
+        undo.redo_patch();
+        assert_eq!(text(&undo), "hello world");
+        undo.undo_patch();
+        assert_eq!(text(&undo), "hello ");
+    }
+
+    #[test]
+    fn zero_delay_records_every_edit() {
+        let mut undo = setup("").with_delay(Duration::ZERO);
+        type_text(&mut undo, "abc");
+        assert_eq!(undo.undo_len(), 3);
+        undo.undo_patch();
+        assert_eq!(text(&undo), "ab");
+    }
+
+    #[test]
+    fn undoing_a_delete_restores_formatting_and_cursor() {
+        let mut undo = setup("hello world");
+        let (model, pt) = (&mut undo.model, undo.peritext);
+        pt.toggle_inline(model, 6, 5, TYPE_BOLD, None);
+        undo.cursor = Some(Cursor::select(&pt, &undo.model, 6, 11));
+        undo.edit(|model, pt| pt.del_at(model, 5, 6));
+        undo.cursor = Some(Cursor::at(&pt, &undo.model, 5));
+        assert_eq!(html(&undo), "<p>hello</p>");
+        undo.undo_patch();
+        assert_eq!(html(&undo), "<p>hello <b>world</b></p>");
+        assert_eq!(undo.cursor.unwrap().positions(&undo.model), (6, 11));
+        undo.redo_patch();
+        assert_eq!(html(&undo), "<p>hello</p>");
+        assert_eq!(undo.cursor.unwrap().positions(&undo.model), (5, 5));
+        undo.undo_patch();
+        assert_eq!(html(&undo), "<p>hello <b>world</b></p>");
+    }
+
+    #[test]
+    fn commands_undo_atomically() {
+        let mut undo = setup("hello world");
+        undo.edit(|model, pt| {
+            pt.toggle_inline(model, 0, 5, TYPE_BOLD, None);
+        });
+        undo.edit(|model, pt| {
+            pt.toggle_inline(model, 2, 6, TYPE_BOLD, None);
+        });
+        undo.edit(|model, pt| {
+            pt.split_block(model, 5, None);
+        });
+        assert_eq!(html(&undo), "<p><b>hello</b></p><p><b> wo</b>rld</p>");
+        undo.undo_patch();
+        assert_eq!(html(&undo), "<p><b>hello wo</b>rld</p>");
+        undo.undo_patch();
+        assert_eq!(html(&undo), "<p><b>hello</b> world</p>");
+        undo.undo_patch();
+        assert_eq!(html(&undo), "<p>hello world</p>");
+    }
+
+    #[test]
+    fn inverse_applies_after_concurrent_edits() {
+        let mut undo = setup("hello");
+        let mut peer = undo.model.clone();
+        peer.clock = peer.clock.fork(43);
+        let pt = undo.peritext;
+
+        let local = undo.edit(|model, pt| pt.ins_at(model, 5, " world"));
+        let remote = command(&mut peer, |model| pt.ins_at(model, 0, "say "));
+        undo.apply_remote(&remote);
+        peer.apply_patch(&local);
+        assert_eq!(text(&undo), "say hello world");
+
+        let inverse = undo.undo_patch().unwrap();
+        peer.apply_patch(&inverse);
+        assert_eq!(text(&undo), "say hello");
+        assert_eq!(pt.text(&peer), "say hello");
+
+        let redo = undo.redo_patch().unwrap();
+        peer.apply_patch(&redo);
+        assert_eq!(pt.text(&peer), "say hello world");
+    }
+
+    #[test]
+    fn restored_text_survives_concurrent_insert_next_to_it() {
+        let mut undo = setup("abcdef");
+        let pt = undo.peritext;
+        undo.edit(|model, pt| pt.del_at(model, 2, 2));
+        let mut peer = undo.model.clone();
+        peer.clock = peer.clock.fork(43);
+        let remote = command(&mut peer, |model| pt.ins_at(model, 2, "X"));
+        undo.apply_remote(&remote);
+        undo.undo_patch();
+        assert_eq!(text(&undo), "abcdXef");
+    }
+
+    #[test]
+    fn aliases_are_dropped_with_the_history_using_them() {
+        let mut undo = setup("abc");
+        undo.edit(|model, pt| pt.del_at(model, 1, 1));
+        undo.undo_patch();
+        assert_eq!(undo.aliases.len(), 1);
+        // The restored text is still referenced by the redo entry.
+        undo.redo_patch();
+        undo.undo_patch();
+        assert_eq!(text(&undo), "abc");
+        assert!(!undo.aliases.is_empty());
+
+        // A new edit drops the redo history and, with it, the aliases.
+        undo.edit(|model, pt| pt.ins_at(model, 3, "d"));
+        assert!(undo.aliases.is_empty());
+        undo.undo_patch();
+        assert_eq!(text(&undo), "abc");
+    }
+
+    #[test]
+    fn custom_steps_interleave_with_edits() {
+        let mut undo = setup("");
+        let log: Arc<Mutex<Vec<&str>>> = Arc::default();
+        type_text(&mut undo, "a");
+        let sink = log.clone();
+        UndoManager::push(
+            &mut undo,
+            None,
+            Box::new(move |state| {
+                sink.lock().unwrap().push("undo");
+                let sink = sink.clone();
+                (state, Box::new(move |_| sink.lock().unwrap().push("redo")))
+            }),
+        );
+        UndoManager::undo(&mut undo);
+        assert_eq!(*log.lock().unwrap(), ["undo"]);
+        assert_eq!(text(&undo), "a");
+        UndoManager::undo(&mut undo);
+        assert_eq!(text(&undo), "");
+        UndoManager::redo(&mut undo);
+        UndoManager::redo(&mut undo);
+        assert_eq!(text(&undo), "a");
+        assert_eq!(*log.lock().unwrap(), ["undo", "redo"]);
+    }
+}
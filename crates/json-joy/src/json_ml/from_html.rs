@@ -0,0 +1,303 @@
+//! Lenient HTML parser producing JsonML.
+//!
+//! Not part of the upstream TypeScript, which delegates to an external
+//! parser. Handles elements, attributes, void and raw-text elements,
+//! comments and character references; malformed markup is recovered from
+//! rather than rejected. Elements nested deeper than [`MAX_DEPTH`] are
+//! dropped, keeping their content.
+
+use super::{JsonMlElement, JsonMlNode, Tag, MAX_DEPTH};
+
+/// Elements that never have children.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose content is skipped entirely.
+const RAW: &[&str] = &["script", "style", "template"];
+
+type Attrs = Option<Vec<(String, String)>>;
+
+/// Parse `html` into a fragment element holding the top-level nodes.
+///
+/// Closing tags without a matching open element are ignored; elements left
+/// open at the end are closed. An opening `<p>` or `<li>` closes an open
+/// element of the same name at the top of the stack.
+pub fn from_html(html: &str) -> JsonMlNode {
+    let mut stack: Vec<JsonMlElement> = vec![element(Tag::Fragment, None)];
+    // Names of the open elements dropped for nesting too deep.
+    let mut dropped: Vec<String> = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            push_text(&mut stack, rest);
+            break;
+        };
+        push_text(&mut stack, &rest[..lt]);
+        rest = &rest[lt..];
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').unwrap_or(after.len());
+            let name = after[..end].trim().to_ascii_lowercase();
+            rest = after.get(end + 1..).unwrap_or("");
+            if dropped.last() == Some(&name) {
+                dropped.pop();
+            } else if let Some(depth) = stack.iter().rposition(|el| is_named(el, &name)) {
+                while stack.len() > depth.max(1) {
+                    close(&mut stack);
+                }
+            }
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let (name, attrs, self_closing, after) = open_tag(&rest[1..]);
+            rest = after;
+            if RAW.contains(&name.as_str()) {
+                let closing = format!("</{}", name);
+                rest = find_ascii_ci(rest, &closing).map_or("", |at| {
+                    let tail = &rest[at..];
+                    tail.find('>').map_or("", |end| &tail[end + 1..])
+                });
+                continue;
+            }
+            if matches!(name.as_str(), "p" | "li")
+                && stack.len() > 1
+                && is_named(stack.last().unwrap(), &name)
+            {
+                close(&mut stack);
+            }
+            let empty = self_closing || VOID.contains(&name.as_str());
+            if stack.len() > MAX_DEPTH {
+                if !empty {
+                    dropped.push(name);
+                }
+                continue;
+            }
+            stack.push(element(Tag::Named(name.clone()), attrs));
+            if empty {
+                close(&mut stack);
+            }
+        } else {
+            push_text(&mut stack, "<");
+            rest = &rest[1..];
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    JsonMlNode::Element(stack.pop().unwrap())
+}
+
+/// Parse the inside of an opening tag (after `<`); returns the lower-cased
+/// name, attributes, whether it is self-closing, and the remaining input.
+fn open_tag(input: &str) -> (String, Attrs, bool, &str) {
+    let name_end = input
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(input.len());
+    let name = input[..name_end].to_ascii_lowercase();
+    let mut rest = &input[name_end..];
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+        if let Some(after) = rest.strip_prefix("/>") {
+            self_closing = true;
+            rest = after;
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            rest = after;
+            continue;
+        }
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            rest = after.trim_start();
+            let (raw, after) = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &rest[1..];
+                    let end = body.find(quote).unwrap_or(body.len());
+                    (&body[..end], body.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = rest
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = after;
+        }
+        if !key.is_empty() {
+            attrs.push((key, value));
+        }
+    }
+    let attrs = (!attrs.is_empty()).then_some(attrs);
+    (name, attrs, self_closing, rest)
+}
+
+fn element(tag: Tag, attrs: Attrs) -> JsonMlElement {
+    JsonMlElement {
+        tag,
+        attrs,
+        children: Vec::new(),
+    }
+}
+
+fn is_named(el: &JsonMlElement, name: &str) -> bool {
+    matches!(&el.tag, Tag::Named(n) if n == name)
+}
+
+fn close(stack: &mut Vec<JsonMlElement>) {
+    let el = stack.pop().unwrap();
+    stack
+        .last_mut()
+        .unwrap()
+        .children
+        .push(JsonMlNode::Element(el));
+}
+
+/// Append decoded text to the open element, merging with a preceding text
+/// node.
+fn push_text(stack: &mut [JsonMlElement], raw: &str) {
+    if raw.is_empty() {
+        return;
+    }
+    let text = decode_entities(raw);
+    let children = &mut stack.last_mut().unwrap().children;
+    match children.last_mut() {
+        Some(JsonMlNode::Text(prev)) => prev.push_str(&text),
+        _ => children.push(JsonMlNode::Text(text)),
+    }
+}
+
+/// Byte offset of the first ASCII case-insensitive match of `needle`.
+fn find_ascii_ci(haystack: &str, needle: &str) -> Option<usize> {
+    let needle = needle.as_bytes();
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// Decode numeric character references and the common named ones.
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| {
+                let entity = &rest[1..end + 1];
+                let ch = match entity {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some('\u{00A0}'),
+                    _ => {
+                        let code = if let Some(hex) = entity
+                            .strip_prefix("#x")
+                            .or_else(|| entity.strip_prefix("#X"))
+                        {
+                            u32::from_str_radix(hex, 16).ok()
+                        } else {
+                            entity.strip_prefix('#').and_then(|dec| dec.parse().ok())
+                        };
+                        code.and_then(char::from_u32)
+                    }
+                };
+                ch.map(|ch| (ch, end + 2))
+            });
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_ml::to_html;
+
+    fn round_trip(html: &str) -> String {
+        to_html(&from_html(html), "", "")
+    }
+
+    #[test]
+    fn parses_nested_elements_and_attributes() {
+        assert_eq!(
+            round_trip(r#"<p class=intro>Hi <a href='/x' title="a &amp; b">there</a></p>"#),
+            r#"<p class="intro">Hi <a href="/x" title="a &amp; b">there</a></p>"#
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_flattened() {
+        let n = 100_000;
+        let html = round_trip(&format!("{}x{}", "<b>".repeat(n), "</b>".repeat(n)));
+        assert_eq!(html.matches("<b>").count(), MAX_DEPTH);
+        assert!(html.contains("<b>x</b>"));
+        let unclosed = round_trip(&format!("{}x", "<i>".repeat(n)));
+        assert_eq!(unclosed.matches("<i>").count(), MAX_DEPTH);
+    }
+
+    #[test]
+    fn handles_void_and_self_closing_elements() {
+        assert_eq!(
+            round_trip("a<br>b<img src=x />c"),
+            r#"a<br />b<img src="x" />c"#
+        );
+    }
+
+    #[test]
+    fn skips_comments_doctype_and_scripts() {
+        assert_eq!(
+            round_trip("<!DOCTYPE html><!-- note --><SCRIPT>x<y</script><b>ok</b>"),
+            "<b>ok</b>"
+        );
+    }
+
+    #[test]
+    fn decodes_character_references() {
+        let JsonMlNode::Element(root) = from_html("&lt;&#65;&#x42;&copy2&gt;") else {
+            panic!("expected element");
+        };
+        assert_eq!(root.children, vec![JsonMlNode::Text("<AB&copy2>".into())]);
+    }
+
+    #[test]
+    fn recovers_from_unbalanced_tags() {
+        assert_eq!(
+            round_trip("<ul><li>one<li>two</ul></i><p>a<p>b"),
+            "<ul><li>one</li><li>two</li></ul><p>a</p><p>b</p>"
+        );
+    }
+}
@@ -5,6 +5,16 @@
 //! JsonML represents HTML/XML trees as nested JSON arrays:
 //! `[tag, attrs, ...children]` where attrs is `null` or a key→value map.
 
+mod from_html;
+
+pub use from_html::from_html;
+
+/// How deep parsers of untrusted markup nest elements; deeper content is
+/// flattened into the innermost element kept.
+///
+/// Not part of the upstream TypeScript.
+pub(crate) const MAX_DEPTH: usize = 32;
+
 // ── Types ──────────────────────────────────────────────────────────────────
 
 /// A single tag name.  Numeric tags are serialized as their string form.
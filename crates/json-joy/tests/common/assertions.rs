@@ -42,6 +42,7 @@ pub fn op_to_opcode(op: &Op) -> u8 {
         Op::UpdArr { .. } => JsonCrdtPatchOpcode::UpdArr as u8,
         Op::Del { .. } => JsonCrdtPatchOpcode::Del as u8,
         Op::Nop { .. } => JsonCrdtPatchOpcode::Nop as u8,
+        Op::MovArr { .. } => JsonCrdtPatchOpcode::MovArr as u8,
     }
 }
 
@@ -0,0 +1,160 @@
+use json_joy::json_crdt::model::api::ApiError;
+use json_joy::json_crdt::nodes::{CrdtNode, IndexExt};
+use json_joy::json_crdt::typed::{CrdtSchema, CrdtValue};
+use json_joy::json_crdt::{Model, ModelApi};
+use serde_json::json;
+
+#[derive(Debug, Clone, PartialEq, CrdtSchema)]
+struct Todo {
+    title: String,
+    #[crdt(con)]
+    done: bool,
+    #[crdt(rename = "tags")]
+    labels: Vec<String>,
+    #[crdt(obj)]
+    owner: Person,
+    #[crdt(bin)]
+    hash: Vec<u8>,
+    note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, CrdtSchema)]
+#[crdt(vec)]
+struct Person {
+    #[crdt(con)]
+    name: String,
+    #[crdt(val)]
+    age: u32,
+}
+
+fn todo() -> Todo {
+    Todo {
+        title: "write tests".into(),
+        done: false,
+        labels: vec!["dev".into()],
+        owner: Person {
+            name: "ada".into(),
+            age: 36,
+        },
+        hash: vec![1, 2],
+        note: None,
+    }
+}
+
+fn kind(model: &Model, id: json_joy::json_crdt_patch::clock::Ts) -> &'static str {
+    match IndexExt::get(&model.index, &id) {
+        Some(CrdtNode::Con(_)) => "con",
+        Some(CrdtNode::Val(_)) => "val",
+        Some(CrdtNode::Str(_)) => "str",
+        Some(CrdtNode::Bin(_)) => "bin",
+        Some(CrdtNode::Arr(_)) => "arr",
+        Some(CrdtNode::Obj(_)) => "obj",
+        Some(CrdtNode::Vec(_)) => "vec",
+        _ => "other",
+    }
+}
+
+#[test]
+fn schema_follows_field_attributes() {
+    let mut model = Model::new(500_001);
+    let mut api = ModelApi::new(&mut model);
+    let node = todo().write_root(&mut api);
+    assert_eq!(
+        model.view(),
+        json!({
+            "title": "write tests",
+            "done": false,
+            "tags": ["dev"],
+            "owner": ["ada", 36],
+            "hash": [1, 2],
+            "note": null,
+        })
+    );
+    assert_eq!(kind(&model, node.id), "obj");
+    assert_eq!(kind(&model, node.title_id(&model).unwrap()), "str");
+    assert_eq!(kind(&model, node.done_id(&model).unwrap()), "con");
+    assert_eq!(kind(&model, node.labels_id(&model).unwrap()), "arr");
+    assert_eq!(kind(&model, node.hash_id(&model).unwrap()), "bin");
+    let owner = node.owner_node(&model).unwrap();
+    assert_eq!(kind(&model, owner.id), "vec");
+    assert_eq!(kind(&model, owner.name_id(&model).unwrap()), "con");
+    assert_eq!(kind(&model, owner.age_id(&model).unwrap()), "val");
+}
+
+#[test]
+fn typed_read_round_trips() {
+    let mut model = Model::new(500_002);
+    let mut api = ModelApi::new(&mut model);
+    todo().write_root(&mut api);
+    let node = Todo::root(&model);
+    assert_eq!(node.read(&model).unwrap(), todo());
+    assert_eq!(node.title(&model).unwrap(), "write tests");
+    assert_eq!(node.owner(&model).unwrap().age, 36);
+    assert_eq!(Todo::from_json(&todo().to_json()), Some(todo()));
+
+    let decoded = Model::from_binary(&model.to_binary()).unwrap();
+    assert_eq!(Todo::root(&decoded).read(&decoded).unwrap(), todo());
+}
+
+#[test]
+fn setters_update_fields() {
+    let mut model = Model::new(500_003);
+    let mut api = ModelApi::new(&mut model);
+    let node = todo().write_root(&mut api);
+    let title = node.title_id(api.model).unwrap();
+
+    node.set_title(&mut api, &"write more tests".to_string())
+        .unwrap();
+    node.set_done(&mut api, &true).unwrap();
+    node.set_note(&mut api, &Some("soon".to_string())).unwrap();
+    let owner = node.owner_node(api.model).unwrap();
+    owner.set_age(&mut api, &37).unwrap();
+
+    // Strings are merged in place rather than replaced.
+    assert_eq!(node.title_id(&model).unwrap(), title);
+    let expected = Todo {
+        title: "write more tests".into(),
+        done: true,
+        note: Some("soon".into()),
+        owner: Person {
+            name: "ada".into(),
+            age: 37,
+        },
+        ..todo()
+    };
+    assert_eq!(node.read(&model).unwrap(), expected);
+}
+
+#[test]
+fn concurrent_val_writes_share_the_register() {
+    let mut a = Model::new(500_005);
+    let mut api = ModelApi::new(&mut a);
+    let node = todo().write_root(&mut api);
+    let mut b = Model::from_binary(&a.to_binary()).unwrap();
+    b.clock = b.clock.fork(500_006);
+    let owner = node.owner_node(&a).unwrap();
+    let age = owner.age_id(&a).unwrap();
+
+    let ((), pa) = a.transaction(|api| owner.set_age(api, &40)).unwrap();
+    let ((), pb) = b.transaction(|api| owner.set_age(api, &41)).unwrap();
+    a.apply_patch(&pb);
+    b.apply_patch(&pa);
+
+    assert_eq!(a.view(), b.view());
+    assert_eq!(owner.age_id(&a).unwrap(), age);
+    assert_eq!(owner.age_id(&b).unwrap(), age);
+    assert_eq!(kind(&a, age), "val");
+}
+
+#[test]
+fn reading_a_mismatched_document_fails() {
+    let mut model = Model::new(500_004);
+    let mut api = ModelApi::new(&mut model);
+    let id = api.json(&json!({"title": 1})).unwrap();
+    api.builder.root(id);
+    api.apply();
+    let node = Todo::root(&model);
+    assert_eq!(node.read(&model), Err(ApiError::WrongType));
+    assert_eq!(node.title(&model), Err(ApiError::WrongType));
+    assert_eq!(node.done(&model), Err(ApiError::NotFound));
+}
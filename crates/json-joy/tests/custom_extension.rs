@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use json_joy::json_crdt::codec::{indexed, sidecar, structural};
+use json_joy::json_crdt::model::api::ApiError;
+use json_joy::json_crdt::nodes::{CrdtNode, IndexExt, NodeIndex};
+use json_joy::json_crdt::{AnyExtension, ExtHandle, ExtRef, Extensions, Model, ModelApi};
+use json_joy::json_crdt_patch::clock::Ts;
+use json_joy::json_crdt_patch::schema::{self, NodeBuilder};
+use serde_json::{json, Value};
+
+/// A string that always reads upper-cased.
+struct Shout;
+
+impl AnyExtension for Shout {
+    fn id(&self) -> u32 {
+        42
+    }
+
+    fn name(&self) -> &str {
+        "shout"
+    }
+
+    fn schema(&self, value: &Value) -> Box<dyn NodeBuilder> {
+        Box::new(schema::StrNode {
+            raw: value.as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    fn view(&self, _: &Extensions, index: &NodeIndex, data: Ts) -> Value {
+        match IndexExt::get(index, &data) {
+            Some(CrdtNode::Str(text)) => Value::String(text.view_str().to_uppercase()),
+            _ => Value::Null,
+        }
+    }
+}
+
+struct ShoutApi<'m, 'a> {
+    api: &'m mut ModelApi<'a>,
+    node: ExtRef,
+}
+
+impl<'m, 'a> ExtHandle<'m, 'a> for ShoutApi<'m, 'a> {
+    const EXT_ID: u8 = 42;
+
+    fn wrap(api: &'m mut ModelApi<'a>, node: ExtRef) -> Self {
+        Self { api, node }
+    }
+}
+
+impl ShoutApi<'_, '_> {
+    fn append(&mut self, text: &str) -> Result<(), ApiError> {
+        let len = self.api.str_len(self.node.data).ok_or(ApiError::NotFound)?;
+        self.api.str_ins(self.node.data, len, text)
+    }
+}
+
+fn registry() -> Arc<Extensions> {
+    let mut extensions = Extensions::new();
+    extensions.register(Box::new(Shout)).unwrap();
+    Arc::new(extensions)
+}
+
+fn document() -> Model {
+    let mut model = Model::new(600_001);
+    model.extensions = registry();
+    let mut api = ModelApi::new(&mut model);
+    api.set(&json!({"title": "plain", "list": [1]})).unwrap();
+    let greeting = api.ext_node(&Shout, &json!("hello"));
+    let root = api.model.root.val;
+    api.builder
+        .ins_obj(root, vec![("greeting".into(), greeting)]);
+    api.apply();
+    model
+}
+
+#[test]
+fn registered_extension_renders_its_view() {
+    let mut model = document();
+    assert_eq!(
+        model.view(),
+        json!({"title": "plain", "list": [1], "greeting": "HELLO"})
+    );
+
+    // Without the registry the node is a plain `vec` tuple.
+    let mut plain = model.clone();
+    plain.extensions = Arc::default();
+    let tuple = &plain.view()["greeting"];
+    assert_eq!(tuple[1], json!("hello"));
+
+    let mut api = ModelApi::new(&mut model);
+    api.ext::<ShoutApi>(&[json!("greeting")])
+        .unwrap()
+        .append(" world")
+        .unwrap();
+    assert_eq!(model.view()["greeting"], json!("HELLO WORLD"));
+}
+
+#[test]
+fn api_object_checks_extension_id() {
+    let mut model = document();
+    let mut api = ModelApi::new(&mut model);
+    assert_eq!(
+        api.ext::<ShoutApi>(&[json!("title")]).err(),
+        Some(ApiError::WrongType)
+    );
+    assert_eq!(
+        api.ext::<ShoutApi>(&[json!("missing")]).err(),
+        Some(ApiError::NotFound)
+    );
+    let id = api.find(api.model.root.val, &[json!("greeting")]).unwrap();
+    let (ext_id, node) = ExtRef::of(&api.model.index, id).unwrap();
+    assert_eq!((ext_id, node.id), (42, id));
+}
+
+#[test]
+fn extension_nodes_round_trip_through_structural_codecs() {
+    let model = document();
+    let expected = model.view();
+
+    let (view, meta) = sidecar::binary::encode(&model);
+    let decoded = [
+        structural::binary::decode(&structural::binary::encode(&model)).unwrap(),
+        structural::compact::decode(&structural::compact::encode(&model)).unwrap(),
+        structural::compact_binary::decode(&structural::compact_binary::encode(&model)).unwrap(),
+        structural::verbose::decode(&structural::verbose::encode(&model)).unwrap(),
+        indexed::binary::decode(&indexed::binary::encode(&model)).unwrap(),
+        sidecar::binary::decode(&view, &meta).unwrap(),
+    ];
+    for (i, mut copy) in decoded.into_iter().enumerate() {
+        copy.extensions = registry();
+        assert_eq!(copy.view(), expected, "codec {i}");
+    }
+}